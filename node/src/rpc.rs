@@ -7,7 +7,7 @@
 
 use std::sync::Arc;
 
-use rai_runtime::{opaque::Block, AccountId, Balance, Index};
+use rai_runtime::{opaque::Block, AccountId, AssetId, Balance, BlockNumber, Index};
 pub use sc_rpc_api::DenyUnsafe;
 use sc_transaction_pool_api::TransactionPool;
 use sp_api::ProvideRuntimeApi;
@@ -32,9 +32,13 @@ where
 	C: Send + Sync + 'static,
 	C::Api: substrate_frame_rpc_system::AccountNonceApi<Block, AccountId, Index>,
 	C::Api: pallet_transaction_payment_rpc::TransactionPaymentRuntimeApi<Block, Balance>,
+	C::Api: pallet_portfolio_rpc::PortfolioRuntimeApi<Block, AssetId, AccountId, Balance>,
+	C::Api: pallet_dex_rpc::DexRuntimeApi<Block, AssetId, Balance, BlockNumber>,
 	C::Api: BlockBuilder<Block>,
 	P: TransactionPool + 'static,
 {
+	use pallet_dex_rpc::{Dex, DexApi};
+	use pallet_portfolio_rpc::{Portfolio, PortfolioApi};
 	use pallet_transaction_payment_rpc::{TransactionPayment, TransactionPaymentApi};
 	use substrate_frame_rpc_system::{FullSystem, SystemApi};
 
@@ -45,6 +49,10 @@ where
 
 	io.extend_with(TransactionPaymentApi::to_delegate(TransactionPayment::new(client.clone())));
 
+	io.extend_with(PortfolioApi::to_delegate(Portfolio::new(client.clone())));
+
+	io.extend_with(DexApi::to_delegate(Dex::new(client.clone())));
+
 	// Extend this RPC with a custom API by using the following syntax.
 	// `YourRpcStruct` should have a reference to a client, which is needed
 	// to call into the runtime.
@@ -0,0 +1,299 @@
+//! # Asset Vesting Module
+//!
+//! ## Overview
+//!
+//! Vesting schedules on asset balances, released linearly as `pallet_vesting` does for the native
+//! currency. The unvested balance of an account is held by a lock of `pallet_assets`, which is
+//! lowered to the balance still unvested whenever the account calls `vest` or someone calls
+//! `vest_other` for it.
+//!
+//! A vested transfer moves a balance, of at least `MinVestedTransfer`, into an account under a new
+//! schedule. Other pallets can put a schedule on a balance they pay out with
+//! `add_vesting_schedule`, e.g. for rewards or team allocations. An account can merge two of its
+//! schedules into one, to make room for more.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+pub use pallet::*;
+
+#[cfg(test)]
+mod mock;
+
+#[cfg(test)]
+mod tests;
+
+use frame_support::traits::LockIdentifier;
+pub use pallet_assets::VestingInfo;
+
+/// The id of the lock holding unvested balances.
+pub const VESTING_ID: LockIdentifier = *b"vesting ";
+
+#[frame_support::pallet]
+pub mod pallet {
+	use super::{VestingInfo, VESTING_ID};
+	use frame_support::{pallet_prelude::*, traits::tokens::fungibles::Transfer, transactional};
+	use frame_system::pallet_prelude::*;
+	use pallet_assets::LockableAssets;
+	use sp_runtime::traits::{One, SaturatedConversion, Saturating, StaticLookup, Zero};
+
+	pub(crate) type VestingInfoOf<T> = VestingInfo<
+		<T as pallet_assets::Config>::Balance,
+		<T as frame_system::Config>::BlockNumber,
+	>;
+
+	#[pallet::config]
+	pub trait Config: frame_system::Config + pallet_assets::Config {
+		/// Because this pallet emits events, it depends on the runtime's definition of an event.
+		type Event: From<Event<Self>> + IsType<<Self as frame_system::Config>::Event>;
+
+		/// The assets whose balances are vested.
+		type Assets: LockableAssets<Self::AccountId, AssetId = Self::AssetId, Balance = Self::Balance>
+			+ Transfer<Self::AccountId>;
+
+		/// The maximum number of vesting schedules an account may have on one asset.
+		#[pallet::constant]
+		type MaxVestingSchedules: Get<u32>;
+
+		/// The minimum balance a vested transfer may move.
+		#[pallet::constant]
+		type MinVestedTransfer: Get<Self::Balance>;
+
+		/// The origin which may make a vested transfer from any account.
+		type UpdateOrigin: EnsureOrigin<Self::Origin>;
+	}
+
+	#[pallet::pallet]
+	#[pallet::generate_store(pub(super) trait Store)]
+	#[pallet::without_storage_info]
+	pub struct Pallet<T>(_);
+
+	/// The vesting schedules of each account on each asset.
+	///
+	/// Vesting: double_map AssetId, AccountId => Vec<VestingInfo>
+	#[pallet::storage]
+	#[pallet::getter(fn vesting)]
+	pub type Vesting<T: Config> = StorageDoubleMap<
+		_,
+		Twox64Concat,
+		T::AssetId,
+		Twox64Concat,
+		T::AccountId,
+		BoundedVec<VestingInfoOf<T>, T::MaxVestingSchedules>,
+		ValueQuery,
+	>;
+
+	#[pallet::event]
+	#[pallet::generate_deposit(pub(super) fn deposit_event)]
+	pub enum Event<T: Config> {
+		/// A vesting schedule was added to an account. [asset_id, who, schedule]
+		VestingScheduleAdded(T::AssetId, T::AccountId, VestingInfoOf<T>),
+		/// The balance of an account still unvested was updated. [asset_id, who, unvested]
+		VestingUpdated(T::AssetId, T::AccountId, T::Balance),
+		/// The whole balance of an account vested. [asset_id, who]
+		VestingCompleted(T::AssetId, T::AccountId),
+		/// Two vesting schedules of an account were merged into one. [asset_id, who, merged]
+		VestingSchedulesMerged(T::AssetId, T::AccountId, VestingInfoOf<T>),
+	}
+
+	#[pallet::error]
+	pub enum Error<T> {
+		/// The account has no vesting schedule on the asset.
+		NotVesting,
+		/// The account already has `MaxVestingSchedules` vesting schedules on the asset.
+		AtMaxVestingSchedules,
+		/// The schedule locks nothing or never releases anything.
+		InvalidScheduleParams,
+		/// The vested transfer moves less than `MinVestedTransfer`.
+		AmountLow,
+		/// No vesting schedule at the given index.
+		ScheduleIndexOutOfBounds,
+	}
+
+	#[pallet::call]
+	impl<T: Config> Pallet<T> {
+		/// Unlock the balance of the sender in `asset_id` that has vested.
+		#[pallet::weight(10_000)]
+		pub fn vest(origin: OriginFor<T>, asset_id: T::AssetId) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			Self::do_vest(asset_id, &who)
+		}
+
+		/// Unlock the balance of `target` in `asset_id` that has vested.
+		#[pallet::weight(10_000)]
+		pub fn vest_other(
+			origin: OriginFor<T>,
+			asset_id: T::AssetId,
+			target: <T::Lookup as StaticLookup>::Source,
+		) -> DispatchResult {
+			ensure_signed(origin)?;
+			let target = T::Lookup::lookup(target)?;
+			Self::do_vest(asset_id, &target)
+		}
+
+		/// Transfer `schedule.locked` of `asset_id` from the sender to `target`, vested by
+		/// `schedule`.
+		#[pallet::weight(10_000)]
+		#[transactional]
+		pub fn vested_transfer(
+			origin: OriginFor<T>,
+			asset_id: T::AssetId,
+			target: <T::Lookup as StaticLookup>::Source,
+			schedule: VestingInfoOf<T>,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			let target = T::Lookup::lookup(target)?;
+			Self::do_vested_transfer(asset_id, &who, &target, schedule)
+		}
+
+		/// Transfer `schedule.locked` of `asset_id` from `source` to `target`, vested by
+		/// `schedule`.
+		#[pallet::weight(10_000)]
+		#[transactional]
+		pub fn force_vested_transfer(
+			origin: OriginFor<T>,
+			asset_id: T::AssetId,
+			source: <T::Lookup as StaticLookup>::Source,
+			target: <T::Lookup as StaticLookup>::Source,
+			schedule: VestingInfoOf<T>,
+		) -> DispatchResult {
+			T::UpdateOrigin::ensure_origin(origin)?;
+			let source = T::Lookup::lookup(source)?;
+			let target = T::Lookup::lookup(target)?;
+			Self::do_vested_transfer(asset_id, &source, &target, schedule)
+		}
+
+		/// Merge the vesting schedules of the sender in `asset_id` at `schedule1_index` and
+		/// `schedule2_index` into one, releasing what they still lock from the later of their
+		/// starts, or now, by the later of their ends.
+		#[pallet::weight(10_000)]
+		#[transactional]
+		pub fn merge_schedules(
+			origin: OriginFor<T>,
+			asset_id: T::AssetId,
+			schedule1_index: u32,
+			schedule2_index: u32,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			if schedule1_index == schedule2_index {
+				return Ok(())
+			}
+			let schedule1_index = schedule1_index as usize;
+			let schedule2_index = schedule2_index as usize;
+
+			let mut schedules = Vesting::<T>::get(asset_id, &who);
+			ensure!(!schedules.is_empty(), Error::<T>::NotVesting);
+			ensure!(
+				schedule1_index < schedules.len() && schedule2_index < schedules.len(),
+				Error::<T>::ScheduleIndexOutOfBounds
+			);
+
+			// the later index goes first, so the earlier one still points at its schedule
+			let schedule1 = schedules[schedule1_index];
+			let schedule2 = schedules[schedule2_index];
+			schedules.remove(schedule1_index.max(schedule2_index));
+			schedules.remove(schedule1_index.min(schedule2_index));
+
+			let now = frame_system::Pallet::<T>::block_number();
+			if let Some(merged) = Self::merge_vesting_info(now, schedule1, schedule2) {
+				schedules.try_push(merged).map_err(|_| Error::<T>::AtMaxVestingSchedules)?;
+				Self::deposit_event(Event::VestingSchedulesMerged(asset_id, who.clone(), merged));
+			}
+			Self::update_lock(asset_id, &who, schedules)
+		}
+	}
+
+	impl<T: Config> Pallet<T> {
+		/// Put `schedule` on the balance of `who` in `asset_id`, which must already hold the
+		/// balance it locks.
+		#[transactional]
+		pub fn add_vesting_schedule(
+			asset_id: T::AssetId,
+			who: &T::AccountId,
+			schedule: VestingInfoOf<T>,
+		) -> DispatchResult {
+			ensure!(schedule.is_valid(), Error::<T>::InvalidScheduleParams);
+			Vesting::<T>::try_mutate(asset_id, who, |schedules| {
+				schedules.try_push(schedule).map_err(|_| Error::<T>::AtMaxVestingSchedules)
+			})?;
+			Self::deposit_event(Event::VestingScheduleAdded(asset_id, who.clone(), schedule));
+			Self::do_vest(asset_id, who)
+		}
+
+		fn do_vested_transfer(
+			asset_id: T::AssetId,
+			source: &T::AccountId,
+			target: &T::AccountId,
+			schedule: VestingInfoOf<T>,
+		) -> DispatchResult {
+			ensure!(schedule.is_valid(), Error::<T>::InvalidScheduleParams);
+			ensure!(schedule.locked >= T::MinVestedTransfer::get(), Error::<T>::AmountLow);
+			T::Assets::transfer(asset_id, source, target, schedule.locked, false)?;
+			Self::add_vesting_schedule(asset_id, target, schedule)
+		}
+
+		/// Drop the finished schedules of `who` in `asset_id` and lock what is still unvested.
+		fn do_vest(asset_id: T::AssetId, who: &T::AccountId) -> DispatchResult {
+			let schedules = Vesting::<T>::get(asset_id, who);
+			ensure!(!schedules.is_empty(), Error::<T>::NotVesting);
+			Self::update_lock(asset_id, who, schedules)
+		}
+
+		/// Store the unfinished ones of `schedules` as those of `who` in `asset_id` and lock
+		/// what they leave unvested.
+		fn update_lock(
+			asset_id: T::AssetId,
+			who: &T::AccountId,
+			mut schedules: BoundedVec<VestingInfoOf<T>, T::MaxVestingSchedules>,
+		) -> DispatchResult {
+			let now = frame_system::Pallet::<T>::block_number();
+			schedules.retain(|schedule| !schedule.locked_at(now).is_zero());
+			let unvested = schedules.iter().fold(Zero::zero(), |unvested: T::Balance, schedule| {
+				unvested.saturating_add(schedule.locked_at(now))
+			});
+
+			if schedules.is_empty() {
+				Vesting::<T>::remove(asset_id, who);
+				T::Assets::remove_lock(VESTING_ID, asset_id, who);
+				Self::deposit_event(Event::VestingCompleted(asset_id, who.clone()));
+			} else {
+				Vesting::<T>::insert(asset_id, who, schedules);
+				T::Assets::set_lock(VESTING_ID, asset_id, who, unvested)?;
+				Self::deposit_event(Event::VestingUpdated(asset_id, who.clone(), unvested));
+			}
+			Ok(())
+		}
+
+		/// The schedule releasing what `schedule1` and `schedule2` still lock at `now`, from the
+		/// later of their starts, or `now`, by the later of their ends. What it releases each
+		/// block is rounded up, so it may end a little earlier, but never later. `None` if both
+		/// have finished.
+		fn merge_vesting_info(
+			now: T::BlockNumber,
+			schedule1: VestingInfoOf<T>,
+			schedule2: VestingInfoOf<T>,
+		) -> Option<VestingInfoOf<T>> {
+			let locked1 = schedule1.locked_at(now);
+			let locked2 = schedule2.locked_at(now);
+			match (locked1.is_zero(), locked2.is_zero()) {
+				(true, true) => None,
+				(true, false) => Some(schedule2),
+				(false, true) => Some(schedule1),
+				(false, false) => {
+					let locked = locked1.saturating_add(locked2);
+					let starting_block =
+						now.max(schedule1.starting_block).max(schedule2.starting_block);
+					let ending_block = schedule1.ending_block().max(schedule2.ending_block());
+					let duration: u128 =
+						ending_block.saturating_sub(starting_block).saturated_into();
+					let duration: T::Balance = duration.saturated_into();
+					let duration = duration.max(One::one());
+					let mut per_block = locked / duration;
+					if !(locked % duration).is_zero() {
+						per_block = per_block.saturating_add(One::one());
+					}
+					Some(VestingInfo { locked, per_block, starting_block })
+				},
+			}
+		}
+	}
+}
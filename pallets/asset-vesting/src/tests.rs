@@ -0,0 +1,164 @@
+//! Unit tests for the asset vesting pallet.
+
+use crate::{mock::*, Error, VestingInfo};
+use frame_support::{assert_noop, assert_ok};
+
+/// 1_000 released over blocks 10 to 20.
+const SCHEDULE: VestingInfo<Balance, BlockNumber> =
+	VestingInfo { locked: 1_000, per_block: 100, starting_block: 10 };
+
+fn vested_transfer_to_bob(schedule: VestingInfo<Balance, BlockNumber>) {
+	assert_ok!(AssetVesting::vested_transfer(Origin::signed(ALICE), DOT, BOB, schedule));
+}
+
+#[test]
+fn vested_transfer_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_noop!(
+			AssetVesting::vested_transfer(
+				Origin::signed(ALICE),
+				DOT,
+				BOB,
+				VestingInfo { locked: MIN_VESTED_TRANSFER - 1, ..SCHEDULE }
+			),
+			Error::<Test>::AmountLow
+		);
+		assert_noop!(
+			AssetVesting::vested_transfer(
+				Origin::signed(ALICE),
+				DOT,
+				BOB,
+				VestingInfo { per_block: 0, ..SCHEDULE }
+			),
+			Error::<Test>::InvalidScheduleParams
+		);
+
+		vested_transfer_to_bob(SCHEDULE);
+		System::assert_has_event(Event::AssetVesting(crate::Event::VestingScheduleAdded(
+			DOT, BOB, SCHEDULE,
+		)));
+		System::assert_last_event(Event::AssetVesting(crate::Event::VestingUpdated(
+			DOT, BOB, 1_000,
+		)));
+		assert_eq!(AssetVesting::vesting(DOT, BOB).into_inner(), vec![SCHEDULE]);
+		assert_eq!(Assets::balance(DOT, BOB), 1_000);
+		assert_noop!(
+			Assets::transfer(Origin::signed(BOB), DOT, ALICE, 1),
+			pallet_assets::Error::<Test>::BalanceLow
+		);
+
+		// up to `MaxVestingSchedules`
+		vested_transfer_to_bob(SCHEDULE);
+		vested_transfer_to_bob(SCHEDULE);
+		assert_noop!(
+			AssetVesting::vested_transfer(Origin::signed(ALICE), DOT, BOB, SCHEDULE),
+			Error::<Test>::AtMaxVestingSchedules
+		);
+	});
+}
+
+#[test]
+fn vest_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_noop!(AssetVesting::vest(Origin::signed(BOB), DOT), Error::<Test>::NotVesting);
+		vested_transfer_to_bob(SCHEDULE);
+
+		System::set_block_number(15);
+		assert_ok!(AssetVesting::vest(Origin::signed(BOB), DOT));
+		System::assert_last_event(Event::AssetVesting(crate::Event::VestingUpdated(DOT, BOB, 500)));
+
+		// the minimum balance stays with the locked 500
+		assert_noop!(
+			Assets::transfer(Origin::signed(BOB), DOT, ALICE, 500),
+			pallet_assets::Error::<Test>::BalanceLow
+		);
+		assert_ok!(Assets::transfer(Origin::signed(BOB), DOT, ALICE, 499));
+	});
+}
+
+#[test]
+fn vest_other_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		vested_transfer_to_bob(SCHEDULE);
+
+		System::set_block_number(12);
+		assert_ok!(AssetVesting::vest_other(Origin::signed(ALICE), DOT, BOB));
+		System::assert_last_event(Event::AssetVesting(crate::Event::VestingUpdated(DOT, BOB, 800)));
+		assert_ok!(Assets::transfer(Origin::signed(BOB), DOT, ALICE, 199));
+	});
+}
+
+#[test]
+fn vesting_expires() {
+	ExtBuilder::default().build().execute_with(|| {
+		vested_transfer_to_bob(SCHEDULE);
+
+		System::set_block_number(20);
+		assert_ok!(AssetVesting::vest(Origin::signed(BOB), DOT));
+		System::assert_last_event(Event::AssetVesting(crate::Event::VestingCompleted(DOT, BOB)));
+		assert!(AssetVesting::vesting(DOT, BOB).is_empty());
+		assert_ok!(Assets::transfer(Origin::signed(BOB), DOT, ALICE, 1_000));
+	});
+}
+
+#[test]
+fn merge_schedules_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_noop!(
+			AssetVesting::merge_schedules(Origin::signed(BOB), DOT, 0, 1),
+			Error::<Test>::NotVesting
+		);
+
+		// 600 released over blocks 5 to 35
+		let schedule = VestingInfo { locked: 600, per_block: 20, starting_block: 5 };
+		vested_transfer_to_bob(SCHEDULE);
+		vested_transfer_to_bob(schedule);
+		assert_noop!(
+			AssetVesting::merge_schedules(Origin::signed(BOB), DOT, 0, 2),
+			Error::<Test>::ScheduleIndexOutOfBounds
+		);
+
+		// 500 and 400 left, released over blocks 15 to 35
+		System::set_block_number(15);
+		assert_ok!(AssetVesting::merge_schedules(Origin::signed(BOB), DOT, 0, 1));
+		let merged = VestingInfo { locked: 900, per_block: 45, starting_block: 15 };
+		assert_eq!(AssetVesting::vesting(DOT, BOB).into_inner(), vec![merged]);
+		System::assert_last_event(Event::AssetVesting(crate::Event::VestingUpdated(DOT, BOB, 900)));
+
+		// a finished schedule merges into what the other one leaves
+		vested_transfer_to_bob(SCHEDULE);
+		System::set_block_number(25);
+		assert_ok!(AssetVesting::merge_schedules(Origin::signed(BOB), DOT, 1, 0));
+		assert_eq!(AssetVesting::vesting(DOT, BOB).into_inner(), vec![merged]);
+		System::assert_last_event(Event::AssetVesting(crate::Event::VestingUpdated(DOT, BOB, 450)));
+	});
+}
+
+#[test]
+fn merged_schedule_ends_with_the_later_one() {
+	ExtBuilder::default().build().execute_with(|| {
+		// 630 released over blocks 5 to 37
+		let schedule = VestingInfo { locked: 630, per_block: 20, starting_block: 5 };
+		assert_eq!(schedule.ending_block(), 37);
+		vested_transfer_to_bob(SCHEDULE);
+		vested_transfer_to_bob(schedule);
+
+		// 500 and 430 left, released over the 22 blocks from 15 to 37, 43 a block rounded up
+		System::set_block_number(15);
+		assert_ok!(AssetVesting::merge_schedules(Origin::signed(BOB), DOT, 0, 1));
+		let merged = VestingInfo { locked: 930, per_block: 43, starting_block: 15 };
+		assert_eq!(AssetVesting::vesting(DOT, BOB).into_inner(), vec![merged]);
+		assert_eq!(merged.ending_block(), 37);
+		System::assert_has_event(Event::AssetVesting(crate::Event::VestingSchedulesMerged(
+			DOT, BOB, merged,
+		)));
+
+		System::set_block_number(36);
+		assert_ok!(AssetVesting::vest(Origin::signed(BOB), DOT));
+		System::assert_last_event(Event::AssetVesting(crate::Event::VestingUpdated(DOT, BOB, 27)));
+
+		System::set_block_number(37);
+		assert_ok!(AssetVesting::vest(Origin::signed(BOB), DOT));
+		System::assert_last_event(Event::AssetVesting(crate::Event::VestingCompleted(DOT, BOB)));
+	});
+}
@@ -0,0 +1,135 @@
+//! Airdrops funded from a reserved balance and claimed with Merkle proofs.
+
+use super::*;
+use frame_support::{traits::Get, weights::Weight};
+use sp_runtime::traits::Hash;
+
+impl<T: Config<I>, I: 'static> Pallet<T, I> {
+	// The airdrop calls are not benchmarked yet. Their weights are those of the benchmarked
+	// transfer they amount to, plus the reads and writes of the airdrop itself.
+
+	/// The weight of `create_airdrop`: the asset and the deposit moved into reserves, and the
+	/// airdrop id and details.
+	pub(super) fn create_airdrop_weight() -> Weight {
+		T::WeightInfo::transfer()
+			.saturating_add(T::FreezerWeight::get())
+			.saturating_add(T::DbWeight::get().reads_writes(2, 3))
+	}
+
+	/// The weight of `claim_airdrop` with a proof of `proof_len` nodes: the asset repatriated
+	/// from the reserve, the airdrop and its claims, and about a microsecond to hash each node.
+	pub(super) fn claim_airdrop_weight(proof_len: usize) -> Weight {
+		T::WeightInfo::transfer()
+			.saturating_add(T::FreezerWeight::get())
+			.saturating_add(T::DbWeight::get().reads_writes(3, 3))
+			.saturating_add((proof_len as Weight).saturating_add(1).saturating_mul(1_000_000))
+	}
+
+	/// The weight of `close_airdrop`: the asset and the deposit unreserved, and the airdrop.
+	pub(super) fn close_airdrop_weight() -> Weight {
+		T::WeightInfo::transfer().saturating_add(T::DbWeight::get().reads_writes(1, 1))
+	}
+
+	/// The leaf of the claim of `amount` by `who` in the Merkle tree of an airdrop.
+	pub fn airdrop_leaf(who: &T::AccountId, amount: T::Balance) -> T::Hash {
+		T::Hashing::hash_of(&(AIRDROP_LEAF_PREFIX, who, amount))
+	}
+
+	/// The root of the Merkle tree of an airdrop given a `leaf` and the `proof` of it. Each node
+	/// is the hash of the pair of its children, the smaller first, prefixed apart from the leaves.
+	pub fn airdrop_root(leaf: T::Hash, proof: &[T::Hash]) -> T::Hash {
+		proof.iter().fold(leaf, |node, sibling| {
+			if node <= *sibling {
+				T::Hashing::hash_of(&(AIRDROP_NODE_PREFIX, node, sibling))
+			} else {
+				T::Hashing::hash_of(&(AIRDROP_NODE_PREFIX, sibling, node))
+			}
+		})
+	}
+
+	/// Create an airdrop of `amount` of asset `id`, reserved from the balance of `owner` along
+	/// with the airdrop deposit.
+	pub(super) fn do_create_airdrop(
+		id: T::AssetId,
+		owner: T::AccountId,
+		merkle_root: T::Hash,
+		amount: T::Balance,
+	) -> DispatchResult {
+		ensure!(!amount.is_zero(), Error::<T, I>::ZeroAirdrop);
+		let deposit = T::AirdropDeposit::get();
+		T::Currency::reserve(&owner, deposit)?;
+		Self::reserve_named(&T::AirdropReserveId::get(), id, &owner, amount)?;
+
+		let airdrop_id = NextAirdropId::<T, I>::get();
+		NextAirdropId::<T, I>::put(airdrop_id.checked_add(1).ok_or(ArithmeticError::Overflow)?);
+		Airdrops::<T, I>::insert(
+			airdrop_id,
+			AirdropDetails {
+				asset_id: id,
+				owner: owner.clone(),
+				merkle_root,
+				remaining: amount,
+				deposit,
+			},
+		);
+
+		Self::deposit_event(Event::AirdropCreated {
+			airdrop_id,
+			asset_id: id,
+			owner,
+			amount,
+			merkle_root,
+		});
+		Ok(())
+	}
+
+	/// Pay `who` its claim of `amount` from airdrop `airdrop_id`, if `proof` shows it is part of
+	/// the airdrop.
+	pub(super) fn do_claim_airdrop(
+		airdrop_id: AirdropId,
+		who: T::AccountId,
+		amount: T::Balance,
+		proof: Vec<T::Hash>,
+	) -> DispatchResult {
+		let mut details = Airdrops::<T, I>::get(airdrop_id).ok_or(Error::<T, I>::UnknownAirdrop)?;
+		ensure!(
+			!AirdropClaims::<T, I>::contains_key(airdrop_id, &who),
+			Error::<T, I>::AlreadyClaimed
+		);
+		ensure!(
+			Self::airdrop_root(Self::airdrop_leaf(&who, amount), &proof) == details.merkle_root,
+			Error::<T, I>::InvalidProof
+		);
+		details.remaining =
+			details.remaining.checked_sub(&amount).ok_or(Error::<T, I>::AirdropExhausted)?;
+
+		let not_moved = Self::repatriate_reserved_named(
+			&T::AirdropReserveId::get(),
+			details.asset_id,
+			&details.owner,
+			&who,
+			amount,
+			BalanceStatus::Free,
+		)?;
+		ensure!(not_moved.is_zero(), Error::<T, I>::AirdropExhausted);
+
+		AirdropClaims::<T, I>::insert(airdrop_id, &who, ());
+		Airdrops::<T, I>::insert(airdrop_id, details);
+		Self::deposit_event(Event::AirdropClaimed { airdrop_id, who, amount });
+		Ok(())
+	}
+
+	/// Close airdrop `airdrop_id`, unreserving what is left of it and its deposit for its owner.
+	pub(super) fn do_close_airdrop(airdrop_id: AirdropId, owner: T::AccountId) -> DispatchResult {
+		let details = Airdrops::<T, I>::get(airdrop_id).ok_or(Error::<T, I>::UnknownAirdrop)?;
+		ensure!(details.owner == owner, Error::<T, I>::NoPermission);
+
+		let refund = details.remaining;
+		Self::unreserve_named(&T::AirdropReserveId::get(), details.asset_id, &owner, refund);
+		T::Currency::unreserve(&owner, details.deposit);
+		Airdrops::<T, I>::remove(airdrop_id);
+
+		Self::deposit_event(Event::AirdropClosed { airdrop_id, refund });
+		Ok(())
+	}
+}
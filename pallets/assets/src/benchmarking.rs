@@ -26,9 +26,10 @@ use frame_benchmarking::{
 use frame_support::{
 	dispatch::UnfilteredDispatchable,
 	traits::{EnsureOrigin, Get},
+	BoundedVec,
 };
 use frame_system::RawOrigin as SystemOrigin;
-use sp_runtime::traits::Bounded;
+use sp_runtime::traits::{Bounded, Hash};
 use sp_std::prelude::*;
 
 use crate::Pallet as Assets;
@@ -438,5 +439,71 @@ benchmarks_instance_pallet! {
 		assert_last_event::<T, I>(Event::ApprovalCancelled { asset_id: id, owner: caller, delegate }.into());
 	}
 
+	batch_transfer {
+		let n in 1 .. T::MaxBatchSize::get();
+		let amount = T::Balance::from(100u32);
+		let (caller, _) = create_default_minted_asset::<T, I>(true, amount * (n + 1).into());
+		let targets: Vec<_> = (0..n)
+			.map(|i| (T::Lookup::unlookup(account("target", i, SEED)), amount))
+			.collect();
+		let targets = BoundedVec::try_from(targets).unwrap();
+	}: _(SystemOrigin::Signed(caller.clone()), Default::default(), targets)
+	verify {
+		let target: T::AccountId = account("target", n - 1, SEED);
+		assert_last_event::<T, I>(Event::Transferred { asset_id: Default::default(), from: caller, to: target, amount }.into());
+	}
+
+	batch_mint {
+		let n in 1 .. T::MaxBatchSize::get();
+		let (caller, _) = create_default_asset::<T, I>(true);
+		let amount = T::Balance::from(100u32);
+		let beneficiaries: Vec<_> = (0..n)
+			.map(|i| (T::Lookup::unlookup(account("beneficiary", i, SEED)), amount))
+			.collect();
+		let beneficiaries = BoundedVec::try_from(beneficiaries).unwrap();
+	}: _(SystemOrigin::Signed(caller.clone()), Default::default(), beneficiaries)
+	verify {
+		let beneficiary: T::AccountId = account("beneficiary", n - 1, SEED);
+		assert_last_event::<T, I>(Event::Issued { asset_id: Default::default(), owner: beneficiary, total_supply: amount }.into());
+	}
+
+	create_airdrop {
+		let amount = T::Balance::from(100u32);
+		let (caller, _) = create_default_minted_asset::<T, I>(true, amount * 2u32.into());
+		T::Currency::make_free_balance_be(&caller, DepositBalanceOf::<T, I>::max_value());
+		let merkle_root = T::Hash::default();
+	}: _(SystemOrigin::Signed(caller.clone()), Default::default(), merkle_root, amount)
+	verify {
+		assert_last_event::<T, I>(Event::AirdropCreated { airdrop_id: 0, asset_id: Default::default(), owner: caller, amount, merkle_root }.into());
+	}
+
+	claim_airdrop {
+		let p in 0 .. 32;
+		let amount = T::Balance::from(100u32);
+		let (caller, _) = create_default_minted_asset::<T, I>(true, amount * 2u32.into());
+		T::Currency::make_free_balance_be(&caller, DepositBalanceOf::<T, I>::max_value());
+		let claimant: T::AccountId = account("claimant", 0, SEED);
+		whitelist_account!(claimant);
+		let proof: Vec<T::Hash> = (0..p).map(|i| T::Hashing::hash_of(&i)).collect();
+		let leaf = Assets::<T, I>::airdrop_leaf(&claimant, amount);
+		let merkle_root = Assets::<T, I>::airdrop_root(leaf, &proof);
+		let origin = SystemOrigin::Signed(caller).into();
+		Assets::<T, I>::create_airdrop(origin, Default::default(), merkle_root, amount)?;
+	}: _(SystemOrigin::Signed(claimant.clone()), 0, amount, proof)
+	verify {
+		assert_last_event::<T, I>(Event::AirdropClaimed { airdrop_id: 0, who: claimant, amount }.into());
+	}
+
+	close_airdrop {
+		let amount = T::Balance::from(100u32);
+		let (caller, _) = create_default_minted_asset::<T, I>(true, amount * 2u32.into());
+		T::Currency::make_free_balance_be(&caller, DepositBalanceOf::<T, I>::max_value());
+		let origin = SystemOrigin::Signed(caller.clone()).into();
+		Assets::<T, I>::create_airdrop(origin, Default::default(), T::Hash::default(), amount)?;
+	}: _(SystemOrigin::Signed(caller), 0)
+	verify {
+		assert_last_event::<T, I>(Event::AirdropClosed { airdrop_id: 0, refund: amount }.into());
+	}
+
 	impl_benchmark_test_suite!(Assets, crate::mock::new_test_ext(), crate::mock::Test)
 }
@@ -53,6 +53,17 @@ impl<T: Config<I>, I: 'static> Pallet<T, I> {
 		Account::<T, I>::get(id, who.borrow()).map(|a| a.balance)
 	}
 
+	/// Get the total reserved asset `id` balance of `who`. The reserved balance is part of the
+	/// balance returned by `balance`.
+	pub fn reserved_balance(
+		id: T::AssetId,
+		who: impl sp_std::borrow::Borrow<T::AccountId>,
+	) -> T::Balance {
+		Reserves::<T, I>::get(id, who.borrow())
+			.iter()
+			.fold(Zero::zero(), |total, reserve| total.saturating_add(reserve.amount))
+	}
+
 	/// Get the total supply of an asset `id`.
 	pub fn total_supply(id: T::AssetId) -> T::Balance {
 		Self::maybe_total_supply(id).unwrap_or_default()
@@ -63,6 +74,41 @@ impl<T: Config<I>, I: 'static> Pallet<T, I> {
 		Asset::<T, I>::get(id).map(|x| x.supply)
 	}
 
+	/// Get the owner of asset `id` if the asset exists.
+	pub fn owner(id: T::AssetId) -> Option<T::AccountId> {
+		Asset::<T, I>::get(id).map(|x| x.owner)
+	}
+
+	/// Get the accounts holding asset `id`, with their balances. `None` if some of them don't
+	/// decode, as before a migration of `Extra`.
+	pub fn holders(id: T::AssetId) -> Option<Vec<(T::AccountId, T::Balance)>> {
+		let accounts = Asset::<T, I>::get(id).map_or(0, |details| details.accounts);
+		let holders: Vec<_> = Account::<T, I>::iter_prefix(id)
+			.map(|(who, account)| (who, account.balance))
+			.collect();
+		(holders.len() == accounts as usize).then(|| holders)
+	}
+
+	/// Decode the extra data of every account as `OldExtra` and replace it with `f` of it, when
+	/// migrating to a new `Extra`. Returns the number of accounts translated.
+	pub fn translate_extra<OldExtra: codec::Decode>(
+		mut f: impl FnMut(OldExtra) -> T::Extra,
+	) -> u64 {
+		let mut translated = 0u64;
+		Account::<T, I>::translate::<AssetAccount<T::Balance, DepositBalanceOf<T, I>, OldExtra>, _>(
+			|_, _, account| {
+				translated.saturating_inc();
+				Some(AssetAccount {
+					balance: account.balance,
+					is_frozen: account.is_frozen,
+					reason: account.reason,
+					extra: f(account.extra),
+				})
+			},
+		);
+		translated
+	}
+
 	pub(super) fn new_account(
 		who: &T::AccountId,
 		d: &mut AssetDetails<T::Balance, T::AccountId, DepositBalanceOf<T, I>>,
@@ -102,10 +148,30 @@ impl<T: Config<I>, I: 'static> Pallet<T, I> {
 			ExistenceReason::DepositHeld(_) => result = Keep,
 		}
 		d.accounts = d.accounts.saturating_sub(1);
+		Locks::<T, I>::remove(what, who);
+		Reserves::<T, I>::remove(what, who);
 		T::Freezer::died(what, who);
 		result
 	}
 
+	/// The part of the asset `id` balance of `who` that can't be moved: its reserves, plus the
+	/// largest of its locks and the balance frozen by `T::Freezer`. Locks, like the freezer, apply
+	/// to the balance that isn't reserved.
+	pub(super) fn frozen_balance(id: T::AssetId, who: &T::AccountId) -> Option<T::Balance> {
+		let locked = Locks::<T, I>::get(id, who)
+			.iter()
+			.map(|lock| lock.amount)
+			.max()
+			.unwrap_or_else(Zero::zero);
+		let frozen = T::Freezer::frozen_balance(id, who).unwrap_or_else(Zero::zero).max(locked);
+		let unavailable = Self::reserved_balance(id, who).saturating_add(frozen);
+		if unavailable.is_zero() {
+			None
+		} else {
+			Some(unavailable)
+		}
+	}
+
 	pub(super) fn can_increase(
 		id: T::AssetId,
 		who: &T::AccountId,
@@ -115,6 +181,9 @@ impl<T: Config<I>, I: 'static> Pallet<T, I> {
 			Some(details) => details,
 			None => return DepositConsequence::UnknownAsset,
 		};
+		if !T::Freezer::can_receive(id, who) {
+			return DepositConsequence::CannotCreate
+		}
 		if details.supply.checked_add(&amount).is_none() {
 			return DepositConsequence::Overflow
 		}
@@ -166,7 +235,7 @@ impl<T: Config<I>, I: 'static> Pallet<T, I> {
 			return Frozen
 		}
 		if let Some(rest) = account.balance.checked_sub(&amount) {
-			if let Some(frozen) = T::Freezer::frozen_balance(id, who) {
+			if let Some(frozen) = Self::frozen_balance(id, who) {
 				match frozen.checked_add(&details.min_balance) {
 					Some(required) if rest < required => return Frozen,
 					None => return Overflow,
@@ -205,7 +274,7 @@ impl<T: Config<I>, I: 'static> Pallet<T, I> {
 		let account = Account::<T, I>::get(id, who).ok_or(Error::<T, I>::NoAccount)?;
 		ensure!(!account.is_frozen, Error::<T, I>::Frozen);
 
-		let amount = if let Some(frozen) = T::Freezer::frozen_balance(id, who) {
+		let amount = if let Some(frozen) = Self::frozen_balance(id, who) {
 			// Frozen balance: account CANNOT be deleted
 			let required =
 				frozen.checked_add(&details.min_balance).ok_or(ArithmeticError::Overflow)?;
@@ -610,7 +679,10 @@ impl<T: Config<I>, I: 'static> Pallet<T, I> {
 		is_sufficient: bool,
 		min_balance: T::Balance,
 	) -> DispatchResult {
-		ensure!(!Asset::<T, I>::contains_key(id), Error::<T, I>::InUse);
+		ensure!(
+			id != T::NativeAssetId::get() && !Asset::<T, I>::contains_key(id),
+			Error::<T, I>::InUse
+		);
 		ensure!(!min_balance.is_zero(), Error::<T, I>::MinBalanceZero);
 
 		Asset::<T, I>::insert(
@@ -817,4 +889,41 @@ impl<T: Config<I>, I: 'static> Pallet<T, I> {
 			Ok(())
 		})
 	}
+
+	/// Set the metadata of an asset without taking a deposit. Any deposit already held is left
+	/// alone.
+	pub fn do_force_set_metadata(
+		id: T::AssetId,
+		name: Vec<u8>,
+		symbol: Vec<u8>,
+		decimals: u8,
+		is_frozen: bool,
+	) -> DispatchResult {
+		let bounded_name: BoundedVec<u8, T::StringLimit> =
+			name.clone().try_into().map_err(|_| Error::<T, I>::BadMetadata)?;
+
+		let bounded_symbol: BoundedVec<u8, T::StringLimit> =
+			symbol.clone().try_into().map_err(|_| Error::<T, I>::BadMetadata)?;
+
+		ensure!(Asset::<T, I>::contains_key(id), Error::<T, I>::Unknown);
+		Metadata::<T, I>::try_mutate_exists(id, |metadata| {
+			let deposit = metadata.take().map_or(Zero::zero(), |m| m.deposit);
+			*metadata = Some(AssetMetadata {
+				deposit,
+				name: bounded_name,
+				symbol: bounded_symbol,
+				decimals,
+				is_frozen,
+			});
+
+			Self::deposit_event(Event::MetadataSet {
+				asset_id: id,
+				name,
+				symbol,
+				decimals,
+				is_frozen,
+			});
+			Ok(())
+		})
+	}
 }
@@ -136,6 +136,11 @@ pub use extra_mutator::*;
 mod functions;
 mod impl_fungibles;
 mod impl_stored_map;
+mod lockable;
+pub use lockable::*;
+mod airdrop;
+mod native_adapter;
+pub use native_adapter::*;
 mod types;
 pub use types::*;
 
@@ -156,8 +161,8 @@ use frame_support::{
 	pallet_prelude::DispatchResultWithPostInfo,
 	traits::{
 		tokens::{fungibles, DepositConsequence, WithdrawConsequence},
-		BalanceStatus::Reserved,
-		Currency, ReservableCurrency, StoredMap,
+		BalanceStatus::{self, Reserved},
+		Currency, LockIdentifier, ReservableCurrency, StoredMap,
 	},
 };
 use frame_system::Config as SystemConfig;
@@ -169,7 +174,7 @@ pub use weights::WeightInfo;
 #[frame_support::pallet]
 pub mod pallet {
 	use super::*;
-	use frame_support::pallet_prelude::*;
+	use frame_support::{pallet_prelude::*, transactional};
 	use frame_system::pallet_prelude::*;
 
 	#[pallet::pallet]
@@ -188,6 +193,11 @@ pub mod pallet {
 		/// Identifier for the class of asset.
 		type AssetId: AssetIdTrait;
 
+		/// The asset id `NativeAssetAdapter` presents the native currency under. No asset can be
+		/// created with this id.
+		#[pallet::constant]
+		type NativeAssetId: Get<Self::AssetId>;
+
 		/// The currency mechanism.
 		type Currency: ReservableCurrency<Self::AccountId>;
 
@@ -225,9 +235,36 @@ pub mod pallet {
 		/// respected in all permissionless operations.
 		type Freezer: FrozenBalance<Self::AssetId, Self::AccountId, Self::Balance>;
 
+		/// The weight of one call to the `Freezer`, charged on top of the benchmarked weight of
+		/// each call that consults it, once per account.
+		type FreezerWeight: Get<Weight>;
+
 		/// Additional data to be stored with an account's asset balance.
 		type Extra: Member + Parameter + Default + MaxEncodedLen;
 
+		/// The maximum number of locks that should exist on an account's asset balance.
+		#[pallet::constant]
+		type MaxLocks: Get<u32>;
+
+		/// The maximum number of named reserves that can exist on an account's asset balance.
+		#[pallet::constant]
+		type MaxReserves: Get<u32>;
+
+		/// The id type for named reserves.
+		type ReserveIdentifier: Parameter + Member + MaxEncodedLen + Ord + Copy;
+
+		/// The maximum number of items in a `batch_transfer` or `batch_mint`.
+		#[pallet::constant]
+		type MaxBatchSize: Get<u32>;
+
+		/// The named reserve holding the balances airdrops are funded with.
+		#[pallet::constant]
+		type AirdropReserveId: Get<Self::ReserveIdentifier>;
+
+		/// The amount of funds that must be reserved for an airdrop to be maintained.
+		#[pallet::constant]
+		type AirdropDeposit: Get<DepositBalanceOf<Self, I>>;
+
 		/// Weight information for extrinsics in this pallet.
 		type WeightInfo: WeightInfo;
 	}
@@ -255,6 +292,50 @@ pub mod pallet {
 		ConstU32<300_000>,
 	>;
 
+	#[pallet::storage]
+	/// Any liquidity locks on the asset balance of an account.
+	pub(super) type Locks<T: Config<I>, I: 'static = ()> = StorageDoubleMap<
+		_,
+		Blake2_128Concat,
+		T::AssetId,
+		Blake2_128Concat,
+		T::AccountId,
+		BoundedVec<AssetLock<T::Balance>, T::MaxLocks>,
+		ValueQuery,
+	>;
+
+	#[pallet::storage]
+	/// Named reserves on the asset balance of an account.
+	pub(super) type Reserves<T: Config<I>, I: 'static = ()> = StorageDoubleMap<
+		_,
+		Blake2_128Concat,
+		T::AssetId,
+		Blake2_128Concat,
+		T::AccountId,
+		BoundedVec<AssetReserve<T::ReserveIdentifier, T::Balance>, T::MaxReserves>,
+		ValueQuery,
+	>;
+
+	#[pallet::storage]
+	/// The identifier of the next airdrop.
+	pub(super) type NextAirdropId<T: Config<I>, I: 'static = ()> =
+		StorageValue<_, AirdropId, ValueQuery>;
+
+	#[pallet::storage]
+	/// Details of an airdrop.
+	pub(super) type Airdrops<T: Config<I>, I: 'static = ()> = StorageMap<
+		_,
+		Blake2_128Concat,
+		AirdropId,
+		AirdropDetails<T::AssetId, T::AccountId, T::Balance, DepositBalanceOf<T, I>, T::Hash>,
+	>;
+
+	#[pallet::storage]
+	/// The accounts that have claimed from an airdrop. The claims are kept after the airdrop is
+	/// closed.
+	pub(super) type AirdropClaims<T: Config<I>, I: 'static = ()> =
+		StorageDoubleMap<_, Blake2_128Concat, AirdropId, Blake2_128Concat, T::AccountId, ()>;
+
 	#[pallet::storage]
 	/// Approved balance transfers. First balance is the amount approved for transfer. Second
 	/// is the amount of `T::Currency` reserved for storing this.
@@ -309,7 +390,10 @@ pub mod pallet {
 	impl<T: Config<I>, I: 'static> GenesisBuild<T, I> for GenesisConfig<T, I> {
 		fn build(&self) {
 			for (id, owner, is_sufficient, min_balance) in &self.assets {
-				assert!(!Asset::<T, I>::contains_key(id), "Asset id already in use");
+				assert!(
+					*id != T::NativeAssetId::get() && !Asset::<T, I>::contains_key(id),
+					"Asset id already in use"
+				);
 				assert!(!min_balance.is_zero(), "Min balance should not be zero");
 				Asset::<T, I>::insert(
 					id,
@@ -434,6 +518,31 @@ pub mod pallet {
 		},
 		/// An asset has had its attributes changed by the `Force` origin.
 		AssetStatusChanged { asset_id: T::AssetId },
+		/// Some balance was reserved (moved from free to reserved).
+		Reserved { asset_id: T::AssetId, who: T::AccountId, amount: T::Balance },
+		/// Some balance was unreserved (moved from reserved to free).
+		Unreserved { asset_id: T::AssetId, who: T::AccountId, amount: T::Balance },
+		/// Some balance was moved from the reserve of the first account to the second account.
+		/// Final argument indicates the destination balance type.
+		ReserveRepatriated {
+			asset_id: T::AssetId,
+			from: T::AccountId,
+			to: T::AccountId,
+			amount: T::Balance,
+			destination_status: BalanceStatus,
+		},
+		/// An airdrop was created and funded by its owner.
+		AirdropCreated {
+			airdrop_id: AirdropId,
+			asset_id: T::AssetId,
+			owner: T::AccountId,
+			amount: T::Balance,
+			merkle_root: T::Hash,
+		},
+		/// Some account claimed its part of an airdrop.
+		AirdropClaimed { airdrop_id: AirdropId, who: T::AccountId, amount: T::Balance },
+		/// An airdrop was closed and its unclaimed balance returned to its owner.
+		AirdropClosed { airdrop_id: AirdropId, refund: T::Balance },
 	}
 
 	#[pallet::error]
@@ -470,6 +579,20 @@ pub mod pallet {
 		NoDeposit,
 		/// The operation would result in funds being burned.
 		WouldBurn,
+		/// Number of locks on the account exceeds `MaxLocks`.
+		TooManyLocks,
+		/// Number of named reserves on the account exceeds `MaxReserves`.
+		TooManyReserves,
+		/// An airdrop must be funded with a non-zero amount.
+		ZeroAirdrop,
+		/// The given airdrop ID is unknown.
+		UnknownAirdrop,
+		/// The account has already claimed from the airdrop.
+		AlreadyClaimed,
+		/// The proof doesn't show the claim is part of the airdrop.
+		InvalidProof,
+		/// The airdrop has less left than the amount claimed.
+		AirdropExhausted,
 	}
 
 	#[pallet::call]
@@ -503,7 +626,10 @@ pub mod pallet {
 			let owner = ensure_signed(origin)?;
 			let admin = T::Lookup::lookup(admin)?;
 
-			ensure!(!Asset::<T, I>::contains_key(id), Error::<T, I>::InUse);
+			ensure!(
+				id != T::NativeAssetId::get() && !Asset::<T, I>::contains_key(id),
+				Error::<T, I>::InUse
+			);
 			ensure!(!min_balance.is_zero(), Error::<T, I>::MinBalanceZero);
 
 			let deposit = T::AssetDeposit::get();
@@ -615,7 +741,7 @@ pub mod pallet {
 		///
 		/// Weight: `O(1)`
 		/// Modes: Pre-existing balance of `beneficiary`; Account pre-existence of `beneficiary`.
-		#[pallet::weight(T::WeightInfo::mint())]
+		#[pallet::weight(T::WeightInfo::mint().saturating_add(T::FreezerWeight::get()))]
 		pub fn mint(
 			origin: OriginFor<T>,
 			#[pallet::compact] id: T::AssetId,
@@ -643,7 +769,7 @@ pub mod pallet {
 		///
 		/// Weight: `O(1)`
 		/// Modes: Post-existence of `who`; Pre & post Zombie-status of `who`.
-		#[pallet::weight(T::WeightInfo::burn())]
+		#[pallet::weight(T::WeightInfo::burn().saturating_add(T::FreezerWeight::get()))]
 		pub fn burn(
 			origin: OriginFor<T>,
 			#[pallet::compact] id: T::AssetId,
@@ -676,7 +802,9 @@ pub mod pallet {
 		/// Weight: `O(1)`
 		/// Modes: Pre-existence of `target`; Post-existence of sender; Account pre-existence of
 		/// `target`.
-		#[pallet::weight(T::WeightInfo::transfer())]
+		#[pallet::weight(
+			T::WeightInfo::transfer().saturating_add(T::FreezerWeight::get().saturating_mul(2))
+		)]
 		pub fn transfer(
 			origin: OriginFor<T>,
 			#[pallet::compact] id: T::AssetId,
@@ -708,7 +836,9 @@ pub mod pallet {
 		/// Weight: `O(1)`
 		/// Modes: Pre-existence of `target`; Post-existence of sender; Account pre-existence of
 		/// `target`.
-		#[pallet::weight(T::WeightInfo::transfer_keep_alive())]
+		#[pallet::weight(
+			T::WeightInfo::transfer_keep_alive().saturating_add(T::FreezerWeight::get().saturating_mul(2))
+		)]
 		pub fn transfer_keep_alive(
 			origin: OriginFor<T>,
 			#[pallet::compact] id: T::AssetId,
@@ -741,7 +871,9 @@ pub mod pallet {
 		/// Weight: `O(1)`
 		/// Modes: Pre-existence of `dest`; Post-existence of `source`; Account pre-existence of
 		/// `dest`.
-		#[pallet::weight(T::WeightInfo::force_transfer())]
+		#[pallet::weight(
+			T::WeightInfo::force_transfer().saturating_add(T::FreezerWeight::get().saturating_mul(2))
+		)]
 		pub fn force_transfer(
 			origin: OriginFor<T>,
 			#[pallet::compact] id: T::AssetId,
@@ -1031,33 +1163,7 @@ pub mod pallet {
 			is_frozen: bool,
 		) -> DispatchResult {
 			T::ForceOrigin::ensure_origin(origin)?;
-
-			let bounded_name: BoundedVec<u8, T::StringLimit> =
-				name.clone().try_into().map_err(|_| Error::<T, I>::BadMetadata)?;
-
-			let bounded_symbol: BoundedVec<u8, T::StringLimit> =
-				symbol.clone().try_into().map_err(|_| Error::<T, I>::BadMetadata)?;
-
-			ensure!(Asset::<T, I>::contains_key(id), Error::<T, I>::Unknown);
-			Metadata::<T, I>::try_mutate_exists(id, |metadata| {
-				let deposit = metadata.take().map_or(Zero::zero(), |m| m.deposit);
-				*metadata = Some(AssetMetadata {
-					deposit,
-					name: bounded_name,
-					symbol: bounded_symbol,
-					decimals,
-					is_frozen,
-				});
-
-				Self::deposit_event(Event::MetadataSet {
-					asset_id: id,
-					name,
-					symbol,
-					decimals,
-					is_frozen,
-				});
-				Ok(())
-			})
+			Self::do_force_set_metadata(id, name, symbol, decimals, is_frozen)
 		}
 
 		/// Clear the metadata for an asset.
@@ -1264,7 +1370,9 @@ pub mod pallet {
 		/// Emits `TransferredApproved` on success.
 		///
 		/// Weight: `O(1)`
-		#[pallet::weight(T::WeightInfo::transfer_approved())]
+		#[pallet::weight(
+			T::WeightInfo::transfer_approved().saturating_add(T::FreezerWeight::get().saturating_mul(2))
+		)]
 		pub fn transfer_approved(
 			origin: OriginFor<T>,
 			#[pallet::compact] id: T::AssetId,
@@ -1308,5 +1416,137 @@ pub mod pallet {
 		) -> DispatchResult {
 			Self::do_refund(id, ensure_signed(origin)?, allow_burn)
 		}
+
+		/// Move some assets from the sender account to each of several accounts, all or none.
+		///
+		/// Origin must be Signed.
+		///
+		/// - `id`: The identifier of the asset to have some amount transferred.
+		/// - `targets`: The accounts to be credited, with the amount each is credited. At most
+		/// `MaxBatchSize`.
+		///
+		/// Emits `Transferred` for each target when successful.
+		///
+		/// Weight: `O(T)` where `T` is the number of targets.
+		#[pallet::weight(
+			T::WeightInfo::transfer()
+				.saturating_add(T::FreezerWeight::get().saturating_mul(2))
+				.saturating_mul(targets.len() as Weight)
+		)]
+		#[transactional]
+		pub fn batch_transfer(
+			origin: OriginFor<T>,
+			#[pallet::compact] id: T::AssetId,
+			targets: BoundedVec<(<T::Lookup as StaticLookup>::Source, T::Balance), T::MaxBatchSize>,
+		) -> DispatchResult {
+			let origin = ensure_signed(origin)?;
+
+			let f = TransferFlags { keep_alive: false, best_effort: false, burn_dust: false };
+			for (target, amount) in targets {
+				let dest = T::Lookup::lookup(target)?;
+				Self::do_transfer(id, &origin, &dest, amount, None, f)?;
+			}
+			Ok(())
+		}
+
+		/// Mint assets of a particular class to each of several accounts, all or none.
+		///
+		/// The origin must be Signed and the sender must be the Issuer of the asset `id`.
+		///
+		/// - `id`: The identifier of the asset to have some amount minted.
+		/// - `beneficiaries`: The accounts to be credited, with the amount each is credited. At
+		/// most `MaxBatchSize`.
+		///
+		/// Emits `Issued` for each beneficiary when successful.
+		///
+		/// Weight: `O(B)` where `B` is the number of beneficiaries.
+		#[pallet::weight(
+			T::WeightInfo::mint()
+				.saturating_add(T::FreezerWeight::get())
+				.saturating_mul(beneficiaries.len() as Weight)
+		)]
+		#[transactional]
+		pub fn batch_mint(
+			origin: OriginFor<T>,
+			#[pallet::compact] id: T::AssetId,
+			beneficiaries: BoundedVec<
+				(<T::Lookup as StaticLookup>::Source, T::Balance),
+				T::MaxBatchSize,
+			>,
+		) -> DispatchResult {
+			let origin = ensure_signed(origin)?;
+
+			for (beneficiary, amount) in beneficiaries {
+				let beneficiary = T::Lookup::lookup(beneficiary)?;
+				Self::do_mint(id, &beneficiary, amount, Some(origin.clone()))?;
+			}
+			Ok(())
+		}
+
+		/// Create an airdrop of an asset, funded with `amount` of the sender's balance.
+		///
+		/// Origin must be Signed. `amount` is reserved from the sender's balance until it is
+		/// claimed or the airdrop is closed, and `AirdropDeposit` from the sender's native balance
+		/// until the airdrop is closed.
+		///
+		/// - `id`: The identifier of the asset to be dropped.
+		/// - `merkle_root`: The root of the Merkle tree of the claims. Its leaves are the hashes
+		/// of the encoded `(AIRDROP_LEAF_PREFIX, who, amount)`, and each node is the hash of the
+		/// encoded `(AIRDROP_NODE_PREFIX, left, right)` of its children, the smaller first.
+		/// - `amount`: The amount of the asset to fund the airdrop with. Must be non-zero.
+		///
+		/// Emits `AirdropCreated` event when successful.
+		///
+		/// Weight: `O(1)`
+		#[pallet::weight(Pallet::<T, I>::create_airdrop_weight())]
+		#[transactional]
+		pub fn create_airdrop(
+			origin: OriginFor<T>,
+			#[pallet::compact] id: T::AssetId,
+			merkle_root: T::Hash,
+			#[pallet::compact] amount: T::Balance,
+		) -> DispatchResult {
+			let origin = ensure_signed(origin)?;
+			Self::do_create_airdrop(id, origin, merkle_root, amount)
+		}
+
+		/// Claim the sender's part of an airdrop.
+		///
+		/// Origin must be Signed, and may claim from each airdrop once.
+		///
+		/// - `airdrop_id`: The identifier of the airdrop.
+		/// - `amount`: The amount of the claim.
+		/// - `proof`: The siblings of the nodes on the path from the leaf of the claim to the root
+		/// of the airdrop, from the leaf up.
+		///
+		/// Emits `AirdropClaimed` event when successful.
+		///
+		/// Weight: `O(P)` where `P` is the length of the proof.
+		#[pallet::weight(Pallet::<T, I>::claim_airdrop_weight(proof.len()))]
+		#[transactional]
+		pub fn claim_airdrop(
+			origin: OriginFor<T>,
+			airdrop_id: AirdropId,
+			#[pallet::compact] amount: T::Balance,
+			proof: Vec<T::Hash>,
+		) -> DispatchResult {
+			let origin = ensure_signed(origin)?;
+			Self::do_claim_airdrop(airdrop_id, origin, amount, proof)
+		}
+
+		/// Close an airdrop, returning its unclaimed balance and its deposit to its owner.
+		///
+		/// Origin must be Signed and the sender must be the owner of the airdrop.
+		///
+		/// - `airdrop_id`: The identifier of the airdrop.
+		///
+		/// Emits `AirdropClosed` event when successful.
+		///
+		/// Weight: `O(1)`
+		#[pallet::weight(Pallet::<T, I>::close_airdrop_weight())]
+		pub fn close_airdrop(origin: OriginFor<T>, airdrop_id: AirdropId) -> DispatchResult {
+			let origin = ensure_signed(origin)?;
+			Self::do_close_airdrop(airdrop_id, origin)
+		}
 	}
 }
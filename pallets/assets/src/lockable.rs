@@ -0,0 +1,343 @@
+//! Locks and named reserves on asset balances, as `LockableCurrency` and
+//! `NamedReservableCurrency` for a single currency.
+
+use super::*;
+use frame_support::{traits::Get, transactional};
+
+/// Assets whose balances can be locked. Locks don't stack: the balance that can't be moved is
+/// the largest of the locks on it.
+pub trait LockableAssets<AccountId>: fungibles::Inspect<AccountId> {
+	/// The maximum number of locks an account may have on one asset.
+	type MaxLocks: Get<u32>;
+
+	/// Create a new lock `id` on the `asset` balance of `who`, or replace the lock `id` it
+	/// already has. A lock of zero removes the lock.
+	fn set_lock(
+		id: LockIdentifier,
+		asset: Self::AssetId,
+		who: &AccountId,
+		amount: Self::Balance,
+	) -> DispatchResult;
+
+	/// Raise the lock `id` on the `asset` balance of `who` to at least `amount`, or create it.
+	fn extend_lock(
+		id: LockIdentifier,
+		asset: Self::AssetId,
+		who: &AccountId,
+		amount: Self::Balance,
+	) -> DispatchResult;
+
+	/// Remove the lock `id` on the `asset` balance of `who`.
+	fn remove_lock(id: LockIdentifier, asset: Self::AssetId, who: &AccountId);
+}
+
+/// Assets whose balances can be reserved under a name. The reserved balance stays in the
+/// balance of the account, but can only be moved through these functions.
+pub trait NamedReservableAssets<AccountId>: fungibles::Inspect<AccountId> {
+	/// An identifier for a reserve.
+	type ReserveIdentifier;
+
+	/// The `asset` balance of `who` reserved under `id`.
+	fn reserved_balance_named(
+		id: &Self::ReserveIdentifier,
+		asset: Self::AssetId,
+		who: &AccountId,
+	) -> Self::Balance;
+
+	/// Reserve `value` of the `asset` balance of `who` under `id`. Fails if the balance that can
+	/// be moved is less than `value`.
+	fn reserve_named(
+		id: &Self::ReserveIdentifier,
+		asset: Self::AssetId,
+		who: &AccountId,
+		value: Self::Balance,
+	) -> DispatchResult;
+
+	/// Unreserve up to `value` of the `asset` balance of `who` reserved under `id`. Returns the
+	/// part of `value` that wasn't unreserved.
+	fn unreserve_named(
+		id: &Self::ReserveIdentifier,
+		asset: Self::AssetId,
+		who: &AccountId,
+		value: Self::Balance,
+	) -> Self::Balance;
+
+	/// Burn up to `value` of the `asset` balance of `who` reserved under `id`. Returns the part
+	/// of `value` that wasn't burned.
+	fn slash_reserved_named(
+		id: &Self::ReserveIdentifier,
+		asset: Self::AssetId,
+		who: &AccountId,
+		value: Self::Balance,
+	) -> Self::Balance;
+
+	/// Move up to `value` of the `asset` balance of `slashed` reserved under `id` to
+	/// `beneficiary`, into its free balance or its reserve `id` depending on `status`. Returns
+	/// the part of `value` that wasn't moved.
+	fn repatriate_reserved_named(
+		id: &Self::ReserveIdentifier,
+		asset: Self::AssetId,
+		slashed: &AccountId,
+		beneficiary: &AccountId,
+		value: Self::Balance,
+		status: BalanceStatus,
+	) -> Result<Self::Balance, DispatchError>;
+}
+
+impl<T: Config<I>, I: 'static> Pallet<T, I> {
+	/// Store the `locks` of `who` on asset `id`, removing the entry if there are none.
+	fn update_locks(
+		id: T::AssetId,
+		who: &T::AccountId,
+		locks: BoundedVec<AssetLock<T::Balance>, T::MaxLocks>,
+	) {
+		if locks.is_empty() {
+			Locks::<T, I>::remove(id, who);
+		} else {
+			Locks::<T, I>::insert(id, who, locks);
+		}
+	}
+
+	/// Add `amount` to the reserve `reserve_id` of `who` on asset `id`, creating it if needed.
+	fn add_to_reserve(
+		reserve_id: &T::ReserveIdentifier,
+		id: T::AssetId,
+		who: &T::AccountId,
+		amount: T::Balance,
+	) -> DispatchResult {
+		Reserves::<T, I>::try_mutate(id, who, |reserves| -> DispatchResult {
+			match reserves.binary_search_by_key(reserve_id, |reserve| reserve.id) {
+				Ok(index) => {
+					reserves[index].amount.saturating_accrue(amount);
+				},
+				Err(index) => {
+					reserves
+						.try_insert(index, AssetReserve { id: *reserve_id, amount })
+						.map_err(|_| Error::<T, I>::TooManyReserves)?;
+				},
+			}
+			Ok(())
+		})
+	}
+
+	/// Take up to `amount` from the reserve `reserve_id` of `who` on asset `id`, removing the
+	/// reserve once it is empty. Returns the amount taken.
+	fn take_from_reserve(
+		reserve_id: &T::ReserveIdentifier,
+		id: T::AssetId,
+		who: &T::AccountId,
+		amount: T::Balance,
+	) -> T::Balance {
+		Reserves::<T, I>::mutate_exists(id, who, |maybe_reserves| {
+			let reserves = match maybe_reserves {
+				Some(reserves) => reserves,
+				None => return Zero::zero(),
+			};
+			let taken = match reserves.binary_search_by_key(reserve_id, |reserve| reserve.id) {
+				Ok(index) => {
+					let taken = reserves[index].amount.min(amount);
+					reserves[index].amount.saturating_reduce(taken);
+					if reserves[index].amount.is_zero() {
+						reserves.remove(index);
+					}
+					taken
+				},
+				Err(_) => Zero::zero(),
+			};
+			if reserves.is_empty() {
+				*maybe_reserves = None;
+			}
+			taken
+		})
+	}
+
+	/// Move up to `amount` of the reserve `reserve_id` of `slashed` on asset `id` to
+	/// `beneficiary`. Returns the amount moved.
+	///
+	/// Reserving leaves at least the minimum balance unreserved, so moving reserved balance never
+	/// kills the account.
+	#[transactional]
+	pub(super) fn do_repatriate_reserved_named(
+		reserve_id: &T::ReserveIdentifier,
+		id: T::AssetId,
+		slashed: &T::AccountId,
+		beneficiary: &T::AccountId,
+		amount: T::Balance,
+		status: BalanceStatus,
+	) -> Result<T::Balance, DispatchError> {
+		let details = Asset::<T, I>::get(id).ok_or(Error::<T, I>::Unknown)?;
+		ensure!(!details.is_frozen, Error::<T, I>::Frozen);
+
+		let actual = Self::take_from_reserve(reserve_id, id, slashed, amount);
+		if actual.is_zero() {
+			return Ok(actual)
+		}
+		Account::<T, I>::try_mutate(id, slashed, |maybe_account| -> DispatchResult {
+			let account = maybe_account.as_mut().ok_or(Error::<T, I>::NoAccount)?;
+			ensure!(!account.is_frozen, Error::<T, I>::Frozen);
+			account.balance = account.balance.saturating_sub(actual);
+			Ok(())
+		})?;
+		Self::increase_balance(id, beneficiary, actual, |_| Ok(()))?;
+		if status == BalanceStatus::Reserved {
+			Self::add_to_reserve(reserve_id, id, beneficiary, actual)?;
+		}
+
+		Self::deposit_event(Event::ReserveRepatriated {
+			asset_id: id,
+			from: slashed.clone(),
+			to: beneficiary.clone(),
+			amount: actual,
+			destination_status: status,
+		});
+		Ok(actual)
+	}
+}
+
+impl<T: Config<I>, I: 'static> LockableAssets<<T as SystemConfig>::AccountId> for Pallet<T, I> {
+	type MaxLocks = T::MaxLocks;
+
+	fn set_lock(
+		id: LockIdentifier,
+		asset: T::AssetId,
+		who: &<T as SystemConfig>::AccountId,
+		amount: T::Balance,
+	) -> DispatchResult {
+		if amount.is_zero() {
+			Self::remove_lock(id, asset, who);
+			return Ok(())
+		}
+		ensure!(Account::<T, I>::contains_key(asset, who), Error::<T, I>::NoAccount);
+
+		let mut locks = Locks::<T, I>::get(asset, who);
+		match locks.iter_mut().find(|lock| lock.id == id) {
+			Some(lock) => lock.amount = amount,
+			None => locks
+				.try_push(AssetLock { id, amount })
+				.map_err(|_| Error::<T, I>::TooManyLocks)?,
+		}
+		Self::update_locks(asset, who, locks);
+		Ok(())
+	}
+
+	fn extend_lock(
+		id: LockIdentifier,
+		asset: T::AssetId,
+		who: &<T as SystemConfig>::AccountId,
+		amount: T::Balance,
+	) -> DispatchResult {
+		let locked = Locks::<T, I>::get(asset, who)
+			.iter()
+			.find(|lock| lock.id == id)
+			.map_or_else(Zero::zero, |lock| lock.amount);
+		Self::set_lock(id, asset, who, locked.max(amount))
+	}
+
+	fn remove_lock(id: LockIdentifier, asset: T::AssetId, who: &<T as SystemConfig>::AccountId) {
+		let mut locks = Locks::<T, I>::get(asset, who);
+		locks.retain(|lock| lock.id != id);
+		Self::update_locks(asset, who, locks);
+	}
+}
+
+impl<T: Config<I>, I: 'static> NamedReservableAssets<<T as SystemConfig>::AccountId>
+	for Pallet<T, I>
+{
+	type ReserveIdentifier = T::ReserveIdentifier;
+
+	fn reserved_balance_named(
+		id: &Self::ReserveIdentifier,
+		asset: T::AssetId,
+		who: &<T as SystemConfig>::AccountId,
+	) -> T::Balance {
+		Reserves::<T, I>::get(asset, who)
+			.iter()
+			.find(|reserve| &reserve.id == id)
+			.map_or_else(Zero::zero, |reserve| reserve.amount)
+	}
+
+	fn reserve_named(
+		id: &Self::ReserveIdentifier,
+		asset: T::AssetId,
+		who: &<T as SystemConfig>::AccountId,
+		value: T::Balance,
+	) -> DispatchResult {
+		if value.is_zero() {
+			return Ok(())
+		}
+		ensure!(Self::reducible_balance(asset, who, true)? >= value, Error::<T, I>::BalanceLow);
+		Self::add_to_reserve(id, asset, who, value)?;
+		Self::deposit_event(Event::Reserved { asset_id: asset, who: who.clone(), amount: value });
+		Ok(())
+	}
+
+	fn unreserve_named(
+		id: &Self::ReserveIdentifier,
+		asset: T::AssetId,
+		who: &<T as SystemConfig>::AccountId,
+		value: T::Balance,
+	) -> T::Balance {
+		if value.is_zero() {
+			return value
+		}
+		let actual = Self::take_from_reserve(id, asset, who, value);
+		if !actual.is_zero() {
+			Self::deposit_event(Event::Unreserved {
+				asset_id: asset,
+				who: who.clone(),
+				amount: actual,
+			});
+		}
+		value.saturating_sub(actual)
+	}
+
+	fn slash_reserved_named(
+		id: &Self::ReserveIdentifier,
+		asset: T::AssetId,
+		who: &<T as SystemConfig>::AccountId,
+		value: T::Balance,
+	) -> T::Balance {
+		if value.is_zero() {
+			return value
+		}
+		let actual = Self::take_from_reserve(id, asset, who, value);
+		if actual.is_zero() {
+			return value
+		}
+		Account::<T, I>::mutate(asset, who, |maybe_account| {
+			if let Some(account) = maybe_account {
+				account.balance = account.balance.saturating_sub(actual);
+			}
+		});
+		Asset::<T, I>::mutate(asset, |maybe_details| {
+			if let Some(details) = maybe_details {
+				details.supply = details.supply.saturating_sub(actual);
+			}
+		});
+		Self::deposit_event(Event::Burned { asset_id: asset, owner: who.clone(), balance: actual });
+		value.saturating_sub(actual)
+	}
+
+	fn repatriate_reserved_named(
+		id: &Self::ReserveIdentifier,
+		asset: T::AssetId,
+		slashed: &<T as SystemConfig>::AccountId,
+		beneficiary: &<T as SystemConfig>::AccountId,
+		value: T::Balance,
+		status: BalanceStatus,
+	) -> Result<T::Balance, DispatchError> {
+		if value.is_zero() {
+			return Ok(value)
+		}
+		if slashed == beneficiary {
+			let reserved = Self::reserved_balance_named(id, asset, slashed);
+			return match status {
+				BalanceStatus::Free => Ok(Self::unreserve_named(id, asset, slashed, value)),
+				BalanceStatus::Reserved => Ok(value.saturating_sub(reserved)),
+			}
+		}
+		let actual =
+			Self::do_repatriate_reserved_named(id, asset, slashed, beneficiary, value, status)?;
+		Ok(value.saturating_sub(actual))
+	}
+}
@@ -21,7 +21,7 @@ use super::*;
 use crate as pallet_assets;
 
 use frame_support::{
-	construct_runtime,
+	construct_runtime, parameter_types,
 	traits::{ConstU32, ConstU64, GenesisBuild},
 };
 use sp_core::H256;
@@ -84,10 +84,15 @@ impl pallet_balances::Config for Test {
 	type ReserveIdentifier = [u8; 8];
 }
 
+parameter_types! {
+	pub const AirdropReserveId: [u8; 8] = *b"airdrop ";
+}
+
 impl Config for Test {
 	type Event = Event;
 	type Balance = u64;
 	type AssetId = u32;
+	type NativeAssetId = ConstU32<{ u32::MAX }>;
 	type Currency = Balances;
 	type ForceOrigin = frame_system::EnsureRoot<u64>;
 	type AssetDeposit = ConstU64<1>;
@@ -97,8 +102,15 @@ impl Config for Test {
 	type ApprovalDeposit = ConstU64<1>;
 	type StringLimit = ConstU32<50>;
 	type Freezer = TestFreezer;
+	type FreezerWeight = ();
 	type WeightInfo = ();
 	type Extra = ();
+	type MaxLocks = ConstU32<2>;
+	type MaxReserves = ConstU32<2>;
+	type ReserveIdentifier = [u8; 8];
+	type MaxBatchSize = ConstU32<3>;
+	type AirdropReserveId = AirdropReserveId;
+	type AirdropDeposit = ConstU64<5>;
 }
 
 use std::{cell::RefCell, collections::HashMap};
@@ -110,6 +122,7 @@ pub(crate) enum Hook {
 thread_local! {
 	static FROZEN: RefCell<HashMap<(u32, u64), u64>> = RefCell::new(Default::default());
 	static HOOKS: RefCell<Vec<Hook>> = RefCell::new(Default::default());
+	static BLOCKED: RefCell<Vec<(u32, u64)>> = RefCell::new(Default::default());
 }
 
 pub struct TestFreezer;
@@ -118,6 +131,10 @@ impl FrozenBalance<u32, u64, u64> for TestFreezer {
 		FROZEN.with(|f| f.borrow().get(&(asset, who.clone())).cloned())
 	}
 
+	fn can_receive(asset: u32, who: &u64) -> bool {
+		BLOCKED.with(|b| !b.borrow().contains(&(asset, who.clone())))
+	}
+
 	fn died(asset: u32, who: &u64) {
 		HOOKS.with(|h| h.borrow_mut().push(Hook::Died(asset, who.clone())));
 	}
@@ -129,6 +146,9 @@ pub(crate) fn set_frozen_balance(asset: u32, who: u64, amount: u64) {
 pub(crate) fn clear_frozen_balance(asset: u32, who: u64) {
 	FROZEN.with(|f| f.borrow_mut().remove(&(asset, who)));
 }
+pub(crate) fn set_blocked(asset: u32, who: u64) {
+	BLOCKED.with(|b| b.borrow_mut().push((asset, who)));
+}
 pub(crate) fn hooks() -> Vec<Hook> {
 	HOOKS.with(|h| h.borrow().clone())
 }
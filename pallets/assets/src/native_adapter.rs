@@ -0,0 +1,136 @@
+//! A fungibles adapter presenting the native currency as an asset.
+
+use super::*;
+use frame_support::traits::{tokens::fungible, Get};
+use sp_std::marker::PhantomData;
+
+/// The assets of this pallet together with the native currency `Native`, which is presented as
+/// the asset `T::NativeAssetId`. Every other asset id is passed through to the pallet.
+pub struct NativeAssetAdapter<T, Native, I = ()>(PhantomData<(T, Native, I)>);
+
+impl<T, Native, I> fungibles::Inspect<<T as SystemConfig>::AccountId>
+	for NativeAssetAdapter<T, Native, I>
+where
+	T: Config<I>,
+	Native: fungible::Inspect<<T as SystemConfig>::AccountId, Balance = T::Balance>,
+	I: 'static,
+{
+	type AssetId = T::AssetId;
+	type Balance = T::Balance;
+
+	fn total_issuance(asset: Self::AssetId) -> Self::Balance {
+		if asset == T::NativeAssetId::get() {
+			Native::total_issuance()
+		} else {
+			<Pallet<T, I> as fungibles::Inspect<_>>::total_issuance(asset)
+		}
+	}
+
+	fn minimum_balance(asset: Self::AssetId) -> Self::Balance {
+		if asset == T::NativeAssetId::get() {
+			Native::minimum_balance()
+		} else {
+			<Pallet<T, I> as fungibles::Inspect<_>>::minimum_balance(asset)
+		}
+	}
+
+	fn balance(asset: Self::AssetId, who: &<T as SystemConfig>::AccountId) -> Self::Balance {
+		if asset == T::NativeAssetId::get() {
+			Native::balance(who)
+		} else {
+			<Pallet<T, I> as fungibles::Inspect<_>>::balance(asset, who)
+		}
+	}
+
+	fn reducible_balance(
+		asset: Self::AssetId,
+		who: &<T as SystemConfig>::AccountId,
+		keep_alive: bool,
+	) -> Self::Balance {
+		if asset == T::NativeAssetId::get() {
+			Native::reducible_balance(who, keep_alive)
+		} else {
+			<Pallet<T, I> as fungibles::Inspect<_>>::reducible_balance(asset, who, keep_alive)
+		}
+	}
+
+	fn can_deposit(
+		asset: Self::AssetId,
+		who: &<T as SystemConfig>::AccountId,
+		amount: Self::Balance,
+	) -> DepositConsequence {
+		if asset == T::NativeAssetId::get() {
+			Native::can_deposit(who, amount)
+		} else {
+			<Pallet<T, I> as fungibles::Inspect<_>>::can_deposit(asset, who, amount)
+		}
+	}
+
+	fn can_withdraw(
+		asset: Self::AssetId,
+		who: &<T as SystemConfig>::AccountId,
+		amount: Self::Balance,
+	) -> WithdrawConsequence<Self::Balance> {
+		if asset == T::NativeAssetId::get() {
+			Native::can_withdraw(who, amount)
+		} else {
+			<Pallet<T, I> as fungibles::Inspect<_>>::can_withdraw(asset, who, amount)
+		}
+	}
+}
+
+impl<T, Native, I> fungibles::Mutate<<T as SystemConfig>::AccountId>
+	for NativeAssetAdapter<T, Native, I>
+where
+	T: Config<I>,
+	Native: fungible::Mutate<<T as SystemConfig>::AccountId, Balance = T::Balance>,
+	I: 'static,
+{
+	fn mint_into(
+		asset: Self::AssetId,
+		who: &<T as SystemConfig>::AccountId,
+		amount: Self::Balance,
+	) -> DispatchResult {
+		if asset == T::NativeAssetId::get() {
+			Native::mint_into(who, amount)
+		} else {
+			<Pallet<T, I> as fungibles::Mutate<_>>::mint_into(asset, who, amount)
+		}
+	}
+
+	fn burn_from(
+		asset: Self::AssetId,
+		who: &<T as SystemConfig>::AccountId,
+		amount: Self::Balance,
+	) -> Result<Self::Balance, DispatchError> {
+		if asset == T::NativeAssetId::get() {
+			Native::burn_from(who, amount)
+		} else {
+			<Pallet<T, I> as fungibles::Mutate<_>>::burn_from(asset, who, amount)
+		}
+	}
+}
+
+impl<T, Native, I> fungibles::Transfer<<T as SystemConfig>::AccountId>
+	for NativeAssetAdapter<T, Native, I>
+where
+	T: Config<I>,
+	Native: fungible::Transfer<<T as SystemConfig>::AccountId, Balance = T::Balance>,
+	I: 'static,
+{
+	fn transfer(
+		asset: Self::AssetId,
+		source: &<T as SystemConfig>::AccountId,
+		dest: &<T as SystemConfig>::AccountId,
+		amount: Self::Balance,
+		keep_alive: bool,
+	) -> Result<Self::Balance, DispatchError> {
+		if asset == T::NativeAssetId::get() {
+			Native::transfer(source, dest, amount, keep_alive)
+		} else {
+			<Pallet<T, I> as fungibles::Transfer<_>>::transfer(
+				asset, source, dest, amount, keep_alive,
+			)
+		}
+	}
+}
@@ -19,7 +19,12 @@
 
 use super::*;
 use crate::{mock::*, Error};
-use frame_support::{assert_noop, assert_ok, traits::Currency};
+use codec::{Decode, Encode};
+use frame_support::{
+	assert_noop, assert_ok,
+	traits::{Currency, Get},
+	BoundedVec,
+};
 use pallet_balances::Error as BalancesError;
 use sp_runtime::{traits::ConvertInto, TokenError};
 
@@ -721,6 +726,179 @@ fn freezer_should_work() {
 	});
 }
 
+#[test]
+fn blocked_account_cannot_receive() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Assets::force_create(Origin::root(), 0, 1, true, 1));
+		assert_ok!(Assets::mint(Origin::signed(1), 0, 1, 100));
+		assert_ok!(Assets::transfer(Origin::signed(1), 0, 2, 10));
+
+		set_blocked(0, 2);
+		assert_noop!(Assets::transfer(Origin::signed(1), 0, 2, 10), TokenError::CannotCreate);
+		assert_noop!(Assets::mint(Origin::signed(1), 0, 2, 10), TokenError::CannotCreate);
+		// a blocked account can still send what it holds
+		assert_ok!(Assets::transfer(Origin::signed(2), 0, 1, 10));
+	});
+}
+
+#[test]
+fn locks_should_work() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Assets::force_create(Origin::root(), 0, 1, true, 10));
+		assert_ok!(Assets::mint(Origin::signed(1), 0, 1, 100));
+
+		// locks don't stack.
+		assert_ok!(Assets::set_lock(*b"lock_one", 0, &1, 50));
+		assert_ok!(Assets::set_lock(*b"lock_two", 0, &1, 30));
+		assert_noop!(Assets::transfer(Origin::signed(1), 0, 2, 41), Error::<Test>::BalanceLow);
+		assert_ok!(Assets::transfer(Origin::signed(1), 0, 2, 40));
+
+		// extending never lowers a lock.
+		assert_ok!(Assets::extend_lock(*b"lock_two", 0, &1, 20));
+		assert_noop!(Assets::set_lock(*b"lock_thr", 0, &1, 10), Error::<Test>::TooManyLocks);
+		assert_noop!(Assets::set_lock(*b"lock_one", 0, &3, 10), Error::<Test>::NoAccount);
+
+		Assets::remove_lock(*b"lock_one", 0, &1);
+		assert_noop!(Assets::transfer(Origin::signed(1), 0, 2, 21), Error::<Test>::BalanceLow);
+		assert_ok!(Assets::transfer(Origin::signed(1), 0, 2, 20));
+		assert_eq!(Assets::balance(0, 1), 40);
+	});
+}
+
+#[test]
+fn named_reserves_should_work() {
+	new_test_ext().execute_with(|| {
+		let id = *b"reserve ";
+		assert_ok!(Assets::force_create(Origin::root(), 0, 1, true, 10));
+		assert_ok!(Assets::mint(Origin::signed(1), 0, 1, 100));
+		assert_ok!(Assets::mint(Origin::signed(1), 0, 2, 10));
+
+		assert_ok!(Assets::reserve_named(&id, 0, &1, 50));
+		assert_eq!(Assets::reserved_balance_named(&id, 0, &1), 50);
+		assert_eq!(Assets::balance(0, 1), 100);
+		assert_noop!(Assets::transfer(Origin::signed(1), 0, 2, 41), Error::<Test>::BalanceLow);
+
+		// locks apply to the balance that isn't reserved.
+		assert_ok!(Assets::set_lock(*b"lock_one", 0, &1, 30));
+		assert_noop!(Assets::transfer(Origin::signed(1), 0, 2, 11), Error::<Test>::BalanceLow);
+		assert_ok!(Assets::transfer(Origin::signed(1), 0, 2, 10));
+		assert_noop!(Assets::reserve_named(&id, 0, &1, 1), Error::<Test>::BalanceLow);
+
+		// the reserve can still be moved despite the lock.
+		assert_eq!(
+			Assets::repatriate_reserved_named(&id, 0, &1, &2, 20, BalanceStatus::Free),
+			Ok(0)
+		);
+		assert_eq!(Assets::balance(0, 1), 70);
+		assert_eq!(Assets::balance(0, 2), 40);
+		assert_eq!(Assets::reserved_balance(0, 1), 30);
+
+		assert_eq!(Assets::slash_reserved_named(&id, 0, &1, 40), 10);
+		assert_eq!(Assets::balance(0, 1), 40);
+		assert_eq!(Assets::reserved_balance(0, 1), 0);
+		assert_eq!(Assets::total_supply(0), 80);
+		assert_eq!(Assets::unreserve_named(&id, 0, &1, 10), 10);
+	});
+}
+
+type Batch = BoundedVec<(u64, u64), <Test as Config>::MaxBatchSize>;
+
+fn batch(items: Vec<(u64, u64)>) -> Batch {
+	items.try_into().unwrap()
+}
+
+#[test]
+fn batch_transfer_and_mint_should_work() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Assets::force_create(Origin::root(), 0, 1, true, 1));
+		assert_ok!(Assets::batch_mint(Origin::signed(1), 0, batch(vec![(1, 100), (2, 10)])));
+		assert_eq!(Assets::balance(0, 1), 100);
+		assert_eq!(Assets::balance(0, 2), 10);
+		assert_noop!(
+			Assets::batch_mint(Origin::signed(2), 0, batch(vec![(2, 10)])),
+			Error::<Test>::NoPermission
+		);
+
+		assert_ok!(Assets::batch_transfer(Origin::signed(1), 0, batch(vec![(2, 20), (3, 30)])));
+		assert_eq!(Assets::balance(0, 1), 50);
+		assert_eq!(Assets::balance(0, 2), 30);
+		assert_eq!(Assets::balance(0, 3), 30);
+
+		// a failing item reverts the whole batch.
+		assert_noop!(
+			Assets::batch_transfer(Origin::signed(1), 0, batch(vec![(2, 20), (3, 40)])),
+			Error::<Test>::BalanceLow
+		);
+
+		// a batch of more than `MaxBatchSize` items doesn't decode.
+		let items = vec![(2u64, 1u64), (3, 1), (4, 1), (5, 1)].encode();
+		assert!(Batch::decode(&mut &items[..]).is_err());
+	});
+}
+
+#[test]
+fn airdrops_should_work() {
+	new_test_ext().execute_with(|| {
+		let reserve_id = AirdropReserveId::get();
+		assert_ok!(Assets::force_create(Origin::root(), 0, 1, true, 1));
+		assert_ok!(Assets::mint(Origin::signed(1), 0, 1, 100));
+		Balances::make_free_balance_be(&1, 100);
+
+		let leaf_2 = Assets::airdrop_leaf(&2, 30);
+		let leaf_3 = Assets::airdrop_leaf(&3, 20);
+		let root = Assets::airdrop_root(leaf_2, &[leaf_3]);
+		assert_eq!(Assets::airdrop_root(leaf_3, &[leaf_2]), root);
+
+		assert_noop!(
+			Assets::create_airdrop(Origin::signed(1), 0, root, 0),
+			Error::<Test>::ZeroAirdrop
+		);
+		assert_ok!(Assets::create_airdrop(Origin::signed(1), 0, root, 60));
+		assert_eq!(Assets::reserved_balance_named(&reserve_id, 0, &1), 60);
+		assert_eq!(Balances::reserved_balance(&1), 5);
+		assert_noop!(Assets::transfer(Origin::signed(1), 0, 2, 41), Error::<Test>::BalanceLow);
+
+		assert_ok!(Assets::claim_airdrop(Origin::signed(2), 0, 30, vec![leaf_3]));
+		assert_eq!(Assets::balance(0, 1), 70);
+		assert_eq!(Assets::balance(0, 2), 30);
+		assert_eq!(Assets::reserved_balance_named(&reserve_id, 0, &1), 30);
+		assert_noop!(
+			Assets::claim_airdrop(Origin::signed(2), 0, 30, vec![leaf_3]),
+			Error::<Test>::AlreadyClaimed
+		);
+		assert_noop!(
+			Assets::claim_airdrop(Origin::signed(3), 0, 25, vec![leaf_2]),
+			Error::<Test>::InvalidProof
+		);
+		assert_ok!(Assets::claim_airdrop(Origin::signed(3), 0, 20, vec![leaf_2]));
+		assert_eq!(Assets::balance(0, 3), 20);
+
+		assert_noop!(Assets::close_airdrop(Origin::signed(2), 0), Error::<Test>::NoPermission);
+		assert_ok!(Assets::close_airdrop(Origin::signed(1), 0));
+		assert_eq!(Assets::reserved_balance(0, 1), 0);
+		assert_eq!(Balances::reserved_balance(&1), 0);
+		assert_eq!(Assets::balance(0, 1), 50);
+		assert_noop!(
+			Assets::claim_airdrop(Origin::signed(3), 0, 20, vec![leaf_2]),
+			Error::<Test>::UnknownAirdrop
+		);
+
+		// an airdrop can't pay out more than it was funded with.
+		assert_ok!(Assets::create_airdrop(Origin::signed(1), 0, leaf_2, 10));
+		assert_noop!(
+			Assets::claim_airdrop(Origin::signed(2), 1, 30, vec![]),
+			Error::<Test>::AirdropExhausted
+		);
+
+		// the deposit must be covered by the native balance.
+		Balances::make_free_balance_be(&1, 4);
+		assert_noop!(
+			Assets::create_airdrop(Origin::signed(1), 0, root, 10),
+			BalancesError::<Test>::InsufficientBalance
+		);
+	});
+}
+
 #[test]
 fn imbalances_should_work() {
 	use frame_support::traits::tokens::fungibles::Balanced;
@@ -924,3 +1102,26 @@ fn querying_allowance_should_work() {
 		assert_eq!(Assets::allowance(0, &1, &2), 0);
 	});
 }
+
+#[test]
+fn native_asset_adapter_should_work() {
+	new_test_ext().execute_with(|| {
+		type Currencies = NativeAssetAdapter<Test, Balances>;
+		let native = u32::MAX;
+		assert_noop!(
+			Assets::force_create(Origin::root(), native, 1, true, 1),
+			Error::<Test>::InUse
+		);
+		assert_noop!(Assets::create(Origin::signed(1), native, 1, 1), Error::<Test>::InUse);
+
+		Balances::make_free_balance_be(&1, 100);
+		assert_ok!(<Currencies as fungibles::Transfer<_>>::transfer(native, &1, &2, 50, false));
+		assert_eq!(Balances::free_balance(&2), 50);
+		assert_eq!(<Currencies as fungibles::Inspect<_>>::balance(native, &1), 50);
+
+		assert_ok!(Assets::force_create(Origin::root(), 0, 1, true, 1));
+		assert_ok!(<Currencies as fungibles::Mutate<_>>::mint_into(0, &1, 100));
+		assert_eq!(Assets::balance(0, 1), 100);
+		assert_eq!(<Currencies as fungibles::Inspect<_>>::total_issuance(0), 100);
+	});
+}
@@ -22,7 +22,10 @@ use frame_support::{
 	pallet_prelude::*,
 	traits::{fungible, tokens::BalanceConversion},
 };
-use sp_runtime::{traits::Convert, FixedPointNumber, FixedPointOperand, FixedU128};
+use sp_runtime::{
+	traits::{Convert, One, SaturatedConversion},
+	FixedPointNumber, FixedPointOperand, FixedU128,
+};
 
 pub(super) type DepositBalanceOf<T, I = ()> =
 	<<T as Config<I>>::Currency as Currency<<T as SystemConfig>::AccountId>>::Balance;
@@ -78,6 +81,91 @@ pub struct Approval<Balance, DepositBalance> {
 	pub(super) deposit: DepositBalance,
 }
 
+/// A lock on some asset balance of an account, as `BalanceLock` of `pallet_balances`.
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug, MaxEncodedLen, TypeInfo)]
+pub struct AssetLock<Balance> {
+	/// An identifier for this lock. Only one lock may be in existence for each identifier.
+	pub id: LockIdentifier,
+	/// The amount below which the balance of the account can't be reduced while the lock exists.
+	pub amount: Balance,
+}
+
+/// A named reserve of some asset balance of an account, as `ReserveData` of `pallet_balances`.
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug, MaxEncodedLen, TypeInfo)]
+pub struct AssetReserve<ReserveIdentifier, Balance> {
+	/// The identifier of this reserve.
+	pub id: ReserveIdentifier,
+	/// The amount reserved. It is kept in the balance of the account but can't be moved except
+	/// through the reserve.
+	pub amount: Balance,
+}
+
+/// The identifier of an airdrop.
+pub type AirdropId = u32;
+
+/// Prepended to the encoded `(who, amount)` of a claim when hashing it into a leaf of the Merkle
+/// tree of an airdrop, so that no leaf can pass for a node.
+pub const AIRDROP_LEAF_PREFIX: u8 = 0;
+
+/// Prepended to the encoded pair of children when hashing them into a node of the Merkle tree of
+/// an airdrop.
+pub const AIRDROP_NODE_PREFIX: u8 = 1;
+
+/// An airdrop of an asset, claimed with proofs of membership in a Merkle tree of `(who, amount)`
+/// leaves. It is funded from a balance reserved by its owner.
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug, MaxEncodedLen, TypeInfo)]
+pub struct AirdropDetails<AssetId, AccountId, Balance, DepositBalance, Hash> {
+	/// The asset dropped.
+	pub(super) asset_id: AssetId,
+	/// The account funding the airdrop, which can close it.
+	pub(super) owner: AccountId,
+	/// The root of the Merkle tree of the claims.
+	pub(super) merkle_root: Hash,
+	/// The balance of the owner still reserved for claims.
+	pub(super) remaining: Balance,
+	/// The native balance reserved by the owner for the airdrop.
+	pub(super) deposit: DepositBalance,
+}
+
+/// A balance released linearly from `starting_block` on, shared by the pallets that lock asset
+/// balances on a vesting schedule.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug, MaxEncodedLen, TypeInfo)]
+pub struct VestingInfo<Balance, BlockNumber> {
+	/// The balance locked until `starting_block`.
+	pub locked: Balance,
+	/// The balance released every block from `starting_block` on.
+	pub per_block: Balance,
+	/// The block the release starts at.
+	pub starting_block: BlockNumber,
+}
+
+impl<Balance: AtLeast32BitUnsigned + Copy, BlockNumber: AtLeast32BitUnsigned + Copy>
+	VestingInfo<Balance, BlockNumber>
+{
+	/// Whether the schedule locks anything and ever releases it.
+	pub fn is_valid(&self) -> bool {
+		!self.locked.is_zero() && !self.per_block.is_zero()
+	}
+
+	/// The balance still locked at block `now`.
+	pub fn locked_at(&self, now: BlockNumber) -> Balance {
+		let elapsed: u128 = now.saturating_sub(self.starting_block).saturated_into();
+		self.locked
+			.saturating_sub(self.per_block.saturating_mul(elapsed.saturated_into()))
+	}
+
+	/// The block the whole balance is released by.
+	pub fn ending_block(&self) -> BlockNumber {
+		let per_block = self.per_block.max(One::one());
+		let mut duration = self.locked / per_block;
+		if !(self.locked % per_block).is_zero() {
+			duration = duration.saturating_add(One::one());
+		}
+		let duration: u128 = duration.saturated_into();
+		self.starting_block.saturating_add(duration.saturated_into())
+	}
+}
+
 #[test]
 fn ensure_bool_decodes_to_consumer_or_sufficient() {
 	assert_eq!(false.encode(), ExistenceReason::<()>::Consumer.encode());
@@ -172,6 +260,14 @@ pub trait FrozenBalance<AssetId, AccountId, Balance> {
 	/// If `None` is returned, then nothing special is enforced.
 	fn frozen_balance(asset: AssetId, who: &AccountId) -> Option<Balance>;
 
+	/// Return whether `who` may receive `asset`.
+	///
+	/// Deposits to an account that may not receive the asset fail as if the account could not be
+	/// created, even if it already exists.
+	fn can_receive(_asset: AssetId, _who: &AccountId) -> bool {
+		true
+	}
+
 	/// Called when an account has been removed.
 	///
 	/// # Warning
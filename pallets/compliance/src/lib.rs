@@ -0,0 +1,302 @@
+//! # Compliance Module
+//!
+//! ## Overview
+//!
+//! Holding restrictions for assets, enforced by `pallet_assets` through this pallet as its
+//! `Freezer`. The manager of an asset can put it under an allow list, so that only the listed
+//! accounts may receive it, or under a deny list, so that the listed accounts may not. Accounts
+//! that may not hold an asset can't move the balance they already have either. The accounts of
+//! pallets holding assets for their users, such as the DEX, are `ExemptAccounts` that may receive
+//! an asset under an allow list without being listed, so that it can still be traded.
+//!
+//! The manager can also lock the balance of a holder with a vesting lock, a `VestingInfo` of
+//! `pallet_assets` released linearly. The lock is kept with the balance as the `Extra` data of
+//! `pallet_assets`, and goes away with the account. It overlaps with the locks of
+//! `pallet_assets`, such as those of vesting schedules: `pallet_assets` freezes the largest of the
+//! vesting lock and those locks, not their sum.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+pub use pallet::*;
+
+pub mod migrations;
+
+#[cfg(test)]
+mod mock;
+
+#[cfg(test)]
+mod tests;
+
+use codec::{Decode, Encode, MaxEncodedLen};
+use frame_support::{traits::Get, weights::Weight};
+use pallet_assets::VestingInfo;
+use scale_info::TypeInfo;
+use sp_runtime::RuntimeDebug;
+use sp_std::marker::PhantomData;
+
+/// The compliance data kept with the balance of an account, as `pallet_assets::Config::Extra`.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, Default, RuntimeDebug, MaxEncodedLen, TypeInfo)]
+pub struct AccountCompliance<Balance, BlockNumber> {
+	/// The vesting lock on the balance, if any.
+	pub vesting: Option<VestingInfo<Balance, BlockNumber>>,
+}
+
+/// Who may hold an asset, given the accounts listed for it.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug, MaxEncodedLen, TypeInfo)]
+pub enum ListPolicy {
+	/// Only the listed accounts may hold the asset.
+	AllowList,
+	/// The listed accounts may not hold the asset.
+	DenyList,
+}
+
+/// The account managing the compliance of an asset, usually its issuer.
+pub trait AssetManager<AssetId, AccountId> {
+	fn manager(asset: AssetId) -> Option<AccountId>;
+}
+
+/// The weight of a call to this pallet as the `Freezer` of `pallet_assets`, to be its
+/// `FreezerWeight`: the list policy of the asset and the listing of the account are read. The
+/// vesting lock is read along with the balance.
+pub struct FreezerWeight<T>(PhantomData<T>);
+
+impl<T: frame_system::Config> Get<Weight> for FreezerWeight<T> {
+	fn get() -> Weight {
+		T::DbWeight::get().reads(2)
+	}
+}
+
+#[frame_support::pallet]
+pub mod pallet {
+	use super::{AccountCompliance, AssetManager, ListPolicy, VestingInfo};
+	use frame_support::{
+		pallet_prelude::*,
+		traits::{Contains, StorageVersion, StoredMap},
+		transactional,
+	};
+	use frame_system::pallet_prelude::*;
+	use pallet_assets::FrozenBalance;
+	use sp_runtime::traits::Zero;
+	use sp_std::vec::Vec;
+
+	pub(crate) type AccountComplianceOf<T> = AccountCompliance<
+		<T as pallet_assets::Config>::Balance,
+		<T as frame_system::Config>::BlockNumber,
+	>;
+
+	pub(crate) type VestingInfoOf<T> = VestingInfo<
+		<T as pallet_assets::Config>::Balance,
+		<T as frame_system::Config>::BlockNumber,
+	>;
+
+	const STORAGE_VERSION: StorageVersion = StorageVersion::new(1);
+
+	#[pallet::config]
+	pub trait Config: frame_system::Config + pallet_assets::Config {
+		/// Because this pallet emits events, it depends on the runtime's definition of an event.
+		type Event: From<Event<Self>> + IsType<<Self as frame_system::Config>::Event>;
+
+		/// Where the compliance data of every account is kept, `pallet_assets` with
+		/// `AccountCompliance` as its `Extra`.
+		type Extras: StoredMap<(Self::AssetId, Self::AccountId), AccountComplianceOf<Self>>;
+
+		/// The account managing the compliance of an asset.
+		type AssetManager: AssetManager<Self::AssetId, Self::AccountId>;
+
+		/// The accounts that may hold an asset under an allow list without being listed, the
+		/// accounts of the pallets holding assets for their users.
+		type ExemptAccounts: Contains<Self::AccountId>;
+
+		/// The origin which may manage the compliance of any asset in place of its manager.
+		type UpdateOrigin: EnsureOrigin<Self::Origin>;
+	}
+
+	#[pallet::pallet]
+	#[pallet::generate_store(pub(super) trait Store)]
+	#[pallet::storage_version(STORAGE_VERSION)]
+	#[pallet::without_storage_info]
+	pub struct Pallet<T>(_);
+
+	/// The list policy of each asset. Assets without one may be held by anyone.
+	///
+	/// Policies: map AssetId => Option<ListPolicy>
+	#[pallet::storage]
+	#[pallet::getter(fn policies)]
+	pub type Policies<T: Config> = StorageMap<_, Twox64Concat, T::AssetId, ListPolicy>;
+
+	/// The accounts listed for each asset.
+	///
+	/// Listed: double_map AssetId, AccountId => ()
+	#[pallet::storage]
+	#[pallet::getter(fn listed)]
+	pub type Listed<T: Config> =
+		StorageDoubleMap<_, Twox64Concat, T::AssetId, Twox64Concat, T::AccountId, ()>;
+
+	#[pallet::event]
+	#[pallet::generate_deposit(pub(super) fn deposit_event)]
+	pub enum Event<T: Config> {
+		/// The list policy of an asset was set or removed. [asset_id, policy]
+		PolicySet(T::AssetId, Option<ListPolicy>),
+		/// An account was listed for an asset. [asset_id, who]
+		Listed(T::AssetId, T::AccountId),
+		/// An account was taken off the list of an asset. [asset_id, who]
+		Unlisted(T::AssetId, T::AccountId),
+		/// A vesting lock was put on the balance of an account. [asset_id, who, lock]
+		VestingLockSet(T::AssetId, T::AccountId, VestingInfoOf<T>),
+		/// The vesting lock on the balance of an account was removed. [asset_id, who]
+		VestingLockRemoved(T::AssetId, T::AccountId),
+	}
+
+	#[pallet::error]
+	pub enum Error<T> {
+		/// The origin is neither the manager of the asset nor `UpdateOrigin`.
+		NotManager,
+		/// The account holds none of the asset.
+		NoAccount,
+		/// The vesting lock locks nothing or never releases anything.
+		InvalidVestingLock,
+	}
+
+	#[pallet::call]
+	impl<T: Config> Pallet<T> {
+		/// Set the list policy of `asset_id`, or remove it with `None`. The listed accounts are
+		/// kept either way.
+		#[pallet::weight(10_000)]
+		pub fn set_policy(
+			origin: OriginFor<T>,
+			asset_id: T::AssetId,
+			policy: Option<ListPolicy>,
+		) -> DispatchResult {
+			Self::ensure_manager_or_update_origin(origin, asset_id)?;
+			Policies::<T>::set(asset_id, policy);
+			Self::deposit_event(Event::PolicySet(asset_id, policy));
+			Ok(())
+		}
+
+		/// Put `accounts` on the list of `asset_id`.
+		#[pallet::weight(10_000_u64.saturating_mul(accounts.len() as u64))]
+		pub fn list(
+			origin: OriginFor<T>,
+			asset_id: T::AssetId,
+			accounts: Vec<T::AccountId>,
+		) -> DispatchResult {
+			Self::ensure_manager_or_update_origin(origin, asset_id)?;
+			for who in accounts {
+				Listed::<T>::insert(asset_id, &who, ());
+				Self::deposit_event(Event::Listed(asset_id, who));
+			}
+			Ok(())
+		}
+
+		/// Take `accounts` off the list of `asset_id`.
+		#[pallet::weight(10_000_u64.saturating_mul(accounts.len() as u64))]
+		pub fn unlist(
+			origin: OriginFor<T>,
+			asset_id: T::AssetId,
+			accounts: Vec<T::AccountId>,
+		) -> DispatchResult {
+			Self::ensure_manager_or_update_origin(origin, asset_id)?;
+			for who in accounts {
+				Listed::<T>::remove(asset_id, &who);
+				Self::deposit_event(Event::Unlisted(asset_id, who));
+			}
+			Ok(())
+		}
+
+		/// Put `lock` on the balance of `who` in `asset_id`, replacing any vesting lock `who`
+		/// already has.
+		#[pallet::weight(10_000)]
+		#[transactional]
+		pub fn set_vesting_lock(
+			origin: OriginFor<T>,
+			asset_id: T::AssetId,
+			who: T::AccountId,
+			lock: VestingInfoOf<T>,
+		) -> DispatchResult {
+			Self::ensure_manager_or_update_origin(origin, asset_id)?;
+			ensure!(lock.is_valid(), Error::<T>::InvalidVestingLock);
+			T::Extras::try_mutate_exists(&(asset_id, who.clone()), |maybe_extra| {
+				let extra = maybe_extra.as_mut().ok_or(Error::<T>::NoAccount)?;
+				extra.vesting = Some(lock);
+				Ok::<_, DispatchError>(())
+			})?;
+			Self::deposit_event(Event::VestingLockSet(asset_id, who, lock));
+			Ok(())
+		}
+
+		/// Remove the vesting lock on the balance of `who` in `asset_id`.
+		#[pallet::weight(10_000)]
+		#[transactional]
+		pub fn remove_vesting_lock(
+			origin: OriginFor<T>,
+			asset_id: T::AssetId,
+			who: T::AccountId,
+		) -> DispatchResult {
+			Self::ensure_manager_or_update_origin(origin, asset_id)?;
+			T::Extras::try_mutate_exists(&(asset_id, who.clone()), |maybe_extra| {
+				let extra = maybe_extra.as_mut().ok_or(Error::<T>::NoAccount)?;
+				extra.vesting = None;
+				Ok::<_, DispatchError>(())
+			})?;
+			Self::deposit_event(Event::VestingLockRemoved(asset_id, who));
+			Ok(())
+		}
+	}
+
+	impl<T: Config> Pallet<T> {
+		/// Whether `who` may hold `asset_id` under its list policy.
+		pub fn can_hold(asset_id: T::AssetId, who: &T::AccountId) -> bool {
+			match Policies::<T>::get(asset_id) {
+				None => true,
+				Some(ListPolicy::AllowList) =>
+					T::ExemptAccounts::contains(who) || Listed::<T>::contains_key(asset_id, who),
+				Some(ListPolicy::DenyList) => !Listed::<T>::contains_key(asset_id, who),
+			}
+		}
+
+		fn ensure_manager_or_update_origin(
+			origin: OriginFor<T>,
+			asset_id: T::AssetId,
+		) -> DispatchResult {
+			match T::UpdateOrigin::try_origin(origin) {
+				Ok(_) => Ok(()),
+				Err(origin) => {
+					let who = ensure_signed(origin)?;
+					ensure!(
+						T::AssetManager::manager(asset_id) == Some(who),
+						Error::<T>::NotManager
+					);
+					Ok(())
+				},
+			}
+		}
+	}
+
+	impl<T: Config> FrozenBalance<T::AssetId, T::AccountId, T::Balance> for Pallet<T> {
+		/// The vesting lock still locked, which `pallet_assets` freezes along with its own locks by
+		/// taking the largest of them, or the whole balance of an account that may not hold the
+		/// asset.
+		fn frozen_balance(asset_id: T::AssetId, who: &T::AccountId) -> Option<T::Balance> {
+			let frozen = if Self::can_hold(asset_id, who) {
+				T::Extras::get(&(asset_id, who.clone()))
+					.vesting
+					.map(|lock| lock.locked_at(frame_system::Pallet::<T>::block_number()))
+					.unwrap_or_else(Zero::zero)
+			} else {
+				// an account that may not hold the asset can't move it either
+				pallet_assets::Pallet::<T>::balance(asset_id, who)
+			};
+			if frozen.is_zero() {
+				None
+			} else {
+				Some(frozen)
+			}
+		}
+
+		fn can_receive(asset_id: T::AssetId, who: &T::AccountId) -> bool {
+			Self::can_hold(asset_id, who)
+		}
+
+		fn died(_asset_id: T::AssetId, _who: &T::AccountId) {}
+	}
+}
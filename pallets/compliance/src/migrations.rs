@@ -0,0 +1,47 @@
+//! Storage migrations for the compliance pallet.
+
+use super::*;
+use frame_support::{
+	log,
+	traits::{Get, GetStorageVersion, StorageVersion},
+	weights::Weight,
+};
+
+/// Gives every account of `pallet_assets` an empty `AccountCompliance`, for runtimes that used
+/// `()` as the `Extra` of `pallet_assets` before adding this pallet.
+pub mod v1 {
+	use super::*;
+	use frame_support::traits::OnRuntimeUpgrade;
+	use sp_std::marker::PhantomData;
+
+	/// Runs [`migrate`] from the runtime, ahead of the migrations that decode the asset accounts.
+	pub struct MigrateToV1<T>(PhantomData<T>);
+
+	impl<T: Config> OnRuntimeUpgrade for MigrateToV1<T> {
+		fn on_runtime_upgrade() -> Weight {
+			migrate::<T>()
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade() -> Result<(), &'static str> {
+			frame_support::ensure!(
+				Pallet::<T>::on_chain_storage_version() == 1,
+				"compliance storage version isn't 1"
+			);
+			Ok(())
+		}
+	}
+
+	pub fn migrate<T: Config>() -> Weight {
+		let on_chain_version = Pallet::<T>::on_chain_storage_version();
+		if on_chain_version >= 1 {
+			return T::DbWeight::get().reads(1)
+		}
+
+		let translated = pallet_assets::Pallet::<T>::translate_extra::<()>(|_| Default::default());
+		log::info!(target: "runtime::compliance", "translated {} asset accounts", translated);
+
+		StorageVersion::new(1).put::<Pallet<T>>();
+		T::DbWeight::get().reads_writes(translated.saturating_add(1), translated.saturating_add(1))
+	}
+}
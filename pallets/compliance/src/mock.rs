@@ -0,0 +1,168 @@
+//! Mocks for the compliance pallet.
+
+use crate as pallet_compliance;
+use frame_support::{
+	construct_runtime, parameter_types,
+	traits::{ConstU128, ConstU16, ConstU32, ConstU64, IsInVec},
+};
+use frame_system::EnsureRoot;
+use sp_core::H256;
+use sp_runtime::{
+	testing::Header,
+	traits::{BlakeTwo256, IdentityLookup},
+};
+
+pub type AccountId = u128;
+pub type AssetId = u32;
+pub type Balance = u128;
+pub type BlockNumber = u64;
+
+/// The owner, and so the manager, of DOT.
+pub const ALICE: AccountId = 1;
+pub const BOB: AccountId = 2;
+pub const CHARLIE: AccountId = 3;
+/// The account of a pallet holding assets for its users.
+pub const PALLET: AccountId = 4;
+
+pub const NATIVE: AssetId = 0;
+pub const DOT: AssetId = 1;
+
+type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
+type Block = frame_system::mocking::MockBlock<Test>;
+
+construct_runtime!(
+	pub enum Test where
+		Block = Block,
+		NodeBlock = Block,
+		UncheckedExtrinsic = UncheckedExtrinsic,
+	{
+		System: frame_system::{Pallet, Call, Config, Storage, Event<T>},
+		Balances: pallet_balances::{Pallet, Call, Storage, Config<T>, Event<T>},
+		Assets: pallet_assets::{Pallet, Call, Storage, Config<T>, Event<T>},
+		Compliance: pallet_compliance::{Pallet, Call, Storage, Event<T>},
+	}
+);
+
+impl frame_system::Config for Test {
+	type BaseCallFilter = frame_support::traits::Everything;
+	type BlockWeights = ();
+	type BlockLength = ();
+	type DbWeight = ();
+	type Origin = Origin;
+	type Call = Call;
+	type Index = u64;
+	type BlockNumber = BlockNumber;
+	type Hash = H256;
+	type Hashing = BlakeTwo256;
+	type AccountId = AccountId;
+	type Lookup = IdentityLookup<Self::AccountId>;
+	type Header = Header;
+	type Event = Event;
+	type BlockHashCount = ConstU64<250>;
+	type Version = ();
+	type PalletInfo = PalletInfo;
+	type AccountData = pallet_balances::AccountData<Balance>;
+	type OnNewAccount = ();
+	type OnKilledAccount = ();
+	type SystemWeightInfo = ();
+	type SS58Prefix = ConstU16<42>;
+	type OnSetCode = ();
+	type MaxConsumers = ConstU32<16>;
+}
+
+impl pallet_balances::Config for Test {
+	type Balance = Balance;
+	type DustRemoval = ();
+	type Event = Event;
+	type ExistentialDeposit = ConstU128<1>;
+	type AccountStore = System;
+	type WeightInfo = ();
+	type MaxLocks = ();
+	type MaxReserves = ();
+	type ReserveIdentifier = [u8; 8];
+}
+
+parameter_types! {
+	pub const AirdropReserveId: [u8; 8] = *b"airdrop ";
+}
+
+impl pallet_assets::Config for Test {
+	type Event = Event;
+	type Balance = Balance;
+	type AssetId = AssetId;
+	type NativeAssetId = ConstU32<NATIVE>;
+	type Currency = Balances;
+	type ForceOrigin = EnsureRoot<AccountId>;
+	type AssetDeposit = ConstU128<1>;
+	type AssetAccountDeposit = ConstU128<10>;
+	type MetadataDepositBase = ConstU128<1>;
+	type MetadataDepositPerByte = ConstU128<1>;
+	type ApprovalDeposit = ConstU128<1>;
+	type StringLimit = ConstU32<50>;
+	type Freezer = Compliance;
+	type FreezerWeight = ();
+	type WeightInfo = ();
+	type Extra = pallet_compliance::AccountCompliance<Balance, BlockNumber>;
+	type MaxLocks = ConstU32<2>;
+	type MaxReserves = ConstU32<2>;
+	type ReserveIdentifier = [u8; 8];
+	type MaxBatchSize = ConstU32<3>;
+	type AirdropReserveId = AirdropReserveId;
+	type AirdropDeposit = ConstU128<1>;
+}
+
+/// Assets are managed by their owner.
+pub struct AssetOwner;
+impl pallet_compliance::AssetManager<AssetId, AccountId> for AssetOwner {
+	fn manager(asset: AssetId) -> Option<AccountId> {
+		Assets::owner(asset)
+	}
+}
+
+parameter_types! {
+	pub PalletAccounts: Vec<AccountId> = vec![PALLET];
+}
+
+impl pallet_compliance::Config for Test {
+	type Event = Event;
+	type Extras = Assets;
+	type AssetManager = AssetOwner;
+	type ExemptAccounts = IsInVec<PalletAccounts>;
+	type UpdateOrigin = EnsureRoot<AccountId>;
+}
+
+pub struct ExtBuilder {
+	balances: Vec<(AccountId, Balance)>,
+	asset_balances: Vec<(AssetId, AccountId, Balance)>,
+}
+
+impl Default for ExtBuilder {
+	fn default() -> Self {
+		Self {
+			balances: vec![(ALICE, 1_000_000), (BOB, 1_000_000), (CHARLIE, 1_000_000)],
+			asset_balances: vec![(DOT, ALICE, 1_000_000), (DOT, BOB, 1_000)],
+		}
+	}
+}
+
+impl ExtBuilder {
+	pub fn build(self) -> sp_io::TestExternalities {
+		let mut t = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
+
+		pallet_balances::GenesisConfig::<Test> { balances: self.balances }
+			.assimilate_storage(&mut t)
+			.unwrap();
+
+		pallet_assets::GenesisConfig::<Test> {
+			assets: vec![(DOT, ALICE, true, 1)],
+			metadata: vec![],
+			accounts: self.asset_balances,
+		}
+		.assimilate_storage(&mut t)
+		.unwrap();
+
+		let mut ext = sp_io::TestExternalities::new(t);
+		ext.execute_with(|| System::set_block_number(1));
+		ext
+	}
+}
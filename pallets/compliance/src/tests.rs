@@ -0,0 +1,204 @@
+//! Unit tests for the compliance pallet.
+
+use crate::{mock::*, AccountCompliance, Error, ListPolicy};
+use frame_support::{
+	assert_noop, assert_ok,
+	storage::{storage_prefix, unhashed},
+	traits::{GetStorageVersion, StorageVersion, StoredMap},
+};
+use pallet_assets::{LockableAssets, VestingInfo};
+use sp_runtime::TokenError;
+
+#[test]
+fn set_policy_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_noop!(
+			Compliance::set_policy(Origin::signed(BOB), DOT, Some(ListPolicy::AllowList)),
+			Error::<Test>::NotManager
+		);
+
+		assert_ok!(Compliance::set_policy(Origin::signed(ALICE), DOT, Some(ListPolicy::AllowList)));
+		System::assert_last_event(Event::Compliance(crate::Event::PolicySet(
+			DOT,
+			Some(ListPolicy::AllowList),
+		)));
+		assert_eq!(Compliance::policies(DOT), Some(ListPolicy::AllowList));
+
+		assert_ok!(Compliance::set_policy(Origin::root(), DOT, None));
+		assert_eq!(Compliance::policies(DOT), None);
+	});
+}
+
+#[test]
+fn list_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_noop!(
+			Compliance::list(Origin::signed(BOB), DOT, vec![BOB]),
+			Error::<Test>::NotManager
+		);
+
+		assert_ok!(Compliance::list(Origin::signed(ALICE), DOT, vec![BOB, CHARLIE]));
+		System::assert_last_event(Event::Compliance(crate::Event::Listed(DOT, CHARLIE)));
+		assert_eq!(Compliance::listed(DOT, BOB), Some(()));
+
+		assert_ok!(Compliance::unlist(Origin::signed(ALICE), DOT, vec![BOB]));
+		System::assert_last_event(Event::Compliance(crate::Event::Unlisted(DOT, BOB)));
+		assert_eq!(Compliance::listed(DOT, BOB), None);
+		assert_eq!(Compliance::listed(DOT, CHARLIE), Some(()));
+	});
+}
+
+#[test]
+fn allow_list_restricts_holders() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(Compliance::set_policy(Origin::signed(ALICE), DOT, Some(ListPolicy::AllowList)));
+		assert_ok!(Compliance::list(Origin::signed(ALICE), DOT, vec![ALICE, BOB]));
+
+		assert_ok!(Assets::transfer(Origin::signed(ALICE), DOT, BOB, 100));
+		assert_noop!(
+			Assets::transfer(Origin::signed(ALICE), DOT, CHARLIE, 100),
+			TokenError::CannotCreate
+		);
+		assert_noop!(
+			Assets::mint(Origin::signed(ALICE), DOT, CHARLIE, 100),
+			TokenError::CannotCreate
+		);
+
+		// taken off the list, BOB can't move what they hold
+		assert_ok!(Compliance::unlist(Origin::signed(ALICE), DOT, vec![BOB]));
+		assert_noop!(
+			Assets::transfer(Origin::signed(BOB), DOT, ALICE, 100),
+			pallet_assets::Error::<Test>::BalanceLow
+		);
+	});
+}
+
+#[test]
+fn allow_list_exempts_pallet_accounts() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(Compliance::set_policy(Origin::signed(ALICE), DOT, Some(ListPolicy::AllowList)));
+		assert_ok!(Compliance::list(Origin::signed(ALICE), DOT, vec![ALICE]));
+
+		// a swap through the pallet account, which pays out to listed accounts only
+		assert_ok!(Assets::transfer(Origin::signed(ALICE), DOT, PALLET, 100));
+		assert_ok!(Assets::transfer(Origin::signed(PALLET), DOT, ALICE, 50));
+		assert_noop!(
+			Assets::transfer(Origin::signed(PALLET), DOT, CHARLIE, 50),
+			TokenError::CannotCreate
+		);
+	});
+}
+
+#[test]
+fn deny_list_restricts_listed_holders() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(Compliance::set_policy(Origin::signed(ALICE), DOT, Some(ListPolicy::DenyList)));
+		assert_ok!(Compliance::list(Origin::signed(ALICE), DOT, vec![BOB]));
+
+		assert_noop!(
+			Assets::transfer(Origin::signed(ALICE), DOT, BOB, 100),
+			TokenError::CannotCreate
+		);
+		assert_ok!(Assets::transfer(Origin::signed(ALICE), DOT, CHARLIE, 100));
+		assert_noop!(
+			Assets::transfer(Origin::signed(BOB), DOT, ALICE, 100),
+			pallet_assets::Error::<Test>::BalanceLow
+		);
+
+		// without a policy the list is ignored
+		assert_ok!(Compliance::set_policy(Origin::signed(ALICE), DOT, None));
+		assert_ok!(Assets::transfer(Origin::signed(BOB), DOT, ALICE, 100));
+	});
+}
+
+#[test]
+fn set_vesting_lock_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		let lock = VestingInfo { locked: 600, per_block: 100, starting_block: 10 };
+		assert_noop!(
+			Compliance::set_vesting_lock(Origin::signed(BOB), DOT, BOB, lock),
+			Error::<Test>::NotManager
+		);
+		assert_noop!(
+			Compliance::set_vesting_lock(Origin::signed(ALICE), DOT, CHARLIE, lock),
+			Error::<Test>::NoAccount
+		);
+		assert_noop!(
+			Compliance::set_vesting_lock(
+				Origin::signed(ALICE),
+				DOT,
+				BOB,
+				VestingInfo { per_block: 0, ..lock }
+			),
+			Error::<Test>::InvalidVestingLock
+		);
+
+		assert_ok!(Compliance::set_vesting_lock(Origin::signed(ALICE), DOT, BOB, lock));
+		System::assert_last_event(Event::Compliance(crate::Event::VestingLockSet(DOT, BOB, lock)));
+		assert_eq!(Assets::get(&(DOT, BOB)).vesting, Some(lock));
+
+		// 600 of the 1_000 of BOB is locked, on top of the minimum balance
+		assert_noop!(
+			Assets::transfer(Origin::signed(BOB), DOT, ALICE, 400),
+			pallet_assets::Error::<Test>::BalanceLow
+		);
+		assert_ok!(Assets::transfer(Origin::signed(BOB), DOT, ALICE, 399));
+
+		// 300 is released by block 13
+		System::set_block_number(13);
+		assert_ok!(Assets::transfer(Origin::signed(BOB), DOT, ALICE, 300));
+
+		assert_ok!(Compliance::remove_vesting_lock(Origin::signed(ALICE), DOT, BOB));
+		System::assert_last_event(Event::Compliance(crate::Event::VestingLockRemoved(DOT, BOB)));
+		assert_ok!(Assets::transfer(Origin::signed(BOB), DOT, ALICE, 301));
+	});
+}
+
+#[test]
+fn vesting_lock_overlaps_asset_locks() {
+	ExtBuilder::default().build().execute_with(|| {
+		let lock = VestingInfo { locked: 600, per_block: 100, starting_block: 10 };
+		assert_ok!(Compliance::set_vesting_lock(Origin::signed(ALICE), DOT, BOB, lock));
+		assert_ok!(<Assets as LockableAssets<AccountId>>::set_lock(*b"vesting ", DOT, &BOB, 400));
+
+		// the larger 600 is frozen, not 1_000
+		assert_noop!(
+			Assets::transfer(Origin::signed(BOB), DOT, ALICE, 400),
+			pallet_assets::Error::<Test>::BalanceLow
+		);
+		assert_ok!(Assets::transfer(Origin::signed(BOB), DOT, ALICE, 399));
+
+		// by block 13 the vesting lock is down to 300, under the 400 of the asset lock
+		System::set_block_number(13);
+		assert_noop!(
+			Assets::transfer(Origin::signed(BOB), DOT, ALICE, 201),
+			pallet_assets::Error::<Test>::BalanceLow
+		);
+		assert_ok!(Assets::transfer(Origin::signed(BOB), DOT, ALICE, 200));
+	});
+}
+
+#[test]
+fn migrate_v1_translates_the_extra() {
+	ExtBuilder::default().build().execute_with(|| {
+		// rewrite the asset accounts as they were with `()` as the `Extra`, by dropping the
+		// encoded `AccountCompliance::default()`
+		let prefix = storage_prefix(b"Assets", b"Account");
+		let mut key = prefix.to_vec();
+		while let Some(next) = sp_io::storage::next_key(&key).filter(|k| k.starts_with(&prefix)) {
+			let mut value = unhashed::get_raw(&next).unwrap();
+			assert_eq!(value.pop(), Some(0));
+			unhashed::put_raw(&next, &value);
+			key = next;
+		}
+		StorageVersion::new(0).put::<Compliance>();
+		assert_eq!(Assets::holders(DOT), None);
+
+		crate::migrations::v1::migrate::<Test>();
+
+		assert_eq!(Assets::holders(DOT).map(|holders| holders.len()), Some(2));
+		assert_eq!(Assets::balance(DOT, BOB), 1_000);
+		assert_eq!(Assets::get(&(DOT, BOB)), AccountCompliance::default());
+		assert_eq!(Compliance::on_chain_storage_version(), 1);
+	});
+}
@@ -0,0 +1,42 @@
+//! Runtime API definition for the dex pallet.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+// The `too_many_arguments` warning originates from `decl_runtime_apis` macro.
+#![allow(clippy::too_many_arguments)]
+// The `unnecessary_mut_passed` warning originates from `decl_runtime_apis` macro.
+#![allow(clippy::unnecessary_mut_passed)]
+
+use codec::Codec;
+use pallet_dex::{Price, SwapLimit, TradingPairInfo};
+use sp_std::vec::Vec;
+
+sp_api::decl_runtime_apis! {
+	pub trait DexApi<AssetId, Balance, BlockNumber> where
+		AssetId: Codec,
+		Balance: Codec,
+		BlockNumber: Codec,
+	{
+		/// Reserves and status of every listed trading pair.
+		fn trading_pairs() -> Vec<TradingPairInfo<AssetId, Balance, BlockNumber>>;
+
+		/// Amounts along `path` when supplying exactly `supply_amount`. `None` if the path can't
+		/// be traded.
+		fn quote_exact_supply(path: Vec<AssetId>, supply_amount: Balance) -> Option<Vec<Balance>>;
+
+		/// Amounts along `path` when receiving exactly `target_amount`. `None` if the path can't
+		/// be traded.
+		fn quote_exact_target(path: Vec<AssetId>, target_amount: Balance) -> Option<Vec<Balance>>;
+
+		/// The best priced path from `supply_currency_id` to `target_currency_id` up to
+		/// `TradingPathLimit` long, with its supply and target amounts.
+		fn best_swap_path(
+			supply_currency_id: AssetId,
+			target_currency_id: AssetId,
+			limit: SwapLimit<Balance>,
+		) -> Option<(Vec<AssetId>, Balance, Balance)>;
+
+		/// Time-weighted average price of `currency_id_a` in `currency_id_b` over at least the
+		/// last `window` blocks.
+		fn twap(currency_id_a: AssetId, currency_id_b: AssetId, window: BlockNumber) -> Option<Price>;
+	}
+}
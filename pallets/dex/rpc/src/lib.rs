@@ -0,0 +1,165 @@
+//! RPC interface for the dex pallet.
+
+use std::{fmt::Debug, sync::Arc};
+
+use codec::Codec;
+use jsonrpc_core::{Error as RpcError, ErrorCode, Result};
+use jsonrpc_derive::rpc;
+use pallet_dex::{Price, SwapLimit, TradingPairInfo};
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_runtime::{generic::BlockId, traits::Block as BlockT};
+
+pub use pallet_dex_rpc_runtime_api::DexApi as DexRuntimeApi;
+
+#[rpc]
+pub trait DexApi<BlockHash, AssetId, Balance, BlockNumber> {
+	/// Reserves and status of every listed trading pair.
+	#[rpc(name = "dex_getTradingPairs")]
+	fn trading_pairs(
+		&self,
+		at: Option<BlockHash>,
+	) -> Result<Vec<TradingPairInfo<AssetId, Balance, BlockNumber>>>;
+
+	/// Amounts along `path` when supplying exactly `supply_amount`.
+	#[rpc(name = "dex_quoteExactSupply")]
+	fn quote_exact_supply(
+		&self,
+		path: Vec<AssetId>,
+		supply_amount: Balance,
+		at: Option<BlockHash>,
+	) -> Result<Option<Vec<Balance>>>;
+
+	/// Amounts along `path` when receiving exactly `target_amount`.
+	#[rpc(name = "dex_quoteExactTarget")]
+	fn quote_exact_target(
+		&self,
+		path: Vec<AssetId>,
+		target_amount: Balance,
+		at: Option<BlockHash>,
+	) -> Result<Option<Vec<Balance>>>;
+
+	/// The best priced path from `supply_currency_id` to `target_currency_id`, with its supply
+	/// and target amounts.
+	#[rpc(name = "dex_getBestSwapPath")]
+	fn best_swap_path(
+		&self,
+		supply_currency_id: AssetId,
+		target_currency_id: AssetId,
+		limit: SwapLimit<Balance>,
+		at: Option<BlockHash>,
+	) -> Result<Option<(Vec<AssetId>, Balance, Balance)>>;
+
+	/// Time-weighted average price of `currency_id_a` in `currency_id_b` over at least the last
+	/// `window` blocks.
+	#[rpc(name = "dex_getTwap")]
+	fn twap(
+		&self,
+		currency_id_a: AssetId,
+		currency_id_b: AssetId,
+		window: BlockNumber,
+		at: Option<BlockHash>,
+	) -> Result<Option<Price>>;
+}
+
+/// A struct that implements the [`DexApi`].
+pub struct Dex<C, B> {
+	client: Arc<C>,
+	_marker: std::marker::PhantomData<B>,
+}
+
+impl<C, B> Dex<C, B> {
+	/// Create new `Dex` with the given reference to the client.
+	pub fn new(client: Arc<C>) -> Self {
+		Self { client, _marker: Default::default() }
+	}
+}
+
+/// Error type of this RPC api.
+pub enum Error {
+	/// The call to runtime failed.
+	RuntimeError,
+}
+
+impl From<Error> for i64 {
+	fn from(e: Error) -> i64 {
+		match e {
+			Error::RuntimeError => 1,
+		}
+	}
+}
+
+fn runtime_error(e: impl Debug) -> RpcError {
+	RpcError {
+		code: ErrorCode::ServerError(Error::RuntimeError.into()),
+		message: "Unable to query the dex pallet.".into(),
+		data: Some(format!("{:?}", e).into()),
+	}
+}
+
+impl<C, Block, AssetId, Balance, BlockNumber>
+	DexApi<<Block as BlockT>::Hash, AssetId, Balance, BlockNumber> for Dex<C, Block>
+where
+	Block: BlockT,
+	C: 'static + ProvideRuntimeApi<Block> + HeaderBackend<Block>,
+	C::Api: DexRuntimeApi<Block, AssetId, Balance, BlockNumber>,
+	AssetId: Codec + Send + Sync + 'static,
+	Balance: Codec + Send + Sync + 'static,
+	BlockNumber: Codec + Send + Sync + 'static,
+{
+	fn trading_pairs(
+		&self,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<Vec<TradingPairInfo<AssetId, Balance, BlockNumber>>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+		api.trading_pairs(&at).map_err(runtime_error)
+	}
+
+	fn quote_exact_supply(
+		&self,
+		path: Vec<AssetId>,
+		supply_amount: Balance,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<Option<Vec<Balance>>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+		api.quote_exact_supply(&at, path, supply_amount).map_err(runtime_error)
+	}
+
+	fn quote_exact_target(
+		&self,
+		path: Vec<AssetId>,
+		target_amount: Balance,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<Option<Vec<Balance>>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+		api.quote_exact_target(&at, path, target_amount).map_err(runtime_error)
+	}
+
+	fn best_swap_path(
+		&self,
+		supply_currency_id: AssetId,
+		target_currency_id: AssetId,
+		limit: SwapLimit<Balance>,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<Option<(Vec<AssetId>, Balance, Balance)>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+		api.best_swap_path(&at, supply_currency_id, target_currency_id, limit)
+			.map_err(runtime_error)
+	}
+
+	fn twap(
+		&self,
+		currency_id_a: AssetId,
+		currency_id_b: AssetId,
+		window: BlockNumber,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<Option<Price>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+		api.twap(&at, currency_id_a, currency_id_b, window).map_err(runtime_error)
+	}
+}
@@ -43,12 +43,14 @@ use codec::EncodeLike;
 use serde::{Deserialize, Serialize};
 use sp_runtime::{
 	traits::{AccountIdConversion, One, Zero},
-	ArithmeticError, DispatchError, DispatchResult, FixedPointNumber, FixedU128, RuntimeDebug,
-	SaturatedConversion,
+	ArithmeticError, DispatchError, DispatchResult, FixedPointNumber, FixedU128, PerThing, Permill,
+	RuntimeDebug, SaturatedConversion,
 };
 use sp_std::{prelude::*, vec};
 
+pub mod migrations;
 mod mock;
+mod stable_swap;
 mod tests;
 pub mod weights;
 
@@ -56,6 +58,7 @@ pub use pallet::*;
 
 /// Parameters of TradingPair in Provisioning status
 #[derive(Encode, Decode, Clone, Copy, RuntimeDebug, PartialEq, Eq, MaxEncodedLen, TypeInfo)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
 pub struct ProvisioningParameters<Balance, BlockNumber> {
 	/// limit contribution per time.
 	min_contribution: (Balance, Balance),
@@ -74,6 +77,7 @@ pub type Rate = FixedU128;
 
 /// Status for TradingPair
 #[derive(Clone, Copy, Encode, Decode, RuntimeDebug, PartialEq, Eq, MaxEncodedLen, TypeInfo)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
 pub enum TradingPairStatus<Balance, BlockNumber> {
 	/// Default status,
 	/// can withdraw liquidity, re-enable and list this trading pair.
@@ -91,7 +95,25 @@ impl<Balance, BlockNumber> Default for TradingPairStatus<Balance, BlockNumber> {
 		Self::Disabled
 	}
 }
+/// Pricing curve of a trading pair.
+#[derive(Clone, Copy, Encode, Decode, RuntimeDebug, PartialEq, Eq, MaxEncodedLen, TypeInfo)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub enum Curve {
+	/// Constant product, x * y = k.
+	ConstantProduct,
+	/// StableSwap invariant with amplification coefficient `amplification`, for pairs of
+	/// currencies that trade close to one to one.
+	StableSwap { amplification: u32 },
+}
+
+impl Default for Curve {
+	fn default() -> Self {
+		Self::ConstantProduct
+	}
+}
+
 #[derive(RuntimeDebug, Encode, Decode, Clone, PartialEq, TypeInfo)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
 pub enum SwapLimit<Balance> {
 	/// use exact amount supply amount to swap. (exact_supply_amount, minimum_target_amount)
 	ExactSupply(Balance, Balance),
@@ -140,6 +162,31 @@ impl<T: Decode> Decode for TradingPair<T> {
 
 impl<T: Encode> EncodeLike for TradingPair<T> {}
 
+/// Cumulative prices of a trading pair, in the style of Uniswap V2.
+///
+/// The accumulators hold the inner value of a `Price` summed once per block. They wrap on
+/// overflow, so only the difference between two snapshots is meaningful.
+#[derive(Encode, Decode, Clone, Copy, RuntimeDebug, PartialEq, Eq, TypeInfo)]
+pub struct CumulativePrice<BlockNumber> {
+	/// Accumulated price of currency_0 in currency_1.
+	pub price_0_cumulative: u128,
+	/// Accumulated price of currency_1 in currency_0.
+	pub price_1_cumulative: u128,
+	/// The block the accumulators were last brought up to.
+	pub block_number: BlockNumber,
+}
+
+/// Reserves and status of a trading pair.
+#[derive(Encode, Decode, Clone, PartialEq, RuntimeDebug, TypeInfo)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub struct TradingPairInfo<CurrencyId, Balance, BlockNumber> {
+	pub trading_pair: TradingPair<CurrencyId>,
+	pub dex_share_currency_id: Option<CurrencyId>,
+	pub liquidity_pool: (Balance, Balance),
+	pub status: TradingPairStatus<Balance, BlockNumber>,
+	pub curve: Curve,
+}
+
 pub trait DEXManager<AccountId, CurrencyId, Balance> {
 	fn get_liquidity_pool(
 		currency_id_a: CurrencyId,
@@ -158,6 +205,11 @@ pub trait DEXManager<AccountId, CurrencyId, Balance> {
 		alternative_path_joint_list: Vec<Vec<CurrencyId>>,
 	) -> Option<Vec<CurrencyId>>;
 
+	fn is_trading_pair_enabled(currency_id_a: CurrencyId, currency_id_b: CurrencyId) -> bool;
+
+	/// The trading pair `lp_currency_id` is the dex share currency of, if any.
+	fn get_dex_share_trading_pair(lp_currency_id: CurrencyId) -> Option<TradingPair<CurrencyId>>;
+
 	fn swap_with_specific_path(
 		who: &AccountId,
 		path: &[CurrencyId],
@@ -184,24 +236,55 @@ pub trait DEXManager<AccountId, CurrencyId, Balance> {
 	) -> sp_std::result::Result<(Balance, Balance), DispatchError>;
 }
 
+pub trait DEXIncentives<AccountId, CurrencyId, Balance> {
+	/// Stake `amount` of the DEX share `lp_currency_id` held by `who`.
+	fn do_deposit_dex_share(
+		who: &AccountId,
+		lp_currency_id: CurrencyId,
+		amount: Balance,
+	) -> DispatchResult;
+
+	/// Unstake `amount` of the DEX share `lp_currency_id` back to `who`.
+	fn do_withdraw_dex_share(
+		who: &AccountId,
+		lp_currency_id: CurrencyId,
+		amount: Balance,
+	) -> DispatchResult;
+}
+
+impl<AccountId, CurrencyId, Balance> DEXIncentives<AccountId, CurrencyId, Balance> for () {
+	fn do_deposit_dex_share(_: &AccountId, _: CurrencyId, _: Balance) -> DispatchResult {
+		Ok(())
+	}
+
+	fn do_withdraw_dex_share(_: &AccountId, _: CurrencyId, _: Balance) -> DispatchResult {
+		Ok(())
+	}
+}
+
+pub trait DEXPriceOracle<CurrencyId, BlockNumber> {
+	/// Time-weighted average price of `currency_id_a` in `currency_id_b` over at least the last
+	/// `window` blocks. `None` if the pair has no price history that far back.
+	fn get_twap(
+		currency_id_a: CurrencyId,
+		currency_id_b: CurrencyId,
+		window: BlockNumber,
+	) -> Option<Price>;
+}
+
 #[frame_support::pallet]
 pub mod pallet {
 	use super::*;
 	use frame_support::{
 		log,
-		traits::{
-			tokens::fungibles::{self, Balanced, Create, Inspect, Mutate, Transfer, Unbalanced},
-			Currency as NativeCurrency, ExistenceRequirement,
-			ExistenceRequirement::KeepAlive,
-		},
+		traits::tokens::fungibles::{self, Balanced, Create, Inspect, Mutate, Unbalanced},
 		PalletId,
 	};
 
 	use frame_system::pallet_prelude::*;
 	use sp_runtime::{
 		traits::{
-			AccountIdConversion, CheckedAdd, CheckedDiv, CheckedShl, CheckedShr, CheckedSub, One,
-			Saturating, StaticLookup, UniqueSaturatedFrom,
+			AccountIdConversion, CheckedAdd, CheckedDiv, CheckedSub, One, Saturating, StaticLookup,
 		},
 		FixedPointNumber, FixedPointOperand,
 	};
@@ -209,37 +292,76 @@ pub mod pallet {
 	use sp_core::U256;
 	use sp_std::vec::Vec;
 
+	/// The current storage version.
+	const STORAGE_VERSION: StorageVersion = StorageVersion::new(3);
+
 	#[pallet::config]
 	pub trait Config: frame_system::Config + pallet_assets::Config {
 		type Event: From<Event<Self>> + IsType<<Self as frame_system::Config>::Event>;
 
-		type NativeCurrency: NativeCurrency<Self::AccountId>;
+		/// The currencies of the pools, with the native currency as
+		/// `pallet_assets::Config::NativeAssetId`
+		type Currencies: fungibles::Transfer<
+			Self::AccountId,
+			AssetId = Self::AssetId,
+			Balance = Self::Balance,
+		>;
 
 		/// Trading fee rate
 		/// The first item of the tuple is the numerator of the fee rate, second
 		/// item is the denominator, fee_rate = numerator / denominator,
 		/// use (u32, u32) over `Rate` type to minimize internal division
 		/// operation.
+		/// Used by every trading pair without its own fee rate in `ExchangeFees`.
 		#[pallet::constant]
 		type GetExchangeFee: Get<(u32, u32)>;
 
+		/// The account the protocol share of trading fees is paid to
+		#[pallet::constant]
+		type TreasuryAccount: Get<Self::AccountId>;
+
 		/// The limit for length of trading path
 		#[pallet::constant]
 		type TradingPathLimit: Get<u32>;
 
+		/// The minimum number of blocks between two price observations of a trading pair
+		#[pallet::constant]
+		type PriceObservationInterval: Get<Self::BlockNumber>;
+
+		/// The number of price observations kept per trading pair
+		#[pallet::constant]
+		type MaxPriceObservations: Get<u32>;
+
+		/// The maximum number of path extensions tried when searching the best swap path
+		#[pallet::constant]
+		type MaxSwapPathSearch: Get<u32>;
+
+		/// The maximum amplification coefficient of a StableSwap trading pair
+		#[pallet::constant]
+		type MaxAmplification: Get<u32>;
+
 		/// The DEX's module id, keep all assets in DEX.
 		#[pallet::constant]
 		type PalletId: Get<PalletId>;
 
-		/// The DEX's module id, keep all assets in DEX.
+		/// The shift dex share currency ids were packed with before `DexShareCurrencyIds`, only
+		/// used to migrate them
 		#[pallet::constant]
 		type AssetIdShift: Get<u32>;
 
+		/// The first currency id tried when allocating the dex share currency of a trading pair
+		#[pallet::constant]
+		type DexShareCurrencyIdStart: Get<Self::AssetId>;
+
+		/// The most currency ids tried when allocating the dex share currency of a trading pair
+		#[pallet::constant]
+		type MaxDexShareCurrencyIdSearch: Get<u32>;
+
 		// Weight information for the extrinsics in this module.
 		//type WeightInfo: WeightInfo;
 
-		// DEX incentives
-		//type DEXIncentives: DEXIncentives<Self::AccountId, T::AssetId, T::Balance>;
+		/// DEX incentives, which `remove_liquidity` unstakes shares from when `by_unstake` is set
+		type DEXIncentives: DEXIncentives<Self::AccountId, Self::AssetId, Self::Balance>;
 
 		// The origin which may list, enable or disable trading pairs.
 		type ListingOrigin: EnsureOrigin<Self::Origin>;
@@ -258,6 +380,8 @@ pub mod pallet {
 		MustBeProvisioning,
 		/// Trading pair must be in Disabled status
 		MustBeDisabled,
+		/// Trading pair is already Disabled
+		AlreadyDisabled,
 		/// This trading pair is not allowed to be listed
 		NotAllowedList,
 		/// The increment of provision is invalid
@@ -292,6 +416,20 @@ pub mod pallet {
 		AssetUnregistered,
 		/// The trading path is invalid
 		InvalidTradingPath,
+		/// The fee rate is invalid
+		InvalidFeeRate,
+		/// The provision was converted into dex share, claim it instead
+		ProvisionConverted,
+		/// The provision was never converted into dex share, refund it instead
+		ProvisionNotConverted,
+		/// Some provision of the trading pair is not refunded yet
+		ProvisionNotRefunded,
+		/// The amplification coefficient of the curve is zero or above `MaxAmplification`
+		InvalidCurve,
+		/// No free currency id for the dex share within `MaxDexShareCurrencyIdSearch` tries
+		DexShareCurrencyIdUnavailable,
+		/// The curve of a trading pair holding liquidity can't be changed
+		CurveLocked,
 	}
 
 	#[pallet::event]
@@ -326,9 +464,9 @@ pub mod pallet {
 		/// Use supply currency to swap target currency.
 		Swap { trader: T::AccountId, path: Vec<T::AssetId>, liquidity_changes: Vec<T::Balance> },
 		/// Enable trading pair.
-		EnableTradingPair { trading_pair: TradingPair<T::AssetId> },
+		EnableTradingPair { trading_pair: TradingPair<T::AssetId>, curve: Curve },
 		/// List provisioning trading pair.
-		ListProvisioning { trading_pair: TradingPair<T::AssetId> },
+		ListProvisioning { trading_pair: TradingPair<T::AssetId>, curve: Curve },
 		/// Disable trading pair.
 		DisableTradingPair { trading_pair: TradingPair<T::AssetId> },
 		/// Provisioning trading pair convert to Enabled.
@@ -338,6 +476,32 @@ pub mod pallet {
 			pool_1: T::Balance,
 			share_amount: T::Balance,
 		},
+		/// The fee rate of a trading pair was changed. `None` means `GetExchangeFee` applies.
+		ExchangeFeeUpdated { trading_pair: TradingPair<T::AssetId>, fee_rate: Option<(u32, u32)> },
+		/// The protocol share of trading fees was changed.
+		ProtocolFeeShareUpdated { share: Permill },
+		/// Trading fee was charged on the supply currency of a swap hop.
+		FeeCharged {
+			trading_pair: TradingPair<T::AssetId>,
+			currency_id: T::AssetId,
+			fee: T::Balance,
+			protocol_fee: T::Balance,
+		},
+		/// Accrued protocol fees were paid to the treasury account.
+		ProtocolFeesClaimed { currency_id: T::AssetId, amount: T::Balance },
+		/// A dex share currency was allocated to a trading pair.
+		DexShareCurrencyRegistered {
+			trading_pair: TradingPair<T::AssetId>,
+			currency_id: T::AssetId,
+		},
+		/// Provision of a disabled trading pair was returned to its contributor.
+		RefundProvision {
+			who: T::AccountId,
+			currency_0: T::AssetId,
+			contribution_0: T::Balance,
+			currency_1: T::AssetId,
+			contribution_1: T::Balance,
+		},
 	}
 
 	/// Liquidity pool for TradingPair.
@@ -361,6 +525,68 @@ pub mod pallet {
 		ValueQuery,
 	>;
 
+	/// Fee rate of TradingPair, overriding `GetExchangeFee`.
+	///
+	/// ExchangeFees: map TradingPair => (u32, u32)
+	#[pallet::storage]
+	#[pallet::getter(fn exchange_fees)]
+	pub type ExchangeFees<T: Config> =
+		StorageMap<_, Twox64Concat, TradingPair<T::AssetId>, (u32, u32)>;
+
+	/// The share of every trading fee that goes to the treasury account instead of the
+	/// liquidity providers.
+	///
+	/// ProtocolFeeShare: Permill
+	#[pallet::storage]
+	#[pallet::getter(fn protocol_fee_share)]
+	pub type ProtocolFeeShare<T: Config> = StorageValue<_, Permill, ValueQuery>;
+
+	/// Protocol fees held by the module account until they are claimed to the treasury account.
+	///
+	/// ProtocolFees: map CurrencyId => T::Balance
+	#[pallet::storage]
+	#[pallet::getter(fn protocol_fees)]
+	pub type ProtocolFees<T: Config> =
+		StorageMap<_, Twox64Concat, T::AssetId, T::Balance, ValueQuery>;
+
+	/// Trading fees charged by TradingPair since it was listed, including the protocol share.
+	///
+	/// CumulativeFees: map TradingPair => (T::Balance, T::Balance)
+	#[pallet::storage]
+	#[pallet::getter(fn cumulative_fees)]
+	pub type CumulativeFees<T: Config> =
+		StorageMap<_, Twox64Concat, TradingPair<T::AssetId>, (T::Balance, T::Balance), ValueQuery>;
+
+	/// Latest cumulative prices of TradingPair.
+	///
+	/// CumulativePrices: map TradingPair => CumulativePrice
+	#[pallet::storage]
+	#[pallet::getter(fn cumulative_prices)]
+	pub type CumulativePrices<T: Config> =
+		StorageMap<_, Twox64Concat, TradingPair<T::AssetId>, CumulativePrice<T::BlockNumber>>;
+
+	/// Snapshots of the cumulative prices of TradingPair, oldest first, at least
+	/// `PriceObservationInterval` blocks apart.
+	///
+	/// PriceObservations: map TradingPair => Vec<CumulativePrice>
+	#[pallet::storage]
+	#[pallet::getter(fn price_observations)]
+	pub type PriceObservations<T: Config> = StorageMap<
+		_,
+		Twox64Concat,
+		TradingPair<T::AssetId>,
+		Vec<CumulativePrice<T::BlockNumber>>,
+		ValueQuery,
+	>;
+
+	/// Currencies sharing an enabled trading pair with each currency.
+	///
+	/// TradingPairIndex: map CurrencyId => Vec<CurrencyId>
+	#[pallet::storage]
+	#[pallet::getter(fn trading_pair_index)]
+	pub type TradingPairIndex<T: Config> =
+		StorageMap<_, Twox64Concat, T::AssetId, Vec<T::AssetId>, ValueQuery>;
+
 	/// Provision of TradingPair by AccountId.
 	///
 	/// ProvisioningPool: double_map TradingPair, AccountId => (T::Balance,
@@ -377,6 +603,37 @@ pub mod pallet {
 		ValueQuery,
 	>;
 
+	/// Pricing curve of each trading pair.
+	///
+	/// Curves: map TradingPair => Curve
+	#[pallet::storage]
+	#[pallet::getter(fn curves)]
+	pub type Curves<T: Config> =
+		StorageMap<_, Twox64Concat, TradingPair<T::AssetId>, Curve, ValueQuery>;
+
+	/// Dex share currency allocated to each trading pair.
+	///
+	/// DexShareCurrencyIds: map TradingPair => CurrencyId
+	#[pallet::storage]
+	#[pallet::getter(fn dex_share_currency_ids)]
+	pub type DexShareCurrencyIds<T: Config> =
+		StorageMap<_, Twox64Concat, TradingPair<T::AssetId>, T::AssetId>;
+
+	/// Trading pair of each dex share currency.
+	///
+	/// DexShareTradingPairs: map CurrencyId => TradingPair
+	#[pallet::storage]
+	#[pallet::getter(fn dex_share_trading_pairs)]
+	pub type DexShareTradingPairs<T: Config> =
+		StorageMap<_, Twox64Concat, T::AssetId, TradingPair<T::AssetId>>;
+
+	/// The next currency id tried when allocating a dex share currency, `DexShareCurrencyIdStart`
+	/// if unset.
+	///
+	/// NextDexShareCurrencyId: CurrencyId
+	#[pallet::storage]
+	pub type NextDexShareCurrencyId<T: Config> = StorageValue<_, T::AssetId>;
+
 	/// Initial exchange rate, used to calculate the dex share amount for founders of provisioning
 	///
 	/// InitialShareExchangeRates: map TradingPair => (ExchangeRate, ExchangeRate)
@@ -432,7 +689,12 @@ pub mod pallet {
 			);
 
 			self.initial_enabled_trading_pairs.iter().for_each(|trading_pair| {
+				assert!(
+					<Pallet<T>>::ensure_dex_share_currency(trading_pair).is_ok(),
+					"genesis register dex share currency failed."
+				);
 				TradingPairStatuses::<T>::insert(trading_pair, TradingPairStatus::<_, _>::Enabled);
+				<Pallet<T>>::index_trading_pair(trading_pair);
 			});
 
 			self.initial_added_liquidity_pools.iter().for_each(|(who, trading_pairs_data)| {
@@ -459,55 +721,21 @@ pub mod pallet {
 	}
 
 	#[pallet::pallet]
+	#[pallet::storage_version(STORAGE_VERSION)]
 	#[pallet::without_storage_info]
 	pub struct Pallet<T>(_);
 
 	#[pallet::hooks]
-	impl<T: Config> Hooks<T::BlockNumber> for Pallet<T> {}
+	impl<T: Config> Hooks<T::BlockNumber> for Pallet<T> {
+		fn on_runtime_upgrade() -> Weight {
+			// v3 is run by the runtime, after the migrations it depends on, and runs these first
+			crate::migrations::v1::migrate::<T>()
+				.saturating_add(crate::migrations::v2::migrate::<T>())
+		}
+	}
 
 	#[pallet::call]
 	impl<T: Config> Pallet<T> {
-		#[pallet::weight(100)]
-		#[transactional]
-		pub fn wrap_sofi(origin: OriginFor<T>, amount: u128) -> DispatchResult {
-			let who = ensure_signed(origin)?;
-
-			T::NativeCurrency::transfer(
-				&who,
-				&Self::account_id(),
-				UniqueSaturatedFrom::unique_saturated_from(amount),
-				ExistenceRequirement::KeepAlive,
-			)?;
-
-			pallet_assets::Pallet::<T>::mint_into(
-				T::AssetId::zero(),
-				&who,
-				UniqueSaturatedFrom::unique_saturated_from(amount),
-			)?;
-			Ok(())
-		}
-
-		#[pallet::weight(100)]
-		#[transactional]
-		pub fn unwrap_sofi(origin: OriginFor<T>, amount: u128) -> DispatchResult {
-			let who = ensure_signed(origin)?;
-			<pallet_assets::Pallet<T> as Transfer<T::AccountId>>::transfer(
-				T::AssetId::zero(),
-				&who,
-				&Self::account_id(),
-				UniqueSaturatedFrom::unique_saturated_from(amount),
-				false,
-			)?;
-			T::NativeCurrency::transfer(
-				&Self::account_id(),
-				&who,
-				UniqueSaturatedFrom::unique_saturated_from(amount),
-				ExistenceRequirement::KeepAlive,
-			)?;
-
-			Ok(())
-		}
-
 		#[pallet::weight(100)]
 		#[transactional]
 		pub fn add_liquidity(
@@ -530,6 +758,25 @@ pub mod pallet {
 			Ok(())
 		}
 
+		/// Trading with DEX, swap with exact supply amount
+		///
+		/// - `path`: trading path, at most `TradingPathLimit` currencies long.
+		/// - `supply_amount`: exact supply amount.
+		/// - `min_target_amount`: acceptable minimum target amount.
+		#[pallet::weight(10000)]
+		#[transactional]
+		pub fn swap_with_exact_supply(
+			origin: OriginFor<T>,
+			path: Vec<T::AssetId>,
+			#[pallet::compact] supply_amount: T::Balance,
+			#[pallet::compact] min_target_amount: T::Balance,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			Self::validate_path(&path)?;
+			Self::do_swap_with_exact_supply(&who, &path, supply_amount, min_target_amount)?;
+			Ok(())
+		}
+
 		/// Trading with DEX, swap with exact target amount
 		///
 		/// - `path`: trading path.
@@ -588,6 +835,25 @@ pub mod pallet {
 			Ok(())
 		}
 
+		/// Return the provision of a founder of a trading pair that was disabled while
+		/// provisioning.
+		///
+		/// - `owner`: founder account.
+		/// - `currency_id_a`: currency id A.
+		/// - `currency_id_b`: currency id B.
+		#[pallet::weight(10000)]
+		#[transactional]
+		pub fn refund_provision(
+			origin: OriginFor<T>,
+			owner: T::AccountId,
+			currency_id_a: T::AssetId,
+			currency_id_b: T::AssetId,
+		) -> DispatchResult {
+			let _ = ensure_signed(origin)?;
+			Self::do_refund_provision(&owner, currency_id_a, currency_id_b)?;
+			Ok(())
+		}
+
 		/// Remove liquidity from specific liquidity pool in the form of burning
 		/// shares, and withdrawing currencies in trading pairs from liquidity
 		/// pool in proportion, and withdraw liquidity incentive interest.
@@ -622,7 +888,7 @@ pub mod pallet {
 			Ok(())
 		}
 
-		/// List a new provisioning trading pair.
+		/// List a new provisioning trading pair, which will trade on `curve` once enabled.
 		#[pallet::weight(10000)]
 		#[transactional]
 		pub fn list_provisioning(
@@ -634,11 +900,12 @@ pub mod pallet {
 			#[pallet::compact] target_provision_a: T::Balance,
 			#[pallet::compact] target_provision_b: T::Balance,
 			#[pallet::compact] not_before: T::BlockNumber,
+			curve: Curve,
 		) -> DispatchResult {
 			T::ListingOrigin::ensure_origin(origin)?;
 
-			let dex_id = Self::pair_assetid(currency_id_a, currency_id_b)
-				.ok_or(Error::<T>::InvalidCurrencyId)?;
+			ensure!(currency_id_a != currency_id_b, Error::<T>::InvalidCurrencyId);
+			Self::ensure_valid_curve(curve)?;
 			let trading_pair = Self::get_pair(currency_id_a, currency_id_b);
 			// let  =
 			// 	TradingPair::from_currency_ids(currency_id_a,
@@ -652,10 +919,12 @@ pub mod pallet {
 				Error::<T>::MustBeDisabled
 			);
 			ensure!(
-				pallet_assets::Pallet::<T>::total_issuance(dex_id).is_zero() &&
-					ProvisioningPool::<T>::iter_prefix(trading_pair.clone()).next().is_none(),
+				Self::dex_share_currency_ids(trading_pair.clone()).map_or(true, |dex_id| {
+					pallet_assets::Pallet::<T>::total_issuance(dex_id).is_zero()
+				}) && ProvisioningPool::<T>::iter_prefix(trading_pair.clone()).next().is_none(),
 				Error::<T>::NotAllowedList
 			);
+			Self::ensure_curve_changeable(&trading_pair, curve)?;
 
 			// check_asset_registry(currency_id_a)?;
 			// check_asset_registry(currency_id_b)?;
@@ -675,7 +944,8 @@ pub mod pallet {
 					not_before,
 				}),
 			);
-			Self::deposit_event(Event::ListProvisioning { trading_pair });
+			Curves::<T>::insert(trading_pair.clone(), curve);
+			Self::deposit_event(Event::ListProvisioning { trading_pair, curve });
 			Ok(())
 		}
 
@@ -695,8 +965,6 @@ pub mod pallet {
 		) -> DispatchResult {
 			T::ListingOrigin::ensure_origin(origin)?;
 
-			let dex_share_currency_id = Self::pair_assetid(currency_id_a, currency_id_b)
-				.ok_or(Error::<T>::InvalidCurrencyId)?;
 			let trading_pair = Self::get_pair(currency_id_a, currency_id_b);
 
 			match Self::trading_pair_statuses(trading_pair.clone()) {
@@ -738,8 +1006,6 @@ pub mod pallet {
 		) -> DispatchResult {
 			let _ = ensure_signed(origin)?;
 
-			let dex_share_currency_id = Self::pair_assetid(currency_id_a, currency_id_b)
-				.ok_or(Error::<T>::InvalidCurrencyId)?;
 			let trading_pair = Self::get_pair(currency_id_a, currency_id_b);
 
 			match Self::trading_pair_statuses(trading_pair.clone()) {
@@ -773,12 +1039,7 @@ pub mod pallet {
 						.ok_or(ArithmeticError::Overflow)?;
 
 					// issue total shares to module account
-					<pallet_assets::Pallet<T> as Create<T::AccountId>>::create(
-						dex_share_currency_id,
-						Self::account_id(),
-						true,
-						T::Balance::one(),
-					)?;
+					let dex_share_currency_id = Self::ensure_dex_share_currency(&trading_pair)?;
 					pallet_assets::Pallet::<T>::mint_into(
 						dex_share_currency_id,
 						&Self::account_id(),
@@ -804,6 +1065,7 @@ pub mod pallet {
 						trading_pair.clone(),
 						TradingPairStatus::<_, _>::Enabled,
 					);
+					Self::index_trading_pair(&trading_pair);
 
 					// record initial exchange rate so that founders can use it to calculate their
 					// own shares
@@ -825,7 +1087,7 @@ pub mod pallet {
 			Ok(())
 		}
 
-		/// Enable a trading pair
+		/// Enable a trading pair to trade on `curve`
 		/// if the status of trading pair is `Disabled`, or `Provisioning` without any accumulated
 		/// provision, enable it directly.
 		#[pallet::weight(10000)]
@@ -834,13 +1096,14 @@ pub mod pallet {
 			origin: OriginFor<T>,
 			currency_id_a: T::AssetId,
 			currency_id_b: T::AssetId,
+			curve: Curve,
 		) -> DispatchResult {
 			T::ListingOrigin::ensure_origin(origin)?;
 
+			ensure!(currency_id_a != currency_id_b, Error::<T>::InvalidCurrencyId);
+			Self::ensure_valid_curve(curve)?;
 			let trading_pair = Self::get_pair(currency_id_a, currency_id_b);
 
-			let dex_id = Self::pair_assetid(currency_id_a, currency_id_b)
-				.ok_or(Error::<T>::InvalidCurrencyId)?;
 			match Self::trading_pair_statuses(trading_pair.clone()) {
 				TradingPairStatus::<_, _>::Disabled => {},
 				TradingPairStatus::<_, _>::Provisioning(provisioning_parameters) => {
@@ -852,20 +1115,27 @@ pub mod pallet {
 				},
 				TradingPairStatus::<_, _>::Enabled => return Err(Error::<T>::AlreadyEnabled.into()),
 			}
+			Self::ensure_curve_changeable(&trading_pair, curve)?;
 
-			<pallet_assets::Pallet<T> as Create<T::AccountId>>::create(
-				dex_id,
-				Self::account_id(),
-				true,
-				T::Balance::one(),
-			)?;
+			Self::ensure_dex_share_currency(&trading_pair)?;
+
+			// founders of an aborted provisioning would otherwise claim dex share at a zero rate
+			ensure!(
+				InitialShareExchangeRates::<T>::contains_key(trading_pair.clone()) ||
+					ProvisioningPool::<T>::iter_prefix(trading_pair.clone()).next().is_none(),
+				Error::<T>::ProvisionNotRefunded
+			);
 
 			TradingPairStatuses::<T>::insert(trading_pair.clone(), TradingPairStatus::Enabled);
-			Self::deposit_event(Event::EnableTradingPair { trading_pair });
+			Curves::<T>::insert(trading_pair.clone(), curve);
+			Self::index_trading_pair(&trading_pair);
+			Self::deposit_event(Event::EnableTradingPair { trading_pair, curve });
 			Ok(())
 		}
 
-		/// Disable a `Enabled` trading pair.
+		/// Disable a `Enabled` or `Provisioning` trading pair.
+		/// The provision of a `Provisioning` trading pair can then be taken back with
+		/// `refund_provision`.
 		#[pallet::weight(10000)]
 		#[transactional]
 		pub fn disable_trading_pair(
@@ -877,17 +1147,73 @@ pub mod pallet {
 			let trading_pair = Self::get_pair(currency_id_a, currency_id_b);
 
 			ensure!(
-				matches!(
+				!matches!(
 					Self::trading_pair_statuses(trading_pair.clone()),
-					TradingPairStatus::<_, _>::Enabled
+					TradingPairStatus::<_, _>::Disabled
 				),
-				Error::<T>::MustBeEnabled
+				Error::<T>::AlreadyDisabled
 			);
 
 			TradingPairStatuses::<T>::insert(trading_pair.clone(), TradingPairStatus::Disabled);
+			Self::unindex_trading_pair(&trading_pair);
 			Self::deposit_event(Event::DisableTradingPair { trading_pair });
 			Ok(())
 		}
+
+		/// Set the fee rate of a trading pair.
+		///
+		/// - `fee_rate`: (numerator, denominator) of the fee rate, `None` to fall back to
+		///   `GetExchangeFee`.
+		#[pallet::weight(10000)]
+		#[transactional]
+		pub fn set_exchange_fee(
+			origin: OriginFor<T>,
+			currency_id_a: T::AssetId,
+			currency_id_b: T::AssetId,
+			fee_rate: Option<(u32, u32)>,
+		) -> DispatchResult {
+			T::ListingOrigin::ensure_origin(origin)?;
+			ensure!(currency_id_a != currency_id_b, Error::<T>::InvalidCurrencyId);
+			if let Some((fee_numerator, fee_denominator)) = fee_rate {
+				ensure!(fee_numerator < fee_denominator, Error::<T>::InvalidFeeRate);
+			}
+			let trading_pair = Self::get_pair(currency_id_a, currency_id_b);
+
+			ExchangeFees::<T>::set(trading_pair.clone(), fee_rate);
+			Self::deposit_event(Event::ExchangeFeeUpdated { trading_pair, fee_rate });
+			Ok(())
+		}
+
+		/// Set the share of every trading fee that goes to the treasury account.
+		#[pallet::weight(10000)]
+		pub fn set_protocol_fee_share(origin: OriginFor<T>, share: Permill) -> DispatchResult {
+			T::ListingOrigin::ensure_origin(origin)?;
+			ProtocolFeeShare::<T>::put(share);
+			Self::deposit_event(Event::ProtocolFeeShareUpdated { share });
+			Ok(())
+		}
+
+		/// Pay the protocol fees accrued in `currency_id` to the treasury account.
+		#[pallet::weight(10000)]
+		#[transactional]
+		pub fn claim_protocol_fees(
+			origin: OriginFor<T>,
+			currency_id: T::AssetId,
+		) -> DispatchResult {
+			let _ = ensure_signed(origin)?;
+			let amount = ProtocolFees::<T>::take(currency_id);
+			if !amount.is_zero() {
+				T::Currencies::transfer(
+					currency_id,
+					&Self::account_id(),
+					&T::TreasuryAccount::get(),
+					amount,
+					false,
+				)?;
+				Self::deposit_event(Event::ProtocolFeesClaimed { currency_id, amount });
+			}
+			Ok(())
+		}
 	}
 
 	impl<T: Config> Pallet<T> {
@@ -895,14 +1221,51 @@ pub mod pallet {
 			T::PalletId::get().into_account()
 		}
 
-		fn pair_assetid(x: T::AssetId, y: T::AssetId) -> Option<T::AssetId> {
-			if x == y {
-				None
-			} else if x < y {
-				Some((x + T::AssetId::one()).checked_shl(T::AssetIdShift::get())?.checked_add(&y)?)
-			} else {
-				Some((y + T::AssetId::one()).checked_shl(T::AssetIdShift::get())?.checked_add(&x)?)
+		/// The dex share currency of `trading_pair`, allocated and created on first use.
+		pub(crate) fn ensure_dex_share_currency(
+			trading_pair: &TradingPair<T::AssetId>,
+		) -> sp_std::result::Result<T::AssetId, DispatchError> {
+			if let Some(currency_id) = Self::dex_share_currency_ids(trading_pair.clone()) {
+				return Ok(currency_id)
 			}
+
+			// skip currency ids that are already taken by other assets
+			let mut currency_id =
+				NextDexShareCurrencyId::<T>::get().unwrap_or_else(T::DexShareCurrencyIdStart::get);
+			let mut tried: u32 = 1;
+			while pallet_assets::Pallet::<T>::maybe_total_supply(currency_id).is_some() {
+				ensure!(
+					tried < T::MaxDexShareCurrencyIdSearch::get(),
+					Error::<T>::DexShareCurrencyIdUnavailable
+				);
+				tried = tried.saturating_add(1);
+				currency_id =
+					currency_id.checked_add(&One::one()).ok_or(ArithmeticError::Overflow)?;
+			}
+			NextDexShareCurrencyId::<T>::put(
+				currency_id.checked_add(&One::one()).ok_or(ArithmeticError::Overflow)?,
+			);
+
+			<pallet_assets::Pallet<T> as Create<T::AccountId>>::create(
+				currency_id,
+				Self::account_id(),
+				true,
+				T::Balance::one(),
+			)?;
+			Self::register_dex_share_currency(trading_pair, currency_id);
+			Self::deposit_event(Event::DexShareCurrencyRegistered {
+				trading_pair: trading_pair.clone(),
+				currency_id,
+			});
+			Ok(currency_id)
+		}
+
+		pub(crate) fn register_dex_share_currency(
+			trading_pair: &TradingPair<T::AssetId>,
+			currency_id: T::AssetId,
+		) {
+			DexShareCurrencyIds::<T>::insert(trading_pair.clone(), currency_id);
+			DexShareTradingPairs::<T>::insert(currency_id, trading_pair.clone());
 		}
 
 		fn get_pair(
@@ -916,24 +1279,19 @@ pub mod pallet {
 			}
 		}
 
-		fn split_assetid(both_id: T::AssetId) -> Option<(T::AssetId, T::AssetId)> {
-			let x = both_id.checked_shr(T::AssetIdShift::get())?.checked_sub(&T::AssetId::one())?;
-			let tmp = x.checked_shl(T::AssetIdShift::get())?;
-			let y = both_id.checked_sub(&tmp)?;
-			Some((x, y))
-		}
-
 		fn try_mutate_liquidity_pool<R, E>(
 			trading_pair: TradingPair<T::AssetId>,
 			f: impl FnOnce((&mut T::Balance, &mut T::Balance)) -> sp_std::result::Result<R, E>,
 		) -> sp_std::result::Result<R, E> {
 			LiquidityPool::<T>::try_mutate(
-				trading_pair,
+				trading_pair.clone(),
 				|(pool_0, pool_1)| -> sp_std::result::Result<R, E> {
 					let old_pool_0 = *pool_0;
 					let old_pool_1 = *pool_1;
 					f((pool_0, pool_1)).map(move |result| {
 						if *pool_0 != old_pool_0 || *pool_1 != old_pool_1 {
+							// the reserves held since the last update set the price until now
+							Self::update_cumulative_price(&trading_pair, old_pool_0, old_pool_1);
 							//T::OnLiquidityPoolUpdated::happened(&(*trading_pair, *pool_0,
 							// *pool_1));
 						}
@@ -944,6 +1302,95 @@ pub mod pallet {
 			)
 		}
 
+		/// The marginal prices of currency 0 in currency 1 and of currency 1 in currency 0 on the
+		/// curve of `trading_pair` at the reserves `pool_0` and `pool_1`.
+		fn get_marginal_prices(
+			trading_pair: &TradingPair<T::AssetId>,
+			pool_0: T::Balance,
+			pool_1: T::Balance,
+		) -> Option<(Price, Price)> {
+			match Self::curves(trading_pair) {
+				Curve::ConstantProduct => Some((
+					Price::checked_from_rational(pool_1, pool_0)?,
+					Price::checked_from_rational(pool_0, pool_1)?,
+				)),
+				Curve::StableSwap { amplification } => {
+					let (pool_0, pool_1) =
+						(pool_0.saturated_into::<u128>(), pool_1.saturated_into::<u128>());
+					Some((
+						Price::from_inner(stable_swap::get_price(
+							pool_0,
+							pool_1,
+							amplification,
+							Price::accuracy(),
+						)?),
+						Price::from_inner(stable_swap::get_price(
+							pool_1,
+							pool_0,
+							amplification,
+							Price::accuracy(),
+						)?),
+					))
+				},
+			}
+		}
+
+		/// Bring `cumulative` up to `now` at the marginal price of `trading_pair` given by
+		/// `pool_0` and `pool_1`.
+		fn accumulate_price(
+			trading_pair: &TradingPair<T::AssetId>,
+			cumulative: &mut CumulativePrice<T::BlockNumber>,
+			pool_0: T::Balance,
+			pool_1: T::Balance,
+			now: T::BlockNumber,
+		) {
+			let elapsed: u128 = now.saturating_sub(cumulative.block_number).saturated_into();
+			if let Some((price_0, price_1)) =
+				Self::get_marginal_prices(trading_pair, pool_0, pool_1)
+			{
+				cumulative.price_0_cumulative = cumulative
+					.price_0_cumulative
+					.wrapping_add(price_0.into_inner().wrapping_mul(elapsed));
+				cumulative.price_1_cumulative = cumulative
+					.price_1_cumulative
+					.wrapping_add(price_1.into_inner().wrapping_mul(elapsed));
+			}
+			cumulative.block_number = now;
+		}
+
+		/// Accumulate the price of `trading_pair` given by the reserves `pool_0` and `pool_1`
+		/// held until this block, and record an observation if the last one is old enough.
+		fn update_cumulative_price(
+			trading_pair: &TradingPair<T::AssetId>,
+			pool_0: T::Balance,
+			pool_1: T::Balance,
+		) {
+			let now = frame_system::Pallet::<T>::block_number();
+			let mut cumulative = match Self::cumulative_prices(trading_pair) {
+				Some(cumulative) if cumulative.block_number == now => return,
+				Some(cumulative) => cumulative,
+				None => CumulativePrice {
+					price_0_cumulative: 0,
+					price_1_cumulative: 0,
+					block_number: now,
+				},
+			};
+			Self::accumulate_price(trading_pair, &mut cumulative, pool_0, pool_1, now);
+			CumulativePrices::<T>::insert(trading_pair, cumulative);
+
+			PriceObservations::<T>::mutate(trading_pair, |observations| {
+				let is_due = observations.last().map_or(true, |last| {
+					now.saturating_sub(last.block_number) >= T::PriceObservationInterval::get()
+				});
+				if is_due {
+					if observations.len() >= T::MaxPriceObservations::get() as usize {
+						observations.remove(0);
+					}
+					observations.push(cumulative);
+				}
+			});
+		}
+
 		fn do_claim_dex_share(
 			who: &T::AccountId,
 			currency_id_a: T::AssetId,
@@ -957,7 +1404,7 @@ pub mod pallet {
 				),
 				Error::<T>::StillProvisioning
 			);
-			let dex_id = Self::pair_assetid(currency_id_a, currency_id_b)
+			let dex_id = Self::dex_share_currency_ids(trading_pair.clone())
 				.ok_or(Error::<T>::InvalidCurrencyId)?;
 
 			ProvisioningPool::<T>::try_mutate_exists(
@@ -965,8 +1412,11 @@ pub mod pallet {
 				who,
 				|maybe_contribution| -> DispatchResult {
 					if let Some((contribution_0, contribution_1)) = maybe_contribution.take() {
+						// the provision of a trading pair disabled while provisioning has no
+						// exchange rate and would be claimed as zero dex share
 						let (exchange_rate_0, exchange_rate_1) =
-							Self::initial_share_exchange_rates(trading_pair.clone());
+							InitialShareExchangeRates::<T>::try_get(trading_pair.clone())
+								.map_err(|_| Error::<T>::ProvisionNotConverted)?;
 						let shares_from_provision_0 = exchange_rate_0
 							.checked_mul_int(contribution_0)
 							.ok_or(ArithmeticError::Overflow)?;
@@ -977,7 +1427,7 @@ pub mod pallet {
 							.checked_add(&shares_from_provision_1)
 							.ok_or(ArithmeticError::Overflow)?;
 
-						<pallet_assets::Pallet<T> as Transfer<T::AccountId>>::transfer(
+						T::Currencies::transfer(
 							dex_id,
 							&Self::account_id(),
 							who,
@@ -1000,6 +1450,64 @@ pub mod pallet {
 			Ok(())
 		}
 
+		fn do_refund_provision(
+			who: &T::AccountId,
+			currency_id_a: T::AssetId,
+			currency_id_b: T::AssetId,
+		) -> DispatchResult {
+			let trading_pair = Self::get_pair(currency_id_a, currency_id_b);
+			ensure!(
+				matches!(
+					Self::trading_pair_statuses(trading_pair.clone()),
+					TradingPairStatus::<_, _>::Disabled
+				),
+				Error::<T>::MustBeDisabled
+			);
+			// the provision of a trading pair that was enabled is in the liquidity pool now
+			ensure!(
+				!InitialShareExchangeRates::<T>::contains_key(trading_pair.clone()),
+				Error::<T>::ProvisionConverted
+			);
+
+			ProvisioningPool::<T>::try_mutate_exists(
+				trading_pair.clone(),
+				who,
+				|maybe_contribution| -> DispatchResult {
+					if let Some((contribution_0, contribution_1)) = maybe_contribution.take() {
+						let module_account_id = Self::account_id();
+						T::Currencies::transfer(
+							trading_pair.0,
+							&module_account_id,
+							who,
+							contribution_0,
+							false,
+						)?;
+						T::Currencies::transfer(
+							trading_pair.1,
+							&module_account_id,
+							who,
+							contribution_1,
+							false,
+						)?;
+
+						// decrease ref count
+						frame_system::Pallet::<T>::dec_consumers(who);
+
+						Self::deposit_event(Event::RefundProvision {
+							who: who.clone(),
+							currency_0: trading_pair.0,
+							contribution_0,
+							currency_1: trading_pair.1,
+							contribution_1,
+						});
+					}
+					Ok(())
+				},
+			)?;
+
+			Ok(())
+		}
+
 		fn do_add_provision(
 			who: &T::AccountId,
 			currency_id_a: T::AssetId,
@@ -1039,14 +1547,14 @@ pub mod pallet {
 						pool.1.checked_add(&contribution_1).ok_or(ArithmeticError::Overflow)?;
 
 					let module_account_id = Self::account_id();
-					<pallet_assets::Pallet<T> as Transfer<T::AccountId>>::transfer(
+					T::Currencies::transfer(
 						trading_pair.0,
 						who,
 						&module_account_id,
 						contribution_0,
 						false,
 					)?;
-					<pallet_assets::Pallet<T> as Transfer<T::AccountId>>::transfer(
+					T::Currencies::transfer(
 						trading_pair.1,
 						who,
 						&module_account_id,
@@ -1104,7 +1612,6 @@ pub mod pallet {
 			max_amount_b: T::Balance,
 			min_share_increment: T::Balance,
 		) -> sp_std::result::Result<(T::Balance, T::Balance, T::Balance), DispatchError> {
-			let dex_share_currency_id = Self::pair_assetid(currency_id_a, currency_id_b).unwrap();
 			let trading_pair = Self::get_pair(currency_id_a, currency_id_b);
 
 			ensure!(
@@ -1114,6 +1621,8 @@ pub mod pallet {
 				),
 				Error::<T>::MustBeEnabled,
 			);
+			let dex_share_currency_id = Self::dex_share_currency_ids(trading_pair.clone())
+				.ok_or(Error::<T>::InvalidCurrencyId)?;
 
 			ensure!(
 				!max_amount_a.is_zero() && !max_amount_b.is_zero(),
@@ -1188,8 +1697,8 @@ pub mod pallet {
 				);
 
 				let module_account_id = Self::account_id();
-				<pallet_assets::Pallet<T> as Transfer<T::AccountId>>::transfer(trading_pair.0, who, &module_account_id, pool_0_increment,false)?;
-				<pallet_assets::Pallet<T> as Transfer<T::AccountId>>::transfer(trading_pair.1, who, &module_account_id, pool_1_increment,false)?;
+				T::Currencies::transfer(trading_pair.0, who, &module_account_id, pool_0_increment,false)?;
+				T::Currencies::transfer(trading_pair.1, who, &module_account_id, pool_1_increment,false)?;
 				pallet_assets::Pallet::<T>::mint_into(dex_share_currency_id, &who, share_increment)?;
 
 				*pool_0 = pool_0.checked_add(&pool_0_increment).ok_or(ArithmeticError::Overflow)?;
@@ -1232,7 +1741,8 @@ pub mod pallet {
 			}
 			let trading_pair = Self::get_pair(currency_id_a, currency_id_b);
 
-			let dex_share_currency_id = Self::pair_assetid(currency_id_a, currency_id_b).unwrap();
+			let dex_share_currency_id = Self::dex_share_currency_ids(trading_pair.clone())
+				.ok_or(Error::<T>::InvalidCurrencyId)?;
 
 			Self::try_mutate_liquidity_pool(
 			trading_pair.clone(),
@@ -1255,11 +1765,11 @@ pub mod pallet {
 				);
 
 				if by_unstake {
-					//T::DEXIncentives::do_withdraw_dex_share(who, dex_share_currency_id, remove_share)?;
+					T::DEXIncentives::do_withdraw_dex_share(who, dex_share_currency_id, remove_share)?;
 				}
 				<pallet_assets::Pallet<T> as Unbalanced<T::AccountId>>::decrease_balance(dex_share_currency_id, who, remove_share)?;
-				<pallet_assets::Pallet<T> as Transfer<T::AccountId>>::transfer(trading_pair.0, &module_account_id, who, pool_0_decrement,false)?;
-				<pallet_assets::Pallet<T> as Transfer<T::AccountId>>::transfer(trading_pair.1, &module_account_id, who, pool_1_decrement,false)?;
+				T::Currencies::transfer(trading_pair.0, &module_account_id, who, pool_0_decrement,false)?;
+				T::Currencies::transfer(trading_pair.1, &module_account_id, who, pool_1_decrement,false)?;
 
 				*pool_0 = pool_0.checked_sub(&pool_0_decrement).ok_or(ArithmeticError::Underflow)?;
 				*pool_1 = pool_1.checked_sub(&pool_1_decrement).ok_or(ArithmeticError::Underflow)?;
@@ -1295,57 +1805,124 @@ pub mod pallet {
 			}
 		}
 
+		/// The fee rate of `trading_pair`.
+		pub fn exchange_fee(trading_pair: &TradingPair<T::AssetId>) -> (u32, u32) {
+			Self::exchange_fees(trading_pair).unwrap_or_else(T::GetExchangeFee::get)
+		}
+
+		fn ensure_valid_curve(curve: Curve) -> DispatchResult {
+			if let Curve::StableSwap { amplification } = curve {
+				ensure!(
+					amplification > 0 && amplification <= T::MaxAmplification::get(),
+					Error::<T>::InvalidCurve
+				);
+			}
+			Ok(())
+		}
+
+		/// The curve of a trading pair can only be changed while its pool is empty.
+		fn ensure_curve_changeable(
+			trading_pair: &TradingPair<T::AssetId>,
+			curve: Curve,
+		) -> DispatchResult {
+			let (pool_0, pool_1) = Self::liquidity_pool(trading_pair);
+			ensure!(
+				Self::curves(trading_pair) == curve || (pool_0.is_zero() && pool_1.is_zero()),
+				Error::<T>::CurveLocked
+			);
+			Ok(())
+		}
+
+		/// The invariant of `curve` for a pool holding `pool_0` and `pool_1`.
+		fn invariant(curve: Curve, pool_0: T::Balance, pool_1: T::Balance) -> Option<U256> {
+			let (pool_0, pool_1) = (
+				U256::from(pool_0.saturated_into::<u128>()),
+				U256::from(pool_1.saturated_into::<u128>()),
+			);
+			match curve {
+				Curve::ConstantProduct => pool_0.checked_mul(pool_1),
+				Curve::StableSwap { amplification } =>
+					stable_swap::get_d(pool_0, pool_1, amplification),
+			}
+		}
+
 		/// Get how much target amount will be got for specific supply amount.
-		fn get_target_amount(
+		pub(crate) fn get_target_amount(
 			supply_pool: T::Balance,
 			target_pool: T::Balance,
 			supply_amount: T::Balance,
+			(fee_numerator, fee_denominator): (u32, u32),
+			curve: Curve,
 		) -> T::Balance {
 			if supply_amount.is_zero() || supply_pool.is_zero() || target_pool.is_zero() {
 				Zero::zero()
+			} else if let Curve::StableSwap { amplification } = curve {
+				stable_swap::get_target_amount(
+					supply_pool.saturated_into(),
+					target_pool.saturated_into(),
+					supply_amount.saturated_into(),
+					(fee_numerator, fee_denominator),
+					amplification,
+				)
+				.and_then(|n| T::Balance::try_from(n).ok())
+				.unwrap_or_else(Zero::zero)
 			} else {
-				let (fee_numerator, fee_denominator) = T::GetExchangeFee::get();
-				let supply_amount_with_fee = supply_amount
+				let supply_amount_with_fee = U256::from(supply_amount.saturated_into::<u128>())
 					.saturating_mul(fee_denominator.saturating_sub(fee_numerator).into());
-				let numerator = supply_amount_with_fee.saturating_mul(target_pool);
-				let denominator = supply_pool
+				let numerator = supply_amount_with_fee
+					.saturating_mul(U256::from(target_pool.saturated_into::<u128>()));
+				let denominator = U256::from(supply_pool.saturated_into::<u128>())
 					.saturating_mul(fee_denominator.into())
 					.saturating_add(supply_amount_with_fee);
 
 				numerator
-					.checked_div(&denominator)
-					.and_then(|n| TryInto::<T::Balance>::try_into(n).ok())
+					.checked_div(denominator)
+					.and_then(|n| u128::try_from(n).ok())
+					.and_then(|n| T::Balance::try_from(n).ok())
 					.unwrap_or_else(Zero::zero)
 			}
 		}
 
 		/// Get how much supply amount will be paid for specific target amount.
-		fn get_supply_amount(
+		pub(crate) fn get_supply_amount(
 			supply_pool: T::Balance,
 			target_pool: T::Balance,
 			target_amount: T::Balance,
+			(fee_numerator, fee_denominator): (u32, u32),
+			curve: Curve,
 		) -> T::Balance {
 			if target_amount.is_zero() || supply_pool.is_zero() || target_pool.is_zero() {
 				Zero::zero()
+			} else if let Curve::StableSwap { amplification } = curve {
+				stable_swap::get_supply_amount(
+					supply_pool.saturated_into(),
+					target_pool.saturated_into(),
+					target_amount.saturated_into(),
+					(fee_numerator, fee_denominator),
+					amplification,
+				)
+				.and_then(|n| T::Balance::try_from(n).ok())
+				.unwrap_or_else(Zero::zero)
 			} else {
-				let (fee_numerator, fee_denominator) = T::GetExchangeFee::get();
-				let numerator = supply_pool
-					.saturating_mul(target_amount)
+				let numerator = U256::from(supply_pool.saturated_into::<u128>())
+					.saturating_mul(U256::from(target_amount.saturated_into::<u128>()))
 					.saturating_mul(fee_denominator.into());
-				let denominator = target_pool
-					.saturating_sub(target_amount)
+				let denominator = U256::from(target_pool.saturated_into::<u128>())
+					.saturating_sub(U256::from(target_amount.saturated_into::<u128>()))
 					.saturating_mul(fee_denominator.saturating_sub(fee_numerator).into());
 
 				numerator
-					.checked_div(&denominator)
-					.and_then(|r| r.checked_add(&T::Balance::one())) // add 1 to result so that correct the possible losses caused by remainder
+					.checked_div(denominator)
+					.and_then(|r| r.checked_add(U256::one())) // add 1 to result so that correct the possible losses caused by remainder
 					// discarding in
-					.and_then(|n| TryInto::<T::Balance>::try_into(n).ok())
+					.and_then(|n| u128::try_from(n).ok())
+					.and_then(|n| T::Balance::try_from(n).ok())
 					.unwrap_or_else(Zero::zero)
 			}
 		}
 
-		fn get_target_amounts(
+		/// Amounts along `path` when supplying exactly `supply_amount`.
+		pub fn get_target_amounts(
 			path: &[T::AssetId],
 			supply_amount: T::Balance,
 		) -> sp_std::result::Result<Vec<T::Balance>, DispatchError> {
@@ -1357,8 +1934,6 @@ pub mod pallet {
 
 			let mut i: usize = 0;
 			while i + 1 < path_length {
-				let dex_share_currency_id = Self::pair_assetid(path[i], path[i + 1])
-					.ok_or(Error::<T>::InvalidCurrencyId)?;
 				let trading_pair = Self::get_pair(path[i], path[i + 1]);
 				ensure!(
 					matches!(
@@ -1372,8 +1947,13 @@ pub mod pallet {
 					!supply_pool.is_zero() && !target_pool.is_zero(),
 					Error::<T>::InsufficientLiquidity
 				);
-				let target_amount =
-					Self::get_target_amount(supply_pool, target_pool, target_amounts[i]);
+				let target_amount = Self::get_target_amount(
+					supply_pool,
+					target_pool,
+					target_amounts[i],
+					Self::exchange_fee(&trading_pair),
+					Self::curves(trading_pair.clone()),
+				);
 				ensure!(!target_amount.is_zero(), Error::<T>::ZeroTargetAmount);
 
 				target_amounts[i + 1] = target_amount;
@@ -1383,7 +1963,8 @@ pub mod pallet {
 			Ok(target_amounts)
 		}
 
-		fn get_supply_amounts(
+		/// Amounts along `path` when receiving exactly `target_amount`.
+		pub fn get_supply_amounts(
 			path: &[T::AssetId],
 			target_amount: T::Balance,
 		) -> sp_std::result::Result<Vec<T::Balance>, DispatchError> {
@@ -1395,12 +1976,10 @@ pub mod pallet {
 
 			let mut i: usize = path_length - 1;
 			while i > 0 {
-				let dex_share_currency_id = Self::pair_assetid(path[i - 1], path[i])
-					.ok_or(Error::<T>::InvalidCurrencyId)?;
 				let traiding_pair = Self::get_pair(path[i - 1], path[i]);
 				ensure!(
 					matches!(
-						Self::trading_pair_statuses(traiding_pair),
+						Self::trading_pair_statuses(traiding_pair.clone()),
 						TradingPairStatus::<_, _>::Enabled
 					),
 					Error::<T>::MustBeEnabled
@@ -1410,8 +1989,13 @@ pub mod pallet {
 					!supply_pool.is_zero() && !target_pool.is_zero(),
 					Error::<T>::InsufficientLiquidity
 				);
-				let supply_amount =
-					Self::get_supply_amount(supply_pool, target_pool, supply_amounts[i]);
+				let supply_amount = Self::get_supply_amount(
+					supply_pool,
+					target_pool,
+					supply_amounts[i],
+					Self::exchange_fee(&traiding_pair),
+					Self::curves(traiding_pair.clone()),
+				);
 				ensure!(!supply_amount.is_zero(), Error::<T>::ZeroSupplyAmount);
 
 				supply_amounts[i - 1] = supply_amount;
@@ -1432,7 +2016,7 @@ pub mod pallet {
 			Ok(())
 		}
 
-		fn _swap(
+		pub(crate) fn _swap(
 			supply_currency_id: T::AssetId,
 			target_currency_id: T::AssetId,
 			supply_increment: T::Balance,
@@ -1440,34 +2024,72 @@ pub mod pallet {
 		) -> DispatchResult {
 			let traiding_pair = Self::get_pair(supply_currency_id, target_currency_id);
 
-			Self::try_mutate_liquidity_pool(traiding_pair, |(pool_0, pool_1)| -> DispatchResult {
-				let invariant_before_swap = pool_0.saturating_mul(*pool_1);
+			// the protocol share of the fee is kept out of the pool
+			let (fee_numerator, fee_denominator) = Self::exchange_fee(&traiding_pair);
+			let fee = supply_increment
+				.saturating_mul(fee_numerator.into())
+				.checked_div(&fee_denominator.into())
+				.unwrap_or_else(Zero::zero);
+			let protocol_fee = Self::protocol_fee_share().mul_floor(fee);
+			let pool_increment = supply_increment.saturating_sub(protocol_fee);
+			let curve = Self::curves(traiding_pair.clone());
 
-				if supply_currency_id < target_currency_id {
-					*pool_0 =
-						pool_0.checked_add(&supply_increment).ok_or(ArithmeticError::Overflow)?;
-					*pool_1 =
-						pool_1.checked_sub(&target_decrement).ok_or(ArithmeticError::Underflow)?;
-				} else {
-					*pool_0 =
-						pool_0.checked_sub(&target_decrement).ok_or(ArithmeticError::Underflow)?;
-					*pool_1 =
-						pool_1.checked_add(&supply_increment).ok_or(ArithmeticError::Overflow)?;
-				}
+			Self::try_mutate_liquidity_pool(
+				traiding_pair.clone(),
+				|(pool_0, pool_1)| -> DispatchResult {
+					let invariant_before_swap = Self::invariant(curve, *pool_0, *pool_1)
+						.ok_or(Error::<T>::InvariantCheckFailed)?;
+
+					if supply_currency_id < target_currency_id {
+						*pool_0 =
+							pool_0.checked_add(&pool_increment).ok_or(ArithmeticError::Overflow)?;
+						*pool_1 = pool_1
+							.checked_sub(&target_decrement)
+							.ok_or(ArithmeticError::Underflow)?;
+					} else {
+						*pool_0 = pool_0
+							.checked_sub(&target_decrement)
+							.ok_or(ArithmeticError::Underflow)?;
+						*pool_1 =
+							pool_1.checked_add(&pool_increment).ok_or(ArithmeticError::Overflow)?;
+					}
 
-				// invariant check to ensure the constant product formulas (k = x * y)
-				let invariant_after_swap = pool_0.saturating_mul(*pool_1);
-				ensure!(
-					invariant_after_swap >= invariant_before_swap,
-					Error::<T>::InvariantCheckFailed,
-				);
-				Ok(())
-			})?;
+					// invariant check to ensure the curve formulas, k = x * y for constant product
+					let invariant_after_swap = Self::invariant(curve, *pool_0, *pool_1)
+						.ok_or(Error::<T>::InvariantCheckFailed)?;
+					ensure!(
+						invariant_after_swap >= invariant_before_swap,
+						Error::<T>::InvariantCheckFailed,
+					);
+					Ok(())
+				},
+			)?;
+
+			if !fee.is_zero() {
+				CumulativeFees::<T>::mutate(traiding_pair.clone(), |(fee_0, fee_1)| {
+					if supply_currency_id == traiding_pair.0 {
+						*fee_0 = fee_0.saturating_add(fee);
+					} else {
+						*fee_1 = fee_1.saturating_add(fee);
+					}
+				});
+				if !protocol_fee.is_zero() {
+					ProtocolFees::<T>::mutate(supply_currency_id, |amount| {
+						*amount = amount.saturating_add(protocol_fee)
+					});
+				}
+				Self::deposit_event(Event::FeeCharged {
+					trading_pair: traiding_pair,
+					currency_id: supply_currency_id,
+					fee,
+					protocol_fee,
+				});
+			}
 
 			Ok(())
 		}
 
-		fn _swap_by_path(path: &[T::AssetId], amounts: &[T::Balance]) -> DispatchResult {
+		pub(crate) fn _swap_by_path(path: &[T::AssetId], amounts: &[T::Balance]) -> DispatchResult {
 			let mut i: usize = 0;
 			while i + 1 < path.len() {
 				let (supply_currency_id, target_currency_id) = (path[i], path[i + 1]);
@@ -1485,7 +2107,7 @@ pub mod pallet {
 
 		/// Ensured atomic.
 		#[transactional]
-		fn do_swap_with_exact_supply(
+		pub(crate) fn do_swap_with_exact_supply(
 			who: &T::AccountId,
 			path: &[T::AssetId],
 			supply_amount: T::Balance,
@@ -1499,15 +2121,9 @@ pub mod pallet {
 			let module_account_id = Self::account_id();
 			let actual_target_amount = amounts[amounts.len() - 1];
 
-			<pallet_assets::Pallet<T> as Transfer<T::AccountId>>::transfer(
-				path[0],
-				who,
-				&module_account_id,
-				supply_amount,
-				false,
-			)?;
+			T::Currencies::transfer(path[0], who, &module_account_id, supply_amount, false)?;
 			Self::_swap_by_path(path, &amounts)?;
-			<pallet_assets::Pallet<T> as Transfer<T::AccountId>>::transfer(
+			T::Currencies::transfer(
 				path[path.len() - 1],
 				&module_account_id,
 				who,
@@ -1525,7 +2141,7 @@ pub mod pallet {
 
 		/// Ensured atomic.
 		#[transactional]
-		fn do_swap_with_exact_target(
+		pub(crate) fn do_swap_with_exact_target(
 			who: &T::AccountId,
 			path: &[T::AssetId],
 			target_amount: T::Balance,
@@ -1536,15 +2152,9 @@ pub mod pallet {
 			let module_account_id = Self::account_id();
 			let actual_supply_amount = amounts[0];
 
-			<pallet_assets::Pallet<T> as Transfer<T::AccountId>>::transfer(
-				path[0],
-				who,
-				&module_account_id,
-				actual_supply_amount,
-				false,
-			)?;
+			T::Currencies::transfer(path[0], who, &module_account_id, actual_supply_amount, false)?;
 			Self::_swap_by_path(path, &amounts)?;
-			<pallet_assets::Pallet<T> as Transfer<T::AccountId>>::transfer(
+			T::Currencies::transfer(
 				path[path.len() - 1],
 				&module_account_id,
 				who,
@@ -1560,9 +2170,161 @@ pub mod pallet {
 			Ok(actual_supply_amount)
 		}
 
+		/// Reserves and status of every listed trading pair.
+		pub fn trading_pairs() -> Vec<TradingPairInfo<T::AssetId, T::Balance, T::BlockNumber>> {
+			TradingPairStatuses::<T>::iter()
+				.map(|(trading_pair, status)| TradingPairInfo {
+					liquidity_pool: Self::liquidity_pool(trading_pair.clone()),
+					dex_share_currency_id: Self::dex_share_currency_ids(trading_pair.clone()),
+					curve: Self::curves(trading_pair.clone()),
+					trading_pair,
+					status,
+				})
+				.collect()
+		}
+
+		/// Record both currencies of an enabled trading pair in `TradingPairIndex`.
+		pub(crate) fn index_trading_pair(trading_pair: &TradingPair<T::AssetId>) {
+			for (currency_id, other) in
+				[(trading_pair.0, trading_pair.1), (trading_pair.1, trading_pair.0)]
+			{
+				TradingPairIndex::<T>::mutate(currency_id, |others| {
+					if !others.contains(&other) {
+						others.push(other);
+					}
+				});
+			}
+		}
+
+		fn unindex_trading_pair(trading_pair: &TradingPair<T::AssetId>) {
+			for (currency_id, other) in
+				[(trading_pair.0, trading_pair.1), (trading_pair.1, trading_pair.0)]
+			{
+				TradingPairIndex::<T>::mutate_exists(currency_id, |maybe_others| {
+					if let Some(others) = maybe_others {
+						others.retain(|id| *id != other);
+						if others.is_empty() {
+							*maybe_others = None;
+						}
+					}
+				});
+			}
+		}
+
+		/// The best path from `supply_currency_id` to `target_currency_id` among the paths of
+		/// enabled trading pairs up to `TradingPathLimit` long, with its supply and target amounts.
+		///
+		/// At most `MaxSwapPathSearch` path extensions are tried, shorter paths first.
+		pub fn find_best_swap_path(
+			supply_currency_id: T::AssetId,
+			target_currency_id: T::AssetId,
+			limit: SwapLimit<T::Balance>,
+		) -> Option<(Vec<T::AssetId>, T::Balance, T::Balance)> {
+			let mut best = None;
+			let mut budget = T::MaxSwapPathSearch::get();
+			let path_limit: usize = T::TradingPathLimit::get().saturated_into();
+			for max_length in 2..=path_limit {
+				Self::search_swap_paths(
+					target_currency_id,
+					&limit,
+					max_length,
+					&mut vec![supply_currency_id],
+					&mut budget,
+					&mut best,
+				);
+				if budget.is_zero() {
+					break
+				}
+			}
+			best
+		}
+
+		/// Depth first search of the paths extending `path` to `target_currency_id` that are
+		/// exactly `max_length` long, keeping the best priced one in `best`. Paths never visit a
+		/// currency twice, and every extension consumes one unit of `budget`.
+		fn search_swap_paths(
+			target_currency_id: T::AssetId,
+			limit: &SwapLimit<T::Balance>,
+			max_length: usize,
+			path: &mut Vec<T::AssetId>,
+			budget: &mut u32,
+			best: &mut Option<(Vec<T::AssetId>, T::Balance, T::Balance)>,
+		) {
+			for next in Self::trading_pair_index(path[path.len() - 1]) {
+				if budget.is_zero() {
+					return
+				}
+				// only the target may end a path, and only at `max_length`
+				let ends_path = path.len() + 1 == max_length;
+				if path.contains(&next) || (next == target_currency_id) != ends_path {
+					continue
+				}
+				*budget -= 1;
+				path.push(next);
+				if next == target_currency_id {
+					if let Some((supply_amount, target_amount)) =
+						Self::get_swap_amount(path, limit.clone())
+					{
+						let is_better = match best {
+							Some((_, best_supply, best_target)) =>
+								supply_amount < *best_supply || target_amount > *best_target,
+							None => true,
+						};
+						if is_better {
+							*best = Some((path.clone(), supply_amount, target_amount));
+						}
+					}
+				} else {
+					Self::search_swap_paths(
+						target_currency_id,
+						limit,
+						max_length,
+						path,
+						budget,
+						best,
+					);
+				}
+				path.pop();
+			}
+		}
+
 		// add here
 	}
 
+	impl<T: Config> DEXPriceOracle<T::AssetId, T::BlockNumber> for Pallet<T> {
+		fn get_twap(
+			currency_id_a: T::AssetId,
+			currency_id_b: T::AssetId,
+			window: T::BlockNumber,
+		) -> Option<Price> {
+			let trading_pair = Self::get_pair(currency_id_a, currency_id_b);
+			let now = frame_system::Pallet::<T>::block_number();
+			let start = now.checked_sub(&window)?;
+
+			// the latest snapshot is valid from its block on, as the reserves have not changed since
+			let mut latest = Self::cumulative_prices(&trading_pair)?;
+			let observation = Self::price_observations(&trading_pair)
+				.into_iter()
+				.chain(sp_std::iter::once(latest))
+				.filter(|observation| observation.block_number <= start)
+				.max_by_key(|observation| observation.block_number)?;
+
+			let (pool_0, pool_1) = Self::liquidity_pool(&trading_pair);
+			Self::accumulate_price(&trading_pair, &mut latest, pool_0, pool_1, now);
+			let elapsed: u128 = now.saturating_sub(observation.block_number).saturated_into();
+			if elapsed.is_zero() {
+				return None
+			}
+
+			let price_cumulative = if currency_id_a == trading_pair.0 {
+				latest.price_0_cumulative.wrapping_sub(observation.price_0_cumulative)
+			} else {
+				latest.price_1_cumulative.wrapping_sub(observation.price_1_cumulative)
+			};
+			Some(Price::from_inner(price_cumulative / elapsed))
+		}
+	}
+
 	impl<T: Config> DEXManager<T::AccountId, T::AssetId, T::Balance> for Pallet<T> {
 		fn get_liquidity_pool(
 			currency_id_a: T::AssetId,
@@ -1634,9 +2396,36 @@ pub mod pallet {
 				}
 			}
 
+			if let Some((swap_path, supply_amount, target_amount)) =
+				Self::find_best_swap_path(supply_currency_id, target_currency_id, limit)
+			{
+				let is_better = match maybe_best {
+					Some((_, previous_supply, previous_target)) =>
+						supply_amount < previous_supply || target_amount > previous_target,
+					None => true,
+				};
+				if is_better {
+					maybe_best = Some((swap_path, supply_amount, target_amount));
+				}
+			}
+
 			maybe_best.map(|(path, _, _)| path)
 		}
 
+		fn is_trading_pair_enabled(currency_id_a: T::AssetId, currency_id_b: T::AssetId) -> bool {
+			currency_id_a != currency_id_b &&
+				matches!(
+					Self::trading_pair_statuses(Self::get_pair(currency_id_a, currency_id_b)),
+					TradingPairStatus::<_, _>::Enabled
+				)
+		}
+
+		fn get_dex_share_trading_pair(
+			lp_currency_id: T::AssetId,
+		) -> Option<TradingPair<T::AssetId>> {
+			Self::dex_share_trading_pairs(lp_currency_id)
+		}
+
 		fn swap_with_specific_path(
 			who: &T::AccountId,
 			path: &[T::AssetId],
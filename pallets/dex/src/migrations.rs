@@ -0,0 +1,214 @@
+//! Storage migrations for the dex pallet.
+
+use super::*;
+use frame_support::{
+	log,
+	traits::{Get, GetStorageVersion, StorageVersion},
+	weights::Weight,
+};
+use sp_runtime::traits::{CheckedAdd, CheckedShl};
+
+/// Builds `TradingPairIndex` from the trading pairs that are already enabled.
+pub mod v1 {
+	use super::*;
+
+	pub fn migrate<T: Config>() -> Weight {
+		let on_chain_version = Pallet::<T>::on_chain_storage_version();
+		if on_chain_version >= 1 {
+			return T::DbWeight::get().reads(1)
+		}
+
+		let mut reads: Weight = 1;
+		let mut indexed: Weight = 0;
+		for (trading_pair, status) in TradingPairStatuses::<T>::iter() {
+			reads = reads.saturating_add(1);
+			if matches!(status, TradingPairStatus::<_, _>::Enabled) {
+				Pallet::<T>::index_trading_pair(&trading_pair);
+				indexed = indexed.saturating_add(1);
+			}
+		}
+		log::info!(target: "runtime::dex", "indexed {} enabled trading pairs", indexed);
+
+		StorageVersion::new(1).put::<Pallet<T>>();
+		T::DbWeight::get().reads_writes(
+			reads.saturating_add(indexed.saturating_mul(2)),
+			indexed.saturating_mul(2).saturating_add(1),
+		)
+	}
+}
+
+/// Registers the dex share currencies of existing trading pairs in `DexShareCurrencyIds` and
+/// `DexShareTradingPairs`. Their ids were packed from the pair as
+/// `(currency_0 + 1) << AssetIdShift + currency_1`.
+pub mod v2 {
+	use super::*;
+
+	pub fn migrate<T: Config>() -> Weight {
+		let on_chain_version = Pallet::<T>::on_chain_storage_version();
+		if on_chain_version >= 2 {
+			return T::DbWeight::get().reads(1)
+		}
+
+		let mut reads: Weight = 1;
+		let mut registered: Weight = 0;
+		for (trading_pair, _) in TradingPairStatuses::<T>::iter() {
+			reads = reads.saturating_add(2);
+			let maybe_currency_id = (trading_pair.0 + One::one())
+				.checked_shl(T::AssetIdShift::get())
+				.and_then(|shifted| shifted.checked_add(&trading_pair.1))
+				.filter(|currency_id| {
+					pallet_assets::Pallet::<T>::maybe_total_supply(*currency_id).is_some()
+				});
+			if let Some(currency_id) = maybe_currency_id {
+				Pallet::<T>::register_dex_share_currency(&trading_pair, currency_id);
+				registered = registered.saturating_add(1);
+			}
+		}
+		log::info!(target: "runtime::dex", "registered {} dex share currencies", registered);
+
+		StorageVersion::new(2).put::<Pallet<T>>();
+		T::DbWeight::get().reads_writes(reads, registered.saturating_mul(2).saturating_add(1))
+	}
+}
+
+/// Replaces wrapped SOFI, the asset `NativeAssetId` minted against the native currency locked in
+/// the pallet account, with the native currency itself. Holders get the native currency backing
+/// their wrapped SOFI and the asset is destroyed. The wrapped SOFI of the pallet account backs the
+/// pools, so it stays there as native liquidity.
+///
+/// The payouts and the destruction are all or nothing: if any of them fails, the asset is kept and
+/// the migration is left to run again. The asset accounts have to decode, so runtimes run it after
+/// any migration of the `Extra` of `pallet_assets`, which is ahead of the pallet hooks. It runs
+/// [`v1`] and [`v2`] first, as once it puts version 3 the hooks skip them.
+pub mod v3 {
+	use super::*;
+	#[cfg(feature = "try-runtime")]
+	use frame_support::traits::{tokens::fungibles::Inspect, OnRuntimeUpgradeHelpersExt};
+	use frame_support::{
+		storage::{with_transaction, TransactionOutcome},
+		traits::{
+			tokens::fungibles::{Destroy, Transfer},
+			OnRuntimeUpgrade,
+		},
+	};
+	#[cfg(feature = "try-runtime")]
+	use sp_runtime::traits::Saturating;
+	use sp_std::marker::PhantomData;
+
+	/// Runs [`migrate`] from the runtime, checking the payout under `try-runtime`.
+	pub struct MigrateToV3<T>(PhantomData<T>);
+
+	impl<T: Config> OnRuntimeUpgrade for MigrateToV3<T> {
+		fn on_runtime_upgrade() -> Weight {
+			migrate::<T>()
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn pre_upgrade() -> Result<(), &'static str> {
+			if Pallet::<T>::on_chain_storage_version() >= 3 {
+				return Ok(())
+			}
+
+			let native_asset_id = <T as pallet_assets::Config>::NativeAssetId::get();
+			let module_account_id: T::AccountId = T::PalletId::get().into_account();
+			let holders = pallet_assets::Pallet::<T>::holders(native_asset_id)
+				.ok_or("asset accounts of wrapped SOFI don't decode")?;
+			let payout = holders
+				.iter()
+				.filter(|(who, _)| *who != module_account_id)
+				.fold(T::Balance::zero(), |payout, (_, balance)| payout.saturating_add(*balance));
+			let native_balance = T::Currencies::balance(native_asset_id, &module_account_id);
+			ensure!(native_balance >= payout, "the pallet account can't pay out wrapped SOFI");
+
+			Self::set_temp_storage(native_balance - payout, "native_balance");
+			Ok(())
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade() -> Result<(), &'static str> {
+			ensure!(Pallet::<T>::on_chain_storage_version() == 3, "dex storage version isn't 3");
+
+			let native_asset_id = <T as pallet_assets::Config>::NativeAssetId::get();
+			ensure!(
+				pallet_assets::Pallet::<T>::maybe_total_supply(native_asset_id).is_none(),
+				"wrapped SOFI wasn't destroyed"
+			);
+			if let Some(native_balance) = Self::get_temp_storage::<T::Balance>("native_balance") {
+				let module_account_id: T::AccountId = T::PalletId::get().into_account();
+				ensure!(
+					T::Currencies::balance(native_asset_id, &module_account_id) == native_balance,
+					"wrapped SOFI wasn't paid out in full"
+				);
+			}
+			Ok(())
+		}
+	}
+
+	pub fn migrate<T: Config>() -> Weight {
+		let weight = v1::migrate::<T>().saturating_add(v2::migrate::<T>());
+		let on_chain_version = Pallet::<T>::on_chain_storage_version();
+		if on_chain_version >= 3 {
+			return weight.saturating_add(T::DbWeight::get().reads(1))
+		}
+
+		let native_asset_id = <T as pallet_assets::Config>::NativeAssetId::get();
+		let module_account_id: T::AccountId = T::PalletId::get().into_account();
+		let holders = match pallet_assets::Pallet::<T>::holders(native_asset_id) {
+			Some(holders) => holders,
+			None => {
+				log::error!(
+					target: "runtime::dex",
+					"asset accounts of wrapped SOFI don't decode, migrate the Extra of pallet_assets first"
+				);
+				return weight.saturating_add(T::DbWeight::get().reads(2))
+			},
+		};
+		let paid_out = with_transaction(|| {
+			let mut paid_out: Weight = 0;
+			for (who, balance) in holders.iter().filter(|(who, _)| *who != module_account_id) {
+				if let Err(e) = T::Currencies::transfer(
+					native_asset_id,
+					&module_account_id,
+					who,
+					*balance,
+					false,
+				) {
+					log::error!(
+						target: "runtime::dex",
+						"paying out wrapped SOFI of {:?} failed: {:?}",
+						who,
+						e
+					);
+					return TransactionOutcome::Rollback(None)
+				}
+				paid_out = paid_out.saturating_add(1);
+			}
+			if let Some(witness) = pallet_assets::Pallet::<T>::get_destroy_witness(&native_asset_id)
+			{
+				if let Err(e) = <pallet_assets::Pallet<T> as Destroy<T::AccountId>>::destroy(
+					native_asset_id,
+					witness,
+					None,
+				) {
+					log::error!(target: "runtime::dex", "destroying wrapped SOFI failed: {:?}", e);
+					return TransactionOutcome::Rollback(None)
+				}
+			}
+			TransactionOutcome::Commit(Some(paid_out))
+		});
+
+		let holders = holders.len() as Weight;
+		let paid_out = match paid_out {
+			Some(paid_out) => paid_out,
+			None =>
+				return weight.saturating_add(T::DbWeight::get().reads(holders.saturating_add(2))),
+		};
+		log::info!(target: "runtime::dex", "paid out wrapped SOFI to {} accounts", paid_out);
+
+		StorageVersion::new(3).put::<Pallet<T>>();
+		weight.saturating_add(T::DbWeight::get().reads_writes(
+			holders.saturating_add(paid_out.saturating_mul(2)).saturating_add(2),
+			holders.saturating_add(paid_out.saturating_mul(2)).saturating_add(2),
+		))
+	}
+}
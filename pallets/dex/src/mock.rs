@@ -23,40 +23,38 @@
 use super::*;
 use frame_support::{
 	construct_runtime, ord_parameter_types, parameter_types,
-	traits::{Everything, Nothing},
+	traits::{tokens::fungibles::Transfer, ConstU128, ConstU16, ConstU32, ConstU64, Everything},
 };
-use frame_system::EnsureSignedBy;
-use orml_traits::{parameter_type_with_key, MultiReservableCurrency};
-use primitives::{Amount, TokenSymbol};
+use frame_system::{EnsureRoot, EnsureSignedBy};
 use sp_core::H256;
 use sp_runtime::{testing::Header, traits::IdentityLookup};
-use sp_std::cell::RefCell;
-use support::mocks::MockErc20InfoMapping;
+use std::cell::RefCell;
 
 pub type BlockNumber = u64;
 pub type AccountId = u128;
+pub type CurrencyId = u32;
+pub type Balance = u128;
 
 pub const ALICE: AccountId = 1;
 pub const BOB: AccountId = 2;
-pub const AUSD: CurrencyId = CurrencyId::Token(TokenSymbol::AUSD);
-pub const BTC: CurrencyId = CurrencyId::Token(TokenSymbol::RENBTC);
-pub const DOT: CurrencyId = CurrencyId::Token(TokenSymbol::DOT);
-pub const ACA: CurrencyId = CurrencyId::Token(TokenSymbol::ACA);
+pub const INCENTIVES: AccountId = 4;
+pub const TREASURY: AccountId = 5;
+
+pub const ACA: CurrencyId = 0;
+pub const AUSD: CurrencyId = 1;
+pub const DOT: CurrencyId = 2;
+pub const BTC: CurrencyId = 3;
 
 parameter_types! {
-	pub static AUSDBTCPair: TradingPair = TradingPair::from_currency_ids(AUSD, BTC).unwrap();
-	pub static AUSDDOTPair: TradingPair = TradingPair::from_currency_ids(AUSD, DOT).unwrap();
-	pub static DOTBTCPair: TradingPair = TradingPair::from_currency_ids(DOT, BTC).unwrap();
+	pub AUSDBTCPair: TradingPair<CurrencyId> = TradingPair::new(AUSD, BTC);
+	pub AUSDDOTPair: TradingPair<CurrencyId> = TradingPair::new(AUSD, DOT);
+	pub DOTBTCPair: TradingPair<CurrencyId> = TradingPair::new(DOT, BTC);
 }
 
 mod dex {
 	pub use super::super::*;
 }
 
-parameter_types! {
-	pub const BlockHashCount: BlockNumber = 250;
-}
-
 impl frame_system::Config for Runtime {
 	type Origin = Origin;
 	type Index = u64;
@@ -68,7 +66,7 @@ impl frame_system::Config for Runtime {
 	type Lookup = IdentityLookup<Self::AccountId>;
 	type Header = Header;
 	type Event = Event;
-	type BlockHashCount = BlockHashCount;
+	type BlockHashCount = ConstU64<250>;
 	type BlockWeights = ();
 	type BlockLength = ();
 	type Version = ();
@@ -79,29 +77,72 @@ impl frame_system::Config for Runtime {
 	type DbWeight = ();
 	type BaseCallFilter = Everything;
 	type SystemWeightInfo = ();
-	type SS58Prefix = ();
+	type SS58Prefix = ConstU16<42>;
 	type OnSetCode = ();
-	type MaxConsumers = frame_support::traits::ConstU32<16>;
+	type MaxConsumers = ConstU32<16>;
+}
+
+impl pallet_balances::Config for Runtime {
+	type Balance = Balance;
+	type DustRemoval = ();
+	type Event = Event;
+	type ExistentialDeposit = ConstU128<1>;
+	type AccountStore = System;
+	type WeightInfo = ();
+	type MaxLocks = ();
+	type MaxReserves = ();
+	type ReserveIdentifier = [u8; 8];
 }
 
-parameter_type_with_key! {
-	pub ExistentialDeposits: |_currency_id: CurrencyId| -> Balance {
-		Default::default()
-	};
+parameter_types! {
+	pub const AirdropReserveId: [u8; 8] = *b"airdrop ";
+}
+
+thread_local! {
+	static NATIVE_ASSET_ID: RefCell<CurrencyId> = RefCell::new(ACA);
+}
+
+/// `ACA` unless moved aside, which lets the tests create wrapped SOFI under the native id.
+pub struct NativeAssetId;
+impl NativeAssetId {
+	pub fn set(currency_id: CurrencyId) {
+		NATIVE_ASSET_ID.with(|v| *v.borrow_mut() = currency_id);
+	}
+}
+impl Get<CurrencyId> for NativeAssetId {
+	fn get() -> CurrencyId {
+		NATIVE_ASSET_ID.with(|v| *v.borrow())
+	}
 }
 
-impl orml_tokens::Config for Runtime {
+impl pallet_assets::Config for Runtime {
 	type Event = Event;
 	type Balance = Balance;
-	type Amount = Amount;
-	type CurrencyId = CurrencyId;
+	type AssetId = CurrencyId;
+	type NativeAssetId = NativeAssetId;
+	type Currency = Balances;
+	type ForceOrigin = EnsureRoot<AccountId>;
+	type AssetDeposit = ConstU128<1>;
+	type AssetAccountDeposit = ConstU128<10>;
+	type MetadataDepositBase = ConstU128<1>;
+	type MetadataDepositPerByte = ConstU128<1>;
+	type ApprovalDeposit = ConstU128<1>;
+	type StringLimit = ConstU32<50>;
+	type Freezer = ();
+	type FreezerWeight = ();
 	type WeightInfo = ();
-	type ExistentialDeposits = ExistentialDeposits;
-	type OnDust = ();
-	type MaxLocks = ();
-	type DustRemovalWhitelist = Nothing;
+	type Extra = ();
+	type MaxLocks = ConstU32<2>;
+	type MaxReserves = ConstU32<2>;
+	type ReserveIdentifier = [u8; 8];
+	type MaxBatchSize = ConstU32<3>;
+	type AirdropReserveId = AirdropReserveId;
+	type AirdropDeposit = ConstU128<1>;
 }
 
+pub type Currencies = pallet_assets::NativeAssetAdapter<Runtime, Balances>;
+
+/// Stakes dex shares by moving them to the `INCENTIVES` account.
 pub struct MockDEXIncentives;
 impl DEXIncentives<AccountId, CurrencyId, Balance> for MockDEXIncentives {
 	fn do_deposit_dex_share(
@@ -109,7 +150,8 @@ impl DEXIncentives<AccountId, CurrencyId, Balance> for MockDEXIncentives {
 		lp_currency_id: CurrencyId,
 		amount: Balance,
 	) -> DispatchResult {
-		Tokens::reserve(lp_currency_id, who, amount)
+		<Assets as Transfer<AccountId>>::transfer(lp_currency_id, who, &INCENTIVES, amount, false)
+			.map(|_| ())
 	}
 
 	fn do_withdraw_dex_share(
@@ -117,8 +159,8 @@ impl DEXIncentives<AccountId, CurrencyId, Balance> for MockDEXIncentives {
 		lp_currency_id: CurrencyId,
 		amount: Balance,
 	) -> DispatchResult {
-		let _ = Tokens::unreserve(lp_currency_id, who, amount);
-		Ok(())
+		<Assets as Transfer<AccountId>>::transfer(lp_currency_id, &INCENTIVES, who, amount, false)
+			.map(|_| ())
 	}
 }
 
@@ -128,35 +170,25 @@ ord_parameter_types! {
 
 parameter_types! {
 	pub const GetExchangeFee: (u32, u32) = (1, 100);
-	pub const TradingPathLimit: u32 = 3;
 	pub const DEXPalletId: PalletId = PalletId(*b"aca/dexm");
 }
 
-thread_local! {
-	pub static AUSD_DOT_POOL_RECORD: RefCell<(Balance, Balance)> = RefCell::new((0, 0));
-}
-
-pub struct MockOnLiquidityPoolUpdated;
-impl Happened<(TradingPair, Balance, Balance)> for MockOnLiquidityPoolUpdated {
-	fn happened(info: &(TradingPair, Balance, Balance)) {
-		let (trading_pair, new_pool_0, new_pool_1) = info;
-		if *trading_pair == AUSDDOTPair::get() {
-			AUSD_DOT_POOL_RECORD.with(|v| *v.borrow_mut() = (*new_pool_0, *new_pool_1));
-		}
-	}
-}
-
 impl Config for Runtime {
 	type Event = Event;
-	type Currency = Tokens;
+	type Currencies = Currencies;
 	type GetExchangeFee = GetExchangeFee;
-	type TradingPathLimit = TradingPathLimit;
+	type TreasuryAccount = ConstU128<TREASURY>;
+	type TradingPathLimit = ConstU32<3>;
+	type PriceObservationInterval = ConstU64<10>;
+	type MaxPriceObservations = ConstU32<3>;
+	type MaxSwapPathSearch = ConstU32<16>;
+	type MaxAmplification = ConstU32<1_000>;
 	type PalletId = DEXPalletId;
-	type Erc20InfoMapping = MockErc20InfoMapping;
-	type WeightInfo = ();
+	type AssetIdShift = ConstU32<16>;
+	type DexShareCurrencyIdStart = ConstU32<1_000>;
+	type MaxDexShareCurrencyIdSearch = ConstU32<3>;
 	type DEXIncentives = MockDEXIncentives;
 	type ListingOrigin = EnsureSignedBy<ListingOrigin, AccountId>;
-	type OnLiquidityPoolUpdated = MockOnLiquidityPoolUpdated;
 }
 
 type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Runtime>;
@@ -169,23 +201,27 @@ construct_runtime!(
 		UncheckedExtrinsic = UncheckedExtrinsic,
 	{
 		System: frame_system::{Pallet, Call, Storage, Config, Event<T>},
+		Balances: pallet_balances::{Pallet, Call, Storage, Config<T>, Event<T>},
+		Assets: pallet_assets::{Pallet, Call, Storage, Config<T>, Event<T>},
 		DexModule: dex::{Pallet, Storage, Call, Event<T>, Config<T>},
-		Tokens: orml_tokens::{Pallet, Storage, Event<T>, Config<T>},
 	}
 );
 
 pub struct ExtBuilder {
 	balances: Vec<(AccountId, CurrencyId, Balance)>,
 	initial_listing_trading_pairs:
-		Vec<(TradingPair, (Balance, Balance), (Balance, Balance), BlockNumber)>,
-	initial_enabled_trading_pairs: Vec<TradingPair>,
-	initial_added_liquidity_pools: Vec<(AccountId, Vec<(TradingPair, (Balance, Balance))>)>,
+		Vec<(TradingPair<CurrencyId>, (Balance, Balance), (Balance, Balance), BlockNumber)>,
+	initial_enabled_trading_pairs: Vec<TradingPair<CurrencyId>>,
+	initial_added_liquidity_pools:
+		Vec<(AccountId, Vec<(TradingPair<CurrencyId>, (Balance, Balance))>)>,
 }
 
 impl Default for ExtBuilder {
 	fn default() -> Self {
 		Self {
 			balances: vec![
+				(ALICE, ACA, 1_000_000_000_000_000_000u128),
+				(BOB, ACA, 1_000_000_000_000_000_000u128),
 				(ALICE, AUSD, 1_000_000_000_000_000_000u128),
 				(BOB, AUSD, 1_000_000_000_000_000_000u128),
 				(ALICE, BTC, 1_000_000_000_000_000_000u128),
@@ -222,9 +258,29 @@ impl ExtBuilder {
 	pub fn build(self) -> sp_io::TestExternalities {
 		let mut t = frame_system::GenesisConfig::default().build_storage::<Runtime>().unwrap();
 
-		orml_tokens::GenesisConfig::<Runtime> { balances: self.balances }
-			.assimilate_storage(&mut t)
-			.unwrap();
+		pallet_balances::GenesisConfig::<Runtime> {
+			balances: self
+				.balances
+				.iter()
+				.filter(|(_, currency_id, _)| *currency_id == ACA)
+				.map(|(who, _, amount)| (*who, *amount))
+				.collect(),
+		}
+		.assimilate_storage(&mut t)
+		.unwrap();
+
+		pallet_assets::GenesisConfig::<Runtime> {
+			assets: vec![(AUSD, ALICE, true, 1), (DOT, ALICE, true, 1), (BTC, ALICE, true, 1)],
+			metadata: vec![],
+			accounts: self
+				.balances
+				.into_iter()
+				.filter(|(_, currency_id, _)| *currency_id != ACA)
+				.map(|(who, currency_id, amount)| (currency_id, who, amount))
+				.collect(),
+		}
+		.assimilate_storage(&mut t)
+		.unwrap();
 
 		dex::GenesisConfig::<Runtime> {
 			initial_listing_trading_pairs: self.initial_listing_trading_pairs,
@@ -234,6 +290,8 @@ impl ExtBuilder {
 		.assimilate_storage(&mut t)
 		.unwrap();
 
-		t.into()
+		let mut ext = sp_io::TestExternalities::new(t);
+		ext.execute_with(|| System::set_block_number(1));
+		ext
 	}
 }
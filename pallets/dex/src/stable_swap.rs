@@ -0,0 +1,191 @@
+//! StableSwap math for a pool of two currencies.
+//!
+//! The invariant `D` satisfies `A * n^n * (x + y) + D = A * n^n * D + D^(n + 1) / (n^n * x * y)`
+//! with `n = 2` and is solved with Newton's method, following the Curve whitepaper. Amounts are
+//! rounded in favour of the pool.
+
+use sp_core::U256;
+
+/// The number of currencies in a pool.
+const N_COINS: u32 = 2;
+
+/// The maximum number of Newton iterations before giving up.
+const MAX_ITERATIONS: u32 = 255;
+
+fn to_u128(value: U256) -> Option<u128> {
+	if value > U256::from(u128::MAX) {
+		None
+	} else {
+		Some(value.low_u128())
+	}
+}
+
+fn abs_diff(a: U256, b: U256) -> U256 {
+	if a > b {
+		a - b
+	} else {
+		b - a
+	}
+}
+
+/// The invariant `D` of a pool holding `x` and `y`, the same whichever order they are given in.
+pub(crate) fn get_d(x: U256, y: U256, amplification: u32) -> Option<U256> {
+	let n = U256::from(N_COINS);
+	let (x, y) = if x <= y { (x, y) } else { (y, x) };
+	let sum = x.checked_add(y)?;
+	if sum.is_zero() {
+		return Some(U256::zero())
+	}
+	if x.is_zero() || y.is_zero() {
+		return None
+	}
+
+	let ann = U256::from(amplification).checked_mul(n.checked_pow(n)?)?;
+	let mut d = sum;
+	for _ in 0..MAX_ITERATIONS {
+		// D^(n + 1) / (n^n * x * y)
+		let d_p = d
+			.checked_mul(d)?
+			.checked_div(x.checked_mul(n)?)?
+			.checked_mul(d)?
+			.checked_div(y.checked_mul(n)?)?;
+		let d_prev = d;
+		let numerator = ann.checked_mul(sum)?.checked_add(d_p.checked_mul(n)?)?.checked_mul(d)?;
+		let denominator = ann
+			.checked_sub(U256::one())?
+			.checked_mul(d)?
+			.checked_add(n.checked_add(U256::one())?.checked_mul(d_p)?)?;
+		d = numerator.checked_div(denominator)?;
+		if abs_diff(d, d_prev) <= U256::one() {
+			return Some(d)
+		}
+	}
+
+	None
+}
+
+/// The balance of the other currency that keeps the invariant at `d` when one currency has
+/// balance `x`.
+pub(crate) fn get_y(x: U256, d: U256, amplification: u32) -> Option<U256> {
+	let n = U256::from(N_COINS);
+	if x.is_zero() {
+		return None
+	}
+
+	let ann = U256::from(amplification).checked_mul(n.checked_pow(n)?)?;
+	let c = d
+		.checked_mul(d)?
+		.checked_div(x.checked_mul(n)?)?
+		.checked_mul(d)?
+		.checked_div(ann.checked_mul(n)?)?;
+	let b = x.checked_add(d.checked_div(ann)?)?;
+	let mut y = d;
+	for _ in 0..MAX_ITERATIONS {
+		let y_prev = y;
+		let numerator = y.checked_mul(y)?.checked_add(c)?;
+		let denominator = y.checked_mul(n)?.checked_add(b)?.checked_sub(d)?;
+		y = numerator.checked_div(denominator)?;
+		if abs_diff(y, y_prev) <= U256::one() {
+			return Some(y)
+		}
+	}
+
+	None
+}
+
+/// The first balance from `y` up that keeps the invariant of a pool holding `x` at or above
+/// `d`. Newton's method stops within one unit of the exact balance, on either side of it.
+fn round_up_to_invariant(x: U256, mut y: U256, d: U256, amplification: u32) -> Option<U256> {
+	for _ in 0..MAX_ITERATIONS {
+		if get_d(x, y, amplification)? >= d {
+			return Some(y)
+		}
+		y = y.checked_add(U256::one())?;
+	}
+
+	None
+}
+
+/// The marginal price of `x` in `y` in units of `1 / accuracy`, the ratio of the partial
+/// derivatives of the invariant: `(Ann + r^2 * s / 4) / (Ann + r * s^2 / 4)` with `r = D / x`,
+/// `s = D / y` and `Ann = A * n^n`.
+pub(crate) fn get_price(x: u128, y: u128, amplification: u32, accuracy: u128) -> Option<u128> {
+	let n = U256::from(N_COINS);
+	let accuracy = U256::from(accuracy);
+	let n_pow_n = n.checked_pow(n)?;
+	let d = get_d(x.into(), y.into(), amplification)?;
+	let ann = U256::from(amplification).checked_mul(n_pow_n)?.checked_mul(accuracy)?;
+
+	// r and s scaled by `accuracy`
+	let r = d.checked_mul(accuracy)?.checked_div(x.into())?;
+	let s = d.checked_mul(accuracy)?.checked_div(y.into())?;
+	let numerator = ann.checked_add(
+		r.checked_mul(r)?
+			.checked_div(accuracy)?
+			.checked_mul(s)?
+			.checked_div(accuracy)?
+			.checked_div(n_pow_n)?,
+	)?;
+	let denominator = ann.checked_add(
+		r.checked_mul(s)?
+			.checked_div(accuracy)?
+			.checked_mul(s)?
+			.checked_div(accuracy)?
+			.checked_div(n_pow_n)?,
+	)?;
+
+	to_u128(numerator.checked_mul(accuracy)?.checked_div(denominator)?)
+}
+
+/// Get how much target amount will be got for specific supply amount.
+pub(crate) fn get_target_amount(
+	supply_pool: u128,
+	target_pool: u128,
+	supply_amount: u128,
+	(fee_numerator, fee_denominator): (u32, u32),
+	amplification: u32,
+) -> Option<u128> {
+	let d = get_d(supply_pool.into(), target_pool.into(), amplification)?;
+	let supply_amount_with_fee = U256::from(supply_amount)
+		.checked_mul(fee_denominator.saturating_sub(fee_numerator).into())?
+		.checked_div(fee_denominator.into())?;
+	let new_supply_pool = U256::from(supply_pool).checked_add(supply_amount_with_fee)?;
+	let new_target_pool = round_up_to_invariant(
+		new_supply_pool,
+		get_y(new_supply_pool, d, amplification)?,
+		d,
+		amplification,
+	)?;
+
+	// rounded down, one unit below what keeps the invariant
+	to_u128(U256::from(target_pool).checked_sub(new_target_pool)?.checked_sub(U256::one())?)
+}
+
+/// Get how much supply amount will be paid for specific target amount.
+pub(crate) fn get_supply_amount(
+	supply_pool: u128,
+	target_pool: u128,
+	target_amount: u128,
+	(fee_numerator, fee_denominator): (u32, u32),
+	amplification: u32,
+) -> Option<u128> {
+	let d = get_d(supply_pool.into(), target_pool.into(), amplification)?;
+	let new_target_pool = U256::from(target_pool).checked_sub(target_amount.into())?;
+	let new_supply_pool = round_up_to_invariant(
+		new_target_pool,
+		get_y(new_target_pool, d, amplification)?,
+		d,
+		amplification,
+	)?;
+	// rounded up, one unit above what keeps the invariant
+	let supply_amount_with_fee =
+		new_supply_pool.checked_sub(supply_pool.into())?.checked_add(U256::one())?;
+	let fee_complement = U256::from(fee_denominator.saturating_sub(fee_numerator));
+
+	to_u128(
+		supply_amount_with_fee
+			.checked_mul(fee_denominator.into())?
+			.checked_add(fee_complement.checked_sub(U256::one())?)?
+			.checked_div(fee_complement)?,
+	)
+}
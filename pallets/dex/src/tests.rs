@@ -21,15 +21,21 @@
 #![cfg(test)]
 
 use super::*;
-use frame_support::{assert_noop, assert_ok};
+use frame_support::{
+	assert_noop, assert_ok,
+	traits::{tokens::fungibles::Inspect, GetStorageVersion, OnRuntimeUpgrade, StorageVersion},
+};
 use mock::{
-	AUSDBTCPair, AUSDDOTPair, DOTBTCPair, DexModule, Event, ExtBuilder, ListingOrigin, Origin,
-	Runtime, System, Tokens, ACA, ALICE, AUSD, AUSD_DOT_POOL_RECORD, BOB, BTC, DOT,
+	AUSDBTCPair, AUSDDOTPair, AccountId, Assets, Balance, Balances, Currencies, DEXPalletId,
+	DOTBTCPair, DexModule, Event, ExtBuilder, ListingOrigin, MockDEXIncentives, NativeAssetId,
+	Origin, Runtime, System, ACA, ALICE, AUSD, BOB, BTC, DOT, INCENTIVES, TREASURY,
 };
-use orml_traits::MultiReservableCurrency;
-use sp_core::H160;
+use sp_core::U256;
 use sp_runtime::traits::BadOrigin;
-use std::str::FromStr;
+
+fn dex_account() -> AccountId {
+	DEXPalletId::get().into_account()
+}
 
 #[test]
 fn list_provisioning_work() {
@@ -46,6 +52,7 @@ fn list_provisioning_work() {
 				5_000_000_000_000u128,
 				2_000_000_000_000u128,
 				10,
+				Curve::ConstantProduct,
 			),
 			BadOrigin
 		);
@@ -63,6 +70,7 @@ fn list_provisioning_work() {
 			5_000_000_000_000u128,
 			2_000_000_000_000u128,
 			10,
+			Curve::ConstantProduct,
 		));
 		assert_eq!(
 			DexModule::trading_pair_statuses(AUSDDOTPair::get()),
@@ -75,6 +83,7 @@ fn list_provisioning_work() {
 		);
 		System::assert_last_event(Event::DexModule(crate::Event::ListProvisioning {
 			trading_pair: AUSDDOTPair::get(),
+			curve: Curve::ConstantProduct,
 		}));
 
 		assert_noop!(
@@ -87,6 +96,7 @@ fn list_provisioning_work() {
 				5_000_000_000_000u128,
 				2_000_000_000_000u128,
 				10,
+				Curve::ConstantProduct,
 			),
 			Error::<Runtime>::InvalidCurrencyId
 		);
@@ -101,36 +111,10 @@ fn list_provisioning_work() {
 				5_000_000_000_000u128,
 				2_000_000_000_000u128,
 				10,
+				Curve::ConstantProduct,
 			),
 			Error::<Runtime>::MustBeDisabled
 		);
-
-		assert_noop!(
-			DexModule::list_provisioning(
-				Origin::signed(ListingOrigin::get()),
-				CurrencyId::ForeignAsset(0),
-				AUSD,
-				1_000_000_000_000u128,
-				1_000_000_000_000u128,
-				5_000_000_000_000u128,
-				2_000_000_000_000u128,
-				10,
-			),
-			Error::<Runtime>::AssetUnregistered
-		);
-		assert_noop!(
-			DexModule::list_provisioning(
-				Origin::signed(ListingOrigin::get()),
-				AUSD,
-				CurrencyId::ForeignAsset(0),
-				1_000_000_000_000u128,
-				1_000_000_000_000u128,
-				5_000_000_000_000u128,
-				2_000_000_000_000u128,
-				10,
-			),
-			Error::<Runtime>::AssetUnregistered
-		);
 	});
 }
 
@@ -176,16 +160,8 @@ fn update_provisioning_parameters_work() {
 			5_000_000_000_000u128,
 			2_000_000_000_000u128,
 			10,
+			Curve::ConstantProduct,
 		));
-		assert_eq!(
-			DexModule::trading_pair_statuses(AUSDDOTPair::get()),
-			TradingPairStatus::<_, _>::Provisioning(ProvisioningParameters {
-				min_contribution: (1_000_000_000_000u128, 1_000_000_000_000u128),
-				target_provision: (5_000_000_000_000u128, 2_000_000_000_000u128),
-				accumulated_provision: (0, 0),
-				not_before: 10,
-			})
-		);
 
 		assert_ok!(DexModule::update_provisioning_parameters(
 			Origin::signed(ListingOrigin::get()),
@@ -214,23 +190,42 @@ fn enable_diabled_trading_pair_work() {
 	ExtBuilder::default().build().execute_with(|| {
 		System::set_block_number(1);
 
-		assert_noop!(DexModule::enable_trading_pair(Origin::signed(ALICE), AUSD, DOT), BadOrigin);
+		assert_noop!(
+			DexModule::enable_trading_pair(
+				Origin::signed(ALICE),
+				AUSD,
+				DOT,
+				Curve::ConstantProduct
+			),
+			BadOrigin
+		);
 
 		assert_eq!(
 			DexModule::trading_pair_statuses(AUSDDOTPair::get()),
 			TradingPairStatus::<_, _>::Disabled
 		);
-		assert_ok!(DexModule::enable_trading_pair(Origin::signed(ListingOrigin::get()), AUSD, DOT));
+		assert_ok!(DexModule::enable_trading_pair(
+			Origin::signed(ListingOrigin::get()),
+			AUSD,
+			DOT,
+			Curve::ConstantProduct
+		));
 		assert_eq!(
 			DexModule::trading_pair_statuses(AUSDDOTPair::get()),
 			TradingPairStatus::<_, _>::Enabled
 		);
 		System::assert_last_event(Event::DexModule(crate::Event::EnableTradingPair {
 			trading_pair: AUSDDOTPair::get(),
+			curve: Curve::ConstantProduct,
 		}));
 
 		assert_noop!(
-			DexModule::enable_trading_pair(Origin::signed(ListingOrigin::get()), DOT, AUSD),
+			DexModule::enable_trading_pair(
+				Origin::signed(ListingOrigin::get()),
+				DOT,
+				AUSD,
+				Curve::ConstantProduct
+			),
 			Error::<Runtime>::AlreadyEnabled
 		);
 	});
@@ -250,6 +245,7 @@ fn enable_provisioning_without_provision_work() {
 			5_000_000_000_000u128,
 			2_000_000_000_000u128,
 			10,
+			Curve::ConstantProduct,
 		));
 		assert_ok!(DexModule::list_provisioning(
 			Origin::signed(ListingOrigin::get()),
@@ -260,6 +256,7 @@ fn enable_provisioning_without_provision_work() {
 			5_000_000_000_000u128,
 			2_000_000_000_000u128,
 			10,
+			Curve::ConstantProduct,
 		));
 		assert_ok!(DexModule::add_provision(
 			Origin::signed(ALICE),
@@ -269,26 +266,28 @@ fn enable_provisioning_without_provision_work() {
 			1_000_000_000_000u128
 		));
 
-		assert_eq!(
-			DexModule::trading_pair_statuses(AUSDDOTPair::get()),
-			TradingPairStatus::<_, _>::Provisioning(ProvisioningParameters {
-				min_contribution: (1_000_000_000_000u128, 1_000_000_000_000u128),
-				target_provision: (5_000_000_000_000u128, 2_000_000_000_000u128),
-				accumulated_provision: (0, 0),
-				not_before: 10,
-			})
-		);
-		assert_ok!(DexModule::enable_trading_pair(Origin::signed(ListingOrigin::get()), AUSD, DOT));
+		assert_ok!(DexModule::enable_trading_pair(
+			Origin::signed(ListingOrigin::get()),
+			AUSD,
+			DOT,
+			Curve::ConstantProduct
+		));
 		assert_eq!(
 			DexModule::trading_pair_statuses(AUSDDOTPair::get()),
 			TradingPairStatus::<_, _>::Enabled
 		);
 		System::assert_last_event(Event::DexModule(crate::Event::EnableTradingPair {
 			trading_pair: AUSDDOTPair::get(),
+			curve: Curve::ConstantProduct,
 		}));
 
 		assert_noop!(
-			DexModule::enable_trading_pair(Origin::signed(ListingOrigin::get()), AUSD, BTC),
+			DexModule::enable_trading_pair(
+				Origin::signed(ListingOrigin::get()),
+				AUSD,
+				BTC,
+				Curve::ConstantProduct
+			),
 			Error::<Runtime>::StillProvisioning
 		);
 	});
@@ -299,26 +298,6 @@ fn end_provisioning_trading_work() {
 	ExtBuilder::default().build().execute_with(|| {
 		System::set_block_number(1);
 
-		assert_ok!(DexModule::list_provisioning(
-			Origin::signed(ListingOrigin::get()),
-			AUSD,
-			DOT,
-			1_000_000_000_000u128,
-			1_000_000_000_000u128,
-			5_000_000_000_000u128,
-			2_000_000_000_000u128,
-			10,
-		));
-		assert_eq!(
-			DexModule::trading_pair_statuses(AUSDDOTPair::get()),
-			TradingPairStatus::<_, _>::Provisioning(ProvisioningParameters {
-				min_contribution: (1_000_000_000_000u128, 1_000_000_000_000u128),
-				target_provision: (5_000_000_000_000u128, 2_000_000_000_000u128),
-				accumulated_provision: (0, 0),
-				not_before: 10,
-			})
-		);
-
 		assert_ok!(DexModule::list_provisioning(
 			Origin::signed(ListingOrigin::get()),
 			AUSD,
@@ -328,6 +307,7 @@ fn end_provisioning_trading_work() {
 			5_000_000_000_000u128,
 			2_000_000_000_000u128,
 			10,
+			Curve::ConstantProduct,
 		));
 		assert_ok!(DexModule::add_provision(
 			Origin::signed(ALICE),
@@ -354,14 +334,7 @@ fn end_provisioning_trading_work() {
 		);
 		assert_eq!(DexModule::initial_share_exchange_rates(AUSDBTCPair::get()), Default::default());
 		assert_eq!(DexModule::liquidity_pool(AUSDBTCPair::get()), (0, 0));
-		assert_eq!(Tokens::total_issuance(AUSDBTCPair::get().dex_share_currency_id()), 0);
-		assert_eq!(
-			Tokens::free_balance(
-				AUSDBTCPair::get().dex_share_currency_id(),
-				&DexModule::account_id()
-			),
-			0
-		);
+		assert_eq!(DexModule::dex_share_currency_ids(AUSDBTCPair::get()), None);
 
 		assert_ok!(DexModule::end_provisioning(Origin::signed(ListingOrigin::get()), AUSD, BTC));
 		System::assert_last_event(Event::DexModule(crate::Event::ProvisioningToEnabled {
@@ -382,17 +355,9 @@ fn end_provisioning_trading_work() {
 			DexModule::liquidity_pool(AUSDBTCPair::get()),
 			(1_000_000_000_000u128, 2_000_000_000_000u128)
 		);
-		assert_eq!(
-			Tokens::total_issuance(AUSDBTCPair::get().dex_share_currency_id()),
-			2_000_000_000_000u128
-		);
-		assert_eq!(
-			Tokens::free_balance(
-				AUSDBTCPair::get().dex_share_currency_id(),
-				&DexModule::account_id()
-			),
-			2_000_000_000_000u128
-		);
+		let lp_currency_id = DexModule::dex_share_currency_ids(AUSDBTCPair::get()).unwrap();
+		assert_eq!(Currencies::total_issuance(lp_currency_id), 2_000_000_000_000u128);
+		assert_eq!(Currencies::balance(lp_currency_id, &dex_account()), 2_000_000_000_000u128);
 	});
 }
 
@@ -401,7 +366,12 @@ fn disable_trading_pair_work() {
 	ExtBuilder::default().build().execute_with(|| {
 		System::set_block_number(1);
 
-		assert_ok!(DexModule::enable_trading_pair(Origin::signed(ListingOrigin::get()), AUSD, DOT));
+		assert_ok!(DexModule::enable_trading_pair(
+			Origin::signed(ListingOrigin::get()),
+			AUSD,
+			DOT,
+			Curve::ConstantProduct
+		));
 		assert_eq!(
 			DexModule::trading_pair_statuses(AUSDDOTPair::get()),
 			TradingPairStatus::<_, _>::Enabled
@@ -424,7 +394,7 @@ fn disable_trading_pair_work() {
 
 		assert_noop!(
 			DexModule::disable_trading_pair(Origin::signed(ListingOrigin::get()), AUSD, DOT),
-			Error::<Runtime>::MustBeEnabled
+			Error::<Runtime>::AlreadyDisabled
 		);
 
 		assert_ok!(DexModule::list_provisioning(
@@ -436,10 +406,16 @@ fn disable_trading_pair_work() {
 			5_000_000_000_000u128,
 			2_000_000_000_000u128,
 			10,
+			Curve::ConstantProduct,
 		));
-		assert_noop!(
-			DexModule::disable_trading_pair(Origin::signed(ListingOrigin::get()), AUSD, BTC),
-			Error::<Runtime>::MustBeEnabled
+		assert_ok!(DexModule::disable_trading_pair(
+			Origin::signed(ListingOrigin::get()),
+			AUSD,
+			BTC
+		));
+		assert_eq!(
+			DexModule::trading_pair_statuses(AUSDBTCPair::get()),
+			TradingPairStatus::<_, _>::Disabled
 		);
 	});
 }
@@ -457,9 +433,8 @@ fn on_liquidity_pool_updated_work() {
 				5_000_000_000_000,
 				1_000_000_000_000,
 				0,
-				false,
 			));
-			assert_eq!(AUSD_DOT_POOL_RECORD.with(|v| *v.borrow()), (0, 0));
+			assert_eq!(DexModule::get_liquidity_pool(AUSD, DOT), (0, 0));
 
 			assert_ok!(DexModule::add_liquidity(
 				Origin::signed(ALICE),
@@ -468,9 +443,15 @@ fn on_liquidity_pool_updated_work() {
 				5_000_000_000_000,
 				1_000_000_000_000,
 				0,
-				false,
 			));
-			assert_eq!(AUSD_DOT_POOL_RECORD.with(|v| *v.borrow()), (5000000000000, 1000000000000));
+			assert_eq!(
+				DexModule::get_liquidity_pool(AUSD, DOT),
+				(5_000_000_000_000, 1_000_000_000_000)
+			);
+			assert_eq!(
+				DexModule::get_liquidity_pool(AUSD, BTC),
+				(5_000_000_000_000, 1_000_000_000_000)
+			);
 		});
 }
 
@@ -499,6 +480,7 @@ fn add_provision_work() {
 			5_000_000_000_000_000u128,
 			1_000_000_000_000_000u128,
 			10,
+			Curve::ConstantProduct,
 		));
 
 		assert_noop!(
@@ -512,20 +494,11 @@ fn add_provision_work() {
 			Error::<Runtime>::InvalidContributionIncrement
 		);
 
-		assert_eq!(
-			DexModule::trading_pair_statuses(AUSDDOTPair::get()),
-			TradingPairStatus::<_, _>::Provisioning(ProvisioningParameters {
-				min_contribution: (5_000_000_000_000u128, 1_000_000_000_000u128),
-				target_provision: (5_000_000_000_000_000u128, 1_000_000_000_000_000u128),
-				accumulated_provision: (0, 0),
-				not_before: 10,
-			})
-		);
 		assert_eq!(DexModule::provisioning_pool(AUSDDOTPair::get(), ALICE), (0, 0));
-		assert_eq!(Tokens::free_balance(AUSD, &ALICE), 1_000_000_000_000_000_000u128);
-		assert_eq!(Tokens::free_balance(DOT, &ALICE), 1_000_000_000_000_000_000u128);
-		assert_eq!(Tokens::free_balance(AUSD, &DexModule::account_id()), 0);
-		assert_eq!(Tokens::free_balance(DOT, &DexModule::account_id()), 0);
+		assert_eq!(Currencies::balance(AUSD, &ALICE), 1_000_000_000_000_000_000u128);
+		assert_eq!(Currencies::balance(DOT, &ALICE), 1_000_000_000_000_000_000u128);
+		assert_eq!(Currencies::balance(AUSD, &dex_account()), 0);
+		assert_eq!(Currencies::balance(DOT, &dex_account()), 0);
 		let alice_ref_count_0 = System::consumers(&ALICE);
 
 		assert_ok!(DexModule::add_provision(
@@ -548,12 +521,11 @@ fn add_provision_work() {
 			DexModule::provisioning_pool(AUSDDOTPair::get(), ALICE),
 			(5_000_000_000_000u128, 0)
 		);
-		assert_eq!(Tokens::free_balance(AUSD, &ALICE), 999_995_000_000_000_000u128);
-		assert_eq!(Tokens::free_balance(DOT, &ALICE), 1_000_000_000_000_000_000u128);
-		assert_eq!(Tokens::free_balance(AUSD, &DexModule::account_id()), 5_000_000_000_000u128);
-		assert_eq!(Tokens::free_balance(DOT, &DexModule::account_id()), 0);
-		let alice_ref_count_1 = System::consumers(&ALICE);
-		assert_eq!(alice_ref_count_1, alice_ref_count_0 + 1);
+		assert_eq!(Currencies::balance(AUSD, &ALICE), 999_995_000_000_000_000u128);
+		assert_eq!(Currencies::balance(DOT, &ALICE), 1_000_000_000_000_000_000u128);
+		assert_eq!(Currencies::balance(AUSD, &dex_account()), 5_000_000_000_000u128);
+		assert_eq!(Currencies::balance(DOT, &dex_account()), 0);
+		assert_eq!(System::consumers(&ALICE), alice_ref_count_0 + 1);
 		System::assert_last_event(Event::DexModule(crate::Event::AddProvision {
 			who: ALICE,
 			currency_0: AUSD,
@@ -578,6 +550,7 @@ fn claim_dex_share_work() {
 			5_000_000_000_000_000u128,
 			1_000_000_000_000_000u128,
 			0,
+			Curve::ConstantProduct,
 		));
 
 		assert_ok!(DexModule::add_provision(
@@ -602,40 +575,25 @@ fn claim_dex_share_work() {
 
 		assert_ok!(DexModule::end_provisioning(Origin::signed(ListingOrigin::get()), AUSD, DOT));
 
-		let lp_currency_id = AUSDDOTPair::get().dex_share_currency_id();
+		let lp_currency_id = DexModule::dex_share_currency_ids(AUSDDOTPair::get()).unwrap();
 
-		assert!(InitialShareExchangeRates::<Runtime>::contains_key(AUSDDOTPair::get()),);
 		assert_eq!(
 			DexModule::initial_share_exchange_rates(AUSDDOTPair::get()),
 			(ExchangeRate::one(), ExchangeRate::saturating_from_rational(5, 1))
 		);
-		assert_eq!(
-			Tokens::free_balance(lp_currency_id, &DexModule::account_id()),
-			10_000_000_000_000_000u128
-		);
-		assert_eq!(
-			DexModule::provisioning_pool(AUSDDOTPair::get(), ALICE),
-			(1_000_000_000_000_000u128, 200_000_000_000_000u128)
-		);
-		assert_eq!(
-			DexModule::provisioning_pool(AUSDDOTPair::get(), BOB),
-			(4_000_000_000_000_000u128, 800_000_000_000_000u128)
-		);
-		assert_eq!(Tokens::free_balance(lp_currency_id, &ALICE), 0);
-		assert_eq!(Tokens::free_balance(lp_currency_id, &BOB), 0);
+		assert_eq!(Currencies::balance(lp_currency_id, &dex_account()), 10_000_000_000_000_000u128);
+		assert_eq!(Currencies::balance(lp_currency_id, &ALICE), 0);
+		assert_eq!(Currencies::balance(lp_currency_id, &BOB), 0);
 
 		let alice_ref_count_0 = System::consumers(&ALICE);
 		let bob_ref_count_0 = System::consumers(&BOB);
 
 		assert_ok!(DexModule::claim_dex_share(Origin::signed(ALICE), ALICE, AUSD, DOT));
-		assert_eq!(
-			Tokens::free_balance(lp_currency_id, &DexModule::account_id()),
-			8_000_000_000_000_000u128
-		);
+		assert_eq!(Currencies::balance(lp_currency_id, &dex_account()), 8_000_000_000_000_000u128);
 		assert_eq!(DexModule::provisioning_pool(AUSDDOTPair::get(), ALICE), (0, 0));
-		assert_eq!(Tokens::free_balance(lp_currency_id, &ALICE), 2_000_000_000_000_000u128);
+		assert_eq!(Currencies::balance(lp_currency_id, &ALICE), 2_000_000_000_000_000u128);
 		assert_eq!(System::consumers(&ALICE), alice_ref_count_0 - 1);
-		assert!(InitialShareExchangeRates::<Runtime>::contains_key(AUSDDOTPair::get()),);
+		assert!(InitialShareExchangeRates::<Runtime>::contains_key(AUSDDOTPair::get()));
 
 		assert_ok!(DexModule::disable_trading_pair(
 			Origin::signed(ListingOrigin::get()),
@@ -643,11 +601,109 @@ fn claim_dex_share_work() {
 			DOT
 		));
 		assert_ok!(DexModule::claim_dex_share(Origin::signed(BOB), BOB, AUSD, DOT));
-		assert_eq!(Tokens::free_balance(lp_currency_id, &DexModule::account_id()), 0);
+		assert_eq!(Currencies::balance(lp_currency_id, &dex_account()), 0);
 		assert_eq!(DexModule::provisioning_pool(AUSDDOTPair::get(), BOB), (0, 0));
-		assert_eq!(Tokens::free_balance(lp_currency_id, &BOB), 8_000_000_000_000_000u128);
+		assert_eq!(Currencies::balance(lp_currency_id, &BOB), 8_000_000_000_000_000u128);
 		assert_eq!(System::consumers(&BOB), bob_ref_count_0 - 1);
-		assert!(!InitialShareExchangeRates::<Runtime>::contains_key(AUSDDOTPair::get()),);
+		assert!(!InitialShareExchangeRates::<Runtime>::contains_key(AUSDDOTPair::get()));
+	});
+}
+
+#[test]
+fn refund_provision_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(DexModule::list_provisioning(
+			Origin::signed(ListingOrigin::get()),
+			AUSD,
+			BTC,
+			1_000_000_000_000u128,
+			1_000_000_000_000u128,
+			5_000_000_000_000u128,
+			2_000_000_000_000u128,
+			10,
+			Curve::ConstantProduct,
+		));
+		assert_ok!(DexModule::add_provision(
+			Origin::signed(ALICE),
+			AUSD,
+			BTC,
+			1_000_000_000_000u128,
+			1_000_000_000_000u128
+		));
+		assert_ok!(DexModule::add_provision(
+			Origin::signed(BOB),
+			BTC,
+			AUSD,
+			0,
+			2_000_000_000_000u128
+		));
+
+		assert_noop!(
+			DexModule::refund_provision(Origin::signed(BOB), ALICE, AUSD, BTC),
+			Error::<Runtime>::MustBeDisabled
+		);
+		assert_ok!(DexModule::disable_trading_pair(
+			Origin::signed(ListingOrigin::get()),
+			AUSD,
+			BTC
+		));
+		assert_noop!(
+			DexModule::enable_trading_pair(
+				Origin::signed(ListingOrigin::get()),
+				AUSD,
+				BTC,
+				Curve::ConstantProduct
+			),
+			Error::<Runtime>::ProvisionNotRefunded
+		);
+		assert_noop!(
+			DexModule::claim_dex_share(Origin::signed(BOB), ALICE, AUSD, BTC),
+			Error::<Runtime>::ProvisionNotConverted
+		);
+
+		assert_ok!(DexModule::refund_provision(Origin::signed(BOB), ALICE, AUSD, BTC));
+		System::assert_last_event(Event::DexModule(crate::Event::RefundProvision {
+			who: ALICE,
+			currency_0: AUSD,
+			contribution_0: 1_000_000_000_000u128,
+			currency_1: BTC,
+			contribution_1: 1_000_000_000_000u128,
+		}));
+		assert_eq!(DexModule::provisioning_pool(AUSDBTCPair::get(), ALICE), (0, 0));
+		assert_eq!(Currencies::balance(AUSD, &ALICE), 1_000_000_000_000_000_000u128);
+		assert_eq!(Currencies::balance(BTC, &ALICE), 1_000_000_000_000_000_000u128);
+
+		assert_ok!(DexModule::refund_provision(Origin::signed(BOB), BOB, AUSD, BTC));
+		assert_eq!(Currencies::balance(AUSD, &BOB), 1_000_000_000_000_000_000u128);
+		assert_eq!(Currencies::balance(AUSD, &dex_account()), 0);
+		assert_eq!(Currencies::balance(BTC, &dex_account()), 0);
+
+		assert_ok!(DexModule::enable_trading_pair(
+			Origin::signed(ListingOrigin::get()),
+			AUSD,
+			BTC,
+			Curve::ConstantProduct
+		));
+	});
+}
+
+#[test]
+fn refund_provision_of_converted_provision_fails() {
+	ExtBuilder::default().build().execute_with(|| {
+		ProvisioningPool::<Runtime>::insert(
+			AUSDDOTPair::get(),
+			ALICE,
+			(1_000_000_000_000u128, 1_000_000_000_000u128),
+		);
+		InitialShareExchangeRates::<Runtime>::insert(
+			AUSDDOTPair::get(),
+			(ExchangeRate::one(), ExchangeRate::one()),
+		);
+
+		assert_noop!(
+			DexModule::refund_provision(Origin::signed(BOB), ALICE, AUSD, DOT),
+			Error::<Runtime>::ProvisionConverted
+		);
 	});
 }
 
@@ -656,34 +712,36 @@ fn get_liquidity_work() {
 	ExtBuilder::default().build().execute_with(|| {
 		LiquidityPool::<Runtime>::insert(AUSDDOTPair::get(), (1000, 20));
 		assert_eq!(DexModule::liquidity_pool(AUSDDOTPair::get()), (1000, 20));
-		assert_eq!(DexModule::get_liquidity(AUSD, DOT), (1000, 20));
-		assert_eq!(DexModule::get_liquidity(DOT, AUSD), (20, 1000));
+		assert_eq!(DexModule::get_liquidity_pool(AUSD, DOT), (1000, 20));
+		assert_eq!(DexModule::get_liquidity_pool(DOT, AUSD), (20, 1000));
 	});
 }
 
 #[test]
 fn get_target_amount_work() {
 	ExtBuilder::default().build().execute_with(|| {
-		assert_eq!(DexModule::get_target_amount(10000, 0, 1000), 0);
-		assert_eq!(DexModule::get_target_amount(0, 20000, 1000), 0);
-		assert_eq!(DexModule::get_target_amount(10000, 20000, 0), 0);
-		assert_eq!(DexModule::get_target_amount(10000, 1, 1000000), 0);
-		assert_eq!(DexModule::get_target_amount(10000, 20000, 10000), 9949);
-		assert_eq!(DexModule::get_target_amount(10000, 20000, 1000), 1801);
+		let (fee, curve) = ((1, 100), Curve::ConstantProduct);
+		assert_eq!(DexModule::get_target_amount(10000, 0, 1000, fee, curve), 0);
+		assert_eq!(DexModule::get_target_amount(0, 20000, 1000, fee, curve), 0);
+		assert_eq!(DexModule::get_target_amount(10000, 20000, 0, fee, curve), 0);
+		assert_eq!(DexModule::get_target_amount(10000, 1, 1000000, fee, curve), 0);
+		assert_eq!(DexModule::get_target_amount(10000, 20000, 10000, fee, curve), 9949);
+		assert_eq!(DexModule::get_target_amount(10000, 20000, 1000, fee, curve), 1801);
 	});
 }
 
 #[test]
 fn get_supply_amount_work() {
 	ExtBuilder::default().build().execute_with(|| {
-		assert_eq!(DexModule::get_supply_amount(10000, 0, 1000), 0);
-		assert_eq!(DexModule::get_supply_amount(0, 20000, 1000), 0);
-		assert_eq!(DexModule::get_supply_amount(10000, 20000, 0), 0);
-		assert_eq!(DexModule::get_supply_amount(10000, 1, 1), 0);
-		assert_eq!(DexModule::get_supply_amount(10000, 20000, 9949), 9999);
-		assert_eq!(DexModule::get_target_amount(10000, 20000, 9999), 9949);
-		assert_eq!(DexModule::get_supply_amount(10000, 20000, 1801), 1000);
-		assert_eq!(DexModule::get_target_amount(10000, 20000, 1000), 1801);
+		let (fee, curve) = ((1, 100), Curve::ConstantProduct);
+		assert_eq!(DexModule::get_supply_amount(10000, 0, 1000, fee, curve), 0);
+		assert_eq!(DexModule::get_supply_amount(0, 20000, 1000, fee, curve), 0);
+		assert_eq!(DexModule::get_supply_amount(10000, 20000, 0, fee, curve), 0);
+		assert_eq!(DexModule::get_supply_amount(10000, 1, 1, fee, curve), 0);
+		assert_eq!(DexModule::get_supply_amount(10000, 20000, 9949, fee, curve), 9999);
+		assert_eq!(DexModule::get_target_amount(10000, 20000, 9999, fee, curve), 9949);
+		assert_eq!(DexModule::get_supply_amount(10000, 20000, 1801, fee, curve), 1000);
+		assert_eq!(DexModule::get_target_amount(10000, 20000, 1000, fee, curve), 1801);
 	});
 }
 
@@ -734,15 +792,14 @@ fn get_target_amounts_work() {
 #[test]
 fn calculate_amount_for_big_number_work() {
 	ExtBuilder::default().build().execute_with(|| {
-		LiquidityPool::<Runtime>::insert(
-			AUSDDOTPair::get(),
-			(171_000_000_000_000_000_000_000, 56_000_000_000_000_000_000_000),
-		);
+		let (fee, curve) = ((1, 100), Curve::ConstantProduct);
 		assert_eq!(
 			DexModule::get_supply_amount(
 				171_000_000_000_000_000_000_000,
 				56_000_000_000_000_000_000_000,
-				1_000_000_000_000_000_000_000
+				1_000_000_000_000_000_000_000,
+				fee,
+				curve
 			),
 			3_140_495_867_768_595_041_323
 		);
@@ -750,7 +807,9 @@ fn calculate_amount_for_big_number_work() {
 			DexModule::get_target_amount(
 				171_000_000_000_000_000_000_000,
 				56_000_000_000_000_000_000_000,
-				3_140_495_867_768_595_041_323
+				3_140_495_867_768_595_041_323,
+				fee,
+				curve
 			),
 			1_000_000_000_000_000_000_000
 		);
@@ -806,15 +865,15 @@ fn _swap_work() {
 		.execute_with(|| {
 			LiquidityPool::<Runtime>::insert(AUSDDOTPair::get(), (50000, 10000));
 
-			assert_eq!(DexModule::get_liquidity(AUSD, DOT), (50000, 10000));
+			assert_eq!(DexModule::get_liquidity_pool(AUSD, DOT), (50000, 10000));
 			assert_noop!(
 				DexModule::_swap(AUSD, DOT, 50000, 5001),
 				Error::<Runtime>::InvariantCheckFailed
 			);
 			assert_ok!(DexModule::_swap(AUSD, DOT, 50000, 5000));
-			assert_eq!(DexModule::get_liquidity(AUSD, DOT), (100000, 5000));
+			assert_eq!(DexModule::get_liquidity_pool(AUSD, DOT), (100000, 5000));
 			assert_ok!(DexModule::_swap(DOT, AUSD, 100, 800));
-			assert_eq!(DexModule::get_liquidity(AUSD, DOT), (99200, 5100));
+			assert_eq!(DexModule::get_liquidity_pool(AUSD, DOT), (99200, 5100));
 		});
 }
 
@@ -827,13 +886,13 @@ fn _swap_by_path_work() {
 			LiquidityPool::<Runtime>::insert(AUSDDOTPair::get(), (50000, 10000));
 			LiquidityPool::<Runtime>::insert(AUSDBTCPair::get(), (100000, 10));
 
-			assert_eq!(DexModule::get_liquidity(AUSD, DOT), (50000, 10000));
-			assert_eq!(DexModule::get_liquidity(AUSD, BTC), (100000, 10));
+			assert_eq!(DexModule::get_liquidity_pool(AUSD, DOT), (50000, 10000));
+			assert_eq!(DexModule::get_liquidity_pool(AUSD, BTC), (100000, 10));
 			assert_ok!(DexModule::_swap_by_path(&[DOT, AUSD], &[10000, 25000]));
-			assert_eq!(DexModule::get_liquidity(AUSD, DOT), (25000, 20000));
+			assert_eq!(DexModule::get_liquidity_pool(AUSD, DOT), (25000, 20000));
 			assert_ok!(DexModule::_swap_by_path(&[DOT, AUSD, BTC], &[100000, 20000, 1]));
-			assert_eq!(DexModule::get_liquidity(AUSD, DOT), (5000, 120000));
-			assert_eq!(DexModule::get_liquidity(AUSD, BTC), (120000, 9));
+			assert_eq!(DexModule::get_liquidity_pool(AUSD, DOT), (5000, 120000));
+			assert_eq!(DexModule::get_liquidity_pool(AUSD, BTC), (120000, 9));
 		});
 }
 
@@ -844,6 +903,7 @@ fn add_liquidity_work() {
 		.build()
 		.execute_with(|| {
 			System::set_block_number(1);
+			let lp_currency_id = DexModule::dex_share_currency_ids(AUSDDOTPair::get()).unwrap();
 
 			assert_noop!(
 				DexModule::add_liquidity(
@@ -852,34 +912,19 @@ fn add_liquidity_work() {
 					AUSD,
 					100_000_000,
 					100_000_000,
-					0,
-					false
+					0
 				),
 				Error::<Runtime>::MustBeEnabled
 			);
 			assert_noop!(
-				DexModule::add_liquidity(
-					Origin::signed(ALICE),
-					AUSD,
-					DOT,
-					0,
-					100_000_000,
-					0,
-					false
-				),
+				DexModule::add_liquidity(Origin::signed(ALICE), AUSD, DOT, 0, 100_000_000, 0),
 				Error::<Runtime>::InvalidLiquidityIncrement
 			);
 
-			assert_eq!(DexModule::get_liquidity(AUSD, DOT), (0, 0));
-			assert_eq!(Tokens::free_balance(AUSD, &DexModule::account_id()), 0);
-			assert_eq!(Tokens::free_balance(DOT, &DexModule::account_id()), 0);
-			assert_eq!(Tokens::free_balance(AUSDDOTPair::get().dex_share_currency_id(), &ALICE), 0);
-			assert_eq!(
-				Tokens::reserved_balance(AUSDDOTPair::get().dex_share_currency_id(), &ALICE),
-				0
-			);
-			assert_eq!(Tokens::free_balance(AUSD, &ALICE), 1_000_000_000_000_000_000);
-			assert_eq!(Tokens::free_balance(DOT, &ALICE), 1_000_000_000_000_000_000);
+			assert_eq!(DexModule::get_liquidity_pool(AUSD, DOT), (0, 0));
+			assert_eq!(Currencies::balance(AUSD, &dex_account()), 0);
+			assert_eq!(Currencies::balance(DOT, &dex_account()), 0);
+			assert_eq!(Currencies::balance(lp_currency_id, &ALICE), 0);
 
 			assert_ok!(DexModule::add_liquidity(
 				Origin::signed(ALICE),
@@ -888,7 +933,6 @@ fn add_liquidity_work() {
 				5_000_000_000_000,
 				1_000_000_000_000,
 				0,
-				false,
 			));
 			System::assert_last_event(Event::DexModule(crate::Event::AddLiquidity {
 				who: ALICE,
@@ -898,29 +942,18 @@ fn add_liquidity_work() {
 				pool_1: 1_000_000_000_000,
 				share_increment: 10_000_000_000_000,
 			}));
-			assert_eq!(DexModule::get_liquidity(AUSD, DOT), (5_000_000_000_000, 1_000_000_000_000));
-			assert_eq!(Tokens::free_balance(AUSD, &DexModule::account_id()), 5_000_000_000_000);
-			assert_eq!(Tokens::free_balance(DOT, &DexModule::account_id()), 1_000_000_000_000);
 			assert_eq!(
-				Tokens::free_balance(AUSDDOTPair::get().dex_share_currency_id(), &ALICE),
-				10_000_000_000_000
-			);
-			assert_eq!(
-				Tokens::reserved_balance(AUSDDOTPair::get().dex_share_currency_id(), &ALICE),
-				0
+				DexModule::get_liquidity_pool(AUSD, DOT),
+				(5_000_000_000_000, 1_000_000_000_000)
 			);
-			assert_eq!(Tokens::free_balance(AUSD, &ALICE), 999_995_000_000_000_000);
-			assert_eq!(Tokens::free_balance(DOT, &ALICE), 999_999_000_000_000_000);
-			assert_eq!(Tokens::free_balance(AUSDDOTPair::get().dex_share_currency_id(), &BOB), 0);
-			assert_eq!(
-				Tokens::reserved_balance(AUSDDOTPair::get().dex_share_currency_id(), &BOB),
-				0
-			);
-			assert_eq!(Tokens::free_balance(AUSD, &BOB), 1_000_000_000_000_000_000);
-			assert_eq!(Tokens::free_balance(DOT, &BOB), 1_000_000_000_000_000_000);
+			assert_eq!(Currencies::balance(AUSD, &dex_account()), 5_000_000_000_000);
+			assert_eq!(Currencies::balance(DOT, &dex_account()), 1_000_000_000_000);
+			assert_eq!(Currencies::balance(lp_currency_id, &ALICE), 10_000_000_000_000);
+			assert_eq!(Currencies::balance(AUSD, &ALICE), 999_995_000_000_000_000);
+			assert_eq!(Currencies::balance(DOT, &ALICE), 999_999_000_000_000_000);
 
 			assert_noop!(
-				DexModule::add_liquidity(Origin::signed(BOB), AUSD, DOT, 4, 1, 0, true,),
+				DexModule::add_liquidity(Origin::signed(BOB), AUSD, DOT, 4, 1, 0),
 				Error::<Runtime>::InvalidLiquidityIncrement,
 			);
 
@@ -932,7 +965,6 @@ fn add_liquidity_work() {
 					50_000_000_000_000,
 					8_000_000_000_000,
 					80_000_000_000_001,
-					true,
 				),
 				Error::<Runtime>::UnacceptableShareIncrement
 			);
@@ -944,7 +976,6 @@ fn add_liquidity_work() {
 				50_000_000_000_000,
 				8_000_000_000_000,
 				80_000_000_000_000,
-				true,
 			));
 			System::assert_last_event(Event::DexModule(crate::Event::AddLiquidity {
 				who: BOB,
@@ -955,18 +986,14 @@ fn add_liquidity_work() {
 				share_increment: 80_000_000_000_000,
 			}));
 			assert_eq!(
-				DexModule::get_liquidity(AUSD, DOT),
+				DexModule::get_liquidity_pool(AUSD, DOT),
 				(45_000_000_000_000, 9_000_000_000_000)
 			);
-			assert_eq!(Tokens::free_balance(AUSD, &DexModule::account_id()), 45_000_000_000_000);
-			assert_eq!(Tokens::free_balance(DOT, &DexModule::account_id()), 9_000_000_000_000);
-			assert_eq!(Tokens::free_balance(AUSDDOTPair::get().dex_share_currency_id(), &BOB), 0);
-			assert_eq!(
-				Tokens::reserved_balance(AUSDDOTPair::get().dex_share_currency_id(), &BOB),
-				80_000_000_000_000
-			);
-			assert_eq!(Tokens::free_balance(AUSD, &BOB), 999_960_000_000_000_000);
-			assert_eq!(Tokens::free_balance(DOT, &BOB), 999_992_000_000_000_000);
+			assert_eq!(Currencies::balance(AUSD, &dex_account()), 45_000_000_000_000);
+			assert_eq!(Currencies::balance(DOT, &dex_account()), 9_000_000_000_000);
+			assert_eq!(Currencies::balance(lp_currency_id, &BOB), 80_000_000_000_000);
+			assert_eq!(Currencies::balance(AUSD, &BOB), 999_960_000_000_000_000);
+			assert_eq!(Currencies::balance(DOT, &BOB), 999_992_000_000_000_000);
 		});
 }
 
@@ -977,6 +1004,7 @@ fn remove_liquidity_work() {
 		.build()
 		.execute_with(|| {
 			System::set_block_number(1);
+			let lp_currency_id = DexModule::dex_share_currency_ids(AUSDDOTPair::get()).unwrap();
 
 			assert_ok!(DexModule::add_liquidity(
 				Origin::signed(ALICE),
@@ -984,13 +1012,12 @@ fn remove_liquidity_work() {
 				DOT,
 				5_000_000_000_000,
 				1_000_000_000_000,
-				0,
-				false
+				0
 			));
 			assert_noop!(
 				DexModule::remove_liquidity(
 					Origin::signed(ALICE),
-					AUSDDOTPair::get().dex_share_currency_id(),
+					lp_currency_id,
 					DOT,
 					100_000_000,
 					0,
@@ -1000,16 +1027,6 @@ fn remove_liquidity_work() {
 				Error::<Runtime>::InvalidCurrencyId
 			);
 
-			assert_eq!(DexModule::get_liquidity(AUSD, DOT), (5_000_000_000_000, 1_000_000_000_000));
-			assert_eq!(Tokens::free_balance(AUSD, &DexModule::account_id()), 5_000_000_000_000);
-			assert_eq!(Tokens::free_balance(DOT, &DexModule::account_id()), 1_000_000_000_000);
-			assert_eq!(
-				Tokens::free_balance(AUSDDOTPair::get().dex_share_currency_id(), &ALICE),
-				10_000_000_000_000
-			);
-			assert_eq!(Tokens::free_balance(AUSD, &ALICE), 999_995_000_000_000_000);
-			assert_eq!(Tokens::free_balance(DOT, &ALICE), 999_999_000_000_000_000);
-
 			assert_noop!(
 				DexModule::remove_liquidity(
 					Origin::signed(ALICE),
@@ -1051,15 +1068,15 @@ fn remove_liquidity_work() {
 				pool_1: 800_000_000_000,
 				share_decrement: 8_000_000_000_000,
 			}));
-			assert_eq!(DexModule::get_liquidity(AUSD, DOT), (1_000_000_000_000, 200_000_000_000));
-			assert_eq!(Tokens::free_balance(AUSD, &DexModule::account_id()), 1_000_000_000_000);
-			assert_eq!(Tokens::free_balance(DOT, &DexModule::account_id()), 200_000_000_000);
 			assert_eq!(
-				Tokens::free_balance(AUSDDOTPair::get().dex_share_currency_id(), &ALICE),
-				2_000_000_000_000
+				DexModule::get_liquidity_pool(AUSD, DOT),
+				(1_000_000_000_000, 200_000_000_000)
 			);
-			assert_eq!(Tokens::free_balance(AUSD, &ALICE), 999_999_000_000_000_000);
-			assert_eq!(Tokens::free_balance(DOT, &ALICE), 999_999_800_000_000_000);
+			assert_eq!(Currencies::balance(AUSD, &dex_account()), 1_000_000_000_000);
+			assert_eq!(Currencies::balance(DOT, &dex_account()), 200_000_000_000);
+			assert_eq!(Currencies::balance(lp_currency_id, &ALICE), 2_000_000_000_000);
+			assert_eq!(Currencies::balance(AUSD, &ALICE), 999_999_000_000_000_000);
+			assert_eq!(Currencies::balance(DOT, &ALICE), 999_999_800_000_000_000);
 
 			assert_ok!(DexModule::remove_liquidity(
 				Origin::signed(ALICE),
@@ -1078,12 +1095,12 @@ fn remove_liquidity_work() {
 				pool_1: 200_000_000_000,
 				share_decrement: 2_000_000_000_000,
 			}));
-			assert_eq!(DexModule::get_liquidity(AUSD, DOT), (0, 0));
-			assert_eq!(Tokens::free_balance(AUSD, &DexModule::account_id()), 0);
-			assert_eq!(Tokens::free_balance(DOT, &DexModule::account_id()), 0);
-			assert_eq!(Tokens::free_balance(AUSDDOTPair::get().dex_share_currency_id(), &ALICE), 0);
-			assert_eq!(Tokens::free_balance(AUSD, &ALICE), 1_000_000_000_000_000_000);
-			assert_eq!(Tokens::free_balance(DOT, &ALICE), 1_000_000_000_000_000_000);
+			assert_eq!(DexModule::get_liquidity_pool(AUSD, DOT), (0, 0));
+			assert_eq!(Currencies::balance(AUSD, &dex_account()), 0);
+			assert_eq!(Currencies::balance(DOT, &dex_account()), 0);
+			assert_eq!(Currencies::balance(lp_currency_id, &ALICE), 0);
+			assert_eq!(Currencies::balance(AUSD, &ALICE), 1_000_000_000_000_000_000);
+			assert_eq!(Currencies::balance(DOT, &ALICE), 1_000_000_000_000_000_000);
 
 			assert_ok!(DexModule::add_liquidity(
 				Origin::signed(BOB),
@@ -1091,14 +1108,15 @@ fn remove_liquidity_work() {
 				DOT,
 				5_000_000_000_000,
 				1_000_000_000_000,
-				0,
-				true
+				0
 			));
-			assert_eq!(Tokens::free_balance(AUSDDOTPair::get().dex_share_currency_id(), &BOB), 0);
-			assert_eq!(
-				Tokens::reserved_balance(AUSDDOTPair::get().dex_share_currency_id(), &BOB),
+			assert_ok!(MockDEXIncentives::do_deposit_dex_share(
+				&BOB,
+				lp_currency_id,
 				10_000_000_000_000
-			);
+			));
+			assert_eq!(Currencies::balance(lp_currency_id, &BOB), 0);
+			assert_eq!(Currencies::balance(lp_currency_id, &INCENTIVES), 10_000_000_000_000);
 			assert_ok!(DexModule::remove_liquidity(
 				Origin::signed(BOB),
 				AUSD,
@@ -1108,10 +1126,11 @@ fn remove_liquidity_work() {
 				0,
 				true,
 			));
-			assert_eq!(Tokens::free_balance(AUSDDOTPair::get().dex_share_currency_id(), &BOB), 0);
+			assert_eq!(Currencies::balance(lp_currency_id, &BOB), 0);
+			assert_eq!(Currencies::balance(lp_currency_id, &INCENTIVES), 8_000_000_000_000);
 			assert_eq!(
-				Tokens::reserved_balance(AUSDDOTPair::get().dex_share_currency_id(), &BOB),
-				8_000_000_000_000
+				DexModule::get_liquidity_pool(AUSD, DOT),
+				(4_000_000_000_000, 800_000_000_000)
 			);
 		});
 }
@@ -1131,7 +1150,6 @@ fn do_swap_with_exact_supply_work() {
 				500_000_000_000_000,
 				100_000_000_000_000,
 				0,
-				false,
 			));
 			assert_ok!(DexModule::add_liquidity(
 				Origin::signed(ALICE),
@@ -1140,21 +1158,8 @@ fn do_swap_with_exact_supply_work() {
 				100_000_000_000_000,
 				10_000_000_000,
 				0,
-				false,
 			));
 
-			assert_eq!(
-				DexModule::get_liquidity(AUSD, DOT),
-				(500_000_000_000_000, 100_000_000_000_000)
-			);
-			assert_eq!(DexModule::get_liquidity(AUSD, BTC), (100_000_000_000_000, 10_000_000_000));
-			assert_eq!(Tokens::free_balance(AUSD, &DexModule::account_id()), 600_000_000_000_000);
-			assert_eq!(Tokens::free_balance(DOT, &DexModule::account_id()), 100_000_000_000_000);
-			assert_eq!(Tokens::free_balance(BTC, &DexModule::account_id()), 10_000_000_000);
-			assert_eq!(Tokens::free_balance(AUSD, &BOB), 1_000_000_000_000_000_000);
-			assert_eq!(Tokens::free_balance(DOT, &BOB), 1_000_000_000_000_000_000);
-			assert_eq!(Tokens::free_balance(BTC, &BOB), 1_000_000_000_000_000_000);
-
 			assert_noop!(
 				DexModule::do_swap_with_exact_supply(
 					&BOB,
@@ -1199,16 +1204,13 @@ fn do_swap_with_exact_supply_work() {
 				liquidity_changes: vec![100_000_000_000_000, 248_743_718_592_964],
 			}));
 			assert_eq!(
-				DexModule::get_liquidity(AUSD, DOT),
+				DexModule::get_liquidity_pool(AUSD, DOT),
 				(251_256_281_407_036, 200_000_000_000_000)
 			);
-			assert_eq!(DexModule::get_liquidity(AUSD, BTC), (100_000_000_000_000, 10_000_000_000));
-			assert_eq!(Tokens::free_balance(AUSD, &DexModule::account_id()), 351_256_281_407_036);
-			assert_eq!(Tokens::free_balance(DOT, &DexModule::account_id()), 200_000_000_000_000);
-			assert_eq!(Tokens::free_balance(BTC, &DexModule::account_id()), 10_000_000_000);
-			assert_eq!(Tokens::free_balance(AUSD, &BOB), 1_000_248_743_718_592_964);
-			assert_eq!(Tokens::free_balance(DOT, &BOB), 999_900_000_000_000_000);
-			assert_eq!(Tokens::free_balance(BTC, &BOB), 1_000_000_000_000_000_000);
+			assert_eq!(Currencies::balance(AUSD, &dex_account()), 351_256_281_407_036);
+			assert_eq!(Currencies::balance(DOT, &dex_account()), 200_000_000_000_000);
+			assert_eq!(Currencies::balance(AUSD, &BOB), 1_000_248_743_718_592_964);
+			assert_eq!(Currencies::balance(DOT, &BOB), 999_900_000_000_000_000);
 
 			assert_ok!(DexModule::do_swap_with_exact_supply(
 				&BOB,
@@ -1222,16 +1224,19 @@ fn do_swap_with_exact_supply_work() {
 				liquidity_changes: vec![200_000_000_000_000, 124_996_843_514_053, 5_530_663_837],
 			}));
 			assert_eq!(
-				DexModule::get_liquidity(AUSD, DOT),
+				DexModule::get_liquidity_pool(AUSD, DOT),
 				(126_259_437_892_983, 400_000_000_000_000)
 			);
-			assert_eq!(DexModule::get_liquidity(AUSD, BTC), (224_996_843_514_053, 4_469_336_163));
-			assert_eq!(Tokens::free_balance(AUSD, &DexModule::account_id()), 351_256_281_407_036);
-			assert_eq!(Tokens::free_balance(DOT, &DexModule::account_id()), 400_000_000_000_000);
-			assert_eq!(Tokens::free_balance(BTC, &DexModule::account_id()), 4_469_336_163);
-			assert_eq!(Tokens::free_balance(AUSD, &BOB), 1_000_248_743_718_592_964);
-			assert_eq!(Tokens::free_balance(DOT, &BOB), 999_700_000_000_000_000);
-			assert_eq!(Tokens::free_balance(BTC, &BOB), 1_000_000_005_530_663_837);
+			assert_eq!(
+				DexModule::get_liquidity_pool(AUSD, BTC),
+				(224_996_843_514_053, 4_469_336_163)
+			);
+			assert_eq!(Currencies::balance(AUSD, &dex_account()), 351_256_281_407_036);
+			assert_eq!(Currencies::balance(DOT, &dex_account()), 400_000_000_000_000);
+			assert_eq!(Currencies::balance(BTC, &dex_account()), 4_469_336_163);
+			assert_eq!(Currencies::balance(AUSD, &BOB), 1_000_248_743_718_592_964);
+			assert_eq!(Currencies::balance(DOT, &BOB), 999_700_000_000_000_000);
+			assert_eq!(Currencies::balance(BTC, &BOB), 1_000_000_005_530_663_837);
 		});
 }
 
@@ -1250,7 +1255,6 @@ fn do_swap_with_exact_target_work() {
 				500_000_000_000_000,
 				100_000_000_000_000,
 				0,
-				false,
 			));
 			assert_ok!(DexModule::add_liquidity(
 				Origin::signed(ALICE),
@@ -1259,21 +1263,8 @@ fn do_swap_with_exact_target_work() {
 				100_000_000_000_000,
 				10_000_000_000,
 				0,
-				false,
 			));
 
-			assert_eq!(
-				DexModule::get_liquidity(AUSD, DOT),
-				(500_000_000_000_000, 100_000_000_000_000)
-			);
-			assert_eq!(DexModule::get_liquidity(AUSD, BTC), (100_000_000_000_000, 10_000_000_000));
-			assert_eq!(Tokens::free_balance(AUSD, &DexModule::account_id()), 600_000_000_000_000);
-			assert_eq!(Tokens::free_balance(DOT, &DexModule::account_id()), 100_000_000_000_000);
-			assert_eq!(Tokens::free_balance(BTC, &DexModule::account_id()), 10_000_000_000);
-			assert_eq!(Tokens::free_balance(AUSD, &BOB), 1_000_000_000_000_000_000);
-			assert_eq!(Tokens::free_balance(DOT, &BOB), 1_000_000_000_000_000_000);
-			assert_eq!(Tokens::free_balance(BTC, &BOB), 1_000_000_000_000_000_000);
-
 			assert_noop!(
 				DexModule::do_swap_with_exact_target(
 					&BOB,
@@ -1295,20 +1286,11 @@ fn do_swap_with_exact_target_work() {
 			assert_noop!(
 				DexModule::do_swap_with_exact_target(
 					&BOB,
-					&[DOT, AUSD, DOT],
+					&[DOT, ACA],
 					250_000_000_000_000,
-					200_000_000_000_000,
+					200_000_000_000_000
 				),
-				Error::<Runtime>::InvalidTradingPath,
-			);
-			assert_noop!(
-				DexModule::do_swap_with_exact_target(
-					&BOB,
-					&[DOT, ACA],
-					250_000_000_000_000,
-					200_000_000_000_000
-				),
-				Error::<Runtime>::MustBeEnabled,
+				Error::<Runtime>::MustBeEnabled,
 			);
 
 			assert_ok!(DexModule::do_swap_with_exact_target(
@@ -1323,16 +1305,11 @@ fn do_swap_with_exact_target_work() {
 				liquidity_changes: vec![101_010_101_010_102, 250_000_000_000_000],
 			}));
 			assert_eq!(
-				DexModule::get_liquidity(AUSD, DOT),
+				DexModule::get_liquidity_pool(AUSD, DOT),
 				(250_000_000_000_000, 201_010_101_010_102)
 			);
-			assert_eq!(DexModule::get_liquidity(AUSD, BTC), (100_000_000_000_000, 10_000_000_000));
-			assert_eq!(Tokens::free_balance(AUSD, &DexModule::account_id()), 350_000_000_000_000);
-			assert_eq!(Tokens::free_balance(DOT, &DexModule::account_id()), 201_010_101_010_102);
-			assert_eq!(Tokens::free_balance(BTC, &DexModule::account_id()), 10_000_000_000);
-			assert_eq!(Tokens::free_balance(AUSD, &BOB), 1_000_250_000_000_000_000);
-			assert_eq!(Tokens::free_balance(DOT, &BOB), 999_898_989_898_989_898);
-			assert_eq!(Tokens::free_balance(BTC, &BOB), 1_000_000_000_000_000_000);
+			assert_eq!(Currencies::balance(AUSD, &BOB), 1_000_250_000_000_000_000);
+			assert_eq!(Currencies::balance(DOT, &BOB), 999_898_989_898_989_898);
 
 			assert_ok!(DexModule::do_swap_with_exact_target(
 				&BOB,
@@ -1346,16 +1323,99 @@ fn do_swap_with_exact_target_work() {
 				liquidity_changes: vec![137_654_580_386_993, 101_010_101_010_102, 5_000_000_000],
 			}));
 			assert_eq!(
-				DexModule::get_liquidity(AUSD, DOT),
+				DexModule::get_liquidity_pool(AUSD, DOT),
 				(148_989_898_989_898, 338_664_681_397_095)
 			);
-			assert_eq!(DexModule::get_liquidity(AUSD, BTC), (201_010_101_010_102, 5_000_000_000));
-			assert_eq!(Tokens::free_balance(AUSD, &DexModule::account_id()), 350_000_000_000_000);
-			assert_eq!(Tokens::free_balance(DOT, &DexModule::account_id()), 338_664_681_397_095);
-			assert_eq!(Tokens::free_balance(BTC, &DexModule::account_id()), 5_000_000_000);
-			assert_eq!(Tokens::free_balance(AUSD, &BOB), 1_000_250_000_000_000_000);
-			assert_eq!(Tokens::free_balance(DOT, &BOB), 999_761_335_318_602_905);
-			assert_eq!(Tokens::free_balance(BTC, &BOB), 1_000_000_005_000_000_000);
+			assert_eq!(
+				DexModule::get_liquidity_pool(AUSD, BTC),
+				(201_010_101_010_102, 5_000_000_000)
+			);
+			assert_eq!(Currencies::balance(AUSD, &dex_account()), 350_000_000_000_000);
+			assert_eq!(Currencies::balance(DOT, &dex_account()), 338_664_681_397_095);
+			assert_eq!(Currencies::balance(BTC, &dex_account()), 5_000_000_000);
+			assert_eq!(Currencies::balance(DOT, &BOB), 999_761_335_318_602_905);
+			assert_eq!(Currencies::balance(BTC, &BOB), 1_000_000_005_000_000_000);
+		});
+}
+
+#[test]
+fn swap_with_exact_supply_work() {
+	ExtBuilder::default()
+		.initialize_enabled_trading_pairs()
+		.build()
+		.execute_with(|| {
+			System::set_block_number(1);
+
+			assert_ok!(DexModule::add_liquidity(
+				Origin::signed(ALICE),
+				AUSD,
+				DOT,
+				500_000_000_000_000,
+				100_000_000_000_000,
+				0,
+			));
+			assert_ok!(DexModule::add_liquidity(
+				Origin::signed(ALICE),
+				AUSD,
+				BTC,
+				100_000_000_000_000,
+				10_000_000_000,
+				0,
+			));
+
+			assert_noop!(
+				DexModule::swap_with_exact_supply(
+					Origin::signed(BOB),
+					vec![DOT, AUSD, BTC, DOT],
+					100_000_000_000_000,
+					0
+				),
+				Error::<Runtime>::InvalidTradingPathLength,
+			);
+			assert_noop!(
+				DexModule::swap_with_exact_supply(
+					Origin::signed(BOB),
+					vec![DOT],
+					100_000_000_000_000,
+					0
+				),
+				Error::<Runtime>::InvalidTradingPathLength,
+			);
+			assert_noop!(
+				DexModule::swap_with_exact_supply(
+					Origin::signed(BOB),
+					vec![DOT, AUSD],
+					100_000_000_000_000,
+					250_000_000_000_000
+				),
+				Error::<Runtime>::InsufficientTargetAmount,
+			);
+
+			assert_ok!(DexModule::swap_with_exact_supply(
+				Origin::signed(BOB),
+				vec![DOT, AUSD],
+				100_000_000_000_000,
+				200_000_000_000_000,
+			));
+			System::assert_last_event(Event::DexModule(crate::Event::Swap {
+				trader: BOB,
+				path: vec![DOT, AUSD],
+				liquidity_changes: vec![100_000_000_000_000, 248_743_718_592_964],
+			}));
+
+			assert_ok!(DexModule::swap_with_exact_supply(
+				Origin::signed(BOB),
+				vec![DOT, AUSD, BTC],
+				200_000_000_000_000,
+				1,
+			));
+			System::assert_last_event(Event::DexModule(crate::Event::Swap {
+				trader: BOB,
+				path: vec![DOT, AUSD, BTC],
+				liquidity_changes: vec![200_000_000_000_000, 124_996_843_514_053, 5_530_663_837],
+			}));
+			assert_eq!(Currencies::balance(DOT, &BOB), 999_700_000_000_000_000);
+			assert_eq!(Currencies::balance(BTC, &BOB), 1_000_000_005_530_663_837);
 		});
 }
 
@@ -1368,11 +1428,14 @@ fn initialize_added_liquidity_pools_genesis_work() {
 		.execute_with(|| {
 			System::set_block_number(1);
 
-			assert_eq!(DexModule::get_liquidity(AUSD, DOT), (1000000, 2000000));
-			assert_eq!(Tokens::free_balance(AUSD, &DexModule::account_id()), 2000000);
-			assert_eq!(Tokens::free_balance(DOT, &DexModule::account_id()), 3000000);
+			assert_eq!(DexModule::get_liquidity_pool(AUSD, DOT), (1000000, 2000000));
+			assert_eq!(Currencies::balance(AUSD, &dex_account()), 2000000);
+			assert_eq!(Currencies::balance(DOT, &dex_account()), 3000000);
 			assert_eq!(
-				Tokens::free_balance(AUSDDOTPair::get().dex_share_currency_id(), &ALICE),
+				Currencies::balance(
+					DexModule::dex_share_currency_ids(AUSDDOTPair::get()).unwrap(),
+					&ALICE
+				),
 				2000000
 			);
 		});
@@ -1386,27 +1449,193 @@ fn get_swap_amount_work() {
 		.execute_with(|| {
 			LiquidityPool::<Runtime>::insert(AUSDDOTPair::get(), (50000, 10000));
 			assert_eq!(
-				DexModule::get_swap_amount(&vec![DOT, AUSD], SwapLimit::ExactSupply(10000, 0)),
+				DexModule::get_swap_amount(&[DOT, AUSD], SwapLimit::ExactSupply(10000, 0)),
 				Some((10000, 24874))
 			);
 			assert_eq!(
-				DexModule::get_swap_amount(&vec![DOT, AUSD], SwapLimit::ExactSupply(10000, 24875)),
+				DexModule::get_swap_amount(&[DOT, AUSD], SwapLimit::ExactSupply(10000, 24875)),
 				None
 			);
 			assert_eq!(
 				DexModule::get_swap_amount(
-					&vec![DOT, AUSD],
-					SwapLimit::ExactTarget(Balance::max_value(), 24874)
+					&[DOT, AUSD],
+					SwapLimit::ExactTarget(Balance::MAX, 24874)
 				),
 				Some((10000, 24874))
 			);
 			assert_eq!(
-				DexModule::get_swap_amount(&vec![DOT, AUSD], SwapLimit::ExactTarget(9999, 24874)),
+				DexModule::get_swap_amount(&[DOT, AUSD], SwapLimit::ExactTarget(9999, 24874)),
 				None
 			);
 		});
 }
 
+#[test]
+fn swap_with_specific_path_work() {
+	ExtBuilder::default()
+		.initialize_enabled_trading_pairs()
+		.build()
+		.execute_with(|| {
+			System::set_block_number(1);
+			assert_ok!(DexModule::add_liquidity(
+				Origin::signed(ALICE),
+				AUSD,
+				DOT,
+				500_000_000_000_000,
+				100_000_000_000_000,
+				0,
+			));
+
+			assert_noop!(
+				DexModule::swap_with_specific_path(
+					&BOB,
+					&[DOT, AUSD],
+					SwapLimit::ExactSupply(100_000_000_000_000, 248_743_718_592_965)
+				),
+				Error::<Runtime>::InsufficientTargetAmount
+			);
+
+			assert_ok!(DexModule::swap_with_specific_path(
+				&BOB,
+				&[DOT, AUSD],
+				SwapLimit::ExactSupply(100_000_000_000_000, 200_000_000_000_000)
+			));
+			System::assert_last_event(Event::DexModule(crate::Event::Swap {
+				trader: BOB,
+				path: vec![DOT, AUSD],
+				liquidity_changes: vec![100_000_000_000_000, 248_743_718_592_964],
+			}));
+
+			assert_noop!(
+				DexModule::swap_with_specific_path(
+					&BOB,
+					&[AUSD, DOT],
+					SwapLimit::ExactTarget(253_794_223_643_470, 100_000_000_000_000)
+				),
+				Error::<Runtime>::ExcessiveSupplyAmount
+			);
+
+			assert_ok!(DexModule::swap_with_specific_path(
+				&BOB,
+				&[AUSD, DOT],
+				SwapLimit::ExactTarget(300_000_000_000_000, 100_000_000_000_000)
+			));
+			System::assert_last_event(Event::DexModule(crate::Event::Swap {
+				trader: BOB,
+				path: vec![AUSD, DOT],
+				liquidity_changes: vec![253_794_223_643_471, 100_000_000_000_000],
+			}));
+		});
+}
+
+#[test]
+fn get_dex_share_currency_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_eq!(
+			DexModule::trading_pair_statuses(AUSDDOTPair::get()),
+			TradingPairStatus::<_, _>::Disabled
+		);
+		assert_eq!(DexModule::dex_share_currency_ids(AUSDDOTPair::get()), None);
+
+		assert_ok!(DexModule::enable_trading_pair(
+			Origin::signed(ListingOrigin::get()),
+			AUSD,
+			DOT,
+			Curve::ConstantProduct
+		));
+		assert_eq!(DexModule::dex_share_currency_ids(AUSDDOTPair::get()), Some(1_000));
+		assert_eq!(DexModule::get_dex_share_trading_pair(1_000), Some(AUSDDOTPair::get()));
+		assert_eq!(Assets::maybe_total_supply(1_000), Some(0));
+
+		// disabling keeps the dex share currency for the next listing
+		assert_ok!(DexModule::disable_trading_pair(
+			Origin::signed(ListingOrigin::get()),
+			AUSD,
+			DOT
+		));
+		assert_eq!(DexModule::dex_share_currency_ids(AUSDDOTPair::get()), Some(1_000));
+	});
+}
+
+#[test]
+fn trading_pairs_work() {
+	ExtBuilder::default()
+		.initialize_enabled_trading_pairs()
+		.initialize_added_liquidity_pools(ALICE)
+		.build()
+		.execute_with(|| {
+			assert_ok!(DexModule::list_provisioning(
+				Origin::signed(ListingOrigin::get()),
+				ACA,
+				DOT,
+				1_000_000_000_000u128,
+				1_000_000_000_000u128,
+				5_000_000_000_000u128,
+				2_000_000_000_000u128,
+				10,
+				Curve::ConstantProduct,
+			));
+
+			let trading_pairs = DexModule::trading_pairs();
+			assert_eq!(trading_pairs.len(), 4);
+			assert!(trading_pairs.contains(&TradingPairInfo {
+				trading_pair: AUSDDOTPair::get(),
+				dex_share_currency_id: DexModule::dex_share_currency_ids(AUSDDOTPair::get()),
+				liquidity_pool: (1_000_000, 2_000_000),
+				status: TradingPairStatus::<_, _>::Enabled,
+				curve: Curve::ConstantProduct,
+			}));
+			assert!(trading_pairs.contains(&TradingPairInfo {
+				trading_pair: TradingPair::new(ACA, DOT),
+				dex_share_currency_id: None,
+				liquidity_pool: (0, 0),
+				status: TradingPairStatus::<_, _>::Provisioning(ProvisioningParameters {
+					min_contribution: (1_000_000_000_000u128, 1_000_000_000_000u128),
+					target_provision: (5_000_000_000_000u128, 2_000_000_000_000u128),
+					accumulated_provision: (0, 0),
+					not_before: 10,
+				}),
+				curve: Curve::ConstantProduct,
+			}));
+		});
+}
+
+#[test]
+fn trading_pair_index_work() {
+	ExtBuilder::default()
+		.initialize_enabled_trading_pairs()
+		.build()
+		.execute_with(|| {
+			assert_eq!(DexModule::trading_pair_index(AUSD), vec![DOT, BTC]);
+			assert_eq!(DexModule::trading_pair_index(DOT), vec![AUSD, BTC]);
+			assert_eq!(DexModule::trading_pair_index(BTC), vec![AUSD, DOT]);
+
+			assert_ok!(DexModule::disable_trading_pair(
+				Origin::signed(ListingOrigin::get()),
+				AUSD,
+				DOT
+			));
+			assert_eq!(DexModule::trading_pair_index(AUSD), vec![BTC]);
+			assert_eq!(DexModule::trading_pair_index(DOT), vec![BTC]);
+
+			assert_ok!(DexModule::disable_trading_pair(
+				Origin::signed(ListingOrigin::get()),
+				AUSD,
+				BTC
+			));
+			assert!(!TradingPairIndex::<Runtime>::contains_key(AUSD));
+
+			assert_ok!(DexModule::enable_trading_pair(
+				Origin::signed(ListingOrigin::get()),
+				DOT,
+				AUSD,
+				Curve::ConstantProduct
+			));
+			assert_eq!(DexModule::trading_pair_index(AUSD), vec![DOT]);
+			assert_eq!(DexModule::trading_pair_index(DOT), vec![BTC, AUSD]);
+		});
+}
+
 #[test]
 fn get_best_price_swap_path_work() {
 	ExtBuilder::default()
@@ -1417,6 +1646,11 @@ fn get_best_price_swap_path_work() {
 			LiquidityPool::<Runtime>::insert(AUSDBTCPair::get(), (50000, 10000));
 			LiquidityPool::<Runtime>::insert(DOTBTCPair::get(), (10000, 10000));
 
+			// the route through BTC is found without being suggested
+			assert_eq!(
+				DexModule::find_best_swap_path(DOT, AUSD, SwapLimit::ExactSupply(10, 0)),
+				Some((vec![DOT, BTC, AUSD], 10, 44))
+			);
 			assert_eq!(
 				DexModule::get_best_price_swap_path(
 					DOT,
@@ -1424,13 +1658,13 @@ fn get_best_price_swap_path_work() {
 					SwapLimit::ExactSupply(10, 0),
 					vec![]
 				),
-				Some(vec![DOT, AUSD])
+				Some(vec![DOT, BTC, AUSD])
 			);
 			assert_eq!(
 				DexModule::get_best_price_swap_path(
 					DOT,
 					AUSD,
-					SwapLimit::ExactSupply(10, 30),
+					SwapLimit::ExactSupply(10, 45),
 					vec![]
 				),
 				None
@@ -1451,33 +1685,6 @@ fn get_best_price_swap_path_work() {
 					SwapLimit::ExactSupply(10, 0),
 					vec![vec![ACA]]
 				),
-				Some(vec![DOT, AUSD])
-			);
-			assert_eq!(
-				DexModule::get_best_price_swap_path(
-					DOT,
-					AUSD,
-					SwapLimit::ExactSupply(10, 0),
-					vec![vec![DOT]]
-				),
-				Some(vec![DOT, AUSD])
-			);
-			assert_eq!(
-				DexModule::get_best_price_swap_path(
-					DOT,
-					AUSD,
-					SwapLimit::ExactSupply(10, 0),
-					vec![vec![AUSD]]
-				),
-				Some(vec![DOT, AUSD])
-			);
-			assert_eq!(
-				DexModule::get_best_price_swap_path(
-					DOT,
-					AUSD,
-					SwapLimit::ExactSupply(10, 0),
-					vec![vec![BTC]]
-				),
 				Some(vec![DOT, BTC, AUSD])
 			);
 			assert_eq!(
@@ -1491,19 +1698,14 @@ fn get_best_price_swap_path_work() {
 			);
 
 			assert_eq!(
-				DexModule::get_best_price_swap_path(
-					DOT,
-					AUSD,
-					SwapLimit::ExactTarget(20, 30),
-					vec![]
-				),
-				Some(vec![DOT, AUSD])
+				DexModule::find_best_swap_path(DOT, AUSD, SwapLimit::ExactTarget(20, 30)),
+				Some((vec![DOT, BTC, AUSD], 8, 30))
 			);
 			assert_eq!(
 				DexModule::get_best_price_swap_path(
 					DOT,
 					AUSD,
-					SwapLimit::ExactTarget(10, 30),
+					SwapLimit::ExactTarget(7, 30),
 					vec![]
 				),
 				None
@@ -1512,66 +1714,267 @@ fn get_best_price_swap_path_work() {
 				DexModule::get_best_price_swap_path(
 					DOT,
 					AUSD,
-					SwapLimit::ExactTarget(0, 0),
+					SwapLimit::ExactTarget(100000, 20000),
 					vec![]
 				),
-				None
-			);
-			assert_eq!(
-				DexModule::get_best_price_swap_path(
-					DOT,
-					AUSD,
-					SwapLimit::ExactTarget(20, 30),
-					vec![vec![ACA]]
-				),
 				Some(vec![DOT, AUSD])
 			);
+
+			assert_ok!(DexModule::disable_trading_pair(
+				Origin::signed(ListingOrigin::get()),
+				DOT,
+				BTC
+			));
 			assert_eq!(
 				DexModule::get_best_price_swap_path(
 					DOT,
 					AUSD,
-					SwapLimit::ExactTarget(20, 30),
-					vec![vec![DOT]]
+					SwapLimit::ExactSupply(10, 0),
+					vec![vec![BTC]]
 				),
 				Some(vec![DOT, AUSD])
 			);
-			assert_eq!(
-				DexModule::get_best_price_swap_path(
-					DOT,
-					AUSD,
-					SwapLimit::ExactTarget(20, 30),
-					vec![vec![AUSD]]
-				),
-				Some(vec![DOT, AUSD])
+		});
+}
+
+#[test]
+fn migrate_v1_indexes_enabled_trading_pairs() {
+	ExtBuilder::default()
+		.initialize_enabled_trading_pairs()
+		.build()
+		.execute_with(|| {
+			TradingPairStatuses::<Runtime>::insert(
+				DOTBTCPair::get(),
+				TradingPairStatus::<_, _>::Disabled,
 			);
-			assert_eq!(
-				DexModule::get_best_price_swap_path(
-					DOT,
+			for currency_id in [AUSD, DOT, BTC] {
+				TradingPairIndex::<Runtime>::remove(currency_id);
+			}
+			StorageVersion::new(0).put::<DexModule>();
+
+			migrations::v1::migrate::<Runtime>();
+
+			assert_eq!(DexModule::trading_pair_index(AUSD).len(), 2);
+			assert_eq!(DexModule::trading_pair_index(DOT), vec![AUSD]);
+			assert_eq!(DexModule::trading_pair_index(BTC), vec![AUSD]);
+			assert_eq!(DexModule::on_chain_storage_version(), 1);
+		});
+}
+
+#[test]
+fn dex_share_currency_skips_taken_ids() {
+	ExtBuilder::default().build().execute_with(|| {
+		for currency_id in [1_000, 1_001] {
+			assert_ok!(Assets::force_create(Origin::root(), currency_id, ALICE, true, 1));
+		}
+		assert_ok!(DexModule::enable_trading_pair(
+			Origin::signed(ListingOrigin::get()),
+			AUSD,
+			DOT,
+			Curve::ConstantProduct
+		));
+		assert_eq!(DexModule::dex_share_currency_ids(AUSDDOTPair::get()), Some(1_002));
+		assert_eq!(DexModule::dex_share_trading_pairs(1_002), Some(AUSDDOTPair::get()));
+		assert_eq!(NextDexShareCurrencyId::<Runtime>::get(), Some(1_003));
+
+		for currency_id in [1_003, 1_004, 1_005] {
+			assert_ok!(Assets::force_create(Origin::root(), currency_id, ALICE, true, 1));
+		}
+		assert_noop!(
+			DexModule::enable_trading_pair(
+				Origin::signed(ListingOrigin::get()),
+				AUSD,
+				BTC,
+				Curve::ConstantProduct
+			),
+			Error::<Runtime>::DexShareCurrencyIdUnavailable
+		);
+	});
+}
+
+#[test]
+fn migrate_v2_registers_packed_dex_share_currencies() {
+	ExtBuilder::default().build().execute_with(|| {
+		// (AUSD + 1) << AssetIdShift + DOT
+		let packed_currency_id = (2 << 16) + DOT;
+		assert_ok!(Assets::force_create(Origin::root(), packed_currency_id, ALICE, true, 1));
+		TradingPairStatuses::<Runtime>::insert(
+			AUSDDOTPair::get(),
+			TradingPairStatus::<_, _>::Enabled,
+		);
+		TradingPairStatuses::<Runtime>::insert(
+			DOTBTCPair::get(),
+			TradingPairStatus::<_, _>::Enabled,
+		);
+		StorageVersion::new(1).put::<DexModule>();
+
+		migrations::v2::migrate::<Runtime>();
+
+		assert_eq!(DexModule::dex_share_currency_ids(AUSDDOTPair::get()), Some(packed_currency_id));
+		assert_eq!(
+			DexModule::dex_share_trading_pairs(packed_currency_id),
+			Some(AUSDDOTPair::get())
+		);
+		assert_eq!(DexModule::dex_share_currency_ids(DOTBTCPair::get()), None);
+		assert_eq!(DexModule::on_chain_storage_version(), 2);
+	});
+}
+
+/// Creates wrapped SOFI under the native id, held by BOB and by the pallet account.
+fn create_wrapped_sofi() {
+	NativeAssetId::set(u32::MAX);
+	assert_ok!(Assets::force_create(Origin::root(), ACA, ALICE, true, 1));
+	NativeAssetId::set(ACA);
+	assert_ok!(Assets::mint(Origin::signed(ALICE), ACA, BOB, 1_000));
+	assert_ok!(Assets::mint(Origin::signed(ALICE), ACA, dex_account(), 5_000));
+	StorageVersion::new(2).put::<DexModule>();
+}
+
+#[test]
+fn migrate_v3_pays_out_wrapped_sofi() {
+	ExtBuilder::default().build().execute_with(|| {
+		create_wrapped_sofi();
+		// the native currency backing the wrapped SOFI
+		assert_ok!(Balances::transfer(Origin::signed(ALICE), dex_account(), 6_000));
+		let bob_balance = Balances::free_balance(BOB);
+
+		migrations::v3::migrate::<Runtime>();
+
+		assert_eq!(Balances::free_balance(BOB), bob_balance + 1_000);
+		assert_eq!(Balances::free_balance(dex_account()), 5_000);
+		assert_eq!(Assets::maybe_total_supply(ACA), None);
+		assert_eq!(DexModule::on_chain_storage_version(), 3);
+	});
+}
+
+#[test]
+fn migrate_v3_keeps_wrapped_sofi_when_a_payout_fails() {
+	ExtBuilder::default().build().execute_with(|| {
+		create_wrapped_sofi();
+		// too little to pay out BOB
+		assert_ok!(Balances::transfer(Origin::signed(ALICE), dex_account(), 500));
+		let bob_balance = Balances::free_balance(BOB);
+
+		migrations::v3::migrate::<Runtime>();
+
+		assert_eq!(Balances::free_balance(BOB), bob_balance);
+		assert_eq!(Balances::free_balance(dex_account()), 500);
+		assert_eq!(Assets::maybe_total_supply(ACA), Some(6_000));
+		assert_eq!(Assets::balance(ACA, BOB), 1_000);
+		assert_eq!(DexModule::on_chain_storage_version(), 2);
+	});
+}
+
+#[test]
+fn migrate_v3_from_v0_keeps_existing_pools_usable() {
+	ExtBuilder::default().build().execute_with(|| {
+		// a pool of a version 0 dex, with its dex share under the packed id and no index
+		let packed_currency_id = (2 << 16) + DOT;
+		assert_ok!(Assets::force_create(Origin::root(), packed_currency_id, ALICE, true, 1));
+		assert_ok!(Assets::mint(Origin::signed(ALICE), packed_currency_id, ALICE, 2_000_000));
+		assert_ok!(Assets::transfer(Origin::signed(ALICE), AUSD, dex_account(), 1_000_000));
+		assert_ok!(Assets::transfer(Origin::signed(ALICE), DOT, dex_account(), 2_000_000));
+		LiquidityPool::<Runtime>::insert(AUSDDOTPair::get(), (1_000_000, 2_000_000));
+		TradingPairStatuses::<Runtime>::insert(
+			AUSDDOTPair::get(),
+			TradingPairStatus::<_, _>::Enabled,
+		);
+		StorageVersion::new(0).put::<DexModule>();
+
+		// the runtime migrations run ahead of the pallet hooks
+		migrations::v3::MigrateToV3::<Runtime>::on_runtime_upgrade();
+		<DexModule as OnRuntimeUpgrade>::on_runtime_upgrade();
+
+		assert_eq!(DexModule::on_chain_storage_version(), 3);
+		assert_eq!(DexModule::trading_pair_index(AUSD), vec![DOT]);
+		assert_eq!(DexModule::dex_share_currency_ids(AUSDDOTPair::get()), Some(packed_currency_id));
+
+		assert_ok!(DexModule::add_liquidity(
+			Origin::signed(BOB),
+			AUSD,
+			DOT,
+			1_000_000,
+			2_000_000,
+			0,
+		));
+		assert_eq!(Assets::balance(packed_currency_id, BOB), 2_000_000);
+		assert_ok!(DexModule::remove_liquidity(
+			Origin::signed(ALICE),
+			AUSD,
+			DOT,
+			2_000_000,
+			0,
+			0,
+			false,
+		));
+		assert_eq!(Assets::balance(packed_currency_id, ALICE), 0);
+		assert_eq!(DexModule::get_liquidity_pool(AUSD, DOT), (1_000_000, 2_000_000));
+	});
+}
+
+#[test]
+fn set_exchange_fee_work() {
+	ExtBuilder::default()
+		.initialize_enabled_trading_pairs()
+		.build()
+		.execute_with(|| {
+			LiquidityPool::<Runtime>::insert(AUSDDOTPair::get(), (50000, 10000));
+
+			assert_noop!(
+				DexModule::set_exchange_fee(Origin::signed(ALICE), AUSD, DOT, Some((0, 1))),
+				BadOrigin
+			);
+			assert_noop!(
+				DexModule::set_exchange_fee(
+					Origin::signed(ListingOrigin::get()),
 					AUSD,
-					SwapLimit::ExactTarget(20, 30),
-					vec![vec![BTC]]
+					AUSD,
+					Some((0, 1))
 				),
-				Some(vec![DOT, BTC, AUSD])
+				Error::<Runtime>::InvalidCurrencyId
 			);
-			assert_eq!(
-				DexModule::get_best_price_swap_path(
-					DOT,
+			assert_noop!(
+				DexModule::set_exchange_fee(
+					Origin::signed(ListingOrigin::get()),
 					AUSD,
-					SwapLimit::ExactTarget(100000, 20000),
-					vec![vec![BTC]]
+					DOT,
+					Some((100, 100))
 				),
-				Some(vec![DOT, AUSD])
+				Error::<Runtime>::InvalidFeeRate
 			);
+
+			assert_eq!(DexModule::exchange_fee(&AUSDDOTPair::get()), (1, 100));
+			assert_ok!(DexModule::set_exchange_fee(
+				Origin::signed(ListingOrigin::get()),
+				DOT,
+				AUSD,
+				Some((0, 1))
+			));
+			System::assert_last_event(Event::DexModule(crate::Event::ExchangeFeeUpdated {
+				trading_pair: AUSDDOTPair::get(),
+				fee_rate: Some((0, 1)),
+			}));
+			assert_eq!(DexModule::exchange_fee(&AUSDDOTPair::get()), (0, 1));
+			assert_eq!(DexModule::exchange_fee(&AUSDBTCPair::get()), (1, 100));
+			assert_eq!(DexModule::get_target_amounts(&[DOT, AUSD], 10000), Ok(vec![10000, 25000]));
+
+			assert_ok!(DexModule::set_exchange_fee(
+				Origin::signed(ListingOrigin::get()),
+				AUSD,
+				DOT,
+				None
+			));
+			assert_eq!(DexModule::exchange_fees(AUSDDOTPair::get()), None);
+			assert_eq!(DexModule::get_target_amounts(&[DOT, AUSD], 10000), Ok(vec![10000, 24874]));
 		});
 }
 
 #[test]
-fn swap_with_specific_path_work() {
+fn protocol_fee_work() {
 	ExtBuilder::default()
 		.initialize_enabled_trading_pairs()
 		.build()
 		.execute_with(|| {
-			System::set_block_number(1);
 			assert_ok!(DexModule::add_liquidity(
 				Origin::signed(ALICE),
 				AUSD,
@@ -1579,94 +1982,229 @@ fn swap_with_specific_path_work() {
 				500_000_000_000_000,
 				100_000_000_000_000,
 				0,
-				false,
 			));
 
 			assert_noop!(
-				DexModule::swap_with_specific_path(
-					&BOB,
-					&vec![DOT, AUSD],
-					SwapLimit::ExactSupply(100_000_000_000_000, 248_743_718_592_965)
-				),
-				Error::<Runtime>::InsufficientTargetAmount
+				DexModule::set_protocol_fee_share(Origin::signed(ALICE), Permill::from_percent(20)),
+				BadOrigin
 			);
+			assert_ok!(DexModule::set_protocol_fee_share(
+				Origin::signed(ListingOrigin::get()),
+				Permill::from_percent(20)
+			));
+			System::assert_last_event(Event::DexModule(crate::Event::ProtocolFeeShareUpdated {
+				share: Permill::from_percent(20),
+			}));
 
-			assert_ok!(DexModule::swap_with_specific_path(
-				&BOB,
-				&vec![DOT, AUSD],
-				SwapLimit::ExactSupply(100_000_000_000_000, 200_000_000_000_000)
+			assert_ok!(DexModule::swap_with_exact_supply(
+				Origin::signed(BOB),
+				vec![DOT, AUSD],
+				100_000_000_000_000,
+				0,
 			));
-			System::assert_last_event(Event::DexModule(crate::Event::Swap {
-				trader: BOB,
-				path: vec![DOT, AUSD],
-				liquidity_changes: vec![100_000_000_000_000, 248_743_718_592_964],
+			System::assert_has_event(Event::DexModule(crate::Event::FeeCharged {
+				trading_pair: AUSDDOTPair::get(),
+				currency_id: DOT,
+				fee: 1_000_000_000_000,
+				protocol_fee: 200_000_000_000,
 			}));
+			// the protocol fee stays in the module account but out of the pool
+			assert_eq!(
+				DexModule::get_liquidity_pool(AUSD, DOT),
+				(251_256_281_407_036, 199_800_000_000_000)
+			);
+			assert_eq!(Currencies::balance(DOT, &dex_account()), 200_000_000_000_000);
+			assert_eq!(DexModule::protocol_fees(DOT), 200_000_000_000);
+			assert_eq!(DexModule::cumulative_fees(AUSDDOTPair::get()), (0, 1_000_000_000_000));
+
+			assert_ok!(DexModule::claim_protocol_fees(Origin::signed(BOB), DOT));
+			System::assert_last_event(Event::DexModule(crate::Event::ProtocolFeesClaimed {
+				currency_id: DOT,
+				amount: 200_000_000_000,
+			}));
+			assert_eq!(DexModule::protocol_fees(DOT), 0);
+			assert_eq!(Currencies::balance(DOT, &TREASURY), 200_000_000_000);
+			assert_eq!(Currencies::balance(DOT, &dex_account()), 199_800_000_000_000);
+
+			assert_ok!(DexModule::claim_protocol_fees(Origin::signed(BOB), DOT));
+			assert_eq!(Currencies::balance(DOT, &TREASURY), 200_000_000_000);
+		});
+}
+
+#[test]
+fn get_twap_work() {
+	ExtBuilder::default()
+		.initialize_enabled_trading_pairs()
+		.initialize_added_liquidity_pools(ALICE)
+		.build()
+		.execute_with(|| {
+			assert_eq!(DexModule::get_twap(AUSD, ACA, 1), None);
+			assert_eq!(DexModule::get_twap(AUSD, DOT, 5), None);
+			assert_eq!(DexModule::get_twap(AUSD, DOT, 1), Some(Price::saturating_from_integer(2)));
+			assert_eq!(
+				DexModule::get_twap(DOT, AUSD, 1),
+				Some(Price::saturating_from_rational(1, 2))
+			);
+
+			// the price of AUSD falls from 2 DOT to 0.5 DOT at block 11
+			System::set_block_number(11);
+			assert_ok!(DexModule::_swap(AUSD, DOT, 1_000_000, 1_000_000));
+			assert_eq!(DexModule::get_liquidity_pool(AUSD, DOT), (2_000_000, 1_000_000));
+			assert_eq!(
+				DexModule::price_observations(AUSDDOTPair::get())
+					.iter()
+					.map(|observation| observation.block_number)
+					.collect::<Vec<_>>(),
+				vec![0, 11]
+			);
+
+			System::set_block_number(21);
+			assert_eq!(
+				DexModule::get_twap(AUSD, DOT, 10),
+				Some(Price::saturating_from_rational(1, 2))
+			);
+			assert_eq!(
+				DexModule::get_twap(AUSD, DOT, 20),
+				Some(Price::from_inner(1_285_714_285_714_285_714))
+			);
+			assert_eq!(DexModule::get_twap(DOT, AUSD, 10), Some(Price::saturating_from_integer(2)));
+
+			// only the latest `MaxPriceObservations` observations are kept
+			assert_ok!(DexModule::_swap(DOT, AUSD, 1_000_000, 1_000_000));
+			System::set_block_number(31);
+			assert_ok!(DexModule::_swap(AUSD, DOT, 1_000_000, 1_000_000));
+			assert_eq!(
+				DexModule::price_observations(AUSDDOTPair::get())
+					.iter()
+					.map(|observation| observation.block_number)
+					.collect::<Vec<_>>(),
+				vec![11, 21, 31]
+			);
+			System::set_block_number(41);
+			assert_eq!(DexModule::get_twap(AUSD, DOT, 40), None);
+			assert_eq!(DexModule::get_twap(AUSD, DOT, 30), Some(Price::saturating_from_integer(1)));
+		});
+}
+
+#[test]
+fn get_twap_uses_stable_swap_marginal_price() {
+	ExtBuilder::default()
+		.initialize_enabled_trading_pairs()
+		.initialize_added_liquidity_pools(ALICE)
+		.build()
+		.execute_with(|| {
+			Curves::<Runtime>::insert(AUSDDOTPair::get(), Curve::StableSwap { amplification: 100 });
+
+			// close to one to one rather than the 2 DOT per AUSD of the reserves
+			assert_eq!(
+				DexModule::get_twap(AUSD, DOT, 1),
+				Some(Price::from_inner(1_004_197_129_205_003_518))
+			);
+			assert_eq!(
+				DexModule::get_twap(DOT, AUSD, 1),
+				Some(Price::from_inner(995_820_413_061_401_325))
+			);
+		});
+}
+
+#[test]
+fn curve_of_pair_with_liquidity_is_locked() {
+	ExtBuilder::default()
+		.initialize_enabled_trading_pairs()
+		.initialize_added_liquidity_pools(ALICE)
+		.build()
+		.execute_with(|| {
+			for (currency_id_a, currency_id_b) in [(AUSD, DOT), (AUSD, BTC)] {
+				assert_ok!(DexModule::disable_trading_pair(
+					Origin::signed(ListingOrigin::get()),
+					currency_id_a,
+					currency_id_b
+				));
+			}
+			LiquidityPool::<Runtime>::remove(AUSDBTCPair::get());
 
 			assert_noop!(
-				DexModule::swap_with_specific_path(
-					&BOB,
-					&vec![AUSD, DOT],
-					SwapLimit::ExactTarget(253_794_223_643_470, 100_000_000_000_000)
+				DexModule::enable_trading_pair(
+					Origin::signed(ListingOrigin::get()),
+					AUSD,
+					DOT,
+					Curve::StableSwap { amplification: 100 }
 				),
-				Error::<Runtime>::ExcessiveSupplyAmount
+				Error::<Runtime>::CurveLocked
 			);
+			assert_ok!(DexModule::enable_trading_pair(
+				Origin::signed(ListingOrigin::get()),
+				AUSD,
+				DOT,
+				Curve::ConstantProduct
+			));
 
-			assert_ok!(DexModule::swap_with_specific_path(
-				&BOB,
-				&vec![AUSD, DOT],
-				SwapLimit::ExactTarget(300_000_000_000_000, 100_000_000_000_000)
+			assert_ok!(DexModule::enable_trading_pair(
+				Origin::signed(ListingOrigin::get()),
+				AUSD,
+				BTC,
+				Curve::StableSwap { amplification: 100 }
 			));
-			System::assert_last_event(Event::DexModule(crate::Event::Swap {
-				trader: BOB,
-				path: vec![AUSD, DOT],
-				liquidity_changes: vec![253_794_223_643_471, 100_000_000_000_000],
-			}));
+			assert_eq!(
+				DexModule::curves(AUSDBTCPair::get()),
+				Curve::StableSwap { amplification: 100 }
+			);
 		});
 }
 
 #[test]
-fn get_liquidity_token_address_work() {
-	ExtBuilder::default().build().execute_with(|| {
-		System::set_block_number(1);
-
-		assert_eq!(
-			DexModule::trading_pair_statuses(AUSDDOTPair::get()),
-			TradingPairStatus::<_, _>::Disabled
-		);
-		assert_eq!(DexModule::get_liquidity_token_address(AUSD, DOT), None);
+fn stable_swap_get_d_and_get_y_round_trip() {
+	let x = U256::from(1_000_000_000_000u128);
+	let y = U256::from(2_000_000_000_000u128);
+
+	let d = stable_swap::get_d(x, x, 100).unwrap();
+	assert_eq!(d, x * 2);
+	assert_eq!(stable_swap::get_y(x, d, 100), Some(x));
+
+	let d = stable_swap::get_d(x, y, 100).unwrap();
+	assert!(d > x + y - x / 1_000 && d < x + y);
+	assert_eq!(stable_swap::get_y(x, d, 100), Some(y));
+	assert_eq!(stable_swap::get_y(y, d, 100), Some(x));
+
+	assert_eq!(stable_swap::get_d(U256::zero(), U256::zero(), 100), Some(U256::zero()));
+	assert_eq!(stable_swap::get_d(x, U256::zero(), 100), None);
+	assert_eq!(stable_swap::get_y(U256::zero(), d, 100), None);
+}
 
-		assert_ok!(DexModule::list_provisioning(
-			Origin::signed(ListingOrigin::get()),
-			AUSD,
-			DOT,
-			1_000_000_000_000u128,
-			1_000_000_000_000u128,
-			5_000_000_000_000u128,
-			2_000_000_000_000u128,
-			10,
-		));
-		assert_eq!(
-			DexModule::trading_pair_statuses(AUSDDOTPair::get()),
-			TradingPairStatus::<_, _>::Provisioning(ProvisioningParameters {
-				min_contribution: (1_000_000_000_000u128, 1_000_000_000_000u128),
-				target_provision: (5_000_000_000_000u128, 2_000_000_000_000u128),
-				accumulated_provision: (0, 0),
-				not_before: 10,
-			})
-		);
-		assert_eq!(
-			DexModule::get_liquidity_token_address(AUSD, DOT),
-			Some(H160::from_str("0x0000000000000000000200000000010000000002").unwrap())
-		);
+#[test]
+fn stable_swap_amounts_round_trip() {
+	let (supply_pool, target_pool) = (1_000_000_000_000u128, 2_000_000_000_000u128);
+
+	let target_amount =
+		stable_swap::get_target_amount(supply_pool, target_pool, 1_000_000_000, (0, 1), 100)
+			.unwrap();
+	assert_eq!(target_amount, 1_004_190_838);
+	assert_eq!(
+		stable_swap::get_supply_amount(supply_pool, target_pool, target_amount, (0, 1), 100),
+		Some(1_000_000_000)
+	);
+}
 
-		assert_ok!(DexModule::enable_trading_pair(Origin::signed(ListingOrigin::get()), AUSD, DOT));
-		assert_eq!(
-			DexModule::trading_pair_statuses(AUSDDOTPair::get()),
-			TradingPairStatus::<_, _>::Enabled
-		);
-		assert_eq!(
-			DexModule::get_liquidity_token_address(AUSD, DOT),
-			Some(H160::from_str("0x0000000000000000000200000000010000000002").unwrap())
-		);
-	});
+#[test]
+fn stable_swap_amounts_keep_invariant() {
+	let (supply_pool, target_pool) = (1_000_000_000_000u128, 10_000_000_000_000u128);
+	let invariant = |supply_pool: u128, target_pool: u128| {
+		let d = stable_swap::get_d(supply_pool.into(), target_pool.into(), 1).unwrap();
+		assert_eq!(stable_swap::get_d(target_pool.into(), supply_pool.into(), 1), Some(d));
+		d
+	};
+	let d = invariant(supply_pool, target_pool);
+
+	for amount in [1, 1_000, 10_000_000_000, 500_000_000_000] {
+		let target_amount =
+			stable_swap::get_target_amount(supply_pool, target_pool, amount, (0, 1), 1).unwrap();
+		assert!(invariant(supply_pool + amount, target_pool - target_amount) >= d);
+
+		let supply_amount =
+			stable_swap::get_supply_amount(supply_pool, target_pool, amount, (0, 1), 1).unwrap();
+		assert!(invariant(supply_pool + supply_amount, target_pool - amount) >= d);
+	}
+	// rounded in favour of the pool
+	assert_eq!(stable_swap::get_target_amount(supply_pool, target_pool, 1, (0, 1), 1), Some(3));
+	assert_eq!(stable_swap::get_supply_amount(supply_pool, target_pool, 1, (0, 1), 1), Some(1));
 }
@@ -0,0 +1,364 @@
+//! # Fee Payment Module
+//!
+//! ## Overview
+//!
+//! Transaction fees paid in any asset with a DEX pool against the native currency. The
+//! `ChargeAssetTxPayment` signed extension takes the place of `ChargeTransactionPayment` and lets
+//! the signer name a fee asset and the most of it they pay. The fee is bought from the pool of that
+//! asset with an exact target swap and withdrawn through the `OnChargeTransaction` of
+//! `pallet_transaction_payment` as usual. An account without the existential deposit of the native
+//! currency buys it along with the fee, so the withdrawal does not reap it.
+//!
+//! The part of the fee left unused after dispatch is swapped back into the fee asset, for no less
+//! than its value at the TWAP of the pool over `TwapWindow` less `MaxRefundSlippage`. If the pool
+//! has no price history that far back or the swap fails, the unused fee is refunded in the native
+//! currency instead.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+pub use pallet::*;
+
+#[cfg(test)]
+mod mock;
+
+#[cfg(test)]
+mod tests;
+
+use codec::{Decode, Encode};
+use frame_support::{
+	dispatch::DispatchResult,
+	traits::{tokens::fungibles::Inspect, Get},
+	transactional,
+	weights::{DispatchInfo, PostDispatchInfo},
+};
+use pallet_dex::{DEXManager, DEXPriceOracle, SwapLimit};
+use pallet_transaction_payment::{ChargeTransactionPayment, OnChargeTransaction};
+use scale_info::TypeInfo;
+use sp_runtime::{
+	traits::{
+		DispatchInfoOf, Dispatchable, PostDispatchInfoOf, SaturatedConversion, Saturating,
+		SignedExtension, Zero,
+	},
+	transaction_validity::{
+		InvalidTransaction, TransactionValidity, TransactionValidityError, ValidTransaction,
+	},
+	DispatchError, FixedPointNumber, FixedPointOperand, Permill,
+};
+
+pub(crate) type BalanceOf<T> = <<T as pallet_transaction_payment::Config>::OnChargeTransaction as OnChargeTransaction<T>>::Balance;
+pub(crate) type LiquidityInfoOf<T> = <<T as pallet_transaction_payment::Config>::OnChargeTransaction as OnChargeTransaction<T>>::LiquidityInfo;
+
+#[frame_support::pallet]
+pub mod pallet {
+	use super::{BalanceOf, Permill};
+	use frame_support::pallet_prelude::*;
+
+	#[pallet::config]
+	pub trait Config:
+		frame_system::Config + pallet_transaction_payment::Config + pallet_dex::Config
+	{
+		/// Because this pallet emits events, it depends on the runtime's definition of an event.
+		type Event: From<Event<Self>> + IsType<<Self as frame_system::Config>::Event>;
+
+		/// The number of blocks the TWAP the unused fee is swapped back at is taken over.
+		#[pallet::constant]
+		type TwapWindow: Get<Self::BlockNumber>;
+
+		/// The largest shortfall of the unused fee swapped back against its value at the TWAP,
+		/// covering the trading fees and the price impact of both swaps.
+		#[pallet::constant]
+		type MaxRefundSlippage: Get<Permill>;
+	}
+
+	#[pallet::pallet]
+	#[pallet::generate_store(pub(super) trait Store)]
+	pub struct Pallet<T>(_);
+
+	#[pallet::event]
+	#[pallet::generate_deposit(pub(super) fn deposit_event)]
+	pub enum Event<T: Config> {
+		/// A transaction fee was paid in a fee asset. The refund amount is in the fee asset, and
+		/// is zero when the unused fee was refunded in the native currency.
+		/// [who, asset_id, actual_fee, tip, supply_amount, refund_amount]
+		AssetTxFeePaid(
+			T::AccountId,
+			T::AssetId,
+			BalanceOf<T>,
+			BalanceOf<T>,
+			T::Balance,
+			T::Balance,
+		),
+		/// The unused fee of a transaction paid in a fee asset could not be swapped back and was
+		/// refunded in the native currency. [who, asset_id, refund]
+		AssetTxFeeRefundedInNative(T::AccountId, T::AssetId, BalanceOf<T>),
+	}
+
+	#[pallet::error]
+	pub enum Error<T> {
+		/// The pool of the fee asset has no price history over `TwapWindow`.
+		NoTwap,
+	}
+
+	#[pallet::call]
+	impl<T: Config> Pallet<T> {}
+}
+
+impl<T: Config> Pallet<T> {
+	/// Buy `native_amount` of the native currency for `who` with at most `max_supply_amount` of
+	/// `asset_id`, returning the amount of `asset_id` paid.
+	#[transactional]
+	pub(crate) fn buy_native(
+		who: &T::AccountId,
+		asset_id: T::AssetId,
+		native_amount: T::Balance,
+		max_supply_amount: T::Balance,
+	) -> Result<T::Balance, DispatchError> {
+		let native_asset_id = <T as pallet_assets::Config>::NativeAssetId::get();
+		let (supply_amount, _) =
+			<pallet_dex::Pallet<T> as DEXManager<_, _, _>>::swap_with_specific_path(
+				who,
+				&[asset_id, native_asset_id],
+				SwapLimit::ExactTarget(max_supply_amount, native_amount),
+			)?;
+
+		Ok(supply_amount)
+	}
+
+	/// Sell `refund` of the native currency of `who` for `asset_id`, for no less than its value at
+	/// the TWAP less `MaxRefundSlippage`, returning the amount of `asset_id` got.
+	#[transactional]
+	pub(crate) fn sell_native(
+		who: &T::AccountId,
+		asset_id: T::AssetId,
+		refund: BalanceOf<T>,
+	) -> Result<T::Balance, DispatchError> {
+		let native_asset_id = <T as pallet_assets::Config>::NativeAssetId::get();
+		let refund_amount: T::Balance = refund.saturated_into::<u128>().saturated_into();
+		let min_target_amount = <pallet_dex::Pallet<T> as DEXPriceOracle<_, _>>::get_twap(
+			native_asset_id,
+			asset_id,
+			T::TwapWindow::get(),
+		)
+		.and_then(|price| price.checked_mul_int(refund_amount))
+		.map(|amount| amount.saturating_sub(T::MaxRefundSlippage::get() * amount))
+		.ok_or(Error::<T>::NoTwap)?;
+		let (_, target_amount) =
+			<pallet_dex::Pallet<T> as DEXManager<_, _, _>>::swap_with_specific_path(
+				who,
+				&[native_asset_id, asset_id],
+				SwapLimit::ExactSupply(refund_amount, min_target_amount),
+			)?;
+
+		Ok(target_amount)
+	}
+}
+
+/// The fee withdrawn before dispatch.
+pub enum InitialPayment<T: Config> {
+	/// No fee was withdrawn.
+	Nothing,
+	/// The fee was withdrawn in the native currency.
+	Native(LiquidityInfoOf<T>),
+	/// The fee, and the existential deposit if the account lacked it, was bought with
+	/// `supply_amount` of `asset_id` and the fee was withdrawn in the native currency.
+	Asset {
+		asset_id: T::AssetId,
+		supply_amount: T::Balance,
+		fee: BalanceOf<T>,
+		liquidity_info: LiquidityInfoOf<T>,
+	},
+}
+
+/// Require the transactor to pay for themselves, in the native currency or in at most
+/// `max_fee_in_asset` of `asset_id`, and possibly include a tip to gain additional priority in the
+/// queue. The tip is in the native currency and paid with the fee.
+#[derive(Encode, Decode, Clone, Eq, PartialEq, TypeInfo)]
+#[scale_info(skip_type_params(T))]
+pub struct ChargeAssetTxPayment<T: Config> {
+	#[codec(compact)]
+	tip: BalanceOf<T>,
+	asset_id: Option<T::AssetId>,
+	max_fee_in_asset: T::Balance,
+}
+
+impl<T: Config> ChargeAssetTxPayment<T>
+where
+	T::Call: Dispatchable<Info = DispatchInfo, PostInfo = PostDispatchInfo>,
+	BalanceOf<T>: Send + Sync + FixedPointOperand,
+{
+	/// Utility constructor. Used only in client/factory code.
+	pub fn from(
+		tip: BalanceOf<T>,
+		asset_id: Option<T::AssetId>,
+		max_fee_in_asset: T::Balance,
+	) -> Self {
+		Self { tip, asset_id, max_fee_in_asset }
+	}
+
+	fn withdraw_fee(
+		&self,
+		who: &T::AccountId,
+		call: &T::Call,
+		info: &DispatchInfoOf<T::Call>,
+		len: usize,
+	) -> Result<(BalanceOf<T>, InitialPayment<T>), TransactionValidityError> {
+		let fee = pallet_transaction_payment::Pallet::<T>::compute_fee(len as u32, info, self.tip);
+		if fee.is_zero() {
+			return Ok((fee, InitialPayment::Nothing))
+		}
+
+		let native_asset_id = <T as pallet_assets::Config>::NativeAssetId::get();
+		match self.asset_id.filter(|asset_id| *asset_id != native_asset_id) {
+			None => <T as pallet_transaction_payment::Config>::OnChargeTransaction::withdraw_fee(
+				who, call, info, fee, self.tip,
+			)
+			.map(|liquidity_info| (fee, InitialPayment::Native(liquidity_info))),
+			Some(asset_id) => {
+				// the withdrawal keeps the account alive, so it has to end up with the existential
+				// deposit
+				let native_balance =
+					<T as pallet_dex::Config>::Currencies::balance(native_asset_id, who);
+				let existential_deposit =
+					<T as pallet_dex::Config>::Currencies::minimum_balance(native_asset_id);
+				let native_amount = fee
+					.saturated_into::<u128>()
+					.saturated_into::<T::Balance>()
+					.saturating_add(existential_deposit.saturating_sub(native_balance));
+				let supply_amount =
+					Pallet::<T>::buy_native(who, asset_id, native_amount, self.max_fee_in_asset)
+						.map_err(|_| TransactionValidityError::from(InvalidTransaction::Payment))?;
+				let liquidity_info =
+					<T as pallet_transaction_payment::Config>::OnChargeTransaction::withdraw_fee(
+						who, call, info, fee, self.tip,
+					)?;
+				Ok((fee, InitialPayment::Asset { asset_id, supply_amount, fee, liquidity_info }))
+			},
+		}
+	}
+}
+
+impl<T: Config> sp_std::fmt::Debug for ChargeAssetTxPayment<T> {
+	#[cfg(feature = "std")]
+	fn fmt(&self, f: &mut sp_std::fmt::Formatter) -> sp_std::fmt::Result {
+		write!(
+			f,
+			"ChargeAssetTxPayment<{:?}, {:?}, {:?}>",
+			self.tip,
+			self.asset_id.encode(),
+			self.max_fee_in_asset
+		)
+	}
+	#[cfg(not(feature = "std"))]
+	fn fmt(&self, _: &mut sp_std::fmt::Formatter) -> sp_std::fmt::Result {
+		Ok(())
+	}
+}
+
+impl<T: Config> SignedExtension for ChargeAssetTxPayment<T>
+where
+	T::Call: Dispatchable<Info = DispatchInfo, PostInfo = PostDispatchInfo>,
+	BalanceOf<T>: Send + Sync + FixedPointOperand,
+	T::AssetId: Send + Sync,
+{
+	const IDENTIFIER: &'static str = "ChargeAssetTxPayment";
+	type AccountId = T::AccountId;
+	type Call = T::Call;
+	type AdditionalSigned = ();
+	type Pre = (BalanceOf<T>, Self::AccountId, InitialPayment<T>);
+
+	fn additional_signed(&self) -> Result<(), TransactionValidityError> {
+		Ok(())
+	}
+
+	fn validate(
+		&self,
+		who: &Self::AccountId,
+		call: &Self::Call,
+		info: &DispatchInfoOf<Self::Call>,
+		len: usize,
+	) -> TransactionValidity {
+		let (fee, _) = self.withdraw_fee(who, call, info, len)?;
+		let priority = ChargeTransactionPayment::<T>::get_priority(info, len, self.tip, fee);
+		Ok(ValidTransaction { priority, ..Default::default() })
+	}
+
+	fn pre_dispatch(
+		self,
+		who: &Self::AccountId,
+		call: &Self::Call,
+		info: &DispatchInfoOf<Self::Call>,
+		len: usize,
+	) -> Result<Self::Pre, TransactionValidityError> {
+		let (_, initial_payment) = self.withdraw_fee(who, call, info, len)?;
+		Ok((self.tip, who.clone(), initial_payment))
+	}
+
+	fn post_dispatch(
+		pre: Self::Pre,
+		info: &DispatchInfoOf<Self::Call>,
+		post_info: &PostDispatchInfoOf<Self::Call>,
+		len: usize,
+		_result: &DispatchResult,
+	) -> Result<(), TransactionValidityError> {
+		let (tip, who, initial_payment) = pre;
+		match initial_payment {
+			InitialPayment::Native(liquidity_info) => {
+				let actual_fee = pallet_transaction_payment::Pallet::<T>::compute_actual_fee(
+					len as u32, info, post_info, tip,
+				);
+				<T as pallet_transaction_payment::Config>::OnChargeTransaction::correct_and_deposit_fee(
+					&who,
+					info,
+					post_info,
+					actual_fee,
+					tip,
+					liquidity_info,
+				)?;
+			},
+			InitialPayment::Asset { asset_id, supply_amount, fee, liquidity_info } => {
+				let actual_fee = pallet_transaction_payment::Pallet::<T>::compute_actual_fee(
+					len as u32, info, post_info, tip,
+				);
+				// refunds the unused fee in the native currency
+				<T as pallet_transaction_payment::Config>::OnChargeTransaction::correct_and_deposit_fee(
+					&who,
+					info,
+					post_info,
+					actual_fee,
+					tip,
+					liquidity_info,
+				)?;
+
+				let refund = fee.saturating_sub(actual_fee);
+				let refund_amount = if refund.is_zero() {
+					Zero::zero()
+				} else {
+					// keeps the native refund if it can't be swapped back
+					Pallet::<T>::sell_native(&who, asset_id, refund).unwrap_or_else(|_| {
+						Pallet::<T>::deposit_event(Event::AssetTxFeeRefundedInNative(
+							who.clone(),
+							asset_id,
+							refund,
+						));
+						Zero::zero()
+					})
+				};
+				Pallet::<T>::deposit_event(Event::AssetTxFeePaid(
+					who,
+					asset_id,
+					actual_fee,
+					tip,
+					supply_amount,
+					refund_amount,
+				));
+			},
+			InitialPayment::Nothing => {
+				// `actual_fee` should be zero here, as is the tip, because the fee
+				// computed before dispatch was zero
+				debug_assert!(tip.is_zero(), "tip should be zero if initial fee was zero.");
+			},
+		}
+
+		Ok(())
+	}
+}
@@ -0,0 +1,218 @@
+//! Mocks for the fee payment pallet.
+
+use crate as pallet_fee_payment;
+use frame_support::{
+	construct_runtime, parameter_types,
+	traits::{ConstU128, ConstU16, ConstU32, ConstU64, ConstU8},
+	weights::IdentityFee,
+	PalletId,
+};
+use frame_system::EnsureRoot;
+use pallet_dex::TradingPair;
+use pallet_transaction_payment::CurrencyAdapter;
+use sp_core::H256;
+use sp_runtime::{
+	testing::Header,
+	traits::{AccountIdConversion, BlakeTwo256, IdentityLookup},
+	Permill,
+};
+
+pub type AccountId = u128;
+pub type AssetId = u32;
+pub type Balance = u128;
+pub type BlockNumber = u64;
+
+pub const ALICE: AccountId = 1;
+pub const BOB: AccountId = 2;
+/// Holds DOT but none of the native currency.
+pub const CHARLIE: AccountId = 3;
+
+pub const NATIVE: AssetId = 0;
+pub const DOT: AssetId = 1;
+
+pub const EXISTENTIAL_DEPOSIT: Balance = 10;
+
+type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
+type Block = frame_system::mocking::MockBlock<Test>;
+
+construct_runtime!(
+	pub enum Test where
+		Block = Block,
+		NodeBlock = Block,
+		UncheckedExtrinsic = UncheckedExtrinsic,
+	{
+		System: frame_system::{Pallet, Call, Config, Storage, Event<T>},
+		Balances: pallet_balances::{Pallet, Call, Storage, Config<T>, Event<T>},
+		TransactionPayment: pallet_transaction_payment::{Pallet, Storage},
+		Assets: pallet_assets::{Pallet, Call, Storage, Config<T>, Event<T>},
+		Dex: pallet_dex::{Pallet, Call, Storage, Config<T>, Event<T>},
+		FeePayment: pallet_fee_payment::{Pallet, Event<T>},
+	}
+);
+
+parameter_types! {
+	/// No base fee, so the fee of a transaction is its length and weight.
+	pub BlockWeights: frame_system::limits::BlockWeights =
+		frame_system::limits::BlockWeights::simple_max(1_000_000);
+}
+
+impl frame_system::Config for Test {
+	type BaseCallFilter = frame_support::traits::Everything;
+	type BlockWeights = BlockWeights;
+	type BlockLength = ();
+	type DbWeight = ();
+	type Origin = Origin;
+	type Call = Call;
+	type Index = u64;
+	type BlockNumber = BlockNumber;
+	type Hash = H256;
+	type Hashing = BlakeTwo256;
+	type AccountId = AccountId;
+	type Lookup = IdentityLookup<Self::AccountId>;
+	type Header = Header;
+	type Event = Event;
+	type BlockHashCount = ConstU64<250>;
+	type Version = ();
+	type PalletInfo = PalletInfo;
+	type AccountData = pallet_balances::AccountData<Balance>;
+	type OnNewAccount = ();
+	type OnKilledAccount = ();
+	type SystemWeightInfo = ();
+	type SS58Prefix = ConstU16<42>;
+	type OnSetCode = ();
+	type MaxConsumers = ConstU32<16>;
+}
+
+impl pallet_balances::Config for Test {
+	type Balance = Balance;
+	type DustRemoval = ();
+	type Event = Event;
+	type ExistentialDeposit = ConstU128<EXISTENTIAL_DEPOSIT>;
+	type AccountStore = System;
+	type WeightInfo = ();
+	type MaxLocks = ();
+	type MaxReserves = ();
+	type ReserveIdentifier = [u8; 8];
+}
+
+impl pallet_transaction_payment::Config for Test {
+	type OnChargeTransaction = CurrencyAdapter<Balances, ()>;
+	type TransactionByteFee = ConstU128<1>;
+	type OperationalFeeMultiplier = ConstU8<5>;
+	type WeightToFee = IdentityFee<Balance>;
+	type FeeMultiplierUpdate = ();
+}
+
+parameter_types! {
+	pub const AirdropReserveId: [u8; 8] = *b"airdrop ";
+}
+
+impl pallet_assets::Config for Test {
+	type Event = Event;
+	type Balance = Balance;
+	type AssetId = AssetId;
+	type NativeAssetId = ConstU32<NATIVE>;
+	type Currency = Balances;
+	type ForceOrigin = EnsureRoot<AccountId>;
+	type AssetDeposit = ConstU128<1>;
+	type AssetAccountDeposit = ConstU128<10>;
+	type MetadataDepositBase = ConstU128<1>;
+	type MetadataDepositPerByte = ConstU128<1>;
+	type ApprovalDeposit = ConstU128<1>;
+	type StringLimit = ConstU32<50>;
+	type Freezer = ();
+	type FreezerWeight = ();
+	type WeightInfo = ();
+	type Extra = ();
+	type MaxLocks = ConstU32<2>;
+	type MaxReserves = ConstU32<2>;
+	type ReserveIdentifier = [u8; 8];
+	type MaxBatchSize = ConstU32<3>;
+	type AirdropReserveId = AirdropReserveId;
+	type AirdropDeposit = ConstU128<1>;
+}
+
+pub type Currencies = pallet_assets::NativeAssetAdapter<Test, Balances>;
+
+parameter_types! {
+	/// Paid both when the fee is bought and when the unused fee is swapped back.
+	pub const GetExchangeFee: (u32, u32) = (3, 1000);
+	pub const DexPalletId: PalletId = PalletId(*b"rai/dexm");
+	pub TreasuryAccount: AccountId = PalletId(*b"rai/trsy").into_account();
+}
+
+impl pallet_dex::Config for Test {
+	type Event = Event;
+	type Currencies = Currencies;
+	type GetExchangeFee = GetExchangeFee;
+	type TreasuryAccount = TreasuryAccount;
+	type TradingPathLimit = ConstU32<3>;
+	type PriceObservationInterval = ConstU64<10>;
+	type MaxPriceObservations = ConstU32<10>;
+	type MaxSwapPathSearch = ConstU32<64>;
+	type MaxAmplification = ConstU32<1_000_000>;
+	type PalletId = DexPalletId;
+	type AssetIdShift = ConstU32<16>;
+	type DexShareCurrencyIdStart = ConstU32<1_000>;
+	type MaxDexShareCurrencyIdSearch = ConstU32<3>;
+	type DEXIncentives = ();
+	type ListingOrigin = EnsureRoot<AccountId>;
+}
+
+parameter_types! {
+	pub const MaxRefundSlippage: Permill = Permill::from_percent(1);
+}
+
+impl pallet_fee_payment::Config for Test {
+	type Event = Event;
+	type TwapWindow = ConstU64<1>;
+	type MaxRefundSlippage = MaxRefundSlippage;
+}
+
+pub struct ExtBuilder {
+	balances: Vec<(AccountId, Balance)>,
+	asset_balances: Vec<(AssetId, AccountId, Balance)>,
+}
+
+impl Default for ExtBuilder {
+	fn default() -> Self {
+		Self {
+			balances: vec![(ALICE, 1_000_000_000), (BOB, 1_000_000_000)],
+			asset_balances: vec![(DOT, ALICE, 1_000_000_000), (DOT, CHARLIE, 1_000_000)],
+		}
+	}
+}
+
+impl ExtBuilder {
+	/// Genesis where ALICE provides a NATIVE/DOT pool of 1_000_000 each.
+	pub fn build(self) -> sp_io::TestExternalities {
+		let mut t = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
+
+		pallet_balances::GenesisConfig::<Test> { balances: self.balances }
+			.assimilate_storage(&mut t)
+			.unwrap();
+
+		pallet_assets::GenesisConfig::<Test> {
+			assets: vec![(DOT, ALICE, true, 1)],
+			metadata: vec![],
+			accounts: self.asset_balances,
+		}
+		.assimilate_storage(&mut t)
+		.unwrap();
+
+		pallet_dex::GenesisConfig::<Test> {
+			initial_listing_trading_pairs: vec![],
+			initial_enabled_trading_pairs: vec![TradingPair::new(NATIVE, DOT)],
+			initial_added_liquidity_pools: vec![(
+				ALICE,
+				vec![(TradingPair::new(NATIVE, DOT), (1_000_000, 1_000_000))],
+			)],
+		}
+		.assimilate_storage(&mut t)
+		.unwrap();
+
+		let mut ext = sp_io::TestExternalities::new(t);
+		ext.execute_with(|| System::set_block_number(1));
+		ext
+	}
+}
@@ -0,0 +1,192 @@
+//! Unit tests for the fee payment pallet.
+
+use crate::{mock::*, ChargeAssetTxPayment};
+use frame_support::{
+	assert_ok,
+	traits::tokens::fungibles::Inspect,
+	weights::{DispatchInfo, PostDispatchInfo, Weight},
+};
+use pallet_dex::{DEXManager, SwapLimit};
+use sp_runtime::{
+	traits::SignedExtension,
+	transaction_validity::{InvalidTransaction, TransactionValidityError},
+};
+
+/// The length of the transactions, which with a byte fee of 1 adds 10 to their fee.
+const LEN: usize = 10;
+
+fn call() -> Call {
+	Call::System(frame_system::Call::remark { remark: vec![] })
+}
+
+fn info(weight: Weight) -> DispatchInfo {
+	DispatchInfo { weight, ..Default::default() }
+}
+
+fn post_info(actual_weight: Weight) -> PostDispatchInfo {
+	PostDispatchInfo { actual_weight: Some(actual_weight), pays_fee: Default::default() }
+}
+
+/// The DOT it takes to buy `native_amount` of the native currency.
+fn supply_amount(native_amount: Balance) -> Balance {
+	Dex::get_swap_amount(&[DOT, NATIVE], SwapLimit::ExactTarget(Balance::MAX, native_amount))
+		.unwrap()
+		.0
+}
+
+/// The DOT `native_amount` of the native currency is sold for.
+fn target_amount(native_amount: Balance) -> Balance {
+	Dex::get_swap_amount(&[NATIVE, DOT], SwapLimit::ExactSupply(native_amount, 0))
+		.unwrap()
+		.1
+}
+
+#[test]
+fn fee_is_paid_in_native() {
+	ExtBuilder::default().build().execute_with(|| {
+		for asset_id in [None, Some(NATIVE)] {
+			let native = Balances::free_balance(ALICE);
+			let pre = ChargeAssetTxPayment::<Test>::from(0, asset_id, 0)
+				.pre_dispatch(&ALICE, &call(), &info(1_000), LEN)
+				.unwrap();
+			assert_eq!(Balances::free_balance(ALICE), native - 1_010);
+
+			assert_ok!(ChargeAssetTxPayment::<Test>::post_dispatch(
+				pre,
+				&info(1_000),
+				&post_info(500),
+				LEN,
+				&Ok(())
+			));
+			assert_eq!(Balances::free_balance(ALICE), native - 510);
+		}
+	});
+}
+
+#[test]
+fn fee_is_paid_in_asset() {
+	ExtBuilder::default().build().execute_with(|| {
+		let native = Balances::free_balance(ALICE);
+		let dot = Currencies::balance(DOT, &ALICE);
+		let supply_amount = supply_amount(1_010);
+
+		let pre = ChargeAssetTxPayment::<Test>::from(0, Some(DOT), supply_amount)
+			.pre_dispatch(&ALICE, &call(), &info(1_000), LEN)
+			.unwrap();
+		assert_eq!(Balances::free_balance(ALICE), native);
+		assert_eq!(Currencies::balance(DOT, &ALICE), dot - supply_amount);
+
+		// the unused 500 is swapped back, for less than its share of the DOT paid as both swaps
+		// pay the exchange fee
+		let refund_amount = target_amount(500);
+		assert!(refund_amount > 0 && refund_amount < supply_amount * 500 / 1_010);
+		assert_ok!(ChargeAssetTxPayment::<Test>::post_dispatch(
+			pre,
+			&info(1_000),
+			&post_info(500),
+			LEN,
+			&Ok(())
+		));
+		System::assert_last_event(Event::FeePayment(crate::Event::AssetTxFeePaid(
+			ALICE,
+			DOT,
+			510,
+			0,
+			supply_amount,
+			refund_amount,
+		)));
+		assert_eq!(Balances::free_balance(ALICE), native);
+		assert_eq!(Currencies::balance(DOT, &ALICE), dot - supply_amount + refund_amount);
+	});
+}
+
+#[test]
+fn fee_in_asset_is_capped() {
+	ExtBuilder::default().build().execute_with(|| {
+		let dot = Currencies::balance(DOT, &ALICE);
+		let charge = ChargeAssetTxPayment::<Test>::from(0, Some(DOT), supply_amount(1_010) - 1);
+
+		assert_eq!(
+			charge.validate(&ALICE, &call(), &info(1_000), LEN),
+			Err(TransactionValidityError::Invalid(InvalidTransaction::Payment))
+		);
+		assert_eq!(
+			charge.pre_dispatch(&ALICE, &call(), &info(1_000), LEN).map(|_| ()),
+			Err(TransactionValidityError::Invalid(InvalidTransaction::Payment))
+		);
+		assert_eq!(Currencies::balance(DOT, &ALICE), dot);
+	});
+}
+
+#[test]
+fn validate_prioritizes_tips() {
+	ExtBuilder::default().build().execute_with(|| {
+		let priority = |tip| {
+			ChargeAssetTxPayment::<Test>::from(tip, Some(DOT), Balance::MAX)
+				.validate(&ALICE, &call(), &info(1_000), LEN)
+				.unwrap()
+				.priority
+		};
+		assert!(priority(100) > priority(0));
+	});
+}
+
+#[test]
+fn zero_native_account_pays_in_asset() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_eq!(Balances::free_balance(CHARLIE), 0);
+		// buys the existential deposit along with the fee
+		let supply_amount = supply_amount(1_010 + EXISTENTIAL_DEPOSIT);
+
+		let pre = ChargeAssetTxPayment::<Test>::from(0, Some(DOT), supply_amount)
+			.pre_dispatch(&CHARLIE, &call(), &info(1_000), LEN)
+			.unwrap();
+		assert_eq!(Balances::free_balance(CHARLIE), EXISTENTIAL_DEPOSIT);
+		assert_eq!(Currencies::balance(DOT, &CHARLIE), 1_000_000 - supply_amount);
+
+		assert_ok!(ChargeAssetTxPayment::<Test>::post_dispatch(
+			pre,
+			&info(1_000),
+			&post_info(500),
+			LEN,
+			&Ok(())
+		));
+		assert_eq!(Balances::free_balance(CHARLIE), EXISTENTIAL_DEPOSIT);
+	});
+}
+
+#[test]
+fn refund_falls_back_to_native() {
+	ExtBuilder::default().build().execute_with(|| {
+		let supply_amount = supply_amount(1_010);
+		let pre = ChargeAssetTxPayment::<Test>::from(0, Some(DOT), supply_amount)
+			.pre_dispatch(&ALICE, &call(), &info(1_000), LEN)
+			.unwrap();
+
+		// the native currency gets cheaper before the refund
+		assert_ok!(Dex::swap_with_exact_supply(Origin::signed(BOB), vec![NATIVE, DOT], 100_000, 0));
+		let native = Balances::free_balance(ALICE);
+		let dot = Currencies::balance(DOT, &ALICE);
+
+		assert_ok!(ChargeAssetTxPayment::<Test>::post_dispatch(
+			pre,
+			&info(1_000),
+			&post_info(500),
+			LEN,
+			&Ok(())
+		));
+		System::assert_has_event(Event::FeePayment(crate::Event::AssetTxFeeRefundedInNative(
+			ALICE, DOT, 500,
+		)));
+		System::assert_last_event(Event::FeePayment(crate::Event::AssetTxFeePaid(
+			ALICE,
+			DOT,
+			510,
+			0,
+			supply_amount,
+			0,
+		)));
+		assert_eq!(Balances::free_balance(ALICE), native + 500);
+		assert_eq!(Currencies::balance(DOT, &ALICE), dot);
+	});
+}
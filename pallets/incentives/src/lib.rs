@@ -0,0 +1,350 @@
+//! # Incentives Module
+//!
+//! ## Overview
+//!
+//! Liquidity mining for the DEX. Liquidity providers stake the DEX share assets of a trading
+//! pair and earn the per-block reward emission governance has set for that pool, split in
+//! proportion to the staked shares.
+//!
+//! Staked shares and reward funds are both held by the pallet account. Rewards are paid in
+//! `RewardAssetId` and the pallet account has to be funded with it for claims to succeed.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+pub use pallet::*;
+
+#[cfg(test)]
+mod mock;
+
+#[cfg(test)]
+mod tests;
+
+use codec::{Decode, Encode};
+use scale_info::TypeInfo;
+use sp_runtime::{FixedU128, RuntimeDebug};
+
+/// Reward accounting of a pool.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, Default, RuntimeDebug, TypeInfo)]
+pub struct PoolInfo<Balance, BlockNumber> {
+	/// The amount of DEX share staked in the pool.
+	pub total_shares: Balance,
+	/// Rewards emitted per staked share since the pool was first staked in.
+	pub reward_per_share: FixedU128,
+	/// The block up to which rewards have been emitted.
+	pub last_reward_block: BlockNumber,
+	/// Rewards emitted but left out of `reward_per_share` by its precision, carried over to the
+	/// next emission.
+	pub reward_remainder: Balance,
+}
+
+/// Stake of an account in a pool.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, Default, RuntimeDebug, TypeInfo)]
+pub struct StakeInfo<Balance> {
+	/// The amount of DEX share staked.
+	pub shares: Balance,
+	/// The `reward_per_share` of the pool the pending rewards were last brought up to.
+	pub reward_per_share_paid: FixedU128,
+	/// Rewards earned and not yet claimed.
+	pub pending_rewards: Balance,
+}
+
+#[frame_support::pallet]
+pub mod pallet {
+	use super::{PoolInfo, StakeInfo};
+	use frame_support::{
+		pallet_prelude::*, traits::tokens::fungibles::Transfer, transactional, PalletId,
+	};
+	use frame_system::pallet_prelude::*;
+	use pallet_dex::{DEXIncentives, DEXManager};
+	use sp_runtime::{
+		traits::{
+			AccountIdConversion, CheckedAdd, CheckedSub, One, Saturating, UniqueSaturatedFrom,
+			UniqueSaturatedInto, Zero,
+		},
+		ArithmeticError, FixedPointNumber, FixedU128,
+	};
+
+	#[pallet::config]
+	pub trait Config: frame_system::Config + pallet_assets::Config {
+		/// Because this pallet emits events, it depends on the runtime's definition of an event.
+		type Event: From<Event<Self>> + IsType<<Self as frame_system::Config>::Event>;
+
+		/// The pallet id, its account keeps the staked DEX shares and the reward funds.
+		#[pallet::constant]
+		type PalletId: Get<PalletId>;
+
+		/// The currencies of the staked DEX shares and of the rewards, with the native currency as
+		/// `pallet_assets::Config::NativeAssetId`.
+		type Currencies: Transfer<Self::AccountId, AssetId = Self::AssetId, Balance = Self::Balance>;
+
+		/// The asset rewards are paid in.
+		#[pallet::constant]
+		type RewardAssetId: Get<Self::AssetId>;
+
+		/// The DEX, only the dex share currencies of its trading pairs can be incentivized.
+		type DexManager: DEXManager<Self::AccountId, Self::AssetId, Self::Balance>;
+
+		/// The origin which may set the reward emission of a pool.
+		type UpdateOrigin: EnsureOrigin<Self::Origin>;
+	}
+
+	#[pallet::pallet]
+	#[pallet::generate_store(pub(super) trait Store)]
+	#[pallet::without_storage_info]
+	pub struct Pallet<T>(_);
+
+	/// Rewards emitted to each pool per block.
+	///
+	/// RewardRates: map lp_currency_id => Balance
+	#[pallet::storage]
+	#[pallet::getter(fn reward_rates)]
+	pub type RewardRates<T: Config> =
+		StorageMap<_, Twox64Concat, T::AssetId, T::Balance, ValueQuery>;
+
+	/// Reward accounting of each pool.
+	///
+	/// Pools: map lp_currency_id => PoolInfo
+	#[pallet::storage]
+	#[pallet::getter(fn pools)]
+	pub type Pools<T: Config> =
+		StorageMap<_, Twox64Concat, T::AssetId, PoolInfo<T::Balance, T::BlockNumber>, ValueQuery>;
+
+	/// Stakes of each account in each pool.
+	///
+	/// Stakes: double_map lp_currency_id, AccountId => StakeInfo
+	#[pallet::storage]
+	#[pallet::getter(fn stakes)]
+	pub type Stakes<T: Config> = StorageDoubleMap<
+		_,
+		Twox64Concat,
+		T::AssetId,
+		Twox64Concat,
+		T::AccountId,
+		StakeInfo<T::Balance>,
+		ValueQuery,
+	>;
+
+	#[pallet::event]
+	#[pallet::generate_deposit(pub(super) fn deposit_event)]
+	pub enum Event<T: Config> {
+		/// The reward emission of a pool was changed. [lp_currency_id, reward_per_block]
+		RewardRateUpdated(T::AssetId, T::Balance),
+		/// DEX share was staked. [who, lp_currency_id, amount]
+		DepositDexShare(T::AccountId, T::AssetId, T::Balance),
+		/// DEX share was unstaked. [who, lp_currency_id, amount]
+		WithdrawDexShare(T::AccountId, T::AssetId, T::Balance),
+		/// Rewards of a pool were paid out. [who, lp_currency_id, amount]
+		RewardsClaimed(T::AccountId, T::AssetId, T::Balance),
+	}
+
+	#[pallet::error]
+	pub enum Error<T> {
+		/// The pool has no reward emission.
+		NotIncentivized,
+		/// More DEX share is unstaked than was staked.
+		NotEnoughShares,
+		/// The currency is not the DEX share of a trading pair.
+		InvalidDexShare,
+	}
+
+	#[pallet::call]
+	impl<T: Config> Pallet<T> {
+		/// Stake `amount` of the DEX share `lp_currency_id`.
+		#[pallet::weight(10_000)]
+		#[transactional]
+		pub fn deposit_dex_share(
+			origin: OriginFor<T>,
+			lp_currency_id: T::AssetId,
+			#[pallet::compact] amount: T::Balance,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			<Self as DEXIncentives<_, _, _>>::do_deposit_dex_share(&who, lp_currency_id, amount)
+		}
+
+		/// Unstake `amount` of the DEX share `lp_currency_id`. Earned rewards stay claimable.
+		#[pallet::weight(10_000)]
+		#[transactional]
+		pub fn withdraw_dex_share(
+			origin: OriginFor<T>,
+			lp_currency_id: T::AssetId,
+			#[pallet::compact] amount: T::Balance,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			<Self as DEXIncentives<_, _, _>>::do_withdraw_dex_share(&who, lp_currency_id, amount)
+		}
+
+		/// Pay out the rewards earned by staking in `lp_currency_id`.
+		#[pallet::weight(10_000)]
+		#[transactional]
+		pub fn claim_rewards(origin: OriginFor<T>, lp_currency_id: T::AssetId) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			let (pool, mut stake) = Self::accrue_rewards(&who, lp_currency_id);
+			let reward = stake.pending_rewards;
+			if !reward.is_zero() {
+				T::Currencies::transfer(
+					T::RewardAssetId::get(),
+					&Self::account_id(),
+					&who,
+					reward,
+					false,
+				)?;
+				stake.pending_rewards = Zero::zero();
+				Self::deposit_event(Event::RewardsClaimed(who.clone(), lp_currency_id, reward));
+			}
+
+			Pools::<T>::insert(lp_currency_id, pool);
+			Self::put_stake(lp_currency_id, &who, stake);
+			Ok(())
+		}
+
+		/// Set the rewards emitted to the stakers of `lp_currency_id` every block.
+		///
+		/// Rewards up to the current block are emitted at the previous rate.
+		#[pallet::weight(10_000)]
+		pub fn set_reward_rate(
+			origin: OriginFor<T>,
+			lp_currency_id: T::AssetId,
+			#[pallet::compact] reward_per_block: T::Balance,
+		) -> DispatchResult {
+			T::UpdateOrigin::ensure_origin(origin)?;
+			Self::ensure_dex_share(lp_currency_id)?;
+			let pool = Self::accrue_pool(lp_currency_id);
+			Pools::<T>::insert(lp_currency_id, pool);
+
+			if reward_per_block.is_zero() {
+				RewardRates::<T>::remove(lp_currency_id);
+			} else {
+				RewardRates::<T>::insert(lp_currency_id, reward_per_block);
+			}
+			Self::deposit_event(Event::RewardRateUpdated(lp_currency_id, reward_per_block));
+			Ok(())
+		}
+	}
+
+	impl<T: Config> Pallet<T> {
+		pub fn account_id() -> T::AccountId {
+			T::PalletId::get().into_account()
+		}
+
+		fn ensure_dex_share(lp_currency_id: T::AssetId) -> DispatchResult {
+			ensure!(
+				T::DexManager::get_dex_share_trading_pair(lp_currency_id).is_some(),
+				Error::<T>::InvalidDexShare
+			);
+			Ok(())
+		}
+
+		/// Rewards `who` could claim from `lp_currency_id` at the current block.
+		pub fn pending_rewards(who: &T::AccountId, lp_currency_id: T::AssetId) -> T::Balance {
+			Self::accrue_rewards(who, lp_currency_id).1.pending_rewards
+		}
+
+		/// The pool brought up to the current block. Nothing is written.
+		fn accrue_pool(lp_currency_id: T::AssetId) -> PoolInfo<T::Balance, T::BlockNumber> {
+			let now = frame_system::Pallet::<T>::block_number();
+			let mut pool = Self::pools(lp_currency_id);
+			if pool.last_reward_block >= now {
+				return pool
+			}
+
+			let reward_per_block = Self::reward_rates(lp_currency_id);
+			if !pool.total_shares.is_zero() && !reward_per_block.is_zero() {
+				let elapsed: u128 =
+					now.saturating_sub(pool.last_reward_block).unique_saturated_into();
+				let emitted = reward_per_block
+					.saturating_mul(T::Balance::unique_saturated_from(elapsed))
+					.saturating_add(pool.reward_remainder);
+				if let Some(increment) =
+					FixedU128::checked_from_rational(emitted, pool.total_shares)
+				{
+					pool.reward_per_share = pool.reward_per_share.saturating_add(increment);
+
+					// round the distributed rewards up, stakes are paid from the sum of the
+					// increments and must not get more than was emitted
+					let total_shares: u128 = pool.total_shares.unique_saturated_into();
+					let mut distributed = increment.saturating_mul_int(pool.total_shares);
+					if (increment.into_inner() % FixedU128::DIV)
+						.saturating_mul(total_shares % FixedU128::DIV) %
+						FixedU128::DIV != 0
+					{
+						distributed = distributed.saturating_add(One::one());
+					}
+					pool.reward_remainder = emitted.saturating_sub(distributed);
+				}
+			}
+			pool.last_reward_block = now;
+			pool
+		}
+
+		/// The pool and the stake of `who` in it brought up to the current block. Nothing is
+		/// written.
+		fn accrue_rewards(
+			who: &T::AccountId,
+			lp_currency_id: T::AssetId,
+		) -> (PoolInfo<T::Balance, T::BlockNumber>, StakeInfo<T::Balance>) {
+			let pool = Self::accrue_pool(lp_currency_id);
+			let mut stake = Self::stakes(lp_currency_id, who);
+			let earned = pool
+				.reward_per_share
+				.saturating_sub(stake.reward_per_share_paid)
+				.saturating_mul_int(stake.shares);
+			stake.pending_rewards = stake.pending_rewards.saturating_add(earned);
+			stake.reward_per_share_paid = pool.reward_per_share;
+			(pool, stake)
+		}
+
+		fn put_stake(lp_currency_id: T::AssetId, who: &T::AccountId, stake: StakeInfo<T::Balance>) {
+			if stake.shares.is_zero() && stake.pending_rewards.is_zero() {
+				Stakes::<T>::remove(lp_currency_id, who);
+			} else {
+				Stakes::<T>::insert(lp_currency_id, who, stake);
+			}
+		}
+	}
+
+	impl<T: Config> DEXIncentives<T::AccountId, T::AssetId, T::Balance> for Pallet<T> {
+		fn do_deposit_dex_share(
+			who: &T::AccountId,
+			lp_currency_id: T::AssetId,
+			amount: T::Balance,
+		) -> DispatchResult {
+			if amount.is_zero() {
+				return Ok(())
+			}
+			Self::ensure_dex_share(lp_currency_id)?;
+			ensure!(!Self::reward_rates(lp_currency_id).is_zero(), Error::<T>::NotIncentivized);
+
+			let (mut pool, mut stake) = Self::accrue_rewards(who, lp_currency_id);
+			T::Currencies::transfer(lp_currency_id, who, &Self::account_id(), amount, false)?;
+			pool.total_shares =
+				pool.total_shares.checked_add(&amount).ok_or(ArithmeticError::Overflow)?;
+			stake.shares = stake.shares.checked_add(&amount).ok_or(ArithmeticError::Overflow)?;
+
+			Pools::<T>::insert(lp_currency_id, pool);
+			Self::put_stake(lp_currency_id, who, stake);
+			Self::deposit_event(Event::DepositDexShare(who.clone(), lp_currency_id, amount));
+			Ok(())
+		}
+
+		fn do_withdraw_dex_share(
+			who: &T::AccountId,
+			lp_currency_id: T::AssetId,
+			amount: T::Balance,
+		) -> DispatchResult {
+			if amount.is_zero() {
+				return Ok(())
+			}
+
+			let (mut pool, mut stake) = Self::accrue_rewards(who, lp_currency_id);
+			stake.shares = stake.shares.checked_sub(&amount).ok_or(Error::<T>::NotEnoughShares)?;
+			pool.total_shares =
+				pool.total_shares.checked_sub(&amount).ok_or(ArithmeticError::Underflow)?;
+			T::Currencies::transfer(lp_currency_id, &Self::account_id(), who, amount, false)?;
+
+			Pools::<T>::insert(lp_currency_id, pool);
+			Self::put_stake(lp_currency_id, who, stake);
+			Self::deposit_event(Event::WithdrawDexShare(who.clone(), lp_currency_id, amount));
+			Ok(())
+		}
+	}
+}
@@ -0,0 +1,206 @@
+//! Mocks for the incentives pallet.
+
+use crate as pallet_incentives;
+use frame_support::{
+	construct_runtime, parameter_types,
+	traits::{ConstU128, ConstU16, ConstU32, ConstU64, GenesisBuild},
+	PalletId,
+};
+use frame_system::EnsureRoot;
+use pallet_dex::TradingPair;
+use sp_core::H256;
+use sp_runtime::{
+	testing::Header,
+	traits::{AccountIdConversion, BlakeTwo256, IdentityLookup},
+};
+
+pub type AccountId = u128;
+pub type AssetId = u32;
+pub type Balance = u128;
+pub type BlockNumber = u64;
+
+pub const ALICE: AccountId = 1;
+pub const BOB: AccountId = 2;
+
+pub const NATIVE: AssetId = 0;
+pub const DOT: AssetId = 1;
+pub const BTC: AssetId = 2;
+pub const REWARD: AssetId = 3;
+/// The dex share of the DOT/BTC pair, the first one allocated from `DexShareCurrencyIdStart`.
+pub const DOT_BTC_LP: AssetId = 1_000;
+
+type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
+type Block = frame_system::mocking::MockBlock<Test>;
+
+construct_runtime!(
+	pub enum Test where
+		Block = Block,
+		NodeBlock = Block,
+		UncheckedExtrinsic = UncheckedExtrinsic,
+	{
+		System: frame_system::{Pallet, Call, Config, Storage, Event<T>},
+		Balances: pallet_balances::{Pallet, Call, Storage, Config<T>, Event<T>},
+		Assets: pallet_assets::{Pallet, Call, Storage, Config<T>, Event<T>},
+		Dex: pallet_dex::{Pallet, Call, Storage, Config<T>, Event<T>},
+		Incentives: pallet_incentives::{Pallet, Call, Storage, Event<T>},
+	}
+);
+
+impl frame_system::Config for Test {
+	type BaseCallFilter = frame_support::traits::Everything;
+	type BlockWeights = ();
+	type BlockLength = ();
+	type DbWeight = ();
+	type Origin = Origin;
+	type Call = Call;
+	type Index = u64;
+	type BlockNumber = BlockNumber;
+	type Hash = H256;
+	type Hashing = BlakeTwo256;
+	type AccountId = AccountId;
+	type Lookup = IdentityLookup<Self::AccountId>;
+	type Header = Header;
+	type Event = Event;
+	type BlockHashCount = ConstU64<250>;
+	type Version = ();
+	type PalletInfo = PalletInfo;
+	type AccountData = pallet_balances::AccountData<Balance>;
+	type OnNewAccount = ();
+	type OnKilledAccount = ();
+	type SystemWeightInfo = ();
+	type SS58Prefix = ConstU16<42>;
+	type OnSetCode = ();
+	type MaxConsumers = ConstU32<16>;
+}
+
+impl pallet_balances::Config for Test {
+	type Balance = Balance;
+	type DustRemoval = ();
+	type Event = Event;
+	type ExistentialDeposit = ConstU128<1>;
+	type AccountStore = System;
+	type WeightInfo = ();
+	type MaxLocks = ();
+	type MaxReserves = ();
+	type ReserveIdentifier = [u8; 8];
+}
+
+parameter_types! {
+	pub const AirdropReserveId: [u8; 8] = *b"airdrop ";
+}
+
+impl pallet_assets::Config for Test {
+	type Event = Event;
+	type Balance = Balance;
+	type AssetId = AssetId;
+	type NativeAssetId = ConstU32<NATIVE>;
+	type Currency = Balances;
+	type ForceOrigin = EnsureRoot<AccountId>;
+	type AssetDeposit = ConstU128<1>;
+	type AssetAccountDeposit = ConstU128<10>;
+	type MetadataDepositBase = ConstU128<1>;
+	type MetadataDepositPerByte = ConstU128<1>;
+	type ApprovalDeposit = ConstU128<1>;
+	type StringLimit = ConstU32<50>;
+	type Freezer = ();
+	type FreezerWeight = ();
+	type WeightInfo = ();
+	type Extra = ();
+	type MaxLocks = ConstU32<2>;
+	type MaxReserves = ConstU32<2>;
+	type ReserveIdentifier = [u8; 8];
+	type MaxBatchSize = ConstU32<3>;
+	type AirdropReserveId = AirdropReserveId;
+	type AirdropDeposit = ConstU128<1>;
+}
+
+pub type Currencies = pallet_assets::NativeAssetAdapter<Test, Balances>;
+
+parameter_types! {
+	pub const GetExchangeFee: (u32, u32) = (1, 1000);
+	pub const DexPalletId: PalletId = PalletId(*b"rai/dexm");
+	pub TreasuryAccount: AccountId = PalletId(*b"rai/trsy").into_account();
+}
+
+impl pallet_dex::Config for Test {
+	type Event = Event;
+	type Currencies = Currencies;
+	type GetExchangeFee = GetExchangeFee;
+	type TreasuryAccount = TreasuryAccount;
+	type TradingPathLimit = ConstU32<4>;
+	type PriceObservationInterval = ConstU64<10>;
+	type MaxPriceObservations = ConstU32<10>;
+	type MaxSwapPathSearch = ConstU32<64>;
+	type MaxAmplification = ConstU32<1_000_000>;
+	type PalletId = DexPalletId;
+	type AssetIdShift = ConstU32<16>;
+	type DexShareCurrencyIdStart = ConstU32<1_000>;
+	type MaxDexShareCurrencyIdSearch = ConstU32<3>;
+	type DEXIncentives = Incentives;
+	type ListingOrigin = EnsureRoot<AccountId>;
+}
+
+parameter_types! {
+	pub const IncentivesPalletId: PalletId = PalletId(*b"rai/inct");
+}
+
+impl pallet_incentives::Config for Test {
+	type Event = Event;
+	type PalletId = IncentivesPalletId;
+	type Currencies = Currencies;
+	type RewardAssetId = ConstU32<REWARD>;
+	type DexManager = Dex;
+	type UpdateOrigin = EnsureRoot<AccountId>;
+}
+
+pub struct ExtBuilder {
+	balances: Vec<(AccountId, Balance)>,
+	asset_balances: Vec<(AssetId, AccountId, Balance)>,
+}
+
+impl Default for ExtBuilder {
+	fn default() -> Self {
+		Self {
+			balances: vec![(ALICE, 1_000_000), (BOB, 1_000_000)],
+			asset_balances: vec![
+				(DOT, ALICE, 10_000_000_000_000_000_000_000),
+				(BTC, ALICE, 10_000_000_000_000_000_000_000),
+				(REWARD, IncentivesPalletId::get().into_account(), 1_000_000),
+			],
+		}
+	}
+}
+
+impl ExtBuilder {
+	/// Genesis where ALICE holds all 200_000 shares of the DOT/BTC pool.
+	pub fn build(self) -> sp_io::TestExternalities {
+		let mut t = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
+
+		pallet_balances::GenesisConfig::<Test> { balances: self.balances }
+			.assimilate_storage(&mut t)
+			.unwrap();
+
+		pallet_assets::GenesisConfig::<Test> {
+			assets: vec![(DOT, ALICE, true, 1), (BTC, ALICE, true, 1), (REWARD, ALICE, true, 1)],
+			metadata: vec![],
+			accounts: self.asset_balances,
+		}
+		.assimilate_storage(&mut t)
+		.unwrap();
+
+		pallet_dex::GenesisConfig::<Test> {
+			initial_listing_trading_pairs: vec![],
+			initial_enabled_trading_pairs: vec![TradingPair::new(DOT, BTC)],
+			initial_added_liquidity_pools: vec![(
+				ALICE,
+				vec![(TradingPair::new(DOT, BTC), (100_000, 100_000))],
+			)],
+		}
+		.assimilate_storage(&mut t)
+		.unwrap();
+
+		let mut ext = sp_io::TestExternalities::new(t);
+		ext.execute_with(|| System::set_block_number(1));
+		ext
+	}
+}
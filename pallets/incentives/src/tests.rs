@@ -0,0 +1,162 @@
+//! Unit tests for the incentives pallet.
+
+use crate::{mock::*, Error};
+use frame_support::{assert_noop, assert_ok, traits::tokens::fungibles::Inspect};
+use sp_runtime::traits::BadOrigin;
+
+fn share_balance(who: &AccountId) -> Balance {
+	Currencies::balance(DOT_BTC_LP, who)
+}
+
+#[test]
+fn set_reward_rate_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_noop!(
+			Incentives::set_reward_rate(Origin::signed(ALICE), DOT_BTC_LP, 100),
+			BadOrigin
+		);
+		assert_noop!(
+			Incentives::set_reward_rate(Origin::root(), BTC, 100),
+			Error::<Test>::InvalidDexShare
+		);
+
+		assert_ok!(Incentives::set_reward_rate(Origin::root(), DOT_BTC_LP, 100));
+		assert_eq!(Incentives::reward_rates(DOT_BTC_LP), 100);
+		System::assert_last_event(Event::Incentives(crate::Event::RewardRateUpdated(
+			DOT_BTC_LP, 100,
+		)));
+
+		assert_ok!(Incentives::set_reward_rate(Origin::root(), DOT_BTC_LP, 0));
+		assert!(!crate::RewardRates::<Test>::contains_key(DOT_BTC_LP));
+	});
+}
+
+#[test]
+fn deposit_dex_share_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_noop!(
+			Incentives::deposit_dex_share(Origin::signed(ALICE), BTC, 1_000),
+			Error::<Test>::InvalidDexShare
+		);
+		assert_noop!(
+			Incentives::deposit_dex_share(Origin::signed(ALICE), DOT_BTC_LP, 1_000),
+			Error::<Test>::NotIncentivized
+		);
+
+		assert_ok!(Incentives::set_reward_rate(Origin::root(), DOT_BTC_LP, 100));
+		assert_ok!(Incentives::deposit_dex_share(Origin::signed(ALICE), DOT_BTC_LP, 1_000));
+		System::assert_last_event(Event::Incentives(crate::Event::DepositDexShare(
+			ALICE, DOT_BTC_LP, 1_000,
+		)));
+		assert_eq!(share_balance(&ALICE), 199_000);
+		assert_eq!(share_balance(&Incentives::account_id()), 1_000);
+		assert_eq!(Incentives::pools(DOT_BTC_LP).total_shares, 1_000);
+		assert_eq!(Incentives::stakes(DOT_BTC_LP, ALICE).shares, 1_000);
+	});
+}
+
+#[test]
+fn rewards_are_split_by_staked_shares() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(Assets::transfer(Origin::signed(ALICE), DOT_BTC_LP, BOB, 3_000));
+		assert_ok!(Incentives::set_reward_rate(Origin::root(), DOT_BTC_LP, 100));
+		assert_ok!(Incentives::deposit_dex_share(Origin::signed(ALICE), DOT_BTC_LP, 1_000));
+
+		System::set_block_number(3);
+		assert_eq!(Incentives::pending_rewards(&ALICE, DOT_BTC_LP), 200);
+		assert_ok!(Incentives::deposit_dex_share(Origin::signed(BOB), DOT_BTC_LP, 3_000));
+
+		System::set_block_number(7);
+		assert_eq!(Incentives::pending_rewards(&ALICE, DOT_BTC_LP), 300);
+		assert_eq!(Incentives::pending_rewards(&BOB, DOT_BTC_LP), 300);
+	});
+}
+
+#[test]
+fn reward_remainder_is_carried_over() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(Dex::add_liquidity(
+			Origin::signed(ALICE),
+			DOT,
+			BTC,
+			3_000_000_000_000_000_000_000,
+			3_000_000_000_000_000_000_000,
+			0,
+		));
+		assert_ok!(Incentives::set_reward_rate(Origin::root(), DOT_BTC_LP, 10_000));
+		assert_ok!(Incentives::deposit_dex_share(
+			Origin::signed(ALICE),
+			DOT_BTC_LP,
+			3_000_000_000_000_000_000_000,
+		));
+
+		// 10_000 / 3e21 per share is cut to 3e-18, leaving 1_000 undistributed
+		System::set_block_number(2);
+		assert_ok!(Incentives::claim_rewards(Origin::signed(ALICE), DOT_BTC_LP));
+		assert_eq!(Currencies::balance(REWARD, &ALICE), 9_000);
+		assert_eq!(Incentives::pools(DOT_BTC_LP).reward_remainder, 1_000);
+
+		// 21_000 emitted over the next two blocks, the remainder included
+		System::set_block_number(4);
+		assert_eq!(Incentives::pending_rewards(&ALICE, DOT_BTC_LP), 21_000);
+		assert_ok!(Incentives::claim_rewards(Origin::signed(ALICE), DOT_BTC_LP));
+		assert_eq!(Currencies::balance(REWARD, &ALICE), 30_000);
+		assert_eq!(Incentives::pools(DOT_BTC_LP).reward_remainder, 0);
+	});
+}
+
+#[test]
+fn withdraw_dex_share_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(Incentives::set_reward_rate(Origin::root(), DOT_BTC_LP, 100));
+		assert_ok!(Incentives::deposit_dex_share(Origin::signed(ALICE), DOT_BTC_LP, 1_000));
+
+		System::set_block_number(3);
+		assert_noop!(
+			Incentives::withdraw_dex_share(Origin::signed(ALICE), DOT_BTC_LP, 1_001),
+			Error::<Test>::NotEnoughShares
+		);
+		assert_ok!(Incentives::withdraw_dex_share(Origin::signed(ALICE), DOT_BTC_LP, 1_000));
+		System::assert_last_event(Event::Incentives(crate::Event::WithdrawDexShare(
+			ALICE, DOT_BTC_LP, 1_000,
+		)));
+		assert_eq!(share_balance(&ALICE), 200_000);
+		assert_eq!(Incentives::pools(DOT_BTC_LP).total_shares, 0);
+
+		// rewards earned before the withdrawal stay claimable
+		System::set_block_number(5);
+		assert_eq!(Incentives::pending_rewards(&ALICE, DOT_BTC_LP), 200);
+	});
+}
+
+#[test]
+fn claim_rewards_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(Incentives::set_reward_rate(Origin::root(), DOT_BTC_LP, 100));
+		assert_ok!(Incentives::deposit_dex_share(Origin::signed(ALICE), DOT_BTC_LP, 1_000));
+
+		System::set_block_number(4);
+		assert_ok!(Incentives::claim_rewards(Origin::signed(ALICE), DOT_BTC_LP));
+		System::assert_last_event(Event::Incentives(crate::Event::RewardsClaimed(
+			ALICE, DOT_BTC_LP, 300,
+		)));
+		assert_eq!(Currencies::balance(REWARD, &ALICE), 300);
+		assert_eq!(Currencies::balance(REWARD, &Incentives::account_id()), 999_700);
+		assert_eq!(Incentives::pending_rewards(&ALICE, DOT_BTC_LP), 0);
+	});
+}
+
+#[test]
+fn remove_liquidity_by_unstake_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(Incentives::set_reward_rate(Origin::root(), DOT_BTC_LP, 100));
+		assert_ok!(Incentives::deposit_dex_share(Origin::signed(ALICE), DOT_BTC_LP, 1_000));
+
+		System::set_block_number(3);
+		assert_ok!(Dex::remove_liquidity(Origin::signed(ALICE), DOT, BTC, 1_000, 0, 0, true));
+		assert_eq!(share_balance(&ALICE), 199_000);
+		assert_eq!(share_balance(&Incentives::account_id()), 0);
+		assert_eq!(Incentives::stakes(DOT_BTC_LP, ALICE).shares, 0);
+		assert_eq!(Incentives::pending_rewards(&ALICE, DOT_BTC_LP), 200);
+	});
+}
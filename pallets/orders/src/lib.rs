@@ -0,0 +1,418 @@
+//! # Orders Module
+//!
+//! ## Overview
+//!
+//! Limit orders against the DEX pools. An order swaps its supply currency along a path for at
+//! least a minimum target amount. The supply is escrowed in the pallet account when the order is
+//! placed, and orders are filled in `on_idle` once the pools give the limit price.
+//!
+//! A TWAP order is a limit order that is filled in chunks of `chunk_amount`, at most one chunk
+//! every `chunk_interval` blocks. Each chunk has to meet the limit price in proportion to its
+//! size. Orders can be cancelled by their owner, and the unfilled supply of expired orders is
+//! returned to the owner.
+//!
+//! `OrderDeposit` is reserved from the owner of every open order, so that the orders scanned in
+//! `on_idle` are paid for. It is returned when the order completes, is cancelled or expires.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+pub use pallet::*;
+
+#[cfg(test)]
+mod mock;
+
+#[cfg(test)]
+mod tests;
+
+use codec::{Decode, Encode};
+use scale_info::TypeInfo;
+use sp_runtime::RuntimeDebug;
+use sp_std::vec::Vec;
+
+pub type OrderId = u64;
+
+/// An order to swap along `path`.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub struct Order<AccountId, AssetId, Balance, BlockNumber, DepositBalance> {
+	/// The account the order was placed by.
+	pub owner: AccountId,
+	/// The native deposit reserved from the owner.
+	pub deposit: DepositBalance,
+	/// The swap path, from the supply currency to the target currency.
+	pub path: Vec<AssetId>,
+	/// The supply amount of the whole order.
+	pub supply_amount: Balance,
+	/// The minimum target amount of the whole order.
+	pub min_target_amount: Balance,
+	/// The supply amount still escrowed.
+	pub remaining_supply: Balance,
+	/// The target amount received so far.
+	pub filled_target: Balance,
+	/// The supply amount swapped at most per fill.
+	pub chunk_amount: Balance,
+	/// The number of blocks between two fills.
+	pub chunk_interval: BlockNumber,
+	/// The block from which the order can be filled next.
+	pub next_fill: BlockNumber,
+	/// The last block the order can be filled at.
+	pub expiry: BlockNumber,
+}
+
+#[frame_support::pallet]
+pub mod pallet {
+	use super::{Order, OrderId};
+	use frame_support::{
+		log,
+		pallet_prelude::*,
+		traits::{tokens::fungibles::Transfer, Currency, ReservableCurrency},
+		transactional, PalletId,
+	};
+	use frame_system::pallet_prelude::*;
+	use pallet_dex::{DEXManager, Ratio, SwapLimit};
+	use sp_runtime::{
+		traits::{AccountIdConversion, Saturating, Zero},
+		ArithmeticError, FixedPointNumber,
+	};
+	use sp_std::vec::Vec;
+
+	pub(crate) type OrderOf<T> = Order<
+		<T as frame_system::Config>::AccountId,
+		<T as pallet_assets::Config>::AssetId,
+		<T as pallet_assets::Config>::Balance,
+		<T as frame_system::Config>::BlockNumber,
+		BalanceOf<T>,
+	>;
+
+	pub(crate) type BalanceOf<T> = <<T as Config>::NativeCurrency as Currency<
+		<T as frame_system::Config>::AccountId,
+	>>::Balance;
+
+	#[pallet::config]
+	pub trait Config: frame_system::Config + pallet_assets::Config {
+		/// Because this pallet emits events, it depends on the runtime's definition of an event.
+		type Event: From<Event<Self>> + IsType<<Self as frame_system::Config>::Event>;
+
+		/// The pallet id, its account keeps the escrowed supply of the orders.
+		#[pallet::constant]
+		type PalletId: Get<PalletId>;
+
+		/// The currencies orders are placed in, with the native currency as
+		/// `pallet_assets::Config::NativeAssetId`.
+		type Currencies: Transfer<Self::AccountId, AssetId = Self::AssetId, Balance = Self::Balance>;
+
+		type NativeCurrency: ReservableCurrency<Self::AccountId>;
+
+		type DexManager: DEXManager<Self::AccountId, Self::AssetId, Self::Balance>;
+
+		/// The maximum number of currencies in the path of an order, the DEX's
+		/// `TradingPathLimit`.
+		#[pallet::constant]
+		type TradingPathLimit: Get<u32>;
+
+		/// The native deposit reserved from the owner of an open order.
+		#[pallet::constant]
+		type OrderDeposit: Get<BalanceOf<Self>>;
+
+		/// The maximum number of orders filled in one block.
+		#[pallet::constant]
+		type MaxFillsPerBlock: Get<u32>;
+
+		/// The weight reserved for filling one order.
+		#[pallet::constant]
+		type FillWeight: Get<Weight>;
+	}
+
+	#[pallet::pallet]
+	#[pallet::generate_store(pub(super) trait Store)]
+	#[pallet::without_storage_info]
+	pub struct Pallet<T>(_);
+
+	#[pallet::hooks]
+	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+		fn on_idle(now: T::BlockNumber, remaining_weight: Weight) -> Weight {
+			let mut consumed_weight: Weight = T::DbWeight::get().reads(1);
+			let mut fills: u32 = 0;
+			let mut last_key = None;
+
+			// resume after the order the previous scan stopped at, so every order gets its turn
+			let iter = match FillCursor::<T>::get() {
+				Some(key) => Orders::<T>::iter_from(key),
+				None => Orders::<T>::iter(),
+			};
+			for (order_id, mut order) in iter {
+				consumed_weight = consumed_weight.saturating_add(T::DbWeight::get().reads(1));
+				if fills >= T::MaxFillsPerBlock::get() ||
+					consumed_weight.saturating_add(T::FillWeight::get()) > remaining_weight
+				{
+					if let Some(key) = last_key {
+						FillCursor::<T>::put(key);
+					}
+					return consumed_weight.saturating_add(T::DbWeight::get().writes(1))
+				}
+				last_key = Some(Orders::<T>::hashed_key_for(order_id));
+
+				if order.expiry < now {
+					consumed_weight = consumed_weight.saturating_add(T::FillWeight::get());
+					if let Err(e) = Self::close_order(order_id, &order) {
+						log::warn!(
+							target: "runtime::orders",
+							"refund of expired order {:?} failed: {:?}",
+							order_id,
+							e,
+						);
+					} else {
+						Self::deposit_event(Event::OrderExpired(order_id, order.remaining_supply));
+					}
+					continue
+				}
+				if order.next_fill > now {
+					continue
+				}
+
+				consumed_weight = consumed_weight.saturating_add(T::FillWeight::get());
+				fills += 1;
+				match Self::fill_order(order_id, &mut order, now) {
+					Ok(true) if order.remaining_supply.is_zero() => {
+						T::NativeCurrency::unreserve(&order.owner, order.deposit);
+						Orders::<T>::remove(order_id);
+						Self::deposit_event(Event::OrderCompleted(order_id));
+					},
+					Ok(true) => Orders::<T>::insert(order_id, order),
+					Ok(false) => {},
+					Err(e) => log::warn!(
+						target: "runtime::orders",
+						"fill of order {:?} failed: {:?}",
+						order_id,
+						e,
+					),
+				}
+			}
+
+			FillCursor::<T>::kill();
+			consumed_weight.saturating_add(T::DbWeight::get().writes(1))
+		}
+	}
+
+	/// The id the next order is placed with.
+	///
+	/// NextOrderId: OrderId
+	#[pallet::storage]
+	#[pallet::getter(fn next_order_id)]
+	pub type NextOrderId<T: Config> = StorageValue<_, OrderId, ValueQuery>;
+
+	/// Open orders.
+	///
+	/// Orders: map order_id => Order
+	#[pallet::storage]
+	#[pallet::getter(fn orders)]
+	pub type Orders<T: Config> = StorageMap<_, Twox64Concat, OrderId, OrderOf<T>>;
+
+	/// The raw key of the order the last `on_idle` scan stopped at.
+	///
+	/// FillCursor: Vec<u8>
+	#[pallet::storage]
+	pub type FillCursor<T: Config> = StorageValue<_, Vec<u8>>;
+
+	#[pallet::event]
+	#[pallet::generate_deposit(pub(super) fn deposit_event)]
+	pub enum Event<T: Config> {
+		/// An order was placed. [order_id, owner]
+		OrderPlaced(OrderId, T::AccountId),
+		/// A chunk of an order was swapped. [order_id, supply_amount, target_amount]
+		OrderFilled(OrderId, T::Balance, T::Balance),
+		/// The whole supply of an order was swapped. [order_id]
+		OrderCompleted(OrderId),
+		/// An order was cancelled and its unfilled supply returned. [order_id, refund]
+		OrderCancelled(OrderId, T::Balance),
+		/// An order expired and its unfilled supply was returned. [order_id, refund]
+		OrderExpired(OrderId, T::Balance),
+	}
+
+	#[pallet::error]
+	pub enum Error<T> {
+		/// The path is too short or too long, ends where it starts or crosses a trading pair that
+		/// is not enabled.
+		InvalidPath,
+		/// The supply amount or the chunk amount is zero.
+		ZeroAmount,
+		/// The expiry block has passed.
+		Expired,
+		/// The order does not exist.
+		OrderNotFound,
+		/// The origin is not the owner of the order.
+		NotOwner,
+	}
+
+	#[pallet::call]
+	impl<T: Config> Pallet<T> {
+		/// Place an order to swap `supply_amount` along `path` for at least `min_target_amount`,
+		/// filled at once when the pools allow it until `expiry`.
+		#[pallet::weight(10_000)]
+		#[transactional]
+		pub fn place_limit_order(
+			origin: OriginFor<T>,
+			path: Vec<T::AssetId>,
+			#[pallet::compact] supply_amount: T::Balance,
+			#[pallet::compact] min_target_amount: T::Balance,
+			expiry: T::BlockNumber,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			Self::do_place_order(
+				who,
+				path,
+				supply_amount,
+				min_target_amount,
+				supply_amount,
+				Zero::zero(),
+				expiry,
+			)
+		}
+
+		/// Place an order to swap `supply_amount` along `path` for at least `min_target_amount`,
+		/// filled in chunks of `chunk_amount` at most every `chunk_interval` blocks until
+		/// `expiry`. Each chunk has to meet the limit price in proportion to its size.
+		#[pallet::weight(10_000)]
+		#[transactional]
+		pub fn place_twap_order(
+			origin: OriginFor<T>,
+			path: Vec<T::AssetId>,
+			#[pallet::compact] supply_amount: T::Balance,
+			#[pallet::compact] min_target_amount: T::Balance,
+			#[pallet::compact] chunk_amount: T::Balance,
+			chunk_interval: T::BlockNumber,
+			expiry: T::BlockNumber,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			Self::do_place_order(
+				who,
+				path,
+				supply_amount,
+				min_target_amount,
+				chunk_amount,
+				chunk_interval,
+				expiry,
+			)
+		}
+
+		/// Cancel an order and return its unfilled supply.
+		#[pallet::weight(10_000)]
+		#[transactional]
+		pub fn cancel_order(origin: OriginFor<T>, order_id: OrderId) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			let order = Self::orders(order_id).ok_or(Error::<T>::OrderNotFound)?;
+			ensure!(order.owner == who, Error::<T>::NotOwner);
+
+			Self::close_order(order_id, &order)?;
+			Self::deposit_event(Event::OrderCancelled(order_id, order.remaining_supply));
+			Ok(())
+		}
+	}
+
+	impl<T: Config> Pallet<T> {
+		pub fn account_id() -> T::AccountId {
+			T::PalletId::get().into_account()
+		}
+
+		fn do_place_order(
+			who: T::AccountId,
+			path: Vec<T::AssetId>,
+			supply_amount: T::Balance,
+			min_target_amount: T::Balance,
+			chunk_amount: T::Balance,
+			chunk_interval: T::BlockNumber,
+			expiry: T::BlockNumber,
+		) -> DispatchResult {
+			ensure!(
+				path.len() >= 2 &&
+					path.len() <= T::TradingPathLimit::get() as usize &&
+					path.first() != path.last() &&
+					path
+						.windows(2)
+						.all(|hop| T::DexManager::is_trading_pair_enabled(hop[0], hop[1])),
+				Error::<T>::InvalidPath
+			);
+			ensure!(!supply_amount.is_zero() && !chunk_amount.is_zero(), Error::<T>::ZeroAmount);
+			let now = frame_system::Pallet::<T>::block_number();
+			ensure!(expiry >= now, Error::<T>::Expired);
+
+			let deposit = T::OrderDeposit::get();
+			T::NativeCurrency::reserve(&who, deposit)?;
+			T::Currencies::transfer(path[0], &who, &Self::account_id(), supply_amount, false)?;
+
+			let order_id =
+				NextOrderId::<T>::try_mutate(|next_id| -> Result<OrderId, DispatchError> {
+					let order_id = *next_id;
+					*next_id = next_id.checked_add(1).ok_or(ArithmeticError::Overflow)?;
+					Ok(order_id)
+				})?;
+			Orders::<T>::insert(
+				order_id,
+				Order {
+					owner: who.clone(),
+					deposit,
+					path,
+					supply_amount,
+					min_target_amount,
+					remaining_supply: supply_amount,
+					filled_target: Zero::zero(),
+					chunk_amount: chunk_amount.min(supply_amount),
+					chunk_interval,
+					next_fill: now,
+					expiry,
+				},
+			);
+			Self::deposit_event(Event::OrderPlaced(order_id, who));
+			Ok(())
+		}
+
+		/// Swap the next chunk of `order` if the pools give at least its limit price. Returns
+		/// whether anything was swapped.
+		#[transactional]
+		fn fill_order(
+			order_id: OrderId,
+			order: &mut OrderOf<T>,
+			now: T::BlockNumber,
+		) -> Result<bool, DispatchError> {
+			let chunk = order.chunk_amount.min(order.remaining_supply);
+			let min_target_amount = Ratio::checked_from_rational(chunk, order.supply_amount)
+				.and_then(|ratio| ratio.checked_mul_int(order.min_target_amount))
+				.ok_or(ArithmeticError::Overflow)?;
+			let limit = SwapLimit::ExactSupply(chunk, min_target_amount);
+			if T::DexManager::get_swap_amount(&order.path, limit.clone()).is_none() {
+				return Ok(false)
+			}
+
+			let (supply_amount, target_amount) =
+				T::DexManager::swap_with_specific_path(&Self::account_id(), &order.path, limit)?;
+			T::Currencies::transfer(
+				order.path[order.path.len() - 1],
+				&Self::account_id(),
+				&order.owner,
+				target_amount,
+				false,
+			)?;
+
+			order.remaining_supply = order.remaining_supply.saturating_sub(supply_amount);
+			order.filled_target = order.filled_target.saturating_add(target_amount);
+			order.next_fill = now.saturating_add(order.chunk_interval);
+			Self::deposit_event(Event::OrderFilled(order_id, supply_amount, target_amount));
+			Ok(true)
+		}
+
+		/// Return the unfilled supply and the deposit of `order` to its owner and remove it.
+		fn close_order(order_id: OrderId, order: &OrderOf<T>) -> DispatchResult {
+			if !order.remaining_supply.is_zero() {
+				T::Currencies::transfer(
+					order.path[0],
+					&Self::account_id(),
+					&order.owner,
+					order.remaining_supply,
+					false,
+				)?;
+			}
+			T::NativeCurrency::unreserve(&order.owner, order.deposit);
+			Orders::<T>::remove(order_id);
+			Ok(())
+		}
+	}
+}
@@ -0,0 +1,176 @@
+//! Unit tests for the orders pallet.
+
+use crate::{mock::*, Error, Order};
+use frame_support::{
+	assert_noop, assert_ok,
+	traits::{tokens::fungibles::Inspect, Hooks, ReservableCurrency},
+};
+use pallet_dex::{DEXManager, SwapLimit};
+
+fn place_limit_order(supply_amount: Balance, min_target_amount: Balance) {
+	assert_ok!(Orders::place_limit_order(
+		Origin::signed(ALICE),
+		vec![DOT, BTC],
+		supply_amount,
+		min_target_amount,
+		10,
+	));
+}
+
+fn target_amount(supply_amount: Balance) -> Balance {
+	Dex::get_swap_amount(&[DOT, BTC], SwapLimit::ExactSupply(supply_amount, 0))
+		.unwrap()
+		.1
+}
+
+#[test]
+fn place_limit_order_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		place_limit_order(1_000, 900);
+		System::assert_last_event(Event::Orders(crate::Event::OrderPlaced(0, ALICE)));
+
+		assert_eq!(
+			Orders::orders(0),
+			Some(Order {
+				owner: ALICE,
+				deposit: DEPOSIT,
+				path: vec![DOT, BTC],
+				supply_amount: 1_000,
+				min_target_amount: 900,
+				remaining_supply: 1_000,
+				filled_target: 0,
+				chunk_amount: 1_000,
+				chunk_interval: 0,
+				next_fill: 1,
+				expiry: 10,
+			})
+		);
+		assert_eq!(Orders::next_order_id(), 1);
+		assert_eq!(Balances::reserved_balance(&ALICE), DEPOSIT);
+		assert_eq!(Currencies::balance(DOT, &ALICE), 999_000);
+		assert_eq!(Currencies::balance(DOT, &Orders::account_id()), 1_000);
+	});
+}
+
+#[test]
+fn place_order_fails() {
+	ExtBuilder::default().build().execute_with(|| {
+		for path in [vec![DOT], vec![DOT, BTC, DOT], vec![DOT, ETH], vec![DOT, BTC, ETH, USDT]] {
+			assert_noop!(
+				Orders::place_limit_order(Origin::signed(ALICE), path, 1_000, 900, 10),
+				Error::<Test>::InvalidPath
+			);
+		}
+		assert_noop!(
+			Orders::place_limit_order(Origin::signed(ALICE), vec![DOT, BTC], 0, 900, 10),
+			Error::<Test>::ZeroAmount
+		);
+		assert_noop!(
+			Orders::place_twap_order(Origin::signed(ALICE), vec![DOT, BTC], 1_000, 900, 0, 1, 10),
+			Error::<Test>::ZeroAmount
+		);
+		assert_noop!(
+			Orders::place_limit_order(Origin::signed(ALICE), vec![DOT, BTC], 1_000, 900, 0),
+			Error::<Test>::Expired
+		);
+
+		// BOB can't pay the deposit
+		assert_noop!(
+			Orders::place_limit_order(Origin::signed(BOB), vec![DOT, BTC], 1_000, 900, 10),
+			pallet_balances::Error::<Test>::InsufficientBalance
+		);
+
+		assert_ok!(Orders::place_limit_order(
+			Origin::signed(ALICE),
+			vec![DOT, BTC, ETH],
+			1_000,
+			900,
+			10
+		));
+	});
+}
+
+#[test]
+fn cancel_order_work() {
+	ExtBuilder::default().build().execute_with(|| {
+		place_limit_order(1_000, 900);
+
+		assert_noop!(Orders::cancel_order(Origin::signed(ALICE), 1), Error::<Test>::OrderNotFound);
+		assert_noop!(Orders::cancel_order(Origin::signed(BOB), 0), Error::<Test>::NotOwner);
+
+		assert_ok!(Orders::cancel_order(Origin::signed(ALICE), 0));
+		System::assert_last_event(Event::Orders(crate::Event::OrderCancelled(0, 1_000)));
+		assert_eq!(Orders::orders(0), None);
+		assert_eq!(Balances::reserved_balance(&ALICE), 0);
+		assert_eq!(Currencies::balance(DOT, &ALICE), 1_000_000);
+		assert_eq!(Currencies::balance(DOT, &Orders::account_id()), 0);
+	});
+}
+
+#[test]
+fn limit_order_is_filled_at_its_price() {
+	ExtBuilder::default().build().execute_with(|| {
+		let expected_target = target_amount(1_000);
+		place_limit_order(1_000, expected_target);
+		place_limit_order(1_000, expected_target + 1);
+
+		Orders::on_idle(1, 1_000_000);
+		System::assert_has_event(Event::Orders(crate::Event::OrderFilled(
+			0,
+			1_000,
+			expected_target,
+		)));
+		System::assert_has_event(Event::Orders(crate::Event::OrderCompleted(0)));
+		assert_eq!(Orders::orders(0), None);
+		assert_eq!(Currencies::balance(BTC, &ALICE), expected_target);
+
+		// the second order asks for more than the pool gives
+		assert_eq!(Orders::orders(1).map(|order| order.remaining_supply), Some(1_000));
+		assert_eq!(Balances::reserved_balance(&ALICE), DEPOSIT);
+	});
+}
+
+#[test]
+fn expired_order_is_refunded() {
+	ExtBuilder::default().build().execute_with(|| {
+		place_limit_order(1_000, 2_000);
+
+		Orders::on_idle(10, 1_000_000);
+		assert!(Orders::orders(0).is_some());
+
+		Orders::on_idle(11, 1_000_000);
+		System::assert_last_event(Event::Orders(crate::Event::OrderExpired(0, 1_000)));
+		assert_eq!(Orders::orders(0), None);
+		assert_eq!(Balances::reserved_balance(&ALICE), 0);
+		assert_eq!(Currencies::balance(DOT, &ALICE), 1_000_000);
+	});
+}
+
+#[test]
+fn twap_order_is_filled_in_chunks() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(Orders::place_twap_order(
+			Origin::signed(ALICE),
+			vec![DOT, BTC],
+			2_000,
+			1_000,
+			1_000,
+			5,
+			20,
+		));
+
+		Orders::on_idle(1, 1_000_000);
+		let order = Orders::orders(0).unwrap();
+		assert_eq!(order.remaining_supply, 1_000);
+		assert_eq!(order.next_fill, 6);
+
+		Orders::on_idle(5, 1_000_000);
+		assert_eq!(Orders::orders(0).map(|order| order.remaining_supply), Some(1_000));
+
+		Orders::on_idle(6, 1_000_000);
+		System::assert_has_event(Event::Orders(crate::Event::OrderCompleted(0)));
+		assert_eq!(Orders::orders(0), None);
+		assert_eq!(Balances::reserved_balance(&ALICE), 0);
+		assert_eq!(Currencies::balance(DOT, &ALICE), 998_000);
+	});
+}
@@ -0,0 +1,84 @@
+//! Runtime API definition for the portfolio pallet.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+// The `too_many_arguments` warning originates from `decl_runtime_apis` macro.
+#![allow(clippy::too_many_arguments)]
+// The `unnecessary_mut_passed` warning originates from `decl_runtime_apis` macro.
+#![allow(clippy::unnecessary_mut_passed)]
+
+use codec::{Codec, Decode, Encode};
+use scale_info::TypeInfo;
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
+use sp_runtime::{FixedU128, Perbill, RuntimeDebug};
+use sp_std::vec::Vec;
+
+/// State of a portfolio.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct PortfolioInfo<AssetId, AccountId, Balance> {
+	/// The asset id of the portfolio token.
+	pub port_id: AssetId,
+	/// The component assets of the portfolio.
+	pub components: Vec<AssetId>,
+	/// The target weight of every component, in component order.
+	pub rates: Vec<Perbill>,
+	/// The owner of the portfolio.
+	pub owner: Option<AccountId>,
+	/// The amount of every asset held by the portfolio.
+	pub holdings: Vec<(AssetId, Balance)>,
+}
+
+/// One leg of a portfolio trade.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct RouteQuote<AssetId, Balance> {
+	/// The component traded in this leg.
+	pub asset_id: AssetId,
+	/// The swap path taken through the DEX. A single asset means no swap is made.
+	pub route: Vec<AssetId>,
+	/// The amount put into the route.
+	pub supply_amount: Balance,
+	/// The amount coming out of the route.
+	pub target_amount: Balance,
+}
+
+/// Expected outcome of a portfolio buy or sell.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct PortfolioQuote<AssetId, Balance> {
+	/// Portfolio tokens minted by a buy, or the destination asset received from a sell.
+	pub amount: Balance,
+	/// The leg of every component.
+	pub routes: Vec<RouteQuote<AssetId, Balance>>,
+}
+
+sp_api::decl_runtime_apis! {
+	pub trait PortfolioApi<AssetId, AccountId, Balance> where
+		AssetId: Codec,
+		AccountId: Codec,
+		Balance: Codec,
+	{
+		/// Net asset value per portfolio token of every portfolio, denominated in the
+		/// native currency, the asset `NativeAssetId` of `pallet_assets`.
+		fn nav_per_share() -> Vec<(AssetId, FixedU128)>;
+
+		/// Components, rates, owner and holdings of every portfolio.
+		fn portfolios() -> Vec<PortfolioInfo<AssetId, AccountId, Balance>>;
+
+		/// Quote buying `port_id` with `amount` of the native currency. `None` if the portfolio
+		/// cannot be bought.
+		fn quote_buy(port_id: AssetId, amount: Balance) -> Option<PortfolioQuote<AssetId, Balance>>;
+
+		/// Quote selling `amount` portfolio tokens of `port_id` for `dst_id`. `None` if the
+		/// portfolio cannot be sold.
+		fn quote_sell(
+			port_id: AssetId,
+			dst_id: AssetId,
+			amount: Balance,
+		) -> Option<PortfolioQuote<AssetId, Balance>>;
+	}
+}
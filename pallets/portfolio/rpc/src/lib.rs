@@ -0,0 +1,133 @@
+//! RPC interface for the portfolio pallet.
+
+use std::{fmt::Debug, sync::Arc};
+
+use codec::Codec;
+use jsonrpc_core::{Error as RpcError, ErrorCode, Result};
+use jsonrpc_derive::rpc;
+use pallet_portfolio_rpc_runtime_api::{PortfolioInfo, PortfolioQuote};
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_runtime::{generic::BlockId, traits::Block as BlockT, FixedU128};
+
+pub use pallet_portfolio_rpc_runtime_api::PortfolioApi as PortfolioRuntimeApi;
+
+#[rpc]
+pub trait PortfolioApi<BlockHash, AssetId, AccountId, Balance> {
+	/// Net asset value per portfolio token of every portfolio.
+	#[rpc(name = "portfolio_navPerShare")]
+	fn nav_per_share(&self, at: Option<BlockHash>) -> Result<Vec<(AssetId, FixedU128)>>;
+
+	/// Components, rates, owner and holdings of every portfolio.
+	#[rpc(name = "portfolio_getPortfolios")]
+	fn portfolios(
+		&self,
+		at: Option<BlockHash>,
+	) -> Result<Vec<PortfolioInfo<AssetId, AccountId, Balance>>>;
+
+	/// Quote buying `port_id` with `amount` of the native currency.
+	#[rpc(name = "portfolio_quoteBuy")]
+	fn quote_buy(
+		&self,
+		port_id: AssetId,
+		amount: Balance,
+		at: Option<BlockHash>,
+	) -> Result<Option<PortfolioQuote<AssetId, Balance>>>;
+
+	/// Quote selling `amount` portfolio tokens of `port_id` for `dst_id`.
+	#[rpc(name = "portfolio_quoteSell")]
+	fn quote_sell(
+		&self,
+		port_id: AssetId,
+		dst_id: AssetId,
+		amount: Balance,
+		at: Option<BlockHash>,
+	) -> Result<Option<PortfolioQuote<AssetId, Balance>>>;
+}
+
+/// A struct that implements the [`PortfolioApi`].
+pub struct Portfolio<C, B> {
+	client: Arc<C>,
+	_marker: std::marker::PhantomData<B>,
+}
+
+impl<C, B> Portfolio<C, B> {
+	/// Create new `Portfolio` with the given reference to the client.
+	pub fn new(client: Arc<C>) -> Self {
+		Self { client, _marker: Default::default() }
+	}
+}
+
+/// Error type of this RPC api.
+pub enum Error {
+	/// The call to runtime failed.
+	RuntimeError,
+}
+
+impl From<Error> for i64 {
+	fn from(e: Error) -> i64 {
+		match e {
+			Error::RuntimeError => 1,
+		}
+	}
+}
+
+fn runtime_error(e: impl Debug) -> RpcError {
+	RpcError {
+		code: ErrorCode::ServerError(Error::RuntimeError.into()),
+		message: "Unable to query the portfolio pallet.".into(),
+		data: Some(format!("{:?}", e).into()),
+	}
+}
+
+impl<C, Block, AssetId, AccountId, Balance>
+	PortfolioApi<<Block as BlockT>::Hash, AssetId, AccountId, Balance> for Portfolio<C, Block>
+where
+	Block: BlockT,
+	C: 'static + ProvideRuntimeApi<Block> + HeaderBackend<Block>,
+	C::Api: PortfolioRuntimeApi<Block, AssetId, AccountId, Balance>,
+	AssetId: Codec + Send + Sync + 'static,
+	AccountId: Codec + Send + Sync + 'static,
+	Balance: Codec + Send + Sync + 'static,
+{
+	fn nav_per_share(
+		&self,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<Vec<(AssetId, FixedU128)>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+		api.nav_per_share(&at).map_err(runtime_error)
+	}
+
+	fn portfolios(
+		&self,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<Vec<PortfolioInfo<AssetId, AccountId, Balance>>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+		api.portfolios(&at).map_err(runtime_error)
+	}
+
+	fn quote_buy(
+		&self,
+		port_id: AssetId,
+		amount: Balance,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<Option<PortfolioQuote<AssetId, Balance>>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+		api.quote_buy(&at, port_id, amount).map_err(runtime_error)
+	}
+
+	fn quote_sell(
+		&self,
+		port_id: AssetId,
+		dst_id: AssetId,
+		amount: Balance,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<Option<PortfolioQuote<AssetId, Balance>>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+		api.quote_sell(&at, port_id, dst_id, amount).map_err(runtime_error)
+	}
+}
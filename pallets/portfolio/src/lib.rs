@@ -12,26 +12,87 @@ mod tests;
 #[cfg(feature = "runtime-benchmarks")]
 mod benchmarking;
 
+pub mod migrations;
+
+use codec::{Decode, Encode};
+use scale_info::TypeInfo;
+use sp_runtime::{FixedU128, Perbill, Permill, RuntimeDebug};
+
+/// Fees a portfolio pays to its owner.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub struct PortfolioFees<BlockNumber> {
+	/// Yearly share of the portfolio tokens minted to the owner, accrued every block.
+	pub management_fee: Permill,
+	/// Share of the NAV gain above `high_water_mark` minted to the owner.
+	pub performance_fee: Permill,
+	/// The block up to which the management fee has been accrued.
+	pub last_accrual: BlockNumber,
+	/// The highest NAV per portfolio token, valued at the TWAP of the components, a performance
+	/// fee has been charged at. Zero until the components have a price history.
+	pub high_water_mark: FixedU128,
+}
+
+/// Automatic rebalancing settings of a portfolio.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub struct RebalanceSchedule<BlockNumber> {
+	/// The number of blocks between two rebalance checks.
+	pub period: BlockNumber,
+	/// The largest distance between the achieved and the target weight of any component that
+	/// is tolerated without rebalancing.
+	pub drift_threshold: Perbill,
+	/// The block from which the next rebalance check is due.
+	pub next_rebalance: BlockNumber,
+}
+
+/// Lifecycle status of a portfolio.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub enum PortfolioStatus {
+	/// The portfolio can be traded.
+	Active,
+	/// Buys, sells and in-kind creations and redemptions are suspended.
+	Paused,
+	/// The portfolio is winding down. Holders can only redeem in kind.
+	Closed,
+}
+
+impl Default for PortfolioStatus {
+	fn default() -> Self {
+		PortfolioStatus::Active
+	}
+}
+
 #[frame_support::pallet]
 pub mod pallet {
 	use frame_support::{
+		log,
 		pallet_prelude::{ValueQuery, *},
 		traits::{
-			fungible::Transfer as NativeTransfer,
-			tokens::fungibles::{self, Create, Inspect, Mutate, Transfer},
-			Currency,
+			tokens::fungibles::{self, Create, Inspect, Mutate},
+			Currency, ReservableCurrency,
 		},
 		transactional, PalletId,
 	};
 
+	use super::{PortfolioFees, PortfolioStatus, RebalanceSchedule};
 	use frame_system::pallet_prelude::*;
-	use pallet_dex::{DEXManager, SwapLimit};
+	use pallet_dex::{DEXManager, DEXPriceOracle, Price, Ratio, SwapLimit};
+	use pallet_portfolio_rpc_runtime_api::{PortfolioInfo, PortfolioQuote, RouteQuote};
 	use sp_runtime::{
-		traits::{AccountIdConversion, One, UniqueSaturatedFrom, UniqueSaturatedInto, Zero},
-		Perbill,
+		traits::{
+			AccountIdConversion, CheckedAdd, CheckedDiv, CheckedMul, CheckedSub, One, Saturating,
+			UniqueSaturatedFrom, UniqueSaturatedInto, Zero,
+		},
+		ArithmeticError, FixedPointNumber, Perbill, Permill,
 	};
 	use sp_std::{vec, vec::Vec};
 
+	pub(crate) type BalanceOf<T> = <<T as Config>::NativeCurrency as Currency<
+		<T as frame_system::Config>::AccountId,
+	>>::Balance;
+
+	/// The current storage version.
+	const STORAGE_VERSION: StorageVersion = StorageVersion::new(2);
+
 	#[pallet::config]
 	pub trait Config: frame_system::Config + pallet_assets::Config {
 		/// Because this pallet emits events, it depends on the runtime's definition of an event.
@@ -40,20 +101,119 @@ pub mod pallet {
 		#[pallet::constant]
 		type PalletId: Get<PalletId>;
 
-		// type Currency: fungibles::Create<Self::AccountId>
-		// 	+ fungibles::Mutate<Self::AccountId>
-		// 	+ fungibles::Transfer<Self::AccountId>;
+		/// The currencies held by portfolios, with the native currency as
+		/// `pallet_assets::Config::NativeAssetId`.
+		type Currencies: fungibles::Transfer<
+			Self::AccountId,
+			AssetId = Self::AssetId,
+			Balance = Self::Balance,
+		>;
 
-		type NativeCurrency: NativeTransfer<Self::AccountId>;
+		type NativeCurrency: ReservableCurrency<Self::AccountId>;
 
 		type DexManager: DEXManager<Self::AccountId, Self::AssetId, Self::Balance>;
+
+		/// The TWAP oracle every swap of a portfolio is checked against.
+		type PriceOracle: DEXPriceOracle<Self::AssetId, Self::BlockNumber>;
+
+		/// The number of blocks the TWAP is taken over.
+		#[pallet::constant]
+		type TwapWindow: Get<Self::BlockNumber>;
+
+		/// The largest shortfall of the output of a swap against the TWAP of its path, covering
+		/// the trading fees and the price impact of the swap.
+		#[pallet::constant]
+		type MaxSwapSlippage: Get<Permill>;
+
+		/// The largest shortfall of the output of a rebalance swap against the TWAP of its path.
+		/// A rebalance that can't be made within it is skipped.
+		#[pallet::constant]
+		type MaxRebalanceSlippage: Get<Permill>;
+
+		/// The origin which may manage any portfolio in place of its owner.
+		type UpdateOrigin: EnsureOrigin<Self::Origin>;
+
+		/// The maximum yearly management fee of a portfolio.
+		#[pallet::constant]
+		type MaxManagementFee: Get<Permill>;
+
+		/// The maximum performance fee of a portfolio.
+		#[pallet::constant]
+		type MaxPerformanceFee: Get<Permill>;
+
+		/// The number of blocks the yearly management fee is spread over.
+		#[pallet::constant]
+		type BlocksPerYear: Get<Self::BlockNumber>;
+
+		/// The maximum number of portfolios rebalanced in one block.
+		#[pallet::constant]
+		type MaxRebalancesPerBlock: Get<u32>;
+
+		/// The weight reserved for rebalancing one portfolio.
+		#[pallet::constant]
+		type RebalanceWeight: Get<Weight>;
+
+		/// The maximum number of components in a portfolio.
+		#[pallet::constant]
+		type MaxComponents: Get<u32>;
+
+		/// The native deposit reserved from the creator of a portfolio.
+		#[pallet::constant]
+		type CreationDeposit: Get<BalanceOf<Self>>;
 	}
 
 	#[pallet::pallet]
 	#[pallet::generate_store(pub(super) trait Store)]
+	#[pallet::storage_version(STORAGE_VERSION)]
 	#[pallet::without_storage_info]
 	pub struct Pallet<T>(_);
 
+	#[pallet::hooks]
+	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+		fn on_runtime_upgrade() -> Weight {
+			crate::migrations::v1::migrate::<T>()
+				.saturating_add(crate::migrations::v2::migrate::<T>())
+		}
+
+		fn on_idle(now: T::BlockNumber, remaining_weight: Weight) -> Weight {
+			let mut consumed_weight: Weight = 0;
+			let mut rebalanced: u32 = 0;
+
+			for (port_id, mut schedule) in RebalanceSchedules::<T>::iter() {
+				consumed_weight = consumed_weight.saturating_add(T::DbWeight::get().reads(1));
+				if rebalanced >= T::MaxRebalancesPerBlock::get() ||
+					consumed_weight.saturating_add(T::RebalanceWeight::get()) > remaining_weight
+				{
+					break
+				}
+				if schedule.next_rebalance > now {
+					continue
+				}
+
+				consumed_weight = consumed_weight.saturating_add(T::RebalanceWeight::get());
+				rebalanced += 1;
+
+				schedule.next_rebalance = now.saturating_add(schedule.period);
+				RebalanceSchedules::<T>::insert(port_id, schedule.clone());
+				if Statuses::<T>::get(port_id) == PortfolioStatus::Active &&
+					Self::drift(port_id).map_or(false, |drift| drift > schedule.drift_threshold)
+				{
+					if let Err(e) = Self::do_rebalance(port_id) {
+						log::warn!(
+							target: "runtime::portfolio",
+							"rebalance of portfolio {:?} skipped: {:?}",
+							port_id,
+							e,
+						);
+						Self::deposit_event(Event::RebalanceSkipped(port_id, e));
+					}
+				}
+			}
+
+			consumed_weight
+		}
+	}
+
 	#[pallet::storage]
 	#[pallet::getter(fn components)]
 	pub type Components<T: Config> =
@@ -67,12 +227,61 @@ pub mod pallet {
 	#[pallet::getter(fn owners)]
 	pub type Owners<T: Config> = StorageMap<_, Twox64Concat, T::AssetId, T::AccountId>;
 
+	/// Component holdings of each portfolio.
+	///
+	/// Holdings: double_map port_id, component_id => Balance
+	#[pallet::storage]
+	#[pallet::getter(fn holdings)]
+	pub type Holdings<T: Config> = StorageDoubleMap<
+		_,
+		Twox64Concat,
+		T::AssetId,
+		Twox64Concat,
+		T::AssetId,
+		T::Balance,
+		ValueQuery,
+	>;
+
+	/// Swap paths from the native currency to a component, used to route the components of
+	/// each portfolio.
+	///
+	/// SwapPaths: map port_id => Vec<Vec<AssetId>>
+	#[pallet::storage]
+	#[pallet::getter(fn swap_paths)]
+	pub type SwapPaths<T: Config> =
+		StorageMap<_, Twox64Concat, T::AssetId, Vec<Vec<T::AssetId>>, ValueQuery>;
+
+	/// Fees of each portfolio.
+	///
+	/// Fees: map port_id => PortfolioFees
+	#[pallet::storage]
+	#[pallet::getter(fn fees)]
+	pub type Fees<T: Config> =
+		StorageMap<_, Twox64Concat, T::AssetId, PortfolioFees<T::BlockNumber>>;
+
+	/// Automatic rebalancing settings of each portfolio.
+	///
+	/// RebalanceSchedules: map port_id => RebalanceSchedule
 	#[pallet::storage]
-	#[pallet::getter(fn port_total_balances)]
-	pub type PortTotalBalances<T: Config> = StorageValue<_, Vec<T::Balance>, ValueQuery>;
+	#[pallet::getter(fn rebalance_schedules)]
+	pub type RebalanceSchedules<T: Config> =
+		StorageMap<_, Twox64Concat, T::AssetId, RebalanceSchedule<T::BlockNumber>>;
 
+	/// Lifecycle status of each portfolio.
+	///
+	/// Statuses: map port_id => PortfolioStatus
 	#[pallet::storage]
-	pub type SwapPaths<T: Config> = StorageValue<_, Vec<Vec<T::AssetId>>, ValueQuery>;
+	#[pallet::getter(fn statuses)]
+	pub type Statuses<T: Config> =
+		StorageMap<_, Twox64Concat, T::AssetId, PortfolioStatus, ValueQuery>;
+
+	/// Creation deposit of each portfolio and the account it was reserved from.
+	///
+	/// Deposits: map port_id => (AccountId, Balance)
+	#[pallet::storage]
+	#[pallet::getter(fn deposits)]
+	pub type Deposits<T: Config> =
+		StorageMap<_, Twox64Concat, T::AssetId, (T::AccountId, BalanceOf<T>)>;
 
 	#[pallet::event]
 	#[pallet::generate_deposit(pub(super) fn deposit_event)]
@@ -81,6 +290,38 @@ pub mod pallet {
 		/// parameters. [something, who]
 		PortofioCreated(T::AssetId),
 		PortofioBuy(T::Balance),
+		/// The swap paths of a portfolio were replaced. [port_id]
+		SwapPathsUpdated(T::AssetId),
+		/// Portfolio tokens were minted against a basket of components. [port_id, who, amount]
+		InKindCreated(T::AssetId, T::AccountId, T::Balance),
+		/// Portfolio tokens were redeemed for a basket of components. [port_id, who, amount]
+		InKindRedeemed(T::AssetId, T::AccountId, T::Balance),
+		/// The fees of a portfolio were changed. [port_id, management_fee, performance_fee]
+		FeesUpdated(T::AssetId, Permill, Permill),
+		/// Management fee was minted to the owner. [port_id, owner, amount]
+		ManagementFeeAccrued(T::AssetId, T::AccountId, T::Balance),
+		/// Performance fee was minted to the owner. [port_id, owner, amount, high_water_mark]
+		PerformanceFeeAccrued(T::AssetId, T::AccountId, T::Balance, Price),
+		/// The rebalance schedule of a portfolio was changed. [port_id, period, drift_threshold]
+		RebalanceScheduleUpdated(T::AssetId, T::BlockNumber, Perbill),
+		/// A portfolio was moved back towards its target rates. [port_id, achieved_weights]
+		Rebalanced(T::AssetId, Vec<Perbill>),
+		/// A scheduled rebalance was skipped, as a swap could not be made within
+		/// `MaxRebalanceSlippage` of the TWAP or failed otherwise. [port_id, error]
+		RebalanceSkipped(T::AssetId, DispatchError),
+		/// The owner of a portfolio was changed. [port_id, new_owner]
+		OwnerChanged(T::AssetId, T::AccountId),
+		/// Buys, sells and in-kind creations and redemptions were suspended. [port_id]
+		PortfolioPaused(T::AssetId),
+		/// A paused portfolio can be traded again. [port_id]
+		PortfolioUnpaused(T::AssetId),
+		/// A portfolio was closed, its holdings sold into the native currency if `liquidated`.
+		/// [port_id, liquidated]
+		PortfolioClosed(T::AssetId, bool),
+		/// A closed portfolio was destroyed together with its token. [port_id]
+		PortfolioDestroyed(T::AssetId),
+		/// The holdings of a portfolio were set by `UpdateOrigin`. [port_id]
+		HoldingsSet(T::AssetId),
 	}
 
 	// Errors inform users that something went wrong.
@@ -94,6 +335,41 @@ pub mod pallet {
 		NotEqueOne,
 		NotOwner,
 		NotChange,
+		/// A swap path does not lead from the native currency to a component, or crosses a
+		/// trading pair that is not enabled.
+		InvalidSwapPath,
+		/// The deadline of the trade has passed.
+		Expired,
+		/// Fewer portfolio tokens would be minted than the acceptable minimum.
+		InsufficientPortAmount,
+		/// Less of the destination asset would be received than the acceptable minimum.
+		InsufficientTargetAmount,
+		/// The portfolio has no tokens issued, so there is no basket to match.
+		EmptyPortfolio,
+		/// The fee is above the configured maximum.
+		FeeTooHigh,
+		/// A component is listed more than once.
+		DuplicateComponent,
+		/// The portfolio has no components or more than `MaxComponents`.
+		InvalidComponentCount,
+		/// The portfolio is paused or closed.
+		NotActive,
+		/// The portfolio is already closed.
+		AlreadyClosed,
+		/// The portfolio has to be closed first.
+		NotClosed,
+		/// Portfolio tokens are still outstanding.
+		PortfolioNotEmpty,
+		/// A component can't be valued, as it can't be sold through the DEX.
+		NoPrice,
+		/// A rebalance swap path has no price history over `TwapWindow`.
+		NoTwap,
+		/// Portfolio tokens are outstanding but the holdings are worth nothing, so new tokens can't
+		/// be priced.
+		WorthlessPortfolio,
+		/// The holdings of every portfolio in a component would add up to more than the pallet
+		/// account holds of it.
+		HoldingsNotBacked,
 	}
 
 	impl<T: Config> Pallet<T> {
@@ -104,27 +380,40 @@ pub mod pallet {
 
 	#[pallet::call]
 	impl<T: Config> Pallet<T> {
+		/// Create the portfolio token `port_id` made of `components` with target weights
+		/// `exchange_rates`, which must add up to exactly one.
+		///
+		/// `name`, `symbol` and `decimals` become the metadata of the portfolio token.
+		/// `CreationDeposit` is reserved from the creator until the portfolio is destroyed.
 		#[pallet::weight(10_000)]
 		#[transactional]
 		pub fn create_portofio(
 			origin: OriginFor<T>,
 			port_id: T::AssetId,
 			components: Vec<T::AssetId>,
-			exchange_rates: Vec<u32>,
+			exchange_rates: Vec<Perbill>,
+			name: Vec<u8>,
+			symbol: Vec<u8>,
+			decimals: u8,
 		) -> DispatchResult {
-			let len = components.len();
-			ensure!(len == exchange_rates.len(), Error::<T>::NotEquel);
-			for cid in &components {
-				//ensure!(pallet_assets::Pallet::Asset::<T>::contains_key(cid),
-				// Error::<T>::NotExistId);
-			}
 			let admin = ensure_signed(origin)?;
+			ensure!(
+				!components.is_empty() && components.len() <= T::MaxComponents::get() as usize,
+				Error::<T>::InvalidComponentCount
+			);
+			ensure!(components.len() == exchange_rates.len(), Error::<T>::NotEquel);
+			for (i, cid) in components.iter().enumerate() {
+				ensure!(
+					*cid == T::NativeAssetId::get() ||
+						pallet_assets::Pallet::<T>::maybe_total_supply(*cid).is_some(),
+					Error::<T>::NotExistId
+				);
+				ensure!(!components[..i].contains(cid), Error::<T>::DuplicateComponent);
+			}
+			Self::ensure_valid_rates(&exchange_rates)?;
 
-			let sum: u32 = exchange_rates.iter().sum();
-			ensure!(sum == 100, Error::<T>::NotEquel);
-
-			let exchange_rates: Vec<_> =
-				exchange_rates.into_iter().map(|i| Perbill::from_percent(i)).collect();
+			let deposit = T::CreationDeposit::get();
+			T::NativeCurrency::reserve(&admin, deposit)?;
 
 			let pallet_account = Self::account_id();
 			<pallet_assets::Pallet<T> as Create<T::AccountId>>::create(
@@ -133,22 +422,47 @@ pub mod pallet {
 				true,
 				T::Balance::one(),
 			)?;
-
-			//pallet_assets::Pallet::<T>::mint_into(port_id, &Self::account_id(), 1u32.into())?;
+			pallet_assets::Pallet::<T>::do_force_set_metadata(
+				port_id, name, symbol, decimals, false,
+			)?;
 
 			Components::<T>::insert(port_id, components);
 			Rates::<T>::insert(port_id, exchange_rates);
-			Owners::<T>::insert(port_id, admin);
-			let zero = T::Balance::zero();
-			PortTotalBalances::<T>::put(vec![zero; len]);
+			Owners::<T>::insert(port_id, admin.clone());
+			Deposits::<T>::insert(port_id, (admin, deposit));
 			Self::deposit_event(Event::PortofioCreated(port_id));
 			Ok(())
 		}
 
+		/// Replace the swap paths used when trading the components of `port_id`.
+		///
+		/// The origin must be the portfolio owner or `UpdateOrigin`. Every path must start at the
+		/// native currency buys are paid in, end at a component of `port_id` and only cross
+		/// enabled trading pairs of the DEX. Sells of the component use the path in reverse.
 		#[pallet::weight(10_000)]
-		pub fn set_swap_path(origin: OriginFor<T>, paths: Vec<Vec<T::AssetId>>) -> DispatchResult {
-			let _ = ensure_signed(origin)?;
-			SwapPaths::<T>::put(paths);
+		pub fn set_swap_path(
+			origin: OriginFor<T>,
+			port_id: T::AssetId,
+			paths: Vec<Vec<T::AssetId>>,
+		) -> DispatchResult {
+			Self::ensure_owner_or_update_origin(origin, port_id)?;
+			let components = Components::<T>::get(port_id);
+			for path in &paths {
+				ensure!(
+					path.len() >= 2 &&
+						path.first() == Some(&T::NativeAssetId::get()) &&
+						path.last().map_or(false, |asset_id| components.contains(asset_id)),
+					Error::<T>::InvalidSwapPath
+				);
+				ensure!(
+					path.windows(2)
+						.all(|hop| T::DexManager::is_trading_pair_enabled(hop[0], hop[1])),
+					Error::<T>::InvalidSwapPath
+				);
+			}
+
+			SwapPaths::<T>::insert(port_id, paths);
+			Self::deposit_event(Event::SwapPathsUpdated(port_id));
 			Ok(())
 		}
 
@@ -172,38 +486,54 @@ pub mod pallet {
 		// 	Ok(())
 		// }
 
+		/// Buy portfolio tokens of `port_id` with `amount` of the native currency.
+		///
+		/// - `min_port_amount`: acceptable minimum amount of portfolio tokens to be minted.
+		/// - `deadline`: the last block in which the purchase may be executed.
 		#[pallet::weight(10_000)]
 		#[transactional]
-		pub fn buy(origin: OriginFor<T>, port_id: T::AssetId, amount: u128) -> DispatchResult {
+		pub fn buy(
+			origin: OriginFor<T>,
+			port_id: T::AssetId,
+			amount: u128,
+			min_port_amount: u128,
+			deadline: Option<T::BlockNumber>,
+		) -> DispatchResult {
 			let who = ensure_signed(origin)?;
+			Self::ensure_not_expired(deadline)?;
 			ensure!(Components::<T>::contains_key(port_id), Error::<T>::NotHasAsset);
+			Self::ensure_active(port_id)?;
+			Self::accrue_fees(port_id)?;
 			//let owner = Owners::<T>::get(port_id).ok_or(Error::<T>::NotEquel)?;
 
-			let ids = Components::<T>::get(port_id);
-			let rates = Rates::<T>::get(port_id);
 			let amount: T::Balance = UniqueSaturatedFrom::unique_saturated_from(amount);
+			let min_port_amount: T::Balance =
+				UniqueSaturatedFrom::unique_saturated_from(min_port_amount);
 			Self::deposit_event(Event::PortofioBuy(amount));
 
-			<pallet_assets::Pallet<T> as Transfer<T::AccountId>>::transfer(
-				0u32.into(),
-				&who,
-				&Self::account_id(),
-				amount.clone(),
-				false,
-			)?;
-
-			pallet_assets::Pallet::<T>::mint_into(port_id, &who, amount.clone())?;
-
-			let balances = Self::do_buy(amount, ids, rates)?;
-			let mut saved_balances = PortTotalBalances::<T>::get();
+			let port_amount = Self::buy_port_amount(port_id, amount, |ids, _| {
+				T::Currencies::transfer(
+					T::NativeAssetId::get(),
+					&who,
+					&Self::account_id(),
+					amount,
+					false,
+				)?;
+				Self::do_buy(port_id, amount, ids.to_vec(), Rates::<T>::get(port_id))
+			})?;
+			ensure!(
+				!port_amount.is_zero() && port_amount >= min_port_amount,
+				Error::<T>::InsufficientPortAmount
+			);
 
-			for i in 0..balances.len() {
-				saved_balances[i] += balances[i];
-			}
-			PortTotalBalances::<T>::put(saved_balances);
+			pallet_assets::Pallet::<T>::mint_into(port_id, &who, port_amount)?;
 			Ok(())
 		}
 
+		/// Sell `amount` portfolio tokens of `port_id` for `dst_id`.
+		///
+		/// - `min_dst_amount`: acceptable minimum amount of `dst_id` to be received.
+		/// - `deadline`: the last block in which the sale may be executed.
 		#[pallet::weight(10_000)]
 		#[transactional]
 		pub fn sell(
@@ -211,9 +541,14 @@ pub mod pallet {
 			port_id: T::AssetId,
 			dst_id: T::AssetId,
 			amount: u128,
+			min_dst_amount: u128,
+			deadline: Option<T::BlockNumber>,
 		) -> DispatchResult {
 			let who = ensure_signed(origin.clone())?;
+			Self::ensure_not_expired(deadline)?;
 			ensure!(Components::<T>::contains_key(port_id), Error::<T>::NotHasAsset);
+			Self::ensure_active(port_id)?;
+			Self::accrue_fees(port_id)?;
 
 			let ids = Components::<T>::get(port_id);
 			let amount: T::Balance = UniqueSaturatedFrom::unique_saturated_from(amount);
@@ -225,57 +560,363 @@ pub mod pallet {
 
 			let perbill_rate = Perbill::from_rational(amount, whole);
 
-			let mut saved_balances = PortTotalBalances::<T>::get();
-			//let rate = Rates::<T>::get(port_id);
-			let total = Self::do_sell(&mut saved_balances, ids, perbill_rate, dst_id)?;
-			<pallet_assets::Pallet<T> as Transfer<T::AccountId>>::transfer(
-				dst_id,
-				&Self::account_id(),
-				&who,
-				total,
-				false,
-			)?;
-			PortTotalBalances::<T>::put(saved_balances);
+			let total = Self::do_sell(port_id, ids, perbill_rate, dst_id)?;
+			let min_dst_amount: T::Balance =
+				UniqueSaturatedFrom::unique_saturated_from(min_dst_amount);
+			ensure!(total >= min_dst_amount, Error::<T>::InsufficientTargetAmount);
+			T::Currencies::transfer(dst_id, &Self::account_id(), &who, total, false)?;
 			Ok(())
 		}
 
+		/// Mint `port_amount` portfolio tokens of `port_id` by depositing the same share of every
+		/// component the portfolio holds. No swap is made.
+		#[pallet::weight(10_000)]
+		#[transactional]
+		pub fn create_in_kind(
+			origin: OriginFor<T>,
+			port_id: T::AssetId,
+			port_amount: u128,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			ensure!(Components::<T>::contains_key(port_id), Error::<T>::NotHasAsset);
+			Self::ensure_active(port_id)?;
+			Self::accrue_fees(port_id)?;
+
+			let port_amount: T::Balance = UniqueSaturatedFrom::unique_saturated_from(port_amount);
+			let supply =
+				<pallet_assets::Pallet<T> as Inspect<T::AccountId>>::total_issuance(port_id);
+			ensure!(!supply.is_zero(), Error::<T>::EmptyPortfolio);
+			ensure!(!port_amount.is_zero(), Error::<T>::InsufficientPortAmount);
+
+			let share = Ratio::checked_from_rational(port_amount, supply)
+				.ok_or(ArithmeticError::Overflow)?;
+			for (asset_id, holding) in Holdings::<T>::iter_prefix(port_id).collect::<Vec<_>>() {
+				if holding.is_zero() {
+					continue
+				}
+				// add 1 to the deposit so that the rounding never dilutes the existing holders
+				let deposit = share
+					.checked_mul_int(holding)
+					.and_then(|n| n.checked_add(&T::Balance::one()))
+					.ok_or(ArithmeticError::Overflow)?;
+				T::Currencies::transfer(asset_id, &who, &Self::account_id(), deposit, false)?;
+				Self::increase_holding(port_id, asset_id, deposit)?;
+			}
+
+			pallet_assets::Pallet::<T>::mint_into(port_id, &who, port_amount)?;
+			Self::deposit_event(Event::InKindCreated(port_id, who, port_amount));
+			Ok(())
+		}
+
+		/// Burn `port_amount` portfolio tokens of `port_id` and receive the same share of every
+		/// component the portfolio holds. No swap is made.
+		///
+		/// This is also how holders exit a closed portfolio.
+		#[pallet::weight(10_000)]
+		#[transactional]
+		pub fn redeem_in_kind(
+			origin: OriginFor<T>,
+			port_id: T::AssetId,
+			port_amount: u128,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			ensure!(Components::<T>::contains_key(port_id), Error::<T>::NotHasAsset);
+			ensure!(Statuses::<T>::get(port_id) != PortfolioStatus::Paused, Error::<T>::NotActive);
+			Self::accrue_fees(port_id)?;
+
+			let port_amount: T::Balance = UniqueSaturatedFrom::unique_saturated_from(port_amount);
+			let supply =
+				<pallet_assets::Pallet<T> as Inspect<T::AccountId>>::total_issuance(port_id);
+			ensure!(supply >= port_amount, Error::<T>::NotHasEnoughAsset);
+
+			pallet_assets::Pallet::<T>::burn_from(port_id, &who, port_amount)?;
+
+			let share = Ratio::checked_from_rational(port_amount, supply)
+				.ok_or(ArithmeticError::Overflow)?;
+			for (asset_id, holding) in Holdings::<T>::iter_prefix(port_id).collect::<Vec<_>>() {
+				let withdrawal = share.checked_mul_int(holding).ok_or(ArithmeticError::Overflow)?;
+				if withdrawal.is_zero() {
+					continue
+				}
+				Self::decrease_holding(port_id, asset_id, withdrawal)?;
+				T::Currencies::transfer(asset_id, &Self::account_id(), &who, withdrawal, false)?;
+			}
+
+			Self::deposit_event(Event::InKindRedeemed(port_id, who, port_amount));
+			Ok(())
+		}
+
+		/// Set the yearly management fee and the performance fee of `port_id`.
+		///
+		/// The origin must be the portfolio owner or `UpdateOrigin`. Fees accrued under the
+		/// previous settings are minted first.
+		#[pallet::weight(10_000)]
+		#[transactional]
+		pub fn set_fees(
+			origin: OriginFor<T>,
+			port_id: T::AssetId,
+			management_fee: Permill,
+			performance_fee: Permill,
+		) -> DispatchResult {
+			Self::ensure_owner_or_update_origin(origin, port_id)?;
+			Self::ensure_not_closed(port_id)?;
+			ensure!(
+				management_fee <= T::MaxManagementFee::get() &&
+					performance_fee <= T::MaxPerformanceFee::get(),
+				Error::<T>::FeeTooHigh
+			);
+
+			Self::accrue_fees(port_id)?;
+			let high_water_mark = Self::twap_nav_per_share(port_id).unwrap_or_else(Zero::zero);
+			Fees::<T>::mutate(port_id, |maybe_fees| {
+				let fees = maybe_fees.get_or_insert_with(|| PortfolioFees {
+					management_fee,
+					performance_fee,
+					last_accrual: frame_system::Pallet::<T>::block_number(),
+					high_water_mark,
+				});
+				fees.management_fee = management_fee;
+				fees.performance_fee = performance_fee;
+			});
+			Self::deposit_event(Event::FeesUpdated(port_id, management_fee, performance_fee));
+			Ok(())
+		}
+
+		/// Mint the fees accrued by `port_id` to its owner.
+		#[pallet::weight(10_000)]
+		#[transactional]
+		pub fn accrue(origin: OriginFor<T>, port_id: T::AssetId) -> DispatchResult {
+			let _ = ensure_signed(origin)?;
+			ensure!(Components::<T>::contains_key(port_id), Error::<T>::NotHasAsset);
+			Self::accrue_fees(port_id)
+		}
+
+		/// Replace the target weights of `port_id` and rebalance it. `new_rates` must add up to
+		/// exactly one.
 		#[pallet::weight(10_000)]
 		#[transactional]
 		pub fn change_rate(
 			origin: OriginFor<T>,
 			port_id: T::AssetId,
-			new_rates: Vec<u32>,
+			new_rates: Vec<Perbill>,
 		) -> DispatchResult {
 			let owner = ensure_signed(origin)?;
 			ensure!(Owners::<T>::get(port_id) == Some(owner), Error::<T>::NotOwner);
+			Self::ensure_not_closed(port_id)?;
+			Self::ensure_valid_rates(&new_rates)?;
 
-			let sum: u32 = new_rates.iter().sum();
-			ensure!(sum == 100, Error::<T>::NotEquel);
-
-			let new_rates: Vec<_> =
-				new_rates.into_iter().map(|i| Perbill::from_percent(i)).collect();
 			let old_rates = Rates::<T>::get(port_id);
 			ensure!(new_rates.len() == old_rates.len(), Error::<T>::NotEquel);
 			ensure!(new_rates != old_rates, Error::<T>::NotChange);
 
-			let mut saved_balances = PortTotalBalances::<T>::get();
-			let ids = Components::<T>::get(port_id);
-			let total = Self::do_sell(
-				&mut saved_balances,
-				ids.clone(),
-				Perbill::from_percent(100),
-				0u32.into(),
+			Rates::<T>::insert(port_id, new_rates);
+			Self::do_rebalance(port_id)?;
+			Ok(())
+		}
+
+		/// Rebalance `port_id` every `period` blocks whenever a component has drifted from its
+		/// target rate by more than `drift_threshold`. A zero `period` removes the schedule.
+		///
+		/// The origin must be the portfolio owner or `UpdateOrigin`.
+		#[pallet::weight(10_000)]
+		pub fn set_rebalance_schedule(
+			origin: OriginFor<T>,
+			port_id: T::AssetId,
+			period: T::BlockNumber,
+			drift_threshold: Perbill,
+		) -> DispatchResult {
+			Self::ensure_owner_or_update_origin(origin, port_id)?;
+			Self::ensure_not_closed(port_id)?;
+
+			if period.is_zero() {
+				RebalanceSchedules::<T>::remove(port_id);
+			} else {
+				RebalanceSchedules::<T>::insert(
+					port_id,
+					RebalanceSchedule {
+						period,
+						drift_threshold,
+						next_rebalance: frame_system::Pallet::<T>::block_number()
+							.saturating_add(period),
+					},
+				);
+			}
+			Self::deposit_event(Event::RebalanceScheduleUpdated(port_id, period, drift_threshold));
+			Ok(())
+		}
+
+		/// Hand `port_id` over to `new_owner`. Fees accrued so far are minted to the previous
+		/// owner.
+		///
+		/// The origin must be the portfolio owner or `UpdateOrigin`.
+		#[pallet::weight(10_000)]
+		#[transactional]
+		pub fn transfer_ownership(
+			origin: OriginFor<T>,
+			port_id: T::AssetId,
+			new_owner: T::AccountId,
+		) -> DispatchResult {
+			Self::ensure_owner_or_update_origin(origin, port_id)?;
+			ensure!(Owners::<T>::get(port_id).as_ref() != Some(&new_owner), Error::<T>::NotChange);
+
+			Self::accrue_fees(port_id)?;
+			Owners::<T>::insert(port_id, new_owner.clone());
+			Self::deposit_event(Event::OwnerChanged(port_id, new_owner));
+			Ok(())
+		}
+
+		/// Suspend buys, sells and in-kind creations and redemptions of `port_id`.
+		///
+		/// The origin must be the portfolio owner or `UpdateOrigin`.
+		#[pallet::weight(10_000)]
+		pub fn pause(origin: OriginFor<T>, port_id: T::AssetId) -> DispatchResult {
+			Self::ensure_owner_or_update_origin(origin, port_id)?;
+			Self::ensure_active(port_id)?;
+
+			Statuses::<T>::insert(port_id, PortfolioStatus::Paused);
+			Self::deposit_event(Event::PortfolioPaused(port_id));
+			Ok(())
+		}
+
+		/// Resume trading of the paused `port_id`.
+		///
+		/// The origin must be the portfolio owner or `UpdateOrigin`.
+		#[pallet::weight(10_000)]
+		pub fn unpause(origin: OriginFor<T>, port_id: T::AssetId) -> DispatchResult {
+			Self::ensure_owner_or_update_origin(origin, port_id)?;
+			ensure!(Statuses::<T>::get(port_id) == PortfolioStatus::Paused, Error::<T>::NotChange);
+
+			Statuses::<T>::insert(port_id, PortfolioStatus::Active);
+			Self::deposit_event(Event::PortfolioUnpaused(port_id));
+			Ok(())
+		}
+
+		/// Close `port_id` for good. Fees stop accruing and the rebalance schedule is removed.
+		///
+		/// With `liquidate` every holding is sold into the native currency, so that holders
+		/// redeeming in kind receive their pro rata share of the native currency. Otherwise they
+		/// receive their share of every component.
+		///
+		/// The origin must be the portfolio owner or `UpdateOrigin`.
+		#[pallet::weight(10_000)]
+		#[transactional]
+		pub fn close_portfolio(
+			origin: OriginFor<T>,
+			port_id: T::AssetId,
+			liquidate: bool,
+		) -> DispatchResult {
+			Self::ensure_owner_or_update_origin(origin, port_id)?;
+			Self::ensure_not_closed(port_id)?;
+
+			Self::accrue_fees(port_id)?;
+			if liquidate {
+				let base_id = T::NativeAssetId::get();
+				let ids: Vec<_> = Holdings::<T>::iter_key_prefix(port_id).collect();
+				let total = Self::do_sell(port_id, ids, Perbill::one(), base_id)?;
+				Self::increase_holding(port_id, base_id, total)?;
+			}
+
+			Fees::<T>::remove(port_id);
+			RebalanceSchedules::<T>::remove(port_id);
+			Statuses::<T>::insert(port_id, PortfolioStatus::Closed);
+			Self::deposit_event(Event::PortfolioClosed(port_id, liquidate));
+			Ok(())
+		}
+
+		/// Destroy the token of the closed `port_id` once every holder has redeemed, and clean up
+		/// its storage. Rounding dust left in the holdings goes to the owner and the creation
+		/// deposit is returned.
+		///
+		/// The origin must be the portfolio owner or `UpdateOrigin`.
+		#[pallet::weight(10_000)]
+		#[transactional]
+		pub fn destroy_portfolio(origin: OriginFor<T>, port_id: T::AssetId) -> DispatchResult {
+			Self::ensure_owner_or_update_origin(origin, port_id)?;
+			ensure!(Statuses::<T>::get(port_id) == PortfolioStatus::Closed, Error::<T>::NotClosed);
+			ensure!(
+				<pallet_assets::Pallet<T> as Inspect<T::AccountId>>::total_issuance(port_id)
+					.is_zero(),
+				Error::<T>::PortfolioNotEmpty
+			);
+
+			let owner = Owners::<T>::take(port_id);
+			for (asset_id, holding) in Holdings::<T>::drain_prefix(port_id) {
+				if holding.is_zero() {
+					continue
+				}
+				if let Some(owner) = &owner {
+					T::Currencies::transfer(asset_id, &Self::account_id(), owner, holding, false)?;
+				}
+			}
+
+			let witness =
+				<pallet_assets::Pallet<T> as fungibles::Destroy<T::AccountId>>::get_destroy_witness(
+					&port_id,
+				)
+				.ok_or(Error::<T>::NotHasAsset)?;
+			<pallet_assets::Pallet<T> as fungibles::Destroy<T::AccountId>>::destroy(
+				port_id, witness, None,
 			)?;
 
-			let balances = Self::do_buy(total, ids, new_rates)?;
-			PortTotalBalances::<T>::put(balances);
+			Components::<T>::remove(port_id);
+			Rates::<T>::remove(port_id);
+			SwapPaths::<T>::remove(port_id);
+			Fees::<T>::remove(port_id);
+			RebalanceSchedules::<T>::remove(port_id);
+			Statuses::<T>::remove(port_id);
+			if let Some((depositor, deposit)) = Deposits::<T>::take(port_id) {
+				T::NativeCurrency::unreserve(&depositor, deposit);
+			}
+			Self::deposit_event(Event::PortfolioDestroyed(port_id));
+			Ok(())
+		}
+
+		/// Set the holdings of `port_id` in some of its components, such as those the v1
+		/// migration could not attribute. The holdings of every portfolio in a component must
+		/// stay backed by the balance of the pallet account.
+		///
+		/// The origin must be `UpdateOrigin`.
+		#[pallet::weight(10_000)]
+		#[transactional]
+		pub fn force_set_holdings(
+			origin: OriginFor<T>,
+			port_id: T::AssetId,
+			holdings: Vec<(T::AssetId, T::Balance)>,
+		) -> DispatchResult {
+			T::UpdateOrigin::ensure_origin(origin)?;
+			let components = Components::<T>::get(port_id);
+			ensure!(!components.is_empty(), Error::<T>::NotHasAsset);
+
+			for (asset_id, holding) in holdings {
+				ensure!(components.contains(&asset_id), Error::<T>::NotHasAsset);
+				Holdings::<T>::insert(port_id, asset_id, holding);
+
+				let total = Holdings::<T>::iter()
+					.filter(|(_, id, _)| *id == asset_id)
+					.fold(T::Balance::zero(), |total, (_, _, holding)| {
+						total.saturating_add(holding)
+					});
+				ensure!(
+					total <= T::Currencies::balance(asset_id, &Self::account_id()),
+					Error::<T>::HoldingsNotBacked
+				);
+			}
+
+			Self::deposit_event(Event::HoldingsSet(port_id));
 			Ok(())
 		}
 	}
 
 	impl<T: Config> Pallet<T> {
+		/// Swap `amount` of the native currency held by the pallet account into the components of
+		/// `port_id` according to `rates`, and credit the acquired amounts to the holdings of
+		/// `port_id`.
+		///
+		/// Every swap must return at least `min_target_amount` of its component, on top of the
+		/// caller's check of the total.
 		#[transactional]
 		pub fn do_buy(
+			port_id: T::AssetId,
 			amount: T::Balance,
 			ids: Vec<T::AssetId>,
 			rates: Vec<Perbill>,
@@ -283,82 +924,679 @@ pub mod pallet {
 			let mut balances = Vec::new();
 			for i in 0..ids.len() {
 				let balance = rates[i] * amount;
-				if ids[i] == 0u32.into() {
+				if ids[i] == T::NativeAssetId::get() {
+					Self::increase_holding(port_id, ids[i], balance)?;
 					balances.push(balance);
 					continue
 				}
-				let best_path = {
-					let saved_path = SwapPaths::<T>::get();
-					if !saved_path.is_empty() {
-						T::DexManager::get_best_price_swap_path(
-							0u32.into(),
-							ids[i],
-							SwapLimit::ExactSupply(
-								UniqueSaturatedFrom::unique_saturated_from(balance),
-								T::Balance::zero(),
-							),
-							saved_path,
-						)
-						.unwrap_or_default()
-					} else {
-						vec![0u32.into(), ids[i]]
-					}
-				};
+				let best_path = Self::swap_path(
+					port_id,
+					T::NativeAssetId::get(),
+					ids[i],
+					SwapLimit::ExactSupply(balance, T::Balance::zero()),
+				);
 
+				ensure!(!best_path.is_empty(), Error::<T>::NotHasPath);
 				let (_, acture_out) = T::DexManager::swap_with_specific_path(
 					&Self::account_id(),
 					&best_path,
-					SwapLimit::ExactSupply(
-						UniqueSaturatedFrom::unique_saturated_from(balance),
-						T::Balance::zero(),
-					),
+					SwapLimit::ExactSupply(balance, Self::min_target_amount(&best_path, balance)),
 				)?;
+				Self::increase_holding(port_id, ids[i], acture_out)?;
 				balances.push(acture_out);
 			}
 
 			Ok(balances)
 		}
 
+		/// Take `perbill_rate` of every component held by `port_id` out of its holdings and swap
+		/// it into `dst_id`. Returns the total amount of `dst_id` received by the pallet account.
+		///
+		/// Every swap must return at least `min_target_amount` of `dst_id`, on top of the caller's
+		/// check of the total.
 		#[transactional]
 		pub fn do_sell(
-			saved_balances: &mut Vec<T::Balance>,
+			port_id: T::AssetId,
 			ids: Vec<T::AssetId>,
 			perbill_rate: Perbill,
 			dst_id: T::AssetId,
 		) -> sp_std::result::Result<T::Balance, DispatchError> {
 			let mut total = T::Balance::zero();
 			for i in 0..ids.len() {
-				let exchange_amount = perbill_rate * saved_balances[i];
-				saved_balances[i] -= exchange_amount;
+				let exchange_amount = perbill_rate * Holdings::<T>::get(port_id, ids[i]);
+				Self::decrease_holding(port_id, ids[i], exchange_amount)?;
+				if exchange_amount.is_zero() {
+					continue
+				}
 				if ids[i] == dst_id {
 					total += exchange_amount;
 					continue
 				} else {
-					let best_path = {
-						let saved_path = SwapPaths::<T>::get();
-						if !saved_path.is_empty() {
-							T::DexManager::get_best_price_swap_path(
-								ids[i],
-								dst_id,
-								SwapLimit::ExactSupply(exchange_amount, T::Balance::zero()),
-								saved_path,
-							)
-							.unwrap_or_default()
-						} else {
-							vec![ids[i], dst_id]
-						}
-					};
+					let best_path = Self::swap_path(
+						port_id,
+						ids[i],
+						dst_id,
+						SwapLimit::ExactSupply(exchange_amount, T::Balance::zero()),
+					);
 
 					ensure!(!best_path.is_empty(), Error::<T>::NotHasPath);
 					let (_, acture_out) = T::DexManager::swap_with_specific_path(
 						&Self::account_id(),
 						&best_path,
-						SwapLimit::ExactSupply(exchange_amount, T::Balance::zero()),
+						SwapLimit::ExactSupply(
+							exchange_amount,
+							Self::min_target_amount(&best_path, exchange_amount),
+						),
 					)?;
 					total += acture_out;
 				}
 			}
 			Ok(total)
 		}
+
+		/// Mint the management fee accrued since the last accrual, and the performance fee on the
+		/// NAV gain above the high-water mark, to the owner of `port_id`.
+		///
+		/// The performance fee is valued at the TWAP of the components, so that moving the spot
+		/// prices within a block neither charges it nor raises the high-water mark.
+		pub fn accrue_fees(port_id: T::AssetId) -> DispatchResult {
+			let owner = match Owners::<T>::get(port_id) {
+				Some(owner) => owner,
+				None => return Ok(()),
+			};
+			let mut fees = match Fees::<T>::get(port_id) {
+				Some(fees) => fees,
+				None => return Ok(()),
+			};
+
+			let now = frame_system::Pallet::<T>::block_number();
+			let elapsed: u128 = now.saturating_sub(fees.last_accrual).unique_saturated_into();
+			fees.last_accrual = now;
+
+			let supply =
+				<pallet_assets::Pallet<T> as Inspect<T::AccountId>>::total_issuance(port_id);
+			if !supply.is_zero() && !fees.management_fee.is_zero() && elapsed > 0 {
+				let blocks_per_year: u128 = T::BlocksPerYear::get().unique_saturated_into();
+				let fee_amount = Ratio::saturating_from_rational(elapsed, blocks_per_year.max(1))
+					.saturating_mul(Ratio::from(fees.management_fee))
+					.saturating_mul_int(supply);
+				if !fee_amount.is_zero() {
+					pallet_assets::Pallet::<T>::mint_into(port_id, &owner, fee_amount)?;
+					Self::deposit_event(Event::ManagementFeeAccrued(
+						port_id,
+						owner.clone(),
+						fee_amount,
+					));
+				}
+			}
+
+			if let Some(nav) = Self::twap_nav_per_share(port_id) {
+				if fees.high_water_mark.is_zero() {
+					fees.high_water_mark = nav;
+				} else if nav > fees.high_water_mark && !fees.performance_fee.is_zero() {
+					let supply =
+						<pallet_assets::Pallet<T> as Inspect<T::AccountId>>::total_issuance(
+							port_id,
+						);
+					// dilute the holders by the fee's share of the NAV per token
+					let fee_amount = Ratio::from(fees.performance_fee)
+						.saturating_mul(nav.saturating_sub(fees.high_water_mark))
+						.checked_div(&nav)
+						.unwrap_or_default()
+						.saturating_mul_int(supply);
+					if !fee_amount.is_zero() {
+						pallet_assets::Pallet::<T>::mint_into(port_id, &owner, fee_amount)?;
+					}
+					fees.high_water_mark = Self::twap_nav_per_share(port_id).unwrap_or(nav);
+					Self::deposit_event(Event::PerformanceFeeAccrued(
+						port_id,
+						owner,
+						fee_amount,
+						fees.high_water_mark,
+					));
+				}
+			}
+
+			Fees::<T>::insert(port_id, fees);
+			Ok(())
+		}
+
+		/// Value in the native currency of every component held by `port_id`, in component order.
+		fn component_values(
+			port_id: T::AssetId,
+			ids: &[T::AssetId],
+		) -> sp_std::result::Result<Vec<T::Balance>, DispatchError> {
+			ids.iter()
+				.map(|asset_id| {
+					Self::value_in_base(port_id, *asset_id, Holdings::<T>::get(port_id, asset_id))
+				})
+				.collect()
+		}
+
+		/// The holdings of `port_id` of every component in `ids`, in component order.
+		fn holdings_of(port_id: T::AssetId, ids: &[T::AssetId]) -> Vec<T::Balance> {
+			ids.iter().map(|asset_id| Holdings::<T>::get(port_id, asset_id)).collect()
+		}
+
+		/// Price in the native currency of every component in `ids`, in component order: the
+		/// amount of the native currency the holding of `port_id` fetches per unit when sold
+		/// through the DEX. A component without holding is priced at the sale of what `spend` of
+		/// the native currency buys of it.
+		fn component_prices(
+			port_id: T::AssetId,
+			ids: &[T::AssetId],
+			spend: &[T::Balance],
+		) -> sp_std::result::Result<Vec<Price>, DispatchError> {
+			let base_id = T::NativeAssetId::get();
+			ids.iter()
+				.zip(spend.iter())
+				.map(|(asset_id, spend)| -> sp_std::result::Result<Price, DispatchError> {
+					if *asset_id == base_id {
+						return Ok(Price::one())
+					}
+					let mut amount = Holdings::<T>::get(port_id, asset_id);
+					if amount.is_zero() && !spend.is_zero() {
+						let limit = SwapLimit::ExactSupply(*spend, T::Balance::zero());
+						let path = Self::swap_path(port_id, base_id, *asset_id, limit.clone());
+						amount = T::DexManager::get_swap_amount(&path, limit)
+							.map(|(_, target_amount)| target_amount)
+							.ok_or(Error::<T>::NoPrice)?;
+					}
+					if amount.is_zero() {
+						return Ok(Price::zero())
+					}
+					let value = Self::value_in_base(port_id, *asset_id, amount)?;
+					Price::checked_from_rational(value, amount)
+						.ok_or_else(|| ArithmeticError::Overflow.into())
+				})
+				.collect()
+		}
+
+		/// Value in the native currency of `amounts` of the components priced at `prices`.
+		fn value_at(
+			prices: &[Price],
+			amounts: &[T::Balance],
+		) -> sp_std::result::Result<T::Balance, DispatchError> {
+			prices.iter().zip(amounts.iter()).try_fold(
+				T::Balance::zero(),
+				|acc, (price, amount)| {
+					price
+						.checked_mul_int(*amount)
+						.and_then(|value| acc.checked_add(&value))
+						.ok_or_else(|| ArithmeticError::Overflow.into())
+				},
+			)
+		}
+
+		/// Weight of every component in the net asset value of `port_id`.
+		fn weights_of(values: &[T::Balance]) -> Vec<Perbill> {
+			let nav = values.iter().fold(T::Balance::zero(), |acc, v| acc.saturating_add(*v));
+			values.iter().map(|value| Perbill::from_rational(*value, nav)).collect()
+		}
+
+		/// The largest distance between the achieved and the target weight of any component.
+		pub fn drift(port_id: T::AssetId) -> sp_std::result::Result<Perbill, DispatchError> {
+			let ids = Components::<T>::get(port_id);
+			let rates = Rates::<T>::get(port_id);
+			let weights = Self::weights_of(&Self::component_values(port_id, &ids)?);
+			Ok(weights.iter().zip(rates.iter()).fold(Perbill::zero(), |max, (weight, rate)| {
+				max.max(weight.saturating_sub(*rate)).max(rate.saturating_sub(*weight))
+			}))
+		}
+
+		/// Move the holdings of `port_id` back to its target rates. Only the excess of the
+		/// overweight components is sold into the native currency, and the proceeds are spent on
+		/// the underweight components in proportion to their shortfall. Fails, undoing every swap,
+		/// if a swap can't be made within `MaxRebalanceSlippage` of the TWAP of its path.
+		#[transactional]
+		pub fn do_rebalance(
+			port_id: T::AssetId,
+		) -> sp_std::result::Result<Vec<Perbill>, DispatchError> {
+			let base_id = T::NativeAssetId::get();
+			let ids = Components::<T>::get(port_id);
+			let rates = Rates::<T>::get(port_id);
+			let values = Self::component_values(port_id, &ids)?;
+			let nav = values.iter().fold(T::Balance::zero(), |acc, v| acc.saturating_add(*v));
+
+			let mut proceeds = T::Balance::zero();
+			let mut shortfalls = Vec::with_capacity(ids.len());
+			for i in 0..ids.len() {
+				let target_value = rates[i] * nav;
+				if values[i] <= target_value {
+					shortfalls.push(target_value - values[i]);
+					continue
+				}
+				shortfalls.push(Zero::zero());
+
+				let holding = Holdings::<T>::get(port_id, ids[i]);
+				let sell_amount = Ratio::checked_from_rational(values[i] - target_value, values[i])
+					.and_then(|n| n.checked_mul_int(holding))
+					.ok_or(ArithmeticError::Overflow)?;
+				if sell_amount.is_zero() {
+					continue
+				}
+				Self::decrease_holding(port_id, ids[i], sell_amount)?;
+				if ids[i] == base_id {
+					proceeds = proceeds.saturating_add(sell_amount);
+				} else {
+					let actual_out = Self::rebalance_swap(port_id, ids[i], base_id, sell_amount)?;
+					proceeds = proceeds.saturating_add(actual_out);
+				}
+			}
+
+			let total_shortfall =
+				shortfalls.iter().fold(T::Balance::zero(), |acc, v| acc.saturating_add(*v));
+			let mut remaining = proceeds;
+			for i in 0..ids.len() {
+				if shortfalls[i].is_zero() || remaining.is_zero() {
+					continue
+				}
+				let spend = Ratio::checked_from_rational(shortfalls[i], total_shortfall)
+					.and_then(|n| n.checked_mul_int(proceeds))
+					.ok_or(ArithmeticError::Overflow)?
+					.min(remaining);
+				if spend.is_zero() {
+					continue
+				}
+				remaining -= spend;
+				if ids[i] == base_id {
+					Self::increase_holding(port_id, ids[i], spend)?;
+				} else {
+					let actual_out = Self::rebalance_swap(port_id, base_id, ids[i], spend)?;
+					Self::increase_holding(port_id, ids[i], actual_out)?;
+				}
+			}
+			// rounding dust stays with the portfolio as the native currency
+			if !remaining.is_zero() {
+				Self::increase_holding(port_id, base_id, remaining)?;
+			}
+
+			let weights = Self::weights_of(&Self::component_values(port_id, &ids)?);
+			Self::deposit_event(Event::Rebalanced(port_id, weights.clone()));
+			Ok(weights)
+		}
+
+		/// Swap `supply_amount` of `supply_id` held by `port_id` into `target_id`, for no less
+		/// than the TWAP of the path less `MaxRebalanceSlippage`.
+		fn rebalance_swap(
+			port_id: T::AssetId,
+			supply_id: T::AssetId,
+			target_id: T::AssetId,
+			supply_amount: T::Balance,
+		) -> sp_std::result::Result<T::Balance, DispatchError> {
+			let limit = SwapLimit::ExactSupply(supply_amount, T::Balance::zero());
+			let path = Self::swap_path(port_id, supply_id, target_id, limit);
+			ensure!(!path.is_empty(), Error::<T>::NotHasPath);
+			let min_target_amount =
+				Self::twap_target_amount(&path, supply_amount, T::MaxRebalanceSlippage::get())
+					.ok_or(Error::<T>::NoTwap)?;
+			let limit = SwapLimit::ExactSupply(supply_amount, min_target_amount);
+			let (_, actual_out) =
+				T::DexManager::swap_with_specific_path(&Self::account_id(), &path, limit)?;
+			Ok(actual_out)
+		}
+
+		/// Portfolio tokens of `port_id` minted for `amount` of the native currency, which
+		/// `acquire` spends on the components, given the spend on every component by its rate, and
+		/// returns the amount of every component acquired. Both `buy` and `quote_buy` are valued
+		/// here.
+		///
+		/// The contribution and the holdings are valued at the same prices, taken before the
+		/// component swaps, so the buyer bears the cost of the swaps instead of the holders.
+		fn buy_port_amount(
+			port_id: T::AssetId,
+			amount: T::Balance,
+			acquire: impl FnOnce(
+				&[T::AssetId],
+				&[T::Balance],
+			) -> sp_std::result::Result<Vec<T::Balance>, DispatchError>,
+		) -> sp_std::result::Result<T::Balance, DispatchError> {
+			let ids = Components::<T>::get(port_id);
+			let spend: Vec<T::Balance> =
+				Rates::<T>::get(port_id).iter().map(|rate| *rate * amount).collect();
+			let prices = Self::component_prices(port_id, &ids, &spend)?;
+			let nav = Self::value_at(&prices, &Self::holdings_of(port_id, &ids))?;
+			let supply =
+				<pallet_assets::Pallet<T> as Inspect<T::AccountId>>::total_issuance(port_id);
+
+			let balances = acquire(&ids, &spend)?;
+			let contribution = Self::value_at(&prices, &balances)?;
+			Self::port_amount_for(contribution, nav, supply)
+		}
+
+		/// Portfolio tokens minted for a contribution worth `contribution` in the native currency,
+		/// given the NAV and token supply before the contribution. The contribution and the NAV
+		/// must be valued at the same prices.
+		fn port_amount_for(
+			contribution: T::Balance,
+			nav: T::Balance,
+			supply: T::Balance,
+		) -> sp_std::result::Result<T::Balance, DispatchError> {
+			if supply.is_zero() {
+				return Ok(contribution)
+			}
+			ensure!(!nav.is_zero(), Error::<T>::WorthlessPortfolio);
+			Ratio::checked_from_rational(contribution, nav)
+				.and_then(|n| n.checked_mul_int(supply))
+				.ok_or_else(|| ArithmeticError::Overflow.into())
+		}
+
+		/// Components, rates, owner and holdings of every portfolio.
+		pub fn portfolios() -> Vec<PortfolioInfo<T::AssetId, T::AccountId, T::Balance>> {
+			Components::<T>::iter()
+				.map(|(port_id, components)| PortfolioInfo {
+					port_id,
+					components,
+					rates: Rates::<T>::get(port_id),
+					owner: Owners::<T>::get(port_id),
+					holdings: Holdings::<T>::iter_prefix(port_id).collect(),
+				})
+				.collect()
+		}
+
+		/// Quote `buy` of `port_id` with `amount` of the native currency, before pending fees are
+		/// accrued.
+		pub fn quote_buy(
+			port_id: T::AssetId,
+			amount: T::Balance,
+		) -> Option<PortfolioQuote<T::AssetId, T::Balance>> {
+			if !Components::<T>::contains_key(port_id) ||
+				Statuses::<T>::get(port_id) != PortfolioStatus::Active
+			{
+				return None
+			}
+			let base_id = T::NativeAssetId::get();
+			let mut routes = Vec::new();
+			let amount = Self::buy_port_amount(port_id, amount, |ids, spend| {
+				ids.iter()
+					.zip(spend.iter())
+					.map(|(asset_id, supply_amount)| -> sp_std::result::Result<_, DispatchError> {
+						let (route, target_amount) = if *asset_id == base_id {
+							(vec![base_id], *supply_amount)
+						} else {
+							let limit = SwapLimit::ExactSupply(*supply_amount, T::Balance::zero());
+							let route = Self::swap_path(port_id, base_id, *asset_id, limit.clone());
+							let (_, target_amount) = T::DexManager::get_swap_amount(&route, limit)
+								.ok_or(Error::<T>::NoPrice)?;
+							(route, target_amount)
+						};
+						routes.push(RouteQuote {
+							asset_id: *asset_id,
+							route,
+							supply_amount: *supply_amount,
+							target_amount,
+						});
+						Ok(target_amount)
+					})
+					.collect()
+			})
+			.ok()?;
+			Some(PortfolioQuote { amount, routes })
+		}
+
+		/// Quote `sell` of `amount` portfolio tokens of `port_id` for `dst_id`, before pending
+		/// fees are accrued.
+		pub fn quote_sell(
+			port_id: T::AssetId,
+			dst_id: T::AssetId,
+			amount: T::Balance,
+		) -> Option<PortfolioQuote<T::AssetId, T::Balance>> {
+			if !Components::<T>::contains_key(port_id) ||
+				Statuses::<T>::get(port_id) != PortfolioStatus::Active
+			{
+				return None
+			}
+			let whole =
+				<pallet_assets::Pallet<T> as Inspect<T::AccountId>>::total_issuance(port_id);
+			if amount > whole {
+				return None
+			}
+			let perbill_rate = Perbill::from_rational(amount, whole);
+
+			let mut routes = Vec::new();
+			let mut total = T::Balance::zero();
+			for asset_id in Components::<T>::get(port_id) {
+				let supply_amount = perbill_rate * Holdings::<T>::get(port_id, asset_id);
+				if supply_amount.is_zero() {
+					continue
+				}
+				let (route, target_amount) = if asset_id == dst_id {
+					(vec![dst_id], supply_amount)
+				} else {
+					let limit = SwapLimit::ExactSupply(supply_amount, T::Balance::zero());
+					let route = Self::swap_path(port_id, asset_id, dst_id, limit.clone());
+					let (_, target_amount) = T::DexManager::get_swap_amount(&route, limit)?;
+					(route, target_amount)
+				};
+				total = total.saturating_add(target_amount);
+				routes.push(RouteQuote { asset_id, route, supply_amount, target_amount });
+			}
+			Some(PortfolioQuote { amount: total, routes })
+		}
+
+		/// Net asset value of `port_id` in the native currency.
+		///
+		/// Every holding is valued at the amount of the native currency it would fetch when sold
+		/// through the DEX along the portfolio's swap paths. Fails if a holding can't be sold.
+		pub fn net_asset_value(
+			port_id: T::AssetId,
+		) -> sp_std::result::Result<T::Balance, DispatchError> {
+			let ids = Components::<T>::get(port_id);
+			Self::component_values(port_id, &ids)?.into_iter().try_fold(
+				T::Balance::zero(),
+				|acc, value| {
+					acc.checked_add(&value).ok_or_else(|| ArithmeticError::Overflow.into())
+				},
+			)
+		}
+
+		/// Net asset value per portfolio token of `port_id`, `None` if the portfolio does not
+		/// exist or a holding can't be sold. A portfolio without issued tokens is priced at one.
+		pub fn nav_per_share(port_id: T::AssetId) -> Option<Price> {
+			if !Components::<T>::contains_key(port_id) {
+				return None
+			}
+			let supply =
+				<pallet_assets::Pallet<T> as Inspect<T::AccountId>>::total_issuance(port_id);
+			if supply.is_zero() {
+				return Some(Price::one())
+			}
+			Price::checked_from_rational(Self::net_asset_value(port_id).ok()?, supply)
+		}
+
+		/// Net asset value per portfolio token of every portfolio.
+		pub fn navs_per_share() -> Vec<(T::AssetId, Price)> {
+			Components::<T>::iter_keys()
+				.filter_map(|port_id| Self::nav_per_share(port_id).map(|nav| (port_id, nav)))
+				.collect()
+		}
+
+		/// The amount of the native currency `amount` of `asset_id` fetches when sold through the
+		/// DEX along the swap paths of `port_id`.
+		fn value_in_base(
+			port_id: T::AssetId,
+			asset_id: T::AssetId,
+			amount: T::Balance,
+		) -> sp_std::result::Result<T::Balance, DispatchError> {
+			let base_id = T::NativeAssetId::get();
+			if asset_id == base_id || amount.is_zero() {
+				return Ok(amount)
+			}
+			let limit = SwapLimit::ExactSupply(amount, T::Balance::zero());
+			let path = Self::swap_path(port_id, asset_id, base_id, limit.clone());
+			if let Some((_, target_amount)) = T::DexManager::get_swap_amount(&path, limit) {
+				return Ok(target_amount)
+			}
+			// an amount too small to fetch a single unit is worth nothing, but only if the asset
+			// can be sold at all
+			ensure!(Self::is_sellable(port_id, asset_id), Error::<T>::NoPrice);
+			Ok(Zero::zero())
+		}
+
+		/// Whether `asset_id` trades against the native currency in a pool with liquidity, directly
+		/// or along one of the swap paths of `port_id`.
+		fn is_sellable(port_id: T::AssetId, asset_id: T::AssetId) -> bool {
+			let is_live = |hop: &[T::AssetId]| {
+				let (pool_a, pool_b) = T::DexManager::get_liquidity_pool(hop[0], hop[1]);
+				T::DexManager::is_trading_pair_enabled(hop[0], hop[1]) &&
+					!pool_a.is_zero() &&
+					!pool_b.is_zero()
+			};
+			is_live(&[asset_id, T::NativeAssetId::get()][..]) ||
+				SwapPaths::<T>::get(port_id)
+					.iter()
+					.any(|path| path.last() == Some(&asset_id) && path.windows(2).all(&is_live))
+		}
+
+		/// Net asset value per portfolio token of `port_id`, with every holding valued at the TWAP
+		/// of its path to the native currency. `None` if there are no tokens or a holding has no
+		/// price history over `TwapWindow`.
+		fn twap_nav_per_share(port_id: T::AssetId) -> Option<Price> {
+			let supply =
+				<pallet_assets::Pallet<T> as Inspect<T::AccountId>>::total_issuance(port_id);
+			if supply.is_zero() {
+				return None
+			}
+			let base_id = T::NativeAssetId::get();
+			let nav = Holdings::<T>::iter_prefix(port_id).try_fold(
+				T::Balance::zero(),
+				|acc, (asset_id, holding)| {
+					let value = if asset_id == base_id || holding.is_zero() {
+						holding
+					} else {
+						let limit = SwapLimit::ExactSupply(holding, T::Balance::zero());
+						let path = Self::swap_path(port_id, asset_id, base_id, limit);
+						Self::twap_along(&path)?.checked_mul_int(holding)?
+					};
+					acc.checked_add(&value)
+				},
+			)?;
+			Price::checked_from_rational(nav, supply)
+		}
+
+		/// Time-weighted average price of the first asset of `path` in its last asset, `None` if
+		/// `path` has no hop or a hop has no price history over `TwapWindow`.
+		fn twap_along(path: &[T::AssetId]) -> Option<Price> {
+			if path.len() < 2 {
+				return None
+			}
+			path.windows(2).try_fold(Price::one(), |price, hop| {
+				T::PriceOracle::get_twap(hop[0], hop[1], T::TwapWindow::get())
+					.and_then(|hop_price| price.checked_mul(&hop_price))
+			})
+		}
+
+		/// The least output accepted from swapping `supply_amount` along `path`: its value at the
+		/// TWAP less `MaxSwapSlippage`.
+		///
+		/// A path without price history is only bounded by the caller's check of the total, which
+		/// is the minimum the trader signed for.
+		fn min_target_amount(path: &[T::AssetId], supply_amount: T::Balance) -> T::Balance {
+			Self::twap_target_amount(path, supply_amount, T::MaxSwapSlippage::get())
+				.unwrap_or_else(Zero::zero)
+		}
+
+		/// The value of `supply_amount` at the TWAP of `path` less `slippage`, `None` if the path
+		/// has no price history over `TwapWindow`.
+		fn twap_target_amount(
+			path: &[T::AssetId],
+			supply_amount: T::Balance,
+			slippage: Permill,
+		) -> Option<T::Balance> {
+			Self::twap_along(path)
+				.and_then(|price| price.checked_mul_int(supply_amount))
+				.map(|amount| amount.saturating_sub(slippage * amount))
+		}
+
+		/// The best path from `supply_id` to `target_id` found by the DEX route search, also
+		/// considering the swap paths of `port_id` between the two, in either direction.
+		fn swap_path(
+			port_id: T::AssetId,
+			supply_id: T::AssetId,
+			target_id: T::AssetId,
+			limit: SwapLimit<T::Balance>,
+		) -> Vec<T::AssetId> {
+			let paths = SwapPaths::<T>::get(port_id)
+				.into_iter()
+				.filter_map(|mut path| {
+					if path.first() == Some(&target_id) && path.last() == Some(&supply_id) {
+						path.reverse();
+					}
+					if path.first() == Some(&supply_id) && path.last() == Some(&target_id) {
+						Some(path)
+					} else {
+						None
+					}
+				})
+				.collect();
+			T::DexManager::get_best_price_swap_path(supply_id, target_id, limit, paths)
+				.unwrap_or_default()
+		}
+
+		fn ensure_active(port_id: T::AssetId) -> DispatchResult {
+			ensure!(Statuses::<T>::get(port_id) == PortfolioStatus::Active, Error::<T>::NotActive);
+			Ok(())
+		}
+
+		fn ensure_not_closed(port_id: T::AssetId) -> DispatchResult {
+			ensure!(
+				Statuses::<T>::get(port_id) != PortfolioStatus::Closed,
+				Error::<T>::AlreadyClosed
+			);
+			Ok(())
+		}
+
+		/// Ensure `rates` add up to exactly one.
+		fn ensure_valid_rates(rates: &[Perbill]) -> DispatchResult {
+			let sum = rates
+				.iter()
+				.fold(0u64, |acc, rate| acc.saturating_add(rate.deconstruct().into()));
+			ensure!(sum == u64::from(Perbill::one().deconstruct()), Error::<T>::NotEqueOne);
+			Ok(())
+		}
+
+		fn ensure_not_expired(deadline: Option<T::BlockNumber>) -> DispatchResult {
+			if let Some(deadline) = deadline {
+				ensure!(frame_system::Pallet::<T>::block_number() <= deadline, Error::<T>::Expired);
+			}
+			Ok(())
+		}
+
+		fn ensure_owner_or_update_origin(
+			origin: OriginFor<T>,
+			port_id: T::AssetId,
+		) -> DispatchResult {
+			ensure!(Components::<T>::contains_key(port_id), Error::<T>::NotHasAsset);
+			match T::UpdateOrigin::try_origin(origin) {
+				Ok(_) => Ok(()),
+				Err(origin) => {
+					let who = ensure_signed(origin)?;
+					ensure!(Owners::<T>::get(port_id) == Some(who), Error::<T>::NotOwner);
+					Ok(())
+				},
+			}
+		}
+
+		fn increase_holding(
+			port_id: T::AssetId,
+			asset_id: T::AssetId,
+			amount: T::Balance,
+		) -> DispatchResult {
+			Holdings::<T>::try_mutate(port_id, asset_id, |holding| -> DispatchResult {
+				*holding = holding.checked_add(&amount).ok_or(ArithmeticError::Overflow)?;
+				Ok(())
+			})
+		}
+
+		fn decrease_holding(
+			port_id: T::AssetId,
+			asset_id: T::AssetId,
+			amount: T::Balance,
+		) -> DispatchResult {
+			Holdings::<T>::try_mutate(port_id, asset_id, |holding| -> DispatchResult {
+				*holding = holding.checked_sub(&amount).ok_or(ArithmeticError::Underflow)?;
+				Ok(())
+			})
+		}
 	}
 }
@@ -0,0 +1,85 @@
+//! Storage migrations for the portfolio pallet.
+
+use super::*;
+use frame_support::{
+	log,
+	storage::migration::take_storage_value,
+	traits::{Get, GetStorageVersion, PalletInfoAccess, StorageVersion},
+	weights::Weight,
+};
+use sp_runtime::traits::Saturating;
+use sp_std::vec::Vec;
+
+/// Moves the global `PortTotalBalances` vector into the per-portfolio `Holdings` map.
+///
+/// The old vector was shared by every portfolio and indexed by component position, so it can
+/// only be attributed unambiguously when exactly one portfolio has a matching number of
+/// components. Otherwise the value is dropped and `UpdateOrigin` restores the holdings from the
+/// pallet account's balances with `force_set_holdings`.
+pub mod v1 {
+	use super::*;
+
+	pub fn migrate<T: Config>() -> Weight {
+		let on_chain_version = Pallet::<T>::on_chain_storage_version();
+		if on_chain_version >= 1 {
+			return T::DbWeight::get().reads(1)
+		}
+
+		let pallet_name = <Pallet<T> as PalletInfoAccess>::name();
+		let maybe_balances = take_storage_value::<Vec<T::Balance>>(
+			pallet_name.as_bytes(),
+			b"PortTotalBalances",
+			&[],
+		);
+		let mut reads: Weight = 2;
+		let mut writes: Weight = 2;
+
+		if let Some(balances) = maybe_balances {
+			let candidates: Vec<(T::AssetId, Vec<T::AssetId>)> = Components::<T>::iter()
+				.filter(|(_, components)| components.len() == balances.len())
+				.collect();
+			reads = reads.saturating_add(Components::<T>::iter().count() as Weight);
+
+			match candidates.as_slice() {
+				[(port_id, components)] => {
+					for (asset_id, balance) in components.iter().zip(balances.iter()) {
+						Holdings::<T>::mutate(port_id, asset_id, |holding| {
+							*holding = holding.saturating_add(*balance)
+						});
+						writes = writes.saturating_add(1);
+					}
+				},
+				_ => log::warn!(
+					target: "runtime::portfolio",
+					"PortTotalBalances matches {} portfolios, holdings were not migrated",
+					candidates.len(),
+				),
+			}
+		}
+
+		StorageVersion::new(1).put::<Pallet<T>>();
+		T::DbWeight::get().reads_writes(reads, writes)
+	}
+}
+
+/// Drops the global `SwapPaths` value. Swap path joints are now set per portfolio by its owner.
+pub mod v2 {
+	use super::*;
+
+	pub fn migrate<T: Config>() -> Weight {
+		let on_chain_version = Pallet::<T>::on_chain_storage_version();
+		if on_chain_version >= 2 {
+			return T::DbWeight::get().reads(1)
+		}
+
+		let pallet_name = <Pallet<T> as PalletInfoAccess>::name();
+		if take_storage_value::<Vec<Vec<T::AssetId>>>(pallet_name.as_bytes(), b"SwapPaths", &[])
+			.is_some()
+		{
+			log::info!(target: "runtime::portfolio", "removed the global SwapPaths value");
+		}
+
+		StorageVersion::new(2).put::<Pallet<T>>();
+		T::DbWeight::get().reads_writes(2, 2)
+	}
+}
@@ -1,20 +1,834 @@
-use crate::{mock::*, Error};
-use frame_support::{assert_noop, assert_ok};
+//! Unit tests for the portfolio pallet.
+
+use crate::{migrations, mock::*, Error, Holdings, Rates};
+use frame_support::{
+	assert_noop, assert_ok,
+	storage::migration::{get_storage_value, put_storage_value},
+	traits::{
+		fungibles::metadata::Inspect as _, GetStorageVersion, Hooks, ReservableCurrency,
+		StorageVersion,
+	},
+	weights::Weight,
+};
+use pallet_dex::{DEXManager, Ratio};
+use sp_runtime::{traits::Zero, FixedPointNumber, Perbill, Permill};
+
+fn create_portfolio(port_id: AssetId, components: Vec<AssetId>, rates: Vec<Perbill>) {
+	assert_ok!(Portfolio::create_portofio(
+		Origin::signed(ALICE),
+		port_id,
+		components,
+		rates,
+		b"Portfolio".to_vec(),
+		b"PORT".to_vec(),
+		12,
+	));
+}
+
+fn half_and_half(port_id: AssetId) {
+	create_portfolio(port_id, vec![DOT, BTC], vec![Perbill::from_percent(50); 2]);
+}
+
+#[test]
+fn buy_credits_holdings_of_its_portfolio_only() {
+	ExtBuilder::default().build().execute_with(|| {
+		half_and_half(PORT);
+		half_and_half(PORT + 1);
+
+		assert_ok!(Portfolio::buy(Origin::signed(BOB), PORT, 10_000, 0, None));
+		assert!(Portfolio::holdings(PORT, DOT) > 0);
+		assert!(Portfolio::holdings(PORT, BTC) > 0);
+		assert_eq!(Portfolio::holdings(PORT + 1, DOT), 0);
+		assert_eq!(Portfolio::holdings(PORT + 1, BTC), 0);
+
+		assert_ok!(Portfolio::buy(Origin::signed(BOB), PORT + 1, 4_000, 0, None));
+		assert!(Portfolio::holdings(PORT + 1, DOT) < Portfolio::holdings(PORT, DOT));
+		assert_eq!(
+			Assets::balance(DOT, Portfolio::account_id()),
+			Portfolio::holdings(PORT, DOT) + Portfolio::holdings(PORT + 1, DOT)
+		);
+	});
+}
+
+#[test]
+fn sell_takes_from_its_portfolio_only() {
+	ExtBuilder::default().build().execute_with(|| {
+		half_and_half(PORT);
+		half_and_half(PORT + 1);
+		assert_ok!(Portfolio::buy(Origin::signed(BOB), PORT, 10_000, 0, None));
+		assert_ok!(Portfolio::buy(Origin::signed(BOB), PORT + 1, 10_000, 0, None));
+		let other_dot = Portfolio::holdings(PORT + 1, DOT);
+
+		let port_amount = Assets::balance(PORT, BOB);
+		assert_ok!(Portfolio::sell(Origin::signed(BOB), PORT, NATIVE, port_amount, 0, None));
+		assert_eq!(Portfolio::holdings(PORT, DOT), 0);
+		assert_eq!(Portfolio::holdings(PORT, BTC), 0);
+		assert_eq!(Portfolio::holdings(PORT + 1, DOT), other_dot);
+	});
+}
+
+#[test]
+fn sell_rejects_more_than_the_supply() {
+	ExtBuilder::default().build().execute_with(|| {
+		half_and_half(PORT);
+		assert_ok!(Portfolio::buy(Origin::signed(BOB), PORT, 10_000, 0, None));
+		let supply = Assets::total_supply(PORT);
+
+		assert_noop!(
+			Portfolio::sell(Origin::signed(BOB), PORT, NATIVE, supply + 1, 0, None),
+			Error::<Test>::NotHasEnoughAsset
+		);
+	});
+}
+
+#[test]
+fn migrate_v1_moves_port_total_balances_into_holdings() {
+	ExtBuilder::default().build().execute_with(|| {
+		half_and_half(PORT);
+		create_portfolio(PORT + 1, vec![DOT], vec![Perbill::one()]);
+		StorageVersion::new(0).put::<Portfolio>();
+		put_storage_value(b"Portfolio", b"PortTotalBalances", &[], vec![5u128, 7u128]);
+
+		migrations::v1::migrate::<Test>();
+
+		assert_eq!(Portfolio::holdings(PORT, DOT), 5);
+		assert_eq!(Portfolio::holdings(PORT, BTC), 7);
+		assert_eq!(Portfolio::holdings(PORT + 1, DOT), 0);
+		assert!(
+			get_storage_value::<Vec<Balance>>(b"Portfolio", b"PortTotalBalances", &[]).is_none()
+		);
+		assert_eq!(Portfolio::on_chain_storage_version(), 1);
+	});
+}
+
+#[test]
+fn migrate_v1_drops_ambiguous_port_total_balances() {
+	ExtBuilder::default().build().execute_with(|| {
+		half_and_half(PORT);
+		half_and_half(PORT + 1);
+		StorageVersion::new(0).put::<Portfolio>();
+		put_storage_value(b"Portfolio", b"PortTotalBalances", &[], vec![5u128, 7u128]);
+
+		migrations::v1::migrate::<Test>();
+
+		assert_eq!(Holdings::<Test>::iter().count(), 0);
+		assert!(
+			get_storage_value::<Vec<Balance>>(b"Portfolio", b"PortTotalBalances", &[]).is_none()
+		);
+		assert_eq!(Portfolio::on_chain_storage_version(), 1);
+	});
+}
+
+#[test]
+fn force_set_holdings_restores_dropped_holdings() {
+	ExtBuilder::default().build().execute_with(|| {
+		half_and_half(PORT);
+		half_and_half(PORT + 1);
+		// what the pallet account held under the dropped `PortTotalBalances`
+		assert_ok!(Assets::transfer(Origin::signed(ALICE), DOT, Portfolio::account_id(), 5));
+		assert_ok!(Assets::transfer(Origin::signed(ALICE), BTC, Portfolio::account_id(), 7));
+
+		assert_noop!(
+			Portfolio::force_set_holdings(Origin::signed(ALICE), PORT, vec![(DOT, 5)]),
+			sp_runtime::DispatchError::BadOrigin
+		);
+		assert_noop!(
+			Portfolio::force_set_holdings(Origin::root(), PORT, vec![(NATIVE, 5)]),
+			Error::<Test>::NotHasAsset
+		);
+		assert_noop!(
+			Portfolio::force_set_holdings(Origin::root(), PORT, vec![(DOT, 6)]),
+			Error::<Test>::HoldingsNotBacked
+		);
+
+		assert_ok!(Portfolio::force_set_holdings(Origin::root(), PORT, vec![(DOT, 3), (BTC, 7)]));
+		System::assert_last_event(Event::Portfolio(crate::Event::HoldingsSet(PORT)));
+		assert_ok!(Portfolio::force_set_holdings(Origin::root(), PORT + 1, vec![(DOT, 2)]));
+		assert_noop!(
+			Portfolio::force_set_holdings(Origin::root(), PORT + 1, vec![(BTC, 1)]),
+			Error::<Test>::HoldingsNotBacked
+		);
+
+		assert_eq!(Portfolio::holdings(PORT, DOT), 3);
+		assert_eq!(Portfolio::holdings(PORT, BTC), 7);
+		assert_eq!(Portfolio::holdings(PORT + 1, DOT), 2);
+		assert_eq!(Portfolio::holdings(PORT + 1, BTC), 0);
+	});
+}
 
 #[test]
-fn it_works_for_default_value() {
-	new_test_ext().execute_with(|| {
-		// Dispatch a signed extrinsic.
-		assert_ok!(TemplateModule::do_something(Origin::signed(1), 42));
-		// Read pallet storage and assert an expected result.
-		assert_eq!(TemplateModule::something(), Some(42));
+fn migrate_v1_runs_once() {
+	ExtBuilder::default().build().execute_with(|| {
+		half_and_half(PORT);
+		StorageVersion::new(1).put::<Portfolio>();
+		put_storage_value(b"Portfolio", b"PortTotalBalances", &[], vec![5u128, 7u128]);
+
+		migrations::v1::migrate::<Test>();
+
+		assert_eq!(Portfolio::holdings(PORT, DOT), 0);
+		assert!(
+			get_storage_value::<Vec<Balance>>(b"Portfolio", b"PortTotalBalances", &[]).is_some()
+		);
 	});
 }
 
 #[test]
-fn correct_error_for_none_value() {
-	new_test_ext().execute_with(|| {
-		// Ensure the expected error is thrown when no value is present.
-		assert_noop!(TemplateModule::cause_error(Origin::signed(1)), Error::<Test>::NoneValue);
+fn migrate_v2_removes_the_global_swap_paths() {
+	ExtBuilder::default().build().execute_with(|| {
+		StorageVersion::new(1).put::<Portfolio>();
+		put_storage_value(b"Portfolio", b"SwapPaths", &[], vec![vec![NATIVE, DOT]]);
+
+		migrations::v2::migrate::<Test>();
+
+		assert!(get_storage_value::<Vec<Vec<AssetId>>>(b"Portfolio", b"SwapPaths", &[]).is_none());
+		assert_eq!(Portfolio::on_chain_storage_version(), 2);
+	});
+}
+
+#[test]
+fn set_swap_path_requires_owner_or_update_origin() {
+	ExtBuilder::default().build().execute_with(|| {
+		half_and_half(PORT);
+
+		assert_noop!(
+			Portfolio::set_swap_path(Origin::signed(BOB), PORT, vec![vec![NATIVE, DOT]]),
+			Error::<Test>::NotOwner
+		);
+		assert_ok!(Portfolio::set_swap_path(Origin::signed(ALICE), PORT, vec![vec![NATIVE, DOT]]));
+		assert_ok!(Portfolio::set_swap_path(Origin::root(), PORT, vec![vec![NATIVE, BTC]]));
+		assert_eq!(Portfolio::swap_paths(PORT), vec![vec![NATIVE, BTC]]);
+		assert_eq!(Portfolio::swap_paths(PORT + 1), Vec::<Vec<AssetId>>::new());
+	});
+}
+
+#[test]
+fn set_swap_path_rejects_paths_not_leading_from_native_to_a_component() {
+	ExtBuilder::default().build().execute_with(|| {
+		create_portfolio(PORT, vec![DOT], vec![Perbill::one()]);
+
+		for path in [vec![], vec![NATIVE], vec![DOT, NATIVE], vec![NATIVE, BTC]] {
+			assert_noop!(
+				Portfolio::set_swap_path(Origin::signed(ALICE), PORT, vec![path]),
+				Error::<Test>::InvalidSwapPath
+			);
+		}
+	});
+}
+
+#[test]
+fn set_swap_path_rejects_disabled_hops() {
+	ExtBuilder::default().build().execute_with(|| {
+		half_and_half(PORT);
+
+		assert_noop!(
+			Portfolio::set_swap_path(Origin::signed(ALICE), PORT, vec![vec![NATIVE, BTC, DOT]]),
+			Error::<Test>::InvalidSwapPath
+		);
+	});
+}
+
+#[test]
+fn buy_and_sell_fail_after_the_deadline() {
+	ExtBuilder::default().build().execute_with(|| {
+		half_and_half(PORT);
+		System::set_block_number(5);
+
+		assert_noop!(
+			Portfolio::buy(Origin::signed(BOB), PORT, 10_000, 0, Some(4)),
+			Error::<Test>::Expired
+		);
+		assert_ok!(Portfolio::buy(Origin::signed(BOB), PORT, 10_000, 0, Some(5)));
+		assert_noop!(
+			Portfolio::sell(Origin::signed(BOB), PORT, NATIVE, 1_000, 0, Some(4)),
+			Error::<Test>::Expired
+		);
+	});
+}
+
+#[test]
+fn buy_fails_below_the_minimum_port_amount() {
+	ExtBuilder::default().build().execute_with(|| {
+		half_and_half(PORT);
+
+		assert_noop!(
+			Portfolio::buy(Origin::signed(BOB), PORT, 10_000, 1_000_000, None),
+			Error::<Test>::InsufficientPortAmount
+		);
+	});
+}
+
+#[test]
+fn sell_fails_below_the_minimum_destination_amount() {
+	ExtBuilder::default().build().execute_with(|| {
+		half_and_half(PORT);
+		assert_ok!(Portfolio::buy(Origin::signed(BOB), PORT, 10_000, 0, None));
+		let port_amount = Assets::balance(PORT, BOB);
+
+		assert_noop!(
+			Portfolio::sell(Origin::signed(BOB), PORT, NATIVE, port_amount, 10_000, None),
+			Error::<Test>::InsufficientTargetAmount
+		);
+	});
+}
+
+#[test]
+fn component_swaps_are_bounded_by_the_twap() {
+	ExtBuilder::default().build().execute_with(|| {
+		half_and_half(PORT);
+		System::set_block_number(20);
+
+		// within MaxSwapSlippage of the TWAP
+		assert_ok!(Portfolio::buy(Origin::signed(BOB), PORT, 10_000, 0, None));
+
+		// a front-running swap moves the spot price of DOT by about a fifth
+		assert_ok!(Dex::swap_with_exact_supply(
+			Origin::signed(LP),
+			vec![NATIVE, DOT],
+			1_000_000,
+			0
+		));
+		assert_noop!(
+			Portfolio::buy(Origin::signed(BOB), PORT, 10_000, 0, None),
+			pallet_dex::Error::<Test>::InsufficientTargetAmount
+		);
+	});
+}
+
+#[test]
+fn first_buy_mints_the_value_of_the_contribution() {
+	ExtBuilder::default().build().execute_with(|| {
+		half_and_half(PORT);
+
+		assert_ok!(Portfolio::buy(Origin::signed(BOB), PORT, 10_000, 0, None));
+		let minted = Assets::balance(PORT, BOB);
+		// the swaps cost the trading fee and some price impact
+		assert!(minted > 9_900 && minted < 10_000);
+		assert_eq!(Assets::total_supply(PORT), minted);
+	});
+}
+
+#[test]
+fn buy_does_not_dilute_the_holders() {
+	ExtBuilder::default().build().execute_with(|| {
+		half_and_half(PORT);
+		assert_ok!(Portfolio::buy(Origin::signed(ALICE), PORT, 10_000, 0, None));
+		let nav_per_share = Portfolio::nav_per_share(PORT).unwrap();
+		let alice_tokens = Assets::balance(PORT, ALICE);
+
+		assert_ok!(Portfolio::buy(Origin::signed(BOB), PORT, 50_000, 0, None));
+		assert!(Portfolio::nav_per_share(PORT).unwrap() >= nav_per_share);
+		// the second buyer adds five times the components for about five times the tokens
+		let bob_tokens = Assets::balance(PORT, BOB);
+		assert!(bob_tokens <= alice_tokens * 5 && bob_tokens > alice_tokens * 49 / 10);
+	});
+}
+
+#[test]
+fn quote_buy_matches_buy() {
+	ExtBuilder::default().build().execute_with(|| {
+		half_and_half(PORT);
+		assert_ok!(Portfolio::buy(Origin::signed(ALICE), PORT, 10_000, 0, None));
+
+		let quote = Portfolio::quote_buy(PORT, 30_000).unwrap();
+		assert_ok!(Portfolio::buy(Origin::signed(BOB), PORT, 30_000, 0, None));
+		assert_eq!(Assets::balance(PORT, BOB), quote.amount);
+	});
+}
+
+#[test]
+fn sell_and_redeem_pay_out_the_share_of_the_holdings() {
+	ExtBuilder::default().build().execute_with(|| {
+		half_and_half(PORT);
+		assert_ok!(Portfolio::buy(Origin::signed(BOB), PORT, 10_000, 0, None));
+		let supply = Assets::total_supply(PORT);
+		let (dot, btc) = (Portfolio::holdings(PORT, DOT), Portfolio::holdings(PORT, BTC));
+
+		let quarter = Perbill::from_rational(supply / 4, supply);
+		assert_ok!(Portfolio::sell(Origin::signed(BOB), PORT, NATIVE, supply / 4, 0, None));
+		assert_eq!(Portfolio::holdings(PORT, DOT), dot - quarter * dot);
+		assert_eq!(Portfolio::holdings(PORT, BTC), btc - quarter * btc);
+
+		let supply = Assets::total_supply(PORT);
+		let (dot, btc) = (Portfolio::holdings(PORT, DOT), Portfolio::holdings(PORT, BTC));
+		let half = Ratio::checked_from_rational(supply / 2, supply).unwrap();
+		let dot_before = Assets::balance(DOT, BOB);
+		assert_ok!(Portfolio::redeem_in_kind(Origin::signed(BOB), PORT, supply / 2));
+		assert_eq!(Assets::balance(DOT, BOB) - dot_before, half.saturating_mul_int(dot));
+		assert_eq!(Portfolio::holdings(PORT, BTC), btc - half.saturating_mul_int(btc));
+	});
+}
+
+#[test]
+fn buy_fails_when_a_component_cannot_be_sold() {
+	ExtBuilder::default().build().execute_with(|| {
+		half_and_half(PORT);
+		assert_ok!(Portfolio::buy(Origin::signed(BOB), PORT, 10_000, 0, None));
+		assert_ok!(Dex::disable_trading_pair(Origin::root(), NATIVE, BTC));
+
+		assert!(Portfolio::net_asset_value(PORT).is_err());
+		assert_eq!(Portfolio::nav_per_share(PORT), None);
+		assert_noop!(
+			Portfolio::buy(Origin::signed(BOB), PORT, 10_000, 0, None),
+			Error::<Test>::NoPrice
+		);
+	});
+}
+
+#[test]
+fn dust_holdings_are_worth_nothing() {
+	ExtBuilder::default().build().execute_with(|| {
+		half_and_half(PORT);
+		Holdings::<Test>::insert(PORT, DOT, 1);
+
+		assert_eq!(Portfolio::net_asset_value(PORT), Ok(0));
+	});
+}
+
+#[test]
+fn buy_fails_when_outstanding_tokens_are_worthless() {
+	ExtBuilder::default().build().execute_with(|| {
+		half_and_half(PORT);
+		assert_ok!(Portfolio::buy(Origin::signed(BOB), PORT, 10_000, 0, None));
+		Holdings::<Test>::remove(PORT, DOT);
+		Holdings::<Test>::remove(PORT, BTC);
+
+		assert_noop!(
+			Portfolio::buy(Origin::signed(BOB), PORT, 10_000, 0, None),
+			Error::<Test>::WorthlessPortfolio
+		);
+	});
+}
+
+#[test]
+fn create_in_kind_deposits_the_share_of_every_holding() {
+	ExtBuilder::default().build().execute_with(|| {
+		half_and_half(PORT);
+		assert_noop!(
+			Portfolio::create_in_kind(Origin::signed(ALICE), PORT, 1_000),
+			Error::<Test>::EmptyPortfolio
+		);
+		assert_ok!(Portfolio::buy(Origin::signed(BOB), PORT, 10_000, 0, None));
+		assert_noop!(
+			Portfolio::create_in_kind(Origin::signed(ALICE), PORT, 0),
+			Error::<Test>::InsufficientPortAmount
+		);
+
+		let supply = Assets::total_supply(PORT);
+		let (dot, btc) = (Portfolio::holdings(PORT, DOT), Portfolio::holdings(PORT, BTC));
+		let half = Ratio::checked_from_rational(supply / 2, supply).unwrap();
+		let (alice_dot, alice_btc) = (Assets::balance(DOT, ALICE), Assets::balance(BTC, ALICE));
+		let native_reserves = Dex::get_liquidity_pool(NATIVE, DOT);
+
+		assert_ok!(Portfolio::create_in_kind(Origin::signed(ALICE), PORT, supply / 2));
+		assert_eq!(Assets::balance(PORT, ALICE), supply / 2);
+		assert_eq!(alice_dot - Assets::balance(DOT, ALICE), half.saturating_mul_int(dot) + 1);
+		assert_eq!(alice_btc - Assets::balance(BTC, ALICE), half.saturating_mul_int(btc) + 1);
+		assert_eq!(Portfolio::holdings(PORT, DOT), dot + half.saturating_mul_int(dot) + 1);
+		assert_eq!(Portfolio::holdings(PORT, BTC), btc + half.saturating_mul_int(btc) + 1);
+		// no swap is made
+		assert_eq!(Dex::get_liquidity_pool(NATIVE, DOT), native_reserves);
+	});
+}
+
+#[test]
+fn create_and_redeem_in_kind_round_trip_gains_nothing() {
+	ExtBuilder::default().build().execute_with(|| {
+		half_and_half(PORT);
+		assert_ok!(Portfolio::buy(Origin::signed(BOB), PORT, 10_000, 0, None));
+		let (alice_dot, alice_btc) = (Assets::balance(DOT, ALICE), Assets::balance(BTC, ALICE));
+		let nav_per_share = Portfolio::nav_per_share(PORT).unwrap();
+
+		let amount = Assets::total_supply(PORT) / 3;
+		assert_ok!(Portfolio::create_in_kind(Origin::signed(ALICE), PORT, amount));
+		assert_ok!(Portfolio::redeem_in_kind(Origin::signed(ALICE), PORT, amount));
+
+		assert_eq!(Assets::balance(PORT, ALICE), 0);
+		assert!(Assets::balance(DOT, ALICE) <= alice_dot);
+		assert!(Assets::balance(BTC, ALICE) <= alice_btc);
+		assert!(Portfolio::nav_per_share(PORT).unwrap() >= nav_per_share);
+	});
+}
+
+#[test]
+fn in_kind_requires_an_open_portfolio() {
+	ExtBuilder::default().build().execute_with(|| {
+		half_and_half(PORT);
+		assert_ok!(Portfolio::buy(Origin::signed(BOB), PORT, 10_000, 0, None));
+		let supply = Assets::total_supply(PORT);
+
+		assert_ok!(Portfolio::pause(Origin::signed(ALICE), PORT));
+		assert_noop!(
+			Portfolio::create_in_kind(Origin::signed(ALICE), PORT, 1_000),
+			Error::<Test>::NotActive
+		);
+		assert_noop!(
+			Portfolio::redeem_in_kind(Origin::signed(BOB), PORT, 1_000),
+			Error::<Test>::NotActive
+		);
+
+		assert_ok!(Portfolio::unpause(Origin::signed(ALICE), PORT));
+		assert_ok!(Portfolio::close_portfolio(Origin::signed(ALICE), PORT, false));
+		assert_noop!(
+			Portfolio::create_in_kind(Origin::signed(ALICE), PORT, 1_000),
+			Error::<Test>::NotActive
+		);
+		// holders exit a closed portfolio in kind
+		assert_ok!(Portfolio::redeem_in_kind(Origin::signed(BOB), PORT, supply));
+		assert_eq!(Assets::total_supply(PORT), 0);
+		assert!(Portfolio::holdings(PORT, DOT) <= 1);
+		assert!(Portfolio::holdings(PORT, BTC) <= 1);
+	});
+}
+
+#[test]
+fn set_fees_is_bounded_by_the_config() {
+	ExtBuilder::default().build().execute_with(|| {
+		half_and_half(PORT);
+		assert_noop!(
+			Portfolio::set_fees(
+				Origin::signed(ALICE),
+				PORT,
+				Permill::from_percent(6),
+				Permill::zero()
+			),
+			Error::<Test>::FeeTooHigh
+		);
+		assert_noop!(
+			Portfolio::set_fees(
+				Origin::signed(ALICE),
+				PORT,
+				Permill::zero(),
+				Permill::from_percent(31)
+			),
+			Error::<Test>::FeeTooHigh
+		);
+		assert_noop!(
+			Portfolio::set_fees(Origin::signed(BOB), PORT, Permill::zero(), Permill::zero()),
+			Error::<Test>::NotOwner
+		);
+	});
+}
+
+#[test]
+fn management_fee_accrues_per_block() {
+	ExtBuilder::default().build().execute_with(|| {
+		half_and_half(PORT);
+		assert_ok!(Portfolio::buy(Origin::signed(BOB), PORT, 10_000, 0, None));
+		assert_ok!(Portfolio::set_fees(
+			Origin::signed(ALICE),
+			PORT,
+			Permill::from_percent(5),
+			Permill::zero()
+		));
+		let supply = Assets::total_supply(PORT);
+
+		// a tenth of a year
+		System::set_block_number(101);
+		assert_ok!(Portfolio::accrue(Origin::signed(BOB), PORT));
+		let fee = Ratio::saturating_from_rational(5, 1_000).saturating_mul_int(supply);
+		assert_eq!(Assets::balance(PORT, ALICE), fee);
+		System::assert_last_event(Event::Portfolio(crate::Event::ManagementFeeAccrued(
+			PORT, ALICE, fee,
+		)));
+	});
+}
+
+#[test]
+fn performance_fee_is_valued_at_the_twap() {
+	ExtBuilder::default().build().execute_with(|| {
+		half_and_half(PORT);
+		// the high-water mark waits for a price history
+		assert_ok!(Portfolio::set_fees(
+			Origin::signed(ALICE),
+			PORT,
+			Permill::zero(),
+			Permill::from_percent(30)
+		));
+		assert!(Portfolio::fees(PORT).unwrap().high_water_mark.is_zero());
+
+		System::set_block_number(20);
+		assert_ok!(Portfolio::buy(Origin::signed(BOB), PORT, 10_000, 0, None));
+		assert_ok!(Portfolio::accrue(Origin::signed(BOB), PORT));
+		let high_water_mark = Portfolio::fees(PORT).unwrap().high_water_mark;
+		assert!(!high_water_mark.is_zero());
+
+		// moving the spot price of DOT up by about a fifth charges nothing within the block
+		assert_ok!(Dex::swap_with_exact_supply(
+			Origin::signed(LP),
+			vec![NATIVE, DOT],
+			1_000_000,
+			0
+		));
+		assert_ok!(Portfolio::accrue(Origin::signed(BOB), PORT));
+		assert_eq!(Assets::balance(PORT, ALICE), 0);
+		assert_eq!(Portfolio::fees(PORT).unwrap().high_water_mark, high_water_mark);
+
+		// once the price has held over the TWAP window the gain is charged
+		System::set_block_number(40);
+		assert_ok!(Portfolio::accrue(Origin::signed(BOB), PORT));
+		assert!(Assets::balance(PORT, ALICE) > 0);
+		assert!(Portfolio::fees(PORT).unwrap().high_water_mark > high_water_mark);
+	});
+}
+
+#[test]
+fn scheduled_rebalance_restores_the_target_rates() {
+	ExtBuilder::default().build().execute_with(|| {
+		half_and_half(PORT);
+		System::set_block_number(20);
+		assert_ok!(Portfolio::buy(Origin::signed(BOB), PORT, 10_000, 0, None));
+		assert_ok!(Portfolio::set_rebalance_schedule(
+			Origin::signed(ALICE),
+			PORT,
+			5,
+			Perbill::from_percent(1)
+		));
+		Rates::<Test>::insert(PORT, vec![Perbill::from_percent(70), Perbill::from_percent(30)]);
+
+		// not due yet
+		System::set_block_number(24);
+		Portfolio::on_idle(24, Weight::MAX);
+		assert!(Portfolio::drift(PORT).unwrap() > Perbill::from_percent(15));
+
+		System::set_block_number(25);
+		Portfolio::on_idle(25, Weight::MAX);
+		assert!(Portfolio::drift(PORT).unwrap() < Perbill::from_percent(1));
+		assert_eq!(Portfolio::rebalance_schedules(PORT).unwrap().next_rebalance, 30);
+		assert!(System::events().iter().any(|record| matches!(
+			record.event,
+			Event::Portfolio(crate::Event::Rebalanced(PORT, _))
+		)));
+	});
+}
+
+#[test]
+fn rebalance_is_skipped_beyond_the_slippage_of_the_twap() {
+	ExtBuilder::default().build().execute_with(|| {
+		half_and_half(PORT);
+		System::set_block_number(20);
+		assert_ok!(Portfolio::buy(Origin::signed(BOB), PORT, 10_000, 0, None));
+		assert_ok!(Portfolio::set_rebalance_schedule(
+			Origin::signed(ALICE),
+			PORT,
+			5,
+			Perbill::from_percent(1)
+		));
+		Rates::<Test>::insert(PORT, vec![Perbill::from_percent(70), Perbill::from_percent(30)]);
+
+		// a front-running swap moves the spot price of DOT by about a fifth
+		System::set_block_number(25);
+		assert_ok!(Dex::swap_with_exact_supply(
+			Origin::signed(LP),
+			vec![NATIVE, DOT],
+			1_000_000,
+			0
+		));
+		let (dot, btc) = (Portfolio::holdings(PORT, DOT), Portfolio::holdings(PORT, BTC));
+		Portfolio::on_idle(25, Weight::MAX);
+
+		assert_eq!(Portfolio::holdings(PORT, DOT), dot);
+		assert_eq!(Portfolio::holdings(PORT, BTC), btc);
+		assert_eq!(Portfolio::rebalance_schedules(PORT).unwrap().next_rebalance, 30);
+		System::assert_last_event(Event::Portfolio(crate::Event::RebalanceSkipped(
+			PORT,
+			pallet_dex::Error::<Test>::InsufficientTargetAmount.into(),
+		)));
+	});
+}
+
+#[test]
+fn rebalance_requires_a_price_history() {
+	ExtBuilder::default().build().execute_with(|| {
+		half_and_half(PORT);
+		assert_ok!(Portfolio::buy(Origin::signed(BOB), PORT, 10_000, 0, None));
+
+		assert_noop!(
+			Portfolio::change_rate(
+				Origin::signed(ALICE),
+				PORT,
+				vec![Perbill::from_percent(70), Perbill::from_percent(30)]
+			),
+			Error::<Test>::NoTwap
+		);
+	});
+}
+
+#[test]
+fn create_portofio_validates_the_components() {
+	ExtBuilder::default().build().execute_with(|| {
+		let create = |components: Vec<AssetId>, rates: Vec<Perbill>| {
+			Portfolio::create_portofio(
+				Origin::signed(ALICE),
+				PORT,
+				components,
+				rates,
+				b"Portfolio".to_vec(),
+				b"PORT".to_vec(),
+				12,
+			)
+		};
+		let third = Perbill::from_parts(333_333_333);
+
+		assert_noop!(create(vec![], vec![]), Error::<Test>::InvalidComponentCount);
+		assert_noop!(
+			create(vec![NATIVE, DOT, BTC, 3, 4], vec![Perbill::from_percent(20); 5]),
+			Error::<Test>::InvalidComponentCount
+		);
+		assert_noop!(create(vec![DOT, BTC], vec![Perbill::one()]), Error::<Test>::NotEquel);
+		assert_noop!(
+			create(vec![DOT, 7], vec![Perbill::from_percent(50); 2]),
+			Error::<Test>::NotExistId
+		);
+		assert_noop!(
+			create(vec![DOT, DOT], vec![Perbill::from_percent(50); 2]),
+			Error::<Test>::DuplicateComponent
+		);
+		assert_noop!(create(vec![NATIVE, DOT, BTC], vec![third; 3]), Error::<Test>::NotEqueOne);
+
+		assert_ok!(create(
+			vec![NATIVE, DOT, BTC],
+			vec![third, third, Perbill::from_parts(333_333_334)]
+		));
+		assert_eq!(Portfolio::components(PORT), vec![NATIVE, DOT, BTC]);
+	});
+}
+
+#[test]
+fn create_portofio_sets_the_metadata_and_reserves_the_deposit() {
+	ExtBuilder::default().build().execute_with(|| {
+		half_and_half(PORT);
+
+		assert_eq!(Assets::name(PORT), b"Portfolio".to_vec());
+		assert_eq!(Assets::symbol(PORT), b"PORT".to_vec());
+		assert_eq!(Assets::decimals(PORT), 12);
+		assert_eq!(Balances::reserved_balance(ALICE), 100);
+		assert_eq!(Portfolio::owners(PORT), Some(ALICE));
+		assert_eq!(Portfolio::deposits(PORT), Some((ALICE, 100)));
+
+		// the token id is taken
+		assert!(Portfolio::create_portofio(
+			Origin::signed(BOB),
+			PORT,
+			vec![DOT],
+			vec![Perbill::one()],
+			b"Portfolio".to_vec(),
+			b"PORT".to_vec(),
+			12,
+		)
+		.is_err());
+	});
+}
+
+#[test]
+fn transfer_ownership_hands_over_the_portfolio() {
+	ExtBuilder::default().build().execute_with(|| {
+		half_and_half(PORT);
+		assert_noop!(
+			Portfolio::transfer_ownership(Origin::signed(BOB), PORT, BOB),
+			Error::<Test>::NotOwner
+		);
+		assert_noop!(
+			Portfolio::transfer_ownership(Origin::signed(ALICE), PORT, ALICE),
+			Error::<Test>::NotChange
+		);
+
+		assert_ok!(Portfolio::transfer_ownership(Origin::signed(ALICE), PORT, BOB));
+		assert_eq!(Portfolio::owners(PORT), Some(BOB));
+		assert_noop!(Portfolio::pause(Origin::signed(ALICE), PORT), Error::<Test>::NotOwner);
+		assert_ok!(Portfolio::pause(Origin::signed(BOB), PORT));
+		// the creation deposit stays with the creator
+		assert_eq!(Portfolio::deposits(PORT), Some((ALICE, 100)));
+
+		assert_ok!(Portfolio::transfer_ownership(Origin::root(), PORT, ALICE));
+		assert_eq!(Portfolio::owners(PORT), Some(ALICE));
+	});
+}
+
+#[test]
+fn pause_suspends_trading() {
+	ExtBuilder::default().build().execute_with(|| {
+		half_and_half(PORT);
+		assert_ok!(Portfolio::buy(Origin::signed(BOB), PORT, 10_000, 0, None));
+		assert_noop!(Portfolio::unpause(Origin::signed(ALICE), PORT), Error::<Test>::NotChange);
+
+		assert_ok!(Portfolio::pause(Origin::signed(ALICE), PORT));
+		assert_noop!(
+			Portfolio::buy(Origin::signed(BOB), PORT, 10_000, 0, None),
+			Error::<Test>::NotActive
+		);
+		assert_noop!(
+			Portfolio::sell(Origin::signed(BOB), PORT, NATIVE, 1_000, 0, None),
+			Error::<Test>::NotActive
+		);
+		assert_noop!(Portfolio::pause(Origin::signed(ALICE), PORT), Error::<Test>::NotActive);
+
+		assert_ok!(Portfolio::unpause(Origin::root(), PORT));
+		assert_ok!(Portfolio::sell(Origin::signed(BOB), PORT, NATIVE, 1_000, 0, None));
+	});
+}
+
+#[test]
+fn closed_portfolio_is_liquidated_redeemed_and_destroyed() {
+	ExtBuilder::default().build().execute_with(|| {
+		half_and_half(PORT);
+		assert_ok!(Portfolio::buy(Origin::signed(BOB), PORT, 10_000, 0, None));
+		let supply = Assets::total_supply(PORT);
+		assert_noop!(
+			Portfolio::destroy_portfolio(Origin::signed(ALICE), PORT),
+			Error::<Test>::NotClosed
+		);
+
+		assert_ok!(Portfolio::close_portfolio(Origin::signed(ALICE), PORT, true));
+		assert_eq!(Portfolio::holdings(PORT, DOT), 0);
+		assert_eq!(Portfolio::holdings(PORT, BTC), 0);
+		let native = Portfolio::holdings(PORT, NATIVE);
+		assert!(native > 9_900);
+		assert_noop!(
+			Portfolio::buy(Origin::signed(BOB), PORT, 10_000, 0, None),
+			Error::<Test>::NotActive
+		);
+		assert_noop!(
+			Portfolio::destroy_portfolio(Origin::signed(ALICE), PORT),
+			Error::<Test>::PortfolioNotEmpty
+		);
+
+		// holders receive their share of the proceeds
+		let bob_native = Balances::free_balance(BOB);
+		assert_ok!(Portfolio::redeem_in_kind(Origin::signed(BOB), PORT, supply));
+		assert_eq!(Balances::free_balance(BOB) - bob_native, native);
+
+		assert_ok!(Portfolio::destroy_portfolio(Origin::signed(ALICE), PORT));
+		assert_eq!(Assets::maybe_total_supply(PORT), None);
+		assert!(Portfolio::components(PORT).is_empty());
+		assert_eq!(Portfolio::owners(PORT), None);
+		assert_eq!(Portfolio::deposits(PORT), None);
+		assert_eq!(Balances::reserved_balance(ALICE), 0);
+	});
+}
+
+#[test]
+fn quotes_match_the_trades() {
+	ExtBuilder::default().build().execute_with(|| {
+		half_and_half(PORT);
+		assert_ok!(Portfolio::buy(Origin::signed(ALICE), PORT, 10_000, 0, None));
+
+		let quote = Portfolio::quote_buy(PORT, 20_000).unwrap();
+		assert_eq!(quote.routes.len(), 2);
+		assert_eq!(quote.routes[0].route, vec![NATIVE, DOT]);
+		assert_eq!(quote.routes[1].route, vec![NATIVE, BTC]);
+		let (dot, btc) = (Portfolio::holdings(PORT, DOT), Portfolio::holdings(PORT, BTC));
+		assert_ok!(Portfolio::buy(Origin::signed(BOB), PORT, 20_000, 0, None));
+		assert_eq!(Assets::balance(PORT, BOB), quote.amount);
+		assert_eq!(Portfolio::holdings(PORT, DOT) - dot, quote.routes[0].target_amount);
+		assert_eq!(Portfolio::holdings(PORT, BTC) - btc, quote.routes[1].target_amount);
+
+		let port_amount = quote.amount;
+		let quote = Portfolio::quote_sell(PORT, NATIVE, port_amount).unwrap();
+		let native = Balances::free_balance(BOB);
+		assert_ok!(Portfolio::sell(Origin::signed(BOB), PORT, NATIVE, port_amount, 0, None));
+		assert_eq!(Balances::free_balance(BOB) - native, quote.amount);
+
+		assert_ok!(Portfolio::pause(Origin::signed(ALICE), PORT));
+		assert!(Portfolio::quote_buy(PORT, 20_000).is_none());
 	});
 }
@@ -16,7 +16,10 @@ use sp_consensus_aura::sr25519::AuthorityId as AuraId;
 use sp_core::{crypto::KeyTypeId, OpaqueMetadata};
 use sp_runtime::{
 	create_runtime_str, generic, impl_opaque_keys,
-	traits::{AccountIdLookup, BlakeTwo256, Block as BlockT, IdentifyAccount, NumberFor, Verify},
+	traits::{
+		AccountIdConversion, AccountIdLookup, BlakeTwo256, Block as BlockT, IdentifyAccount,
+		NumberFor, Verify,
+	},
 	transaction_validity::{TransactionSource, TransactionValidity},
 	ApplyExtrinsicResult, MultiSignature,
 };
@@ -60,6 +63,9 @@ pub type AccountId = <<Signature as Verify>::Signer as IdentifyAccount>::Account
 /// Balance of an account.
 pub type Balance = u128;
 
+/// Identifier of an asset class in `pallet_assets`.
+pub type AssetId = u64;
+
 /// Index of a transaction in the chain.
 pub type Index = u32;
 
@@ -102,10 +108,10 @@ pub const VERSION: RuntimeVersion = RuntimeVersion {
 	//   `spec_version`, and `authoring_version` are the same between Wasm and native.
 	// This value is set to 100 to notify Polkadot-JS App (https://polkadot.js.org/apps) to use
 	//   the compatible custom types.
-	spec_version: 1,
+	spec_version: 42,
 	impl_version: 1,
 	apis: RUNTIME_API_VERSIONS,
-	transaction_version: 1,
+	transaction_version: 12,
 	state_version: 1,
 };
 
@@ -275,12 +281,17 @@ parameter_types! {
 	pub const StringLimit: u32 = 50;
 	pub const MetadataDepositBase: Balance = 10 ;
 	pub const MetadataDepositPerByte: Balance = 1 ;
+	// the native currency as an asset
+	pub const NativeAssetId: AssetId = 0;
+	pub const AirdropReserveId: [u8; 8] = *b"airdrop ";
+	pub const AirdropDeposit: Balance = 100 ;
 }
 
 impl pallet_assets::Config for Runtime {
 	type Event = Event;
 	type Balance = u128;
-	type AssetId = u64;
+	type AssetId = AssetId;
+	type NativeAssetId = NativeAssetId;
 	type Currency = Balances;
 	type ForceOrigin = EnsureSigned<AccountId>;
 	type AssetDeposit = AssetDeposit;
@@ -289,46 +300,181 @@ impl pallet_assets::Config for Runtime {
 	type MetadataDepositBase = MetadataDepositBase;
 	type MetadataDepositPerByte = MetadataDepositPerByte;
 	type ApprovalDeposit = ApprovalDeposit;
-	type Freezer = ();
-	type Extra = ();
+	type Freezer = Compliance;
+	type FreezerWeight = pallet_compliance::FreezerWeight<Runtime>;
+	type Extra = pallet_compliance::AccountCompliance<Balance, BlockNumber>;
+	type MaxLocks = ConstU32<50>;
+	type MaxReserves = ConstU32<50>;
+	type ReserveIdentifier = [u8; 8];
+	type MaxBatchSize = ConstU32<100>;
+	type AirdropReserveId = AirdropReserveId;
+	type AirdropDeposit = AirdropDeposit;
 	type WeightInfo = pallet_assets::weights::SubstrateWeight<Runtime>;
 }
 
+/// The assets together with SOFI as the asset `NativeAssetId`.
+pub type Currencies = pallet_assets::NativeAssetAdapter<Runtime, Balances>;
+
 parameter_types! {
 	pub const PortofioPalletId: PalletId = PalletId(*b"portofio");
+	pub const MaxManagementFee: Permill = Permill::from_percent(5);
+	pub const MaxPerformanceFee: Permill = Permill::from_percent(30);
+	pub const BlocksPerYear: BlockNumber = DAYS * 365;
+	pub const MaxRebalancesPerBlock: u32 = 5;
+	pub const RebalanceWeight: Weight = 200_000_000;
+	pub const MaxComponents: u32 = 16;
+	pub const CreationDeposit: Balance = 100;
+	pub const PortfolioTwapWindow: BlockNumber = 30 * MINUTES;
+	pub const MaxSwapSlippage: Permill = Permill::from_percent(5);
+	pub const MaxRebalanceSlippage: Permill = Permill::from_percent(2);
 }
 
 impl pallet_portfolio::Config for Runtime {
 	type Event = Event;
 	type PalletId = PortofioPalletId;
+	type Currencies = Currencies;
 	type NativeCurrency = Balances;
 	type DexManager = Dex;
+	type PriceOracle = Dex;
+	type TwapWindow = PortfolioTwapWindow;
+	type MaxSwapSlippage = MaxSwapSlippage;
+	type MaxRebalanceSlippage = MaxRebalanceSlippage;
+	type UpdateOrigin = EnsureRoot<AccountId>;
+	type MaxManagementFee = MaxManagementFee;
+	type MaxPerformanceFee = MaxPerformanceFee;
+	type BlocksPerYear = BlocksPerYear;
+	type MaxRebalancesPerBlock = MaxRebalancesPerBlock;
+	type RebalanceWeight = RebalanceWeight;
+	type MaxComponents = MaxComponents;
+	type CreationDeposit = CreationDeposit;
 }
 
 parameter_types! {
 	pub const GetExchangeFee: (u32, u32) = (1, 1000);	// 0.1%
 	pub const TradingPathLimit: u32 = 4;
+	pub const MaxSwapPathSearch: u32 = 64;
+	pub const MaxAmplification: u32 = 1_000_000;
+	pub const PriceObservationInterval: BlockNumber = MINUTES;
+	pub const MaxPriceObservations: u32 = 24 * 60;
 	pub const DEXPalletId: PalletId = PalletId(*b"rai/dexm");
+	pub DEXTreasuryAccount: AccountId = PalletId(*b"rai/trsy").into_account();
 	// this value realte to AssetId type
 	pub const AssetIdShift: u32 = 32;
+	// above the ids of the legacy dex share currencies
+	pub const DexShareCurrencyIdStart: AssetId = 1 << 63;
+	pub const MaxDexShareCurrencyIdSearch: u32 = 100;
 	pub EnabledTradingPairs: Vec<TradingPair<u64>> = vec![
 	];
 }
 
 impl pallet_dex::Config for Runtime {
 	type Event = Event;
-	type NativeCurrency = Balances;
+	type Currencies = Currencies;
 	type GetExchangeFee = GetExchangeFee;
+	type TreasuryAccount = DEXTreasuryAccount;
 	type TradingPathLimit = TradingPathLimit;
+	type MaxSwapPathSearch = MaxSwapPathSearch;
+	type MaxAmplification = MaxAmplification;
+	type PriceObservationInterval = PriceObservationInterval;
+	type MaxPriceObservations = MaxPriceObservations;
 	type PalletId = DEXPalletId;
 	//type Erc20InfoMapping = EvmErc20InfoMapping<Runtime>;
-	//type DEXIncentives = Incentives;
+	type DEXIncentives = Incentives;
 	//type WeightInfo = weights::module_dex::WeightInfo<Runtime>;
 	type ListingOrigin = EnsureRoot<AccountId>;
 	type AssetIdShift = AssetIdShift;
+	type DexShareCurrencyIdStart = DexShareCurrencyIdStart;
+	type MaxDexShareCurrencyIdSearch = MaxDexShareCurrencyIdSearch;
 	//type OnLiquidityPoolUpdated = ();
 }
 
+parameter_types! {
+	pub const IncentivesPalletId: PalletId = PalletId(*b"rai/inct");
+	// rewards are paid in SOFI, see `NativeAssetId`
+	pub const RewardAssetId: AssetId = 0;
+}
+
+impl pallet_incentives::Config for Runtime {
+	type Event = Event;
+	type PalletId = IncentivesPalletId;
+	type Currencies = Currencies;
+	type RewardAssetId = RewardAssetId;
+	type DexManager = Dex;
+	type UpdateOrigin = EnsureRoot<AccountId>;
+}
+
+parameter_types! {
+	pub const OrdersPalletId: PalletId = PalletId(*b"rai/ordr");
+	pub const MaxFillsPerBlock: u32 = 10;
+	pub const FillWeight: Weight = 200_000_000;
+	pub const OrderDeposit: Balance = 100;
+}
+
+impl pallet_orders::Config for Runtime {
+	type Event = Event;
+	type PalletId = OrdersPalletId;
+	type Currencies = Currencies;
+	type NativeCurrency = Balances;
+	type DexManager = Dex;
+	type TradingPathLimit = TradingPathLimit;
+	type OrderDeposit = OrderDeposit;
+	type MaxFillsPerBlock = MaxFillsPerBlock;
+	type FillWeight = FillWeight;
+}
+
+parameter_types! {
+	pub const FeeRefundTwapWindow: BlockNumber = 30 * MINUTES;
+	pub const MaxFeeRefundSlippage: Permill = Permill::from_percent(2);
+}
+
+impl pallet_fee_payment::Config for Runtime {
+	type Event = Event;
+	type TwapWindow = FeeRefundTwapWindow;
+	type MaxRefundSlippage = MaxFeeRefundSlippage;
+}
+
+/// Portfolio tokens are managed by the owner of the portfolio, other assets by their owner.
+pub struct ComplianceManager;
+
+impl pallet_compliance::AssetManager<AssetId, AccountId> for ComplianceManager {
+	fn manager(asset: AssetId) -> Option<AccountId> {
+		Portfolio::owners(asset).or_else(|| Assets::owner(asset))
+	}
+}
+
+/// The accounts of the pallets holding assets for their users, which may receive an asset under an
+/// allow list so that it can still be traded.
+pub struct PalletAccounts;
+
+impl frame_support::traits::Contains<AccountId> for PalletAccounts {
+	fn contains(who: &AccountId) -> bool {
+		let pallet_ids = [
+			DEXPalletId::get(),
+			PortofioPalletId::get(),
+			OrdersPalletId::get(),
+			IncentivesPalletId::get(),
+		];
+		*who == DEXTreasuryAccount::get() ||
+			pallet_ids.iter().any(|pallet_id| *who == pallet_id.into_account())
+	}
+}
+
+impl pallet_compliance::Config for Runtime {
+	type Event = Event;
+	type Extras = Assets;
+	type AssetManager = ComplianceManager;
+	type ExemptAccounts = PalletAccounts;
+	type UpdateOrigin = EnsureRoot<AccountId>;
+}
+
+impl pallet_asset_vesting::Config for Runtime {
+	type Event = Event;
+	type Assets = Assets;
+	type MaxVestingSchedules = ConstU32<28>;
+	type MinVestedTransfer = ConstU128<100>;
+	type UpdateOrigin = EnsureRoot<AccountId>;
+}
+
 // Create the runtime by composing the FRAME pallets that were previously configured.
 construct_runtime!(
 	pub enum Runtime where
@@ -348,6 +494,11 @@ construct_runtime!(
 		// Include the custom logic from the pallet-template in the runtime.
 		Portfolio: pallet_portfolio,
 		Dex: pallet_dex,
+		Incentives: pallet_incentives,
+		Orders: pallet_orders,
+		FeePayment: pallet_fee_payment,
+		Compliance: pallet_compliance,
+		AssetVesting: pallet_asset_vesting,
 	}
 );
 
@@ -366,10 +517,16 @@ pub type SignedExtra = (
 	frame_system::CheckEra<Runtime>,
 	frame_system::CheckNonce<Runtime>,
 	frame_system::CheckWeight<Runtime>,
-	pallet_transaction_payment::ChargeTransactionPayment<Runtime>,
+	pallet_fee_payment::ChargeAssetTxPayment<Runtime>,
 );
 /// Unchecked extrinsic type as expected by this runtime.
 pub type UncheckedExtrinsic = generic::UncheckedExtrinsic<Address, Call, Signature, SignedExtra>;
+/// Migrations run ahead of the pallet hooks, in order. Dex v3 decodes the asset accounts, so it
+/// runs after compliance v1 has given them the compliance `Extra`.
+pub type Migrations = (
+	pallet_compliance::migrations::v1::MigrateToV1<Runtime>,
+	pallet_dex::migrations::v3::MigrateToV3<Runtime>,
+);
 /// Executive: handles dispatch to the various modules.
 pub type Executive = frame_executive::Executive<
 	Runtime,
@@ -377,6 +534,7 @@ pub type Executive = frame_executive::Executive<
 	frame_system::ChainContext<Runtime>,
 	Runtime,
 	AllPalletsWithSystem,
+	Migrations,
 >;
 
 #[cfg(feature = "runtime-benchmarks")]
@@ -525,6 +683,77 @@ impl_runtime_apis! {
 		}
 	}
 
+	impl pallet_portfolio_rpc_runtime_api::PortfolioApi<Block, AssetId, AccountId, Balance> for Runtime {
+		fn nav_per_share() -> Vec<(AssetId, sp_runtime::FixedU128)> {
+			Portfolio::navs_per_share()
+		}
+
+		fn portfolios() -> Vec<pallet_portfolio_rpc_runtime_api::PortfolioInfo<AssetId, AccountId, Balance>> {
+			Portfolio::portfolios()
+		}
+
+		fn quote_buy(
+			port_id: AssetId,
+			amount: Balance,
+		) -> Option<pallet_portfolio_rpc_runtime_api::PortfolioQuote<AssetId, Balance>> {
+			Portfolio::quote_buy(port_id, amount)
+		}
+
+		fn quote_sell(
+			port_id: AssetId,
+			dst_id: AssetId,
+			amount: Balance,
+		) -> Option<pallet_portfolio_rpc_runtime_api::PortfolioQuote<AssetId, Balance>> {
+			Portfolio::quote_sell(port_id, dst_id, amount)
+		}
+	}
+
+	impl pallet_dex_rpc_runtime_api::DexApi<Block, AssetId, Balance, BlockNumber> for Runtime {
+		fn trading_pairs() -> Vec<pallet_dex::TradingPairInfo<AssetId, Balance, BlockNumber>> {
+			Dex::trading_pairs()
+		}
+
+		fn quote_exact_supply(path: Vec<AssetId>, supply_amount: Balance) -> Option<Vec<Balance>> {
+			Dex::get_target_amounts(&path, supply_amount).ok()
+		}
+
+		fn quote_exact_target(path: Vec<AssetId>, target_amount: Balance) -> Option<Vec<Balance>> {
+			Dex::get_supply_amounts(&path, target_amount).ok()
+		}
+
+		fn best_swap_path(
+			supply_currency_id: AssetId,
+			target_currency_id: AssetId,
+			limit: pallet_dex::SwapLimit<Balance>,
+		) -> Option<(Vec<AssetId>, Balance, Balance)> {
+			Dex::find_best_swap_path(supply_currency_id, target_currency_id, limit)
+		}
+
+		fn twap(
+			currency_id_a: AssetId,
+			currency_id_b: AssetId,
+			window: BlockNumber,
+		) -> Option<pallet_dex::Price> {
+			<Dex as pallet_dex::DEXPriceOracle<AssetId, BlockNumber>>::get_twap(
+				currency_id_a,
+				currency_id_b,
+				window,
+			)
+		}
+	}
+
+	#[cfg(feature = "try-runtime")]
+	impl frame_try_runtime::TryRuntime<Block> for Runtime {
+		fn on_runtime_upgrade() -> (Weight, Weight) {
+			let weight = Executive::try_runtime_upgrade().unwrap();
+			(weight, BlockWeights::get().max_block)
+		}
+
+		fn execute_block_no_check(block: Block) -> Weight {
+			Executive::execute_block_no_check(block)
+		}
+	}
+
 	#[cfg(feature = "runtime-benchmarks")]
 	impl frame_benchmarking::Benchmark<Block> for Runtime {
 		fn benchmark_metadata(extra: bool) -> (